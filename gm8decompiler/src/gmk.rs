@@ -24,8 +24,9 @@ where
 {
     writer.write_u32::<LE>(1234321)?;
     writer.write_u32::<LE>(match version {
-        GameVersion::GameMaker8_0 => 800,
         GameVersion::GameMaker8_1 => 810,
+        // games from before 8.0 are upgraded to 8.0 projects, the same as GameMaker 8.0 does when opening them
+        _ => 800,
     })?;
     writer.write_u32::<LE>(game_id)?;
     for n in &guid {
@@ -69,10 +70,10 @@ where
     enc.write_u32::<LE>(settings.frequency)?;
     enc.write_u32::<LE>(settings.dont_show_buttons as u32)?;
     match version {
-        GameVersion::GameMaker8_0 => enc.write_u32::<LE>(settings.vsync as u32)?,
         GameVersion::GameMaker8_1 => {
             enc.write_u32::<LE>(((settings.force_cpu_render as u32) << 7) | (settings.vsync as u32))?
         },
+        _ => enc.write_u32::<LE>(settings.vsync as u32)?,
     };
     enc.write_u32::<LE>(settings.disable_screensaver as u32)?;
     enc.write_u32::<LE>(settings.f4_fullscreen_toggle as u32)?;
@@ -144,10 +145,10 @@ where
     enc.write_u32::<LE>(settings.log_errors as u32)?;
     enc.write_u32::<LE>(settings.always_abort as u32)?;
     match version {
-        GameVersion::GameMaker8_0 => enc.write_u32::<LE>(settings.zero_uninitialized_vars as u32)?,
         GameVersion::GameMaker8_1 => enc.write_u32::<LE>(
             ((settings.error_on_uninitialized_args as u32) << 1) | (settings.zero_uninitialized_vars as u32),
         )?,
+        _ => enc.write_u32::<LE>(settings.zero_uninitialized_vars as u32)?,
     };
 
     enc.write_pas_string(&"decompiler clan :police_car: :police_car: :police_car:".into())?; // author
@@ -421,10 +422,10 @@ where
     writer.write_u32::<LE>(font.bold as u32)?;
     writer.write_u32::<LE>(font.italic as u32)?;
     match version {
-        GameVersion::GameMaker8_0 => writer.write_u32::<LE>(font.range_start)?,
         GameVersion::GameMaker8_1 => writer.write_u32::<LE>(
             ((font.aa_level & 0xFF) << 24) | ((font.charset & 0xFF) << 16) | (font.range_start & 0xFFFF),
         )?,
+        _ => writer.write_u32::<LE>(font.range_start)?,
     };
     writer.write_u32::<LE>(font.range_end)?;
     Ok(())
@@ -857,3 +858,64 @@ where
     write_rt_asset(writer, &"Extension Packages".into(), 13, 0)?;
     Ok(())
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use gm8exe::{gmk::from_gmk, test_support::game};
+
+    /// Writes a whole project file the same way `main` does.
+    pub(crate) fn write_gmk(assets: &GameAssets) -> io::Result<Vec<u8>> {
+        let mut gmk = Vec::new();
+        let version = assets.version;
+        write_header(&mut gmk, version, assets.game_id, assets.guid)?;
+        write_settings(&mut gmk, &assets.settings, assets.ico_file_raw.clone(), version)?;
+        write_asset_list(&mut gmk, &assets.triggers, write_trigger, version, false)?;
+        write_timestamp(&mut gmk)?;
+        write_constants(&mut gmk, &assets.constants)?;
+        write_asset_list(&mut gmk, &assets.sounds, write_sound, version, false)?;
+        write_asset_list(&mut gmk, &assets.sprites, write_sprite, version, false)?;
+        write_asset_list(&mut gmk, &assets.backgrounds, write_background, version, false)?;
+        write_asset_list(&mut gmk, &assets.paths, write_path, version, false)?;
+        write_asset_list(&mut gmk, &assets.scripts, write_script, version, false)?;
+        write_asset_list(&mut gmk, &assets.fonts, write_font, version, false)?;
+        write_asset_list(&mut gmk, &assets.timelines, write_timeline, version, false)?;
+        write_asset_list(&mut gmk, &assets.objects, write_object, version, false)?;
+        write_asset_list(&mut gmk, &assets.rooms, write_room, version, false)?;
+        write_room_editor_meta(&mut gmk, assets.last_instance_id, assets.last_tile_id)?;
+        write_included_files(&mut gmk, &assets.included_files)?;
+        write_extensions(&mut gmk, &assets.extensions)?;
+        write_game_information(&mut gmk, &assets.help_dialog)?;
+        write_library_init_code(&mut gmk, &assets.library_init_strings)?;
+        write_room_order(&mut gmk, &assets.room_order)?;
+        write_resource_tree(&mut gmk, assets)?;
+        Ok(gmk)
+    }
+
+    #[test]
+    fn round_trip() {
+        for version in [GameVersion::GameMaker8_0, GameVersion::GameMaker8_1] {
+            let original = game(version);
+            let gmk = write_gmk(&original).unwrap();
+            let assets = from_gmk(&gmk, None::<fn(&str)>, true, false).unwrap();
+            assert_eq!(write_gmk(&assets).unwrap(), gmk);
+
+            // things which are only kept in the decompiler's compatibility blocks
+            let room = assets.rooms[0].as_ref().unwrap();
+            assert!(room.uses_810_features && room.uses_811_features);
+            assert_eq!(room.creation_code.0, original.rooms[0].as_ref().unwrap().creation_code.0);
+            assert_eq!((room.tiles[0].xscale, room.tiles[0].yscale, room.tiles[0].blend), (2.0, 0.5, 65280));
+            let instance = &room.instances[1];
+            assert_eq!((instance.xscale, instance.yscale, instance.blend), (-1.0, 2.5, 255));
+            assert_eq!((instance.angle, &*instance.creation_code.0), (90.0, &b"speed = 2;"[..]));
+
+            // collision masks get rebuilt from the sprite's mask settings
+            let sprite = assets.sprites[0].as_ref().unwrap();
+            let original_sprite = original.sprites[0].as_ref().unwrap();
+            assert_eq!(sprite.colliders.len(), 2);
+            for (map, original_map) in sprite.colliders.iter().zip(&original_sprite.colliders) {
+                assert_eq!(map.data, original_map.data);
+            }
+        }
+    }
+}
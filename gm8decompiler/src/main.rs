@@ -9,6 +9,7 @@ pub mod collision;
 pub mod deobfuscate;
 pub mod gmk;
 pub mod mappings;
+pub mod tree;
 pub mod zlib;
 
 static INFO_STRING: &str = concat!(
@@ -64,6 +65,7 @@ fn main() {
         .optopt("d", "deobfuscate", "set deobfuscation mode auto/on/off (default=auto)", "")
         .optflag("p", "preserve", "preserve broken events (instead of trying to fix them)")
         .optflag("s", "singlethread", "decompile gamedata synchronously (lower RAM usage)")
+        .optflag("t", "tree", "write a folder of source files instead of a gmk")
         .optopt("o", "output", "specify output filename", "FILE");
 
     // parse command line arguments
@@ -92,6 +94,7 @@ fn main() {
         // TODO: Get a better argument parser in general.
         println!(
             "Usage: {} FILENAME [options]
+       {} FOLDER [options]     (a folder written with --tree)
 
 Options:
     -h, --help                print this help message
@@ -100,8 +103,9 @@ Options:
     -d, --deobfuscate <mode>  set deobfuscation mode auto/on/off (defaults to auto)
     -p, --preserve            preserve broken events (instead of trying to fix them)
     -s, --singlethread        decompile gamedata synchronously (lower RAM usage)
+    -t, --tree                write a folder of source files instead of a gmk
     -o, --output <file>       specify output filename",
-            process_path
+            process_path, process_path
         );
         if should_pause {
             pause(true);
@@ -137,6 +141,7 @@ Options:
     };
     let out_path = matches.opt_str("o");
     let preserve = matches.opt_present("p");
+    let tree = matches.opt_present("t");
     // no_pause extracted before help
 
     // print flags for confirmation
@@ -155,6 +160,9 @@ Options:
     if singlethread {
         println!("Single-threaded mode ON: process will not start new threads (slow)");
     }
+    if tree {
+        println!("Tree mode ON: will write a folder of source files");
+    }
     if let Some(path) = &out_path {
         println!("Specified output path: {}", path);
     }
@@ -164,13 +172,13 @@ Options:
 
     // resolve input path
     let input_path = Path::new(input);
-    if !input_path.is_file() {
+    if !input_path.exists() {
         eprintln!("Input file '{}' does not exist.", input);
         process::exit(1);
     }
 
     // allow decompile to handle the rest of main
-    if let Err(e) = decompile(input_path, out_path, !lazy, !singlethread, verbose, deobfuscate, !preserve) {
+    if let Err(e) = decompile(input_path, out_path, !lazy, !singlethread, verbose, deobfuscate, !preserve, tree) {
         eprintln!("Error parsing gamedata:\n{}", e);
         process::exit(1);
     }
@@ -188,14 +196,18 @@ fn decompile(
     verbose: bool,
     deobf_mode: deobfuscate::Mode,
     fix_events: bool,
+    tree: bool,
 ) -> Result<(), String> {
-    // slurp in file contents
-    let file = fs::read(&in_path).map_err(|e| format!("Failed to read '{}': {}", in_path.display(), e))?;
-
-    // parse (entire) gamedata
+    // parse (entire) gamedata, or a source tree written by --tree
     let logger = if verbose { Some(|msg: &str| println!("{}", msg)) } else { None };
-    let mut assets = gm8exe::reader::from_exe(file, logger, strict, multithread) // huge call
-        .map_err(|e| format!("Reader error: {}", e))?;
+    let mut assets = if in_path.is_dir() {
+        gm8exe::tree::from_tree(in_path, logger, multithread)
+    } else {
+        // slurp in file contents
+        let file = fs::read(&in_path).map_err(|e| format!("Failed to read '{}': {}", in_path.display(), e))?;
+        gm8exe::reader::from_exe(file, logger, strict, multithread) // huge call
+    }
+    .map_err(|e| format!("Reader error: {}", e))?;
 
     println!("Successfully parsed game!");
 
@@ -246,17 +258,31 @@ fn decompile(
             .for_each(|ev| fix_event(ev));
     }
 
-    // warn user if they specified .gmk for 8.0 or .gm81 for 8.0
+    if deobfuscate {
+        deobfuscate::process(&mut assets);
+    }
+
+    if tree {
+        let out_path = match out_path {
+            Some(p) => PathBuf::from(p),
+            None => in_path.with_extension(""),
+        };
+        tree::write_tree(&out_path, &assets, multithread)
+            .map_err(|e| format!("Failed to write source tree to '{}': {}", out_path.display(), e))?;
+        println!("Successfully written source tree to '{}'", out_path.display());
+        return Ok(())
+    }
+
+    // warn user if they specified .gmk for 8.1 or .gm81 for 8.0 (games from before 8.0 become 8.0 projects)
     let out_expected_ext = match assets.version {
-        GameVersion::GameMaker8_0 => "gmk",
         GameVersion::GameMaker8_1 => "gm81",
+        _ => "gmk",
     };
     let out_path = match out_path {
         Some(p) => {
             let path = PathBuf::from(p);
-            match (assets.version, path.extension().and_then(|oss| oss.to_str())) {
-                (GameVersion::GameMaker8_0, Some(extension @ "gm81"))
-                | (GameVersion::GameMaker8_1, Some(extension @ "gmk")) => {
+            match path.extension().and_then(|oss| oss.to_str()) {
+                Some(extension @ ("gmk" | "gm81")) if extension != out_expected_ext => {
                     println!(
                         concat!(
                             "***WARNING*** You've specified an output file '{}'",
@@ -266,6 +292,9 @@ fn decompile(
                         path.display(),
                         extension,
                         match assets.version {
+                            GameVersion::GameMaker6_0 => "GameMaker 6.0",
+                            GameVersion::GameMaker6_1 => "GameMaker 6.1",
+                            GameVersion::GameMaker7_0 => "GameMaker 7.0",
                             GameVersion::GameMaker8_0 => "GameMaker 8.0",
                             GameVersion::GameMaker8_1 => "GameMaker 8.1",
                         },
@@ -284,10 +313,6 @@ fn decompile(
         },
     };
 
-    if deobfuscate {
-        deobfuscate::process(&mut assets);
-    }
-
     let mut gmk = fs::File::create(&out_path)
         .map_err(|e| format!("Failed to create output file '{}': {}", out_path.display(), e))?;
 
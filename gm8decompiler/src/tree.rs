@@ -0,0 +1,623 @@
+//! Writes a game out as a tree of source files which can be diffed and version controlled, rather than a .gmk.
+//! The layout is described in `gm8exe::tree`, which reads it back in.
+
+use crate::collision;
+use gm8exe::{
+    asset::{
+        extension::{CallingConvention, FileKind},
+        included_file::ExportSetting,
+        path::ConnectionKind,
+        PascalString,
+    },
+    tree::{file_names, sanitize, write_actions, EVENT_NAMES, INDEX_FILE},
+    AssetList, GameAssets, GameVersion,
+};
+use ini::{EscapePolicy, Ini};
+use rayon::prelude::*;
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+fn asset_names<T>(assets: &AssetList<T>, name: impl Fn(&T) -> &PascalString) -> Vec<Option<String>> {
+    file_names(assets.iter().map(|asset| asset.as_deref().map(&name)))
+}
+
+fn write_index<'a, I>(dir: &Path, file_names: &[Option<String>], names: I) -> io::Result<()>
+where
+    I: IntoIterator<Item = Option<&'a PascalString>>,
+{
+    let mut index = Vec::new();
+    for (file_name, name) in file_names.iter().zip(names) {
+        if let (Some(file_name), Some(name)) = (file_name, name) {
+            index.extend_from_slice(file_name.as_bytes());
+            if file_name.as_bytes() != name.0.as_ref() {
+                index.push(b'\t');
+                index.extend_from_slice(&name.0);
+            }
+        }
+        index.extend_from_slice(b"\r\n");
+    }
+    fs::write(dir.join(INDEX_FILE), index)
+}
+
+/// Makes a folder for an asset group and writes its index, returning the file name for each asset.
+fn asset_dir<T>(
+    path: &Path,
+    group: &str,
+    assets: &AssetList<T>,
+    name: impl Fn(&T) -> &PascalString,
+) -> io::Result<Vec<Option<String>>> {
+    let dir = path.join(group);
+    fs::create_dir(&dir)?;
+    let file_names = asset_names(assets, &name);
+    write_index(&dir, &file_names, assets.iter().map(|asset| asset.as_deref().map(&name)))?;
+    Ok(file_names)
+}
+
+/// Writes each asset in a list, in parallel if `multithread` is set.
+fn write_each<T, F>(assets: &AssetList<T>, file_names: &[Option<String>], multithread: bool, f: F) -> io::Result<()>
+where
+    T: Sync,
+    F: Fn(&T, &str) -> io::Result<()> + Sync,
+{
+    let write = |(asset, file_name): (&Option<Box<T>>, &Option<String>)| match (asset, file_name) {
+        (Some(asset), Some(file_name)) => f(asset, file_name),
+        _ => Ok(()),
+    };
+    if multithread {
+        assets.par_iter().zip(file_names).try_for_each(write)
+    } else {
+        assets.iter().zip(file_names).try_for_each(write)
+    }
+}
+
+fn write_ini(ini: &Ini, path: impl AsRef<Path>) -> io::Result<()> {
+    ini.write_to_file_policy(path, EscapePolicy::Reserved)
+}
+
+fn string(s: &PascalString) -> String {
+    s.to_string()
+}
+
+/// How another asset is referred to: by its file name, or by its ID if it doesn't exist.
+fn reference(file_names: &[Option<String>], id: i32) -> String {
+    match usize::try_from(id).ok().and_then(|id| file_names.get(id)) {
+        Some(Some(file_name)) => file_name.clone(),
+        _ if id < 0 => String::new(),
+        _ => id.to_string(),
+    }
+}
+
+fn list<T: Display>(values: impl IntoIterator<Item = T>) -> String {
+    values.into_iter().map(|x| x.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn write_png(path: impl AsRef<Path>, width: u32, height: u32, colour: png::ColorType, data: &[u8]) -> io::Result<()> {
+    let to_io = |e| io::Error::new(io::ErrorKind::Other, e);
+    let mut encoder = png::Encoder::new(io::BufWriter::new(fs::File::create(path)?), width, height);
+    encoder.set_color(colour);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header().and_then(|mut writer| writer.write_image_data(data)).map_err(to_io)
+}
+
+fn write_game(path: &Path, assets: &GameAssets, room_names: &[Option<String>]) -> io::Result<()> {
+    let settings = &assets.settings;
+    let help = &assets.help_dialog;
+    let mut ini = Ini::new();
+    ini.with_section(Some("game"))
+        .set("version", match assets.version {
+            GameVersion::GameMaker6_0 => "600",
+            GameVersion::GameMaker6_1 => "610",
+            GameVersion::GameMaker7_0 => "700",
+            GameVersion::GameMaker8_0 => "800",
+            GameVersion::GameMaker8_1 => "810",
+        })
+        .set("game_id", assets.game_id.to_string())
+        .set("guid", list(assets.guid))
+        .set("last_instance_id", assets.last_instance_id.to_string())
+        .set("last_tile_id", assets.last_tile_id.to_string());
+    ini.with_section(Some("settings"))
+        .set("fullscreen", settings.fullscreen.to_string())
+        .set("scaling", settings.scaling.to_string())
+        .set("interpolate_pixels", settings.interpolate_pixels.to_string())
+        .set("clear_colour", settings.clear_colour.to_string())
+        .set("allow_resize", settings.allow_resize.to_string())
+        .set("window_on_top", settings.window_on_top.to_string())
+        .set("dont_draw_border", settings.dont_draw_border.to_string())
+        .set("dont_show_buttons", settings.dont_show_buttons.to_string())
+        .set("display_cursor", settings.display_cursor.to_string())
+        .set("freeze_on_lose_focus", settings.freeze_on_lose_focus.to_string())
+        .set("disable_screensaver", settings.disable_screensaver.to_string())
+        .set("force_cpu_render", settings.force_cpu_render.to_string())
+        .set("set_resolution", settings.set_resolution.to_string())
+        .set("colour_depth", settings.colour_depth.to_string())
+        .set("resolution", settings.resolution.to_string())
+        .set("frequency", settings.frequency.to_string())
+        .set("vsync", settings.vsync.to_string())
+        .set("esc_close_game", settings.esc_close_game.to_string())
+        .set("treat_close_as_esc", settings.treat_close_as_esc.to_string())
+        .set("f1_help_menu", settings.f1_help_menu.to_string())
+        .set("f4_fullscreen_toggle", settings.f4_fullscreen_toggle.to_string())
+        .set("f5_save_f6_load", settings.f5_save_f6_load.to_string())
+        .set("f9_screenshot", settings.f9_screenshot.to_string())
+        .set("priority", settings.priority.to_string())
+        .set("transparent", settings.transparent.to_string())
+        .set("translucency", settings.translucency.to_string())
+        .set("loading_bar", settings.loading_bar.to_string())
+        .set("scale_progress_bar", settings.scale_progress_bar.to_string())
+        .set("show_error_messages", settings.show_error_messages.to_string())
+        .set("log_errors", settings.log_errors.to_string())
+        .set("always_abort", settings.always_abort.to_string())
+        .set("zero_uninitialized_vars", settings.zero_uninitialized_vars.to_string())
+        .set("error_on_uninitialized_args", settings.error_on_uninitialized_args.to_string())
+        .set("swap_creation_events", settings.swap_creation_events.to_string());
+    ini.with_section(Some("game_information"))
+        .set("bg_colour", u32::from(help.bg_colour).to_string())
+        .set("new_window", help.new_window.to_string())
+        .set("caption", string(&help.caption))
+        .set("left", help.left.to_string())
+        .set("top", help.top.to_string())
+        .set("width", help.width.to_string())
+        .set("height", help.height.to_string())
+        .set("border", help.border.to_string())
+        .set("resizable", help.resizable.to_string())
+        .set("window_on_top", help.window_on_top.to_string())
+        .set("freeze_game", help.freeze_game.to_string());
+    let mut constants = ini.with_section(Some("constants"));
+    for constant in &assets.constants {
+        constants.set(string(&constant.name), string(&constant.expression));
+    }
+    write_ini(&ini, path.join("game.ini"))?;
+
+    fs::write(path.join("Game Information.rtf"), &help.info.0)?;
+    if let Some(ico) = &assets.ico_file_raw {
+        fs::write(path.join("icon.ico"), ico)?;
+    }
+    for (data, file_name) in [
+        (&settings.backdata, "loading_bar_back.dat"),
+        (&settings.frontdata, "loading_bar_front.dat"),
+        (&settings.custom_load_image, "loading_image.dat"),
+    ] {
+        if let Some(data) = data {
+            fs::write(path.join(file_name), data)?;
+        }
+    }
+
+    let dir = path.join("Library Init");
+    fs::create_dir(&dir)?;
+    for (i, code) in assets.library_init_strings.iter().enumerate() {
+        fs::write(dir.join(format!("{}.gml", i)), &code.0)?;
+    }
+
+    let mut order = Vec::new();
+    for room in &assets.room_order {
+        order.extend_from_slice(reference(room_names, *room).as_bytes());
+        order.extend_from_slice(b"\r\n");
+    }
+    fs::create_dir(path.join("Rooms"))?;
+    fs::write(path.join("Rooms").join("order.txt"), order)
+}
+
+fn write_extensions(path: &Path, assets: &GameAssets) -> io::Result<()> {
+    let dir = path.join("Extensions");
+    fs::create_dir(&dir)?;
+    let extension_names = file_names(assets.extensions.iter().map(|e| Some(&e.name)));
+    write_index(&dir, &extension_names, assets.extensions.iter().map(|e| Some(&e.name)))?;
+    for (extension, file_name) in assets.extensions.iter().zip(extension_names.iter().flatten()) {
+        let dir = dir.join(file_name);
+        fs::create_dir(&dir)?;
+        let mut ini = Ini::new();
+        ini.with_section(Some("extension")).set("folder_name", string(&extension.folder_name));
+        let content_names = file_names(extension.files.iter().map(|f| Some(&f.name)));
+        for (i, (file, content_name)) in extension.files.iter().zip(content_names.iter().flatten()).enumerate() {
+            ini.with_section(Some(format!("file{}", i)))
+                .set("name", string(&file.name))
+                .set("contents", content_name.as_str())
+                .set("kind", (file.kind as u32).to_string())
+                .set("initializer", string(&file.initializer))
+                .set("finalizer", string(&file.finalizer));
+            for (j, function) in file.functions.iter().enumerate() {
+                ini.with_section(Some(format!("file{}.function{}", i, j)))
+                    .set("name", string(&function.name))
+                    .set("external_name", string(&function.external_name))
+                    .set("convention", match function.convention {
+                        CallingConvention::Gml => "2",
+                        CallingConvention::Stdcall => "11",
+                        CallingConvention::Cdecl => "12",
+                        CallingConvention::Unknown => "0",
+                    })
+                    .set("id", function.id.to_string())
+                    .set("arg_count", function.arg_count.to_string())
+                    .set("arg_types", list(function.arg_types.iter().map(|t| *t as u32)))
+                    .set("return_type", (function.return_type as u32).to_string());
+            }
+            for (j, constant) in file.consts.iter().enumerate() {
+                ini.with_section(Some(format!("file{}.const{}", i, j)))
+                    .set("name", string(&constant.name))
+                    .set("value", string(&constant.value));
+            }
+            if file.kind != FileKind::ActionLibrary {
+                fs::write(dir.join(content_name), &file.contents)?;
+            }
+        }
+        write_ini(&ini, dir.join("extension.ini"))?;
+    }
+    Ok(())
+}
+
+fn write_included_files(path: &Path, assets: &GameAssets) -> io::Result<()> {
+    let dir = path.join("Included Files");
+    fs::create_dir(&dir)?;
+    let files = &assets.included_files;
+    let included_names = file_names(files.iter().map(|f| Some(&f.file_name)));
+    write_index(&dir, &included_names, files.iter().map(|f| Some(&f.file_name)))?;
+    for (file, file_name) in files.iter().zip(included_names.iter().flatten()) {
+        let (export, folder) = match &file.export_settings {
+            ExportSetting::NoExport => (0, String::new()),
+            ExportSetting::TempFolder => (1, String::new()),
+            ExportSetting::GameFolder => (2, String::new()),
+            ExportSetting::CustomFolder(folder) => (3, string(folder)),
+        };
+        let mut ini = Ini::new();
+        ini.with_section(Some("file"))
+            .set("source_path", string(&file.source_path))
+            .set("data_exists", file.data_exists.to_string())
+            .set("source_length", file.source_length.to_string())
+            .set("stored_in_gmk", file.stored_in_gmk.to_string())
+            .set("export", export.to_string())
+            .set("export_folder", folder)
+            .set("overwrite_file", file.overwrite_file.to_string())
+            .set("free_memory", file.free_memory.to_string())
+            .set("remove_at_end", file.remove_at_end.to_string());
+        write_ini(&ini, dir.join(format!("{}.ini", file_name)))?;
+        if let Some(data) = &file.embedded_data {
+            fs::write(dir.join(format!("{}.dat", file_name)), data)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `assets` to a new folder at `path`, which mustn't already exist.
+pub fn write_tree(path: &Path, assets: &GameAssets, multithread: bool) -> io::Result<()> {
+    fs::create_dir(path)?;
+
+    let sprite_names = asset_names(&assets.sprites, |x| &x.name);
+    let background_names = asset_names(&assets.backgrounds, |x| &x.name);
+    let object_names = asset_names(&assets.objects, |x| &x.name);
+    let room_names = asset_names(&assets.rooms, |x| &x.name);
+
+    println!("Writing game settings...");
+    write_game(path, assets, &room_names)?;
+
+    println!("Writing {} triggers...", assets.triggers.len());
+    let names = asset_dir(path, "Triggers", &assets.triggers, |x| &x.name)?;
+    write_each(&assets.triggers, &names, multithread, |trigger, file_name| {
+        let dir = path.join("Triggers");
+        let mut ini = Ini::new();
+        ini.with_section(Some("trigger"))
+            .set("moment", (trigger.moment as u32).to_string())
+            .set("constant_name", string(&trigger.constant_name));
+        write_ini(&ini, dir.join(format!("{}.ini", file_name)))?;
+        fs::write(dir.join(format!("{}.gml", file_name)), &trigger.condition.0)
+    })?;
+
+    println!("Writing {} extensions...", assets.extensions.len());
+    write_extensions(path, assets)?;
+
+    println!("Writing {} included files...", assets.included_files.len());
+    write_included_files(path, assets)?;
+
+    println!("Writing {} sounds...", assets.sounds.len());
+    let names = asset_dir(path, "Sounds", &assets.sounds, |x| &x.name)?;
+    write_each(&assets.sounds, &names, multithread, |sound, file_name| {
+        let dir = path.join("Sounds");
+        let mut ini = Ini::new();
+        ini.with_section(Some("sound"))
+            .set("kind", (sound.kind as u32).to_string())
+            .set("extension", string(&sound.extension))
+            .set("source", string(&sound.source))
+            .set("volume", sound.volume.to_string())
+            .set("pan", sound.pan.to_string())
+            .set("preload", sound.preload.to_string())
+            .set("chorus", sound.fx.chorus.to_string())
+            .set("echo", sound.fx.echo.to_string())
+            .set("flanger", sound.fx.flanger.to_string())
+            .set("gargle", sound.fx.gargle.to_string())
+            .set("reverb", sound.fx.reverb.to_string());
+        write_ini(&ini, dir.join(format!("{}.ini", file_name)))?;
+        match &sound.data {
+            Some(data) => fs::write(dir.join(format!("{}{}", file_name, sanitize(&sound.extension.0))), data),
+            None => Ok(()),
+        }
+    })?;
+
+    println!("Writing {} sprites...", assets.sprites.len());
+    fs::create_dir(path.join("Sprites"))?;
+    write_index(&path.join("Sprites"), &sprite_names, assets.sprites.iter().map(|s| s.as_ref().map(|s| &s.name)))?;
+    write_each(&assets.sprites, &sprite_names, multithread, |sprite, file_name| {
+        let dir = path.join("Sprites").join(file_name);
+        fs::create_dir(&dir)?;
+        let mut ini = Ini::new();
+        let mut section = ini.with_section(Some("sprite"));
+        section
+            .set("origin_x", sprite.origin_x.to_string())
+            .set("origin_y", sprite.origin_y.to_string())
+            .set("frames", sprite.frames.len().to_string())
+            .set("per_frame_colliders", sprite.per_frame_colliders.to_string());
+        if let Some(mask) = collision::resolve_map(sprite) {
+            section
+                .set("shape", (mask.shape as u32).to_string())
+                .set("alpha_tolerance", mask.alpha_tolerance.to_string())
+                .set("bbox_left", mask.bbox_left.to_string())
+                .set("bbox_right", mask.bbox_right.to_string())
+                .set("bbox_top", mask.bbox_top.to_string())
+                .set("bbox_bottom", mask.bbox_bottom.to_string());
+        } else if !sprite.frames.is_empty() {
+            println!("WARNING: couldn't resolve collision for sprite {}", sprite.name);
+        }
+        write_ini(&ini, dir.join("sprite.ini"))?;
+        for (i, frame) in sprite.frames.iter().enumerate() {
+            if frame.width * frame.height != 0 {
+                let png = dir.join(format!("{}.png", i));
+                write_png(png, frame.width, frame.height, png::ColorType::Rgba, &frame.data)?;
+            }
+        }
+        Ok(())
+    })?;
+
+    println!("Writing {} backgrounds...", assets.backgrounds.len());
+    fs::create_dir(path.join("Backgrounds"))?;
+    write_index(
+        &path.join("Backgrounds"),
+        &background_names,
+        assets.backgrounds.iter().map(|b| b.as_ref().map(|b| &b.name)),
+    )?;
+    write_each(&assets.backgrounds, &background_names, multithread, |background, file_name| {
+        let dir = path.join("Backgrounds");
+        let mut ini = Ini::new();
+        ini.with_section(Some("background"))
+            .set("width", background.width.to_string())
+            .set("height", background.height.to_string());
+        write_ini(&ini, dir.join(format!("{}.ini", file_name)))?;
+        match &background.data {
+            Some(data) => write_png(
+                dir.join(format!("{}.png", file_name)),
+                background.width,
+                background.height,
+                png::ColorType::Rgba,
+                data,
+            ),
+            None => Ok(()),
+        }
+    })?;
+
+    println!("Writing {} paths...", assets.paths.len());
+    let names = asset_dir(path, "Paths", &assets.paths, |x| &x.name)?;
+    write_each(&assets.paths, &names, multithread, |path_asset, file_name| {
+        let mut ini = Ini::new();
+        ini.with_section(Some("path"))
+            .set("smooth", matches!(path_asset.connection, ConnectionKind::SmoothCurve).to_string())
+            .set("closed", path_asset.closed.to_string())
+            .set("precision", path_asset.precision.to_string());
+        let mut points = ini.with_section(Some("points"));
+        for (i, point) in path_asset.points.iter().enumerate() {
+            points.set(i.to_string(), list([point.x, point.y, point.speed]));
+        }
+        write_ini(&ini, path.join("Paths").join(format!("{}.ini", file_name)))
+    })?;
+
+    println!("Writing {} scripts...", assets.scripts.len());
+    let names = asset_dir(path, "Scripts", &assets.scripts, |x| &x.name)?;
+    write_each(&assets.scripts, &names, multithread, |script, file_name| {
+        fs::write(path.join("Scripts").join(format!("{}.gml", file_name)), &script.source.0)
+    })?;
+
+    println!("Writing {} fonts...", assets.fonts.len());
+    let names = asset_dir(path, "Fonts", &assets.fonts, |x| &x.name)?;
+    write_each(&assets.fonts, &names, multithread, |font, file_name| {
+        let dir = path.join("Fonts");
+        let mut ini = Ini::new();
+        ini.with_section(Some("font"))
+            .set("sys_name", string(&font.sys_name))
+            .set("size", font.size.to_string())
+            .set("bold", font.bold.to_string())
+            .set("italic", font.italic.to_string())
+            .set("range_start", font.range_start.to_string())
+            .set("range_end", font.range_end.to_string())
+            .set("charset", font.charset.to_string())
+            .set("aa_level", font.aa_level.to_string());
+        // each character's position in the glyph map, its size, and how it's placed when drawn
+        let mut glyphs = ini.with_section(Some("glyphs"));
+        for (i, glyph) in font.dmap.chunks_exact(6).enumerate() {
+            if glyph.iter().any(|x| *x != 0) {
+                glyphs.set(i.to_string(), list(glyph));
+            }
+        }
+        write_ini(&ini, dir.join(format!("{}.ini", file_name)))?;
+        if font.map_width * font.map_height != 0 {
+            let png = dir.join(format!("{}.png", file_name));
+            write_png(png, font.map_width, font.map_height, png::ColorType::Grayscale, &font.pixel_map)?;
+        }
+        Ok(())
+    })?;
+
+    println!("Writing {} timelines...", assets.timelines.len());
+    let names = asset_dir(path, "Time Lines", &assets.timelines, |x| &x.name)?;
+    write_each(&assets.timelines, &names, multithread, |timeline, file_name| {
+        let dir = path.join("Time Lines").join(file_name);
+        fs::create_dir(&dir)?;
+        for (moment, actions) in &timeline.moments {
+            fs::write(dir.join(format!("{}.gml", moment)), write_actions(actions))?;
+        }
+        Ok(())
+    })?;
+
+    println!("Writing {} objects...", assets.objects.len());
+    fs::create_dir(path.join("Objects"))?;
+    write_index(&path.join("Objects"), &object_names, assets.objects.iter().map(|o| o.as_ref().map(|o| &o.name)))?;
+    write_each(&assets.objects, &object_names, multithread, |object, file_name| {
+        let dir = path.join("Objects").join(file_name);
+        fs::create_dir(&dir)?;
+        let mut ini = Ini::new();
+        ini.with_section(Some("object"))
+            .set("sprite", reference(&sprite_names, object.sprite_index))
+            .set("solid", object.solid.to_string())
+            .set("visible", object.visible.to_string())
+            .set("depth", object.depth.to_string())
+            .set("persistent", object.persistent.to_string())
+            .set("parent", reference(&object_names, object.parent_index))
+            .set("mask", reference(&sprite_names, object.mask_index));
+        write_ini(&ini, dir.join("object.ini"))?;
+        for (event_name, events) in EVENT_NAMES.iter().zip(&object.events) {
+            for (sub, actions) in events {
+                let sub = match *event_name {
+                    "collision" => reference(&object_names, *sub as i32),
+                    _ => sub.to_string(),
+                };
+                fs::write(dir.join(format!("{}_{}.gml", event_name, sub)), write_actions(actions))?;
+            }
+        }
+        Ok(())
+    })?;
+
+    println!("Writing {} rooms...", assets.rooms.len());
+    write_index(&path.join("Rooms"), &room_names, assets.rooms.iter().map(|r| r.as_ref().map(|r| &r.name)))?;
+    write_each(&assets.rooms, &room_names, multithread, |room, file_name| {
+        let dir = path.join("Rooms").join(file_name);
+        fs::create_dir(&dir)?;
+        let mut ini = Ini::new();
+        ini.with_section(Some("room"))
+            .set("caption", string(&room.caption))
+            .set("width", room.width.to_string())
+            .set("height", room.height.to_string())
+            .set("speed", room.speed.to_string())
+            .set("persistent", room.persistent.to_string())
+            .set("bg_colour", u32::from(room.bg_colour).to_string())
+            .set("clear_screen", room.clear_screen.to_string())
+            .set("clear_region", room.clear_region.to_string())
+            .set("views_enabled", room.views_enabled.to_string())
+            .set("uses_810_features", room.uses_810_features.to_string())
+            .set("uses_811_features", room.uses_811_features.to_string());
+        for (i, background) in room.backgrounds.iter().enumerate() {
+            ini.with_section(Some(format!("background{}", i)))
+                .set("visible", background.visible_on_start.to_string())
+                .set("foreground", background.is_foreground.to_string())
+                .set("background", reference(&background_names, background.source_bg))
+                .set("x", background.xoffset.to_string())
+                .set("y", background.yoffset.to_string())
+                .set("tile_horz", background.tile_horz.to_string())
+                .set("tile_vert", background.tile_vert.to_string())
+                .set("hspeed", background.hspeed.to_string())
+                .set("vspeed", background.vspeed.to_string())
+                .set("stretch", background.stretch.to_string());
+        }
+        for (i, view) in room.views.iter().enumerate() {
+            ini.with_section(Some(format!("view{}", i)))
+                .set("visible", view.visible.to_string())
+                .set("source_x", view.source_x.to_string())
+                .set("source_y", view.source_y.to_string())
+                .set("source_w", view.source_w.to_string())
+                .set("source_h", view.source_h.to_string())
+                .set("port_x", view.port_x.to_string())
+                .set("port_y", view.port_y.to_string())
+                .set("port_w", view.port_w.to_string())
+                .set("port_h", view.port_h.to_string())
+                .set("hborder", view.following.hborder.to_string())
+                .set("vborder", view.following.vborder.to_string())
+                .set("hspeed", view.following.hspeed.to_string())
+                .set("vspeed", view.following.vspeed.to_string())
+                .set("target", reference(&object_names, view.following.target));
+        }
+        write_ini(&ini, dir.join("room.ini"))?;
+        fs::write(dir.join("creation.gml"), &room.creation_code.0)?;
+
+        // instances and tiles are one per line, with the name last as it might have spaces in it
+        let mut instances = fs::File::create(dir.join("instances.txt"))?;
+        writeln!(instances, "# id x y xscale yscale blend angle object\r")?;
+        for instance in &room.instances {
+            writeln!(
+                instances,
+                "{} {} {} {} {} {} {} {}\r",
+                instance.id,
+                instance.x,
+                instance.y,
+                instance.xscale,
+                instance.yscale,
+                instance.blend,
+                instance.angle,
+                reference(&object_names, instance.object),
+            )?;
+            if !instance.creation_code.0.is_empty() {
+                fs::write(dir.join(format!("instance_{}.gml", instance.id)), &instance.creation_code.0)?;
+            }
+        }
+        let mut tiles = fs::File::create(dir.join("tiles.txt"))?;
+        writeln!(tiles, "# id x y tile_x tile_y width height depth xscale yscale blend background\r")?;
+        for tile in &room.tiles {
+            writeln!(
+                tiles,
+                "{} {} {} {} {} {} {} {} {} {} {} {}\r",
+                tile.id,
+                tile.x,
+                tile.y,
+                tile.tile_x,
+                tile.tile_y,
+                tile.width,
+                tile.height,
+                tile.depth,
+                tile.xscale,
+                tile.yscale,
+                tile.blend,
+                reference(&background_names, tile.source_bg),
+            )?;
+        }
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmk::tests::write_gmk;
+    use gm8exe::{test_support::game, tree::from_tree};
+
+    #[test]
+    fn round_trip() {
+        for version in [GameVersion::GameMaker8_0, GameVersion::GameMaker8_1] {
+            let path = std::env::temp_dir().join(format!("gm8decompiler-tree-{}-{:?}", std::process::id(), version));
+            let _ = fs::remove_dir_all(&path);
+            let original = game(version);
+            write_tree(&path, &original, false).unwrap();
+            let assets = from_tree(&path, None::<fn(&str)>, true);
+            fs::remove_dir_all(&path).unwrap();
+            let assets = assets.unwrap();
+
+            // everything a project holds comes back the same, including sprite masks
+            assert_eq!(write_gmk(&assets).unwrap(), write_gmk(&original).unwrap());
+
+            // and so do the things it doesn't
+            let (font, original_font) = (assets.fonts[0].as_ref().unwrap(), original.fonts[0].as_ref().unwrap());
+            assert_eq!(font.dmap, original_font.dmap);
+            assert_eq!(font.pixel_map, original_font.pixel_map);
+            let (sprite, original_sprite) =
+                (assets.sprites[0].as_ref().unwrap(), original.sprites[0].as_ref().unwrap());
+            assert_eq!(sprite.colliders.len(), original_sprite.colliders.len());
+            for (map, original_map) in sprite.colliders.iter().zip(&original_sprite.colliders) {
+                assert_eq!(map.data, original_map.data);
+            }
+            assert_eq!(assets.extensions.len(), original.extensions.len());
+            for (extension, original_extension) in assets.extensions.iter().zip(&original.extensions) {
+                assert_eq!(extension.folder_name.0, original_extension.folder_name.0);
+                for (file, original_file) in extension.files.iter().zip(&original_extension.files) {
+                    assert_eq!(file.contents, original_file.contents);
+                    assert_eq!(file.functions.len(), original_file.functions.len());
+                }
+            }
+        }
+    }
+}
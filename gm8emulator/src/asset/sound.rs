@@ -1,5 +1,5 @@
 use crate::{
-    game::audio::{Mp3Handle, WavHandle},
+    game::audio::{Mp3Handle, WavHandle, WavHandleV1},
     gml,
     math::Real,
 };
@@ -19,3 +19,34 @@ pub enum FileType {
     Wav(WavHandle),
     None,
 }
+
+/// The layout of Sound in version 1 savestates, whose WAV handles didn't have panning or 3D sound.
+#[derive(Serialize, Deserialize)]
+pub struct SoundV1 {
+    name: gml::String,
+    handle: FileTypeV1,
+    gml_kind: Real,
+    gml_preload: Real,
+}
+
+#[derive(Serialize, Deserialize)]
+enum FileTypeV1 {
+    Mp3(Mp3Handle),
+    Wav(WavHandleV1),
+    None,
+}
+
+impl From<SoundV1> for Sound {
+    fn from(sound: SoundV1) -> Self {
+        Self {
+            name: sound.name,
+            handle: match sound.handle {
+                FileTypeV1::Mp3(handle) => FileType::Mp3(handle),
+                FileTypeV1::Wav(handle) => FileType::Wav(handle.into()),
+                FileTypeV1::None => FileType::None,
+            },
+            gml_kind: sound.gml_kind,
+            gml_preload: sound.gml_preload,
+        }
+    }
+}
@@ -1,9 +1,12 @@
 pub mod audio;
 pub mod background;
+pub mod dialog;
 pub mod draw;
 pub mod events;
 pub mod external;
+pub mod framedump;
 pub mod gm_save;
+pub mod highscore;
 pub mod includedfile;
 pub mod model;
 pub mod movement;
@@ -11,10 +14,14 @@ pub mod particle;
 pub mod pathfinding;
 pub mod platform;
 pub mod recording;
+pub mod registry;
 pub mod replay;
 pub mod savestate;
+pub mod statediff;
+pub mod statehash;
 pub mod surface;
 pub mod transition;
+pub mod vfs;
 pub mod view;
 
 pub use background::Background;
@@ -41,7 +48,7 @@ use crate::{
     instance::{DummyFieldHolder, Instance, InstanceState},
     instancelist::{InstanceList, TileList},
     math::Real,
-    render::{atlas::AtlasBuilder, Renderer, RendererOptions, Scaling},
+    render::{self, atlas::AtlasBuilder, Renderer, RendererOptions, Scaling},
     tile,
     types::{Colour, ID},
     util,
@@ -180,6 +187,8 @@ pub struct Game {
     pub swap_creation_events: bool,
 
     pub potential_step_settings: pathfinding::PotentialStepSettings,
+    pub message_settings: dialog::MessageSettings,
+    pub highscore_settings: highscore::HighscoreSettings,
 
     pub fps: u32,                 // initially 0
     pub frame_counter: u32,       // for FPS - gets set to 0 about once per second
@@ -209,6 +218,8 @@ pub struct Game {
     pub open_ini: Option<(ini::Ini, gml::String)>, // keep the filename for writing
     pub open_file: Option<file::TextHandle>,       // for legacy file functions from GM <= 5.1
     pub file_finder: Option<Box<dyn Iterator<Item = PathBuf>>>,
+    pub registry: registry::Registry,
+    pub vfs: vfs::Vfs,
     pub clock: GameClock,
     pub parameters: Vec<String>,
     pub encoding: &'static Encoding,
@@ -220,11 +231,13 @@ pub struct Game {
     pub frame_limiter: bool, // whether to limit FPS of gameplay by room_speed
     pub frame_limit_at: usize, // on which frame to start limiting FPS
     pub ffmpeg_recorder: Option<Child>,
+    pub state_hash_log: Option<statehash::HashLog>, // hashes of each frame's state, for finding desyncs
+    pub frame_dump: Option<framedump::FrameDump>,   // every frame written to disk, for encoding videos
 
     pub audio: audio::AudioManager,
 
     // winit windowing
-    pub window: Window,
+    pub window: Option<Window>, // None when running headless
     pub window_border: bool,
     pub window_caption: String,
     pub window_cursor_gml: i32,
@@ -241,6 +254,8 @@ pub struct Game {
     pub unscaled_width: u32,
     // Height the window is supposed to have, assuming it hasn't been resized by the user
     pub unscaled_height: u32,
+    // Size of the recording UI's window, which dialogs are drawn in while recording
+    pub ui_window_size: (u32, u32),
 }
 
 /// Enum indicating which GameMaker version a game was built with
@@ -248,6 +263,17 @@ pub struct Game {
 pub enum Version {
     GameMaker8_0,
     GameMaker8_1,
+    // savestates store these by position, so older versions go after the ones that were already here
+    GameMaker6_0,
+    GameMaker6_1,
+    GameMaker7_0,
+}
+
+impl Version {
+    /// Whether this is a version from before 8.0, which loads sprites and backgrounds with different settings.
+    pub fn is_pre_8_0(self) -> bool {
+        matches!(self, Version::GameMaker6_0 | Version::GameMaker6_1 | Version::GameMaker7_0)
+    }
 }
 
 /// Enum indicating how this game is being played - normal, recording or replaying
@@ -258,6 +284,24 @@ pub enum PlayType {
     Replay,
 }
 
+/// What state the game was left in after playing back a replay
+pub struct ReplaySummary {
+    pub frames: usize,
+    pub room_id: ID,
+    pub room_name: String,
+    pub seed: i32,
+}
+
+impl std::fmt::Display for ReplaySummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "replay finished\n  > frames played: {}\n  > room: {} ({})\n  > seed: {}",
+            self.frames, self.room_name, self.room_id, self.seed
+        )
+    }
+}
+
 /// Various different types of scene change which can be requested by GML
 #[derive(Clone)]
 pub enum SceneChange {
@@ -338,6 +382,9 @@ impl Game {
         frame_limit_at: usize,
         capture_recording: bool,
         play_type: PlayType,
+        seed_files: bool,
+        headless: bool,
+        software_render: bool,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         // Parse file path
         let mut file_path2 = file_path.clone();
@@ -395,6 +442,9 @@ impl Game {
         } = assets;
 
         let gm_version = match version {
+            gm8exe::GameVersion::GameMaker6_0 => Version::GameMaker6_0,
+            gm8exe::GameVersion::GameMaker6_1 => Version::GameMaker6_1,
+            gm8exe::GameVersion::GameMaker7_0 => Version::GameMaker7_0,
             gm8exe::GameVersion::GameMaker8_0 => Version::GameMaker8_0,
             gm8exe::GameVersion::GameMaker8_1 => Version::GameMaker8_1,
         };
@@ -420,10 +470,8 @@ impl Game {
 
         // manual decode to avoid errors
         let decode_str_maybe = |bytes: Vec<u8>| match gm_version {
-            Version::GameMaker8_0 => {
-                encoding.decode_without_bom_handling_and_without_replacement(&bytes).map(|x| x.into_owned())
-            },
             Version::GameMaker8_1 => String::from_utf8(bytes).ok(),
+            _ => encoding.decode_without_bom_handling_and_without_replacement(&bytes).map(|x| x.into_owned()),
         };
 
         let mut temp_directory = match temp_dir {
@@ -470,6 +518,12 @@ impl Game {
             },
         };
 
+        // Sandbox the game's files when recording or replaying, so savestates can roll them back
+        let mut vfs = vfs::Vfs::default();
+        if play_type != PlayType::Normal {
+            vfs.set_roots(&[&file_path2, &temp_directory], seed_files);
+        }
+
         let included_files = included_files
             .into_iter()
             .map(|i| {
@@ -498,7 +552,7 @@ impl Game {
                     free_after_export: i.free_memory,
                     remove_at_end: i.remove_at_end,
                 };
-                i.export(temp_directory.clone(), program_directory.to_string().into())?;
+                i.export(&mut vfs, temp_directory.clone(), program_directory.to_string().into())?;
                 Ok(i)
             })
             .collect::<Result<Vec<_>, std::io::Error>>()
@@ -596,43 +650,49 @@ impl Game {
         let window_border = !settings.dont_draw_border;
         let window_icons = !settings.dont_show_buttons;
 
-        let connection = ramen::connection::Connection::new()?;
-        #[cfg(unix)]
-        unsafe {
-            let display = connection.xdisplay();
-            let screen = connection.xscreenid();
-            crate::render::opengl::glx::glx_init(display, screen);
-        }
-
-        #[allow(unused_mut)]
-        let mut builder = connection
-            .builder()
-            .class_name("OpenGMK")
-            .visible(false)
-            .size((width as _, height as _))
-            .borderless(!window_border && play_type != PlayType::Record)
-            .title(room1_caption.to_owned())
-            .resizable(match play_type {
-                PlayType::Normal => settings.allow_resize,
-                PlayType::Record => true,
-                PlayType::Replay => false,
-            })
-            .controls(if play_type == PlayType::Record {
-                Some(Controls::new())
-            } else if window_icons {
-                Some(Controls::new().minimise(settings.allow_resize).maximise(settings.allow_resize))
-            } else {
-                None
-            });
+        // Headless mode has no window at all, so there's nothing to connect to either
+        let display = if headless {
+            None
+        } else {
+            let connection = ramen::connection::Connection::new()?;
+            #[cfg(unix)]
+            unsafe {
+                let display = connection.xdisplay();
+                let screen = connection.xscreenid();
+                crate::render::opengl::glx::glx_init(display, screen);
+            }
 
-        // if unix... pass visual...
-        #[cfg(unix)]
-        unsafe {
-            let glx = crate::render::opengl::glx::GLX.as_ref().unwrap();
-            builder = builder.depth(glx.depth).visual(glx.visual);
-        }
+            #[allow(unused_mut)]
+            let mut builder = connection
+                .builder()
+                .class_name("OpenGMK")
+                .visible(false)
+                .size((width as _, height as _))
+                .borderless(!window_border && play_type != PlayType::Record)
+                .title(room1_caption.to_owned())
+                .resizable(match play_type {
+                    PlayType::Normal => settings.allow_resize,
+                    PlayType::Record => true,
+                    PlayType::Replay => false,
+                })
+                .controls(if play_type == PlayType::Record {
+                    Some(Controls::new())
+                } else if window_icons {
+                    Some(Controls::new().minimise(settings.allow_resize).maximise(settings.allow_resize))
+                } else {
+                    None
+                });
+
+            // if unix... pass visual...
+            #[cfg(unix)]
+            unsafe {
+                let glx = crate::render::opengl::glx::GLX.as_ref().unwrap();
+                builder = builder.depth(glx.depth).visual(glx.visual);
+            }
 
-        let window = builder.build()?;
+            let window = builder.build()?;
+            Some((connection, window))
+        };
         let ffmpeg_recorder = capture_recording.then(|| {
             Command::new("ffmpeg")
                 .arg("-y")
@@ -662,11 +722,20 @@ impl Game {
         });
 
         // Set up audio manager
-        let mut audio = audio::AudioManager::new(play_type != PlayType::Record, capture_recording);
+        let mut audio = if headless {
+            audio::AudioManager::new_headless()
+        } else {
+            audio::AudioManager::new(play_type != PlayType::Record, capture_recording)
+        };
 
         // TODO: specific flags here (make wb mutable)
 
-        let mut renderer = Renderer::new((), &connection, &options, &window, settings.clear_colour.into())?;
+        let backend = match &display {
+            Some((connection, window)) => render::Backend::OpenGL { connection, window },
+            None if software_render => render::Backend::Software,
+            None => render::Backend::Headless,
+        };
+        let mut renderer = Renderer::new(backend, &options, settings.clear_colour.into())?;
 
         let mut atlases = AtlasBuilder::new(renderer.max_texture_size() as _);
 
@@ -710,8 +779,8 @@ impl Game {
                                 &*function.external_name.0
                             });
                             let sym = &*symbol.decode(match gm_version {
-                                Version::GameMaker8_0 => encoding,
                                 Version::GameMaker8_1 => encoding_rs::UTF_8,
+                                _ => encoding,
                             });
                             match externals.define(external::dll::ExternalSignature {
                                 dll: dll.to_string(),
@@ -811,7 +880,20 @@ impl Game {
             .map(|(sound_id, o)| {
                 o.map(|b| {
                     use asset::sound::FileType;
+                    use audio::effects::{self, EffectParams};
                     use gm8exe::asset::sound::SoundKind;
+                    // The effects checked in the sound editor are the ones it starts with
+                    let fx = [
+                        (b.fx.chorus, effects::CHORUS),
+                        (b.fx.echo, effects::ECHO),
+                        (b.fx.flanger, effects::FLANGER),
+                        (b.fx.gargle, effects::GARGLE),
+                        (b.fx.reverb, effects::REVERB),
+                    ];
+                    let flags = fx.iter().filter(|(checked, _)| *checked).fold(0, |flags, (_, flag)| flags | flag);
+                    if flags != 0 {
+                        audio.set_effect_params(sound_id as i32, EffectParams { flags, ..Default::default() });
+                    }
                     let handle = match b.data {
                         Some(data) => match b.extension.0.as_ref() {
                             b".mp3" => match audio.add_mp3(data, sound_id as i32) {
@@ -936,8 +1018,8 @@ impl Game {
                 o.map(|b| {
                     let mut tallest_char_height = 0;
                     let charset = match gm_version {
-                        Version::GameMaker8_0 => 1, // DEFAULT_CHARSET
                         Version::GameMaker8_1 => b.charset,
+                        _ => 1, // DEFAULT_CHARSET
                     };
                     let chars = b
                         .dmap
@@ -1337,6 +1419,8 @@ impl Game {
             uninit_args_are_zero: !settings.error_on_uninitialized_args,
             swap_creation_events: settings.swap_creation_events,
             potential_step_settings: Default::default(),
+            message_settings: Default::default(),
+            highscore_settings: Default::default(),
             transition_kind: 0,
             transition_steps: 80,
             cursor_sprite: -1,
@@ -1355,10 +1439,14 @@ impl Game {
             open_ini: None,
             open_file: None,
             file_finder: None,
+            registry: Default::default(),
+            vfs,
             clock: GameClock::SpoofedNanos(0), // to avoid accessing the system timer for now
             frame_limiter,
             frame_limit_at,
             ffmpeg_recorder,
+            state_hash_log: None,
+            frame_dump: None,
             fps: 0,
             frame_counter: 0,
             parameters: game_arguments,
@@ -1371,7 +1459,7 @@ impl Game {
             error_occurred: false,
             error_last: "".to_string().into(),
             audio,
-            window,
+            window: display.map(|(_, window)| window),
             window_border,
             window_icons,
             close_requested: false,
@@ -1382,6 +1470,7 @@ impl Game {
             // load_room sets this
             unscaled_width: 0,
             unscaled_height: 0,
+            ui_window_size: (width, height),
 
             // lazy state
             window_caption: room1_caption.clone(),
@@ -1394,6 +1483,9 @@ impl Game {
         };
 
         game.temp_directory = game.encode_str_maybe(temp_directory.to_str().unwrap()).unwrap().into_owned().into();
+        if let Err(e) = game.registry.open(temp_directory.join(registry::FILE_NAME)) {
+            eprintln!("Could not load the registry file, starting with an empty registry: {}", e);
+        }
 
         // Evaluate constants
         for extension in extensions {
@@ -1427,7 +1519,9 @@ impl Game {
         game.globals.vars.clear();
         game.globalvars.clear();
 
-        game.window.set_visible(true);
+        if let Some(window) = &mut game.window {
+            window.set_visible(true);
+        }
 
         Ok(game)
     }
@@ -1526,25 +1620,27 @@ impl Game {
             };
             if self.play_type != PlayType::Record {
                 self.window_inner_size = (width, height);
-                self.window.set_size((width as _, height as _));
+                if let Some(window) = &mut self.window {
+                    window.set_size((width as _, height as _));
+                }
             }
         }
     }
 
     pub fn decode_str<'a>(&self, string: &'a [u8]) -> Cow<'a, str> {
         match self.gm_version {
-            Version::GameMaker8_0 => self.encoding.decode_without_bom_handling(string).0,
             Version::GameMaker8_1 => String::from_utf8_lossy(string),
+            _ => self.encoding.decode_without_bom_handling(string).0,
         }
     }
 
     pub fn encode_str_maybe<'a>(&self, utf8: &'a str) -> Option<Cow<'a, [u8]>> {
         match self.gm_version {
-            Version::GameMaker8_0 => {
+            Version::GameMaker8_1 => Some(Cow::from(utf8.as_bytes())),
+            _ => {
                 let (encoded, _, is_bad) = self.encoding.encode(utf8);
                 if is_bad { None } else { Some(encoded) }
             },
-            Version::GameMaker8_1 => Some(Cow::from(utf8.as_bytes())),
         }
     }
 
@@ -1853,9 +1949,11 @@ impl Game {
         use std::io::Read;
         self.input.keyboard_clear_all();
         self.input.mouse_clear_all();
-        let mut file = std::fs::File::open(path)
-            .map(std::io::BufReader::new)
+        let data = self
+            .vfs
+            .read(&file::to_path(&path.to_string_lossy()))
             .map_err(|e| gml::Error::FunctionError("game_load".into(), format!("{}", e)))?;
+        let mut file = data.as_slice();
         let mut magnum = [0u8; 4];
         file.read(&mut magnum).map_err(|e| gml::Error::FunctionError("game_load".into(), format!("{}", e)))?;
         if magnum != [0x1d, 0x02, 0x00, 0x00] {
@@ -1878,6 +1976,14 @@ impl Game {
             return Ok(());
         }
 
+        // Advance any sounds which are fading
+        for (sound_id, volume) in self.audio.update_fades(self.clock.as_nanos()) {
+            let sound = self.assets.sounds.get_asset(sound_id);
+            if let Some(asset::sound::FileType::Wav(handle)) = sound.map(|s| &s.handle) {
+                handle.set_volume(volume);
+            }
+        }
+
         // Update xprevious and yprevious for all instances
         let mut iter = self.room.instance_list.iter_by_drawing();
         while let Some(instance) = iter.next(&self.room.instance_list).map(|x| self.room.instance_list.get(x)) {
@@ -2073,10 +2179,14 @@ impl Game {
 
     pub fn process_window_events(&mut self) {
         self.input.mouse_step();
-        self.window.poll_events();
+        let window = match self.window.as_mut() {
+            Some(window) => window,
+            None => return,
+        };
+        window.poll_events();
         match self.play_type {
             PlayType::Normal => {
-                for event in self.window.events().into_iter().copied() {
+                for event in window.events().into_iter().copied() {
                     match event {
                         Event::KeyboardDown(key) => self.input.button_press(input::ramen2vk(key), true),
                         Event::KeyboardUp(key) => self.input.button_release(input::ramen2vk(key), true),
@@ -2300,6 +2410,7 @@ impl Game {
         }
 
         self.input.mouse_move_to((frame.mouse_x as i32, frame.mouse_y as i32));
+        self.input.set_joysticks(frame.joysticks);
         for ev in frame.inputs.iter() {
             match ev {
                 replay::Input::KeyPress(v) => self.input.button_press(*v as u8, true),
@@ -2318,7 +2429,7 @@ impl Game {
         replay: Replay,
         output_bin: Option<PathBuf>,
         start_save_path: Option<&PathBuf>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<ReplaySummary, Box<dyn std::error::Error>> {
         let mut frame_count: usize = 0;
         self.rand.set_seed(replay.start_seed);
         self.clock = GameClock::SpoofedNanos(replay.start_time);
@@ -2349,6 +2460,7 @@ impl Game {
                 },
             }
         } else {
+            self.registry.load_state(replay.start_registry.clone());
             for ev in replay.startup_events.iter() {
                 self.stored_events.push_back(ev.clone());
             }
@@ -2357,10 +2469,17 @@ impl Game {
         }
 
         let mut time_now = Instant::now();
-        return loop {
-            self.window.poll_events();
+        let result = loop {
+            if let Some(window) = &mut self.window {
+                window.poll_events();
+            }
             self.input.mouse_step();
 
+            // Render audio up to the start of this frame, so sounds begin at the time of the frame they were played in
+            if let Err(e) = self.audio.render_until(self.clock.as_nanos()) {
+                break Err(format!("Error writing audio: {}", e).into())
+            }
+
             if self.frame_limit_at > 0 && frame_count == self.frame_limit_at || frame_count == replay.frame_count() {
                 if let Some(bin) = &output_bin {
                     if start_save_path.is_some() {
@@ -2374,13 +2493,17 @@ impl Game {
                     match SaveState::from(&self, new_replay, render_state, clean_state)
                         .save_to_file(bin, &mut savestate::Buffer::new())
                     {
-                        Ok(()) => break Ok(()),
+                        Ok(()) => break Ok(self.replay_summary(frame_count)),
                         Err(e) => break Err(format!("Error saving to {:?}: {:?}", output_bin, e).into()),
                     }
                 }
                 if let Some(recorder) = self.ffmpeg_recorder.take() {
                     self.stop_recording_capture(recorder);
-                    break Ok(());
+                    break Ok(self.replay_summary(frame_count));
+                }
+                // Nobody can watch the game past the end of the replay without a window, so stop here
+                if self.window.is_none() && frame_count == replay.frame_count() {
+                    break Ok(self.replay_summary(frame_count));
                 }
             }
 
@@ -2397,8 +2520,13 @@ impl Game {
                 self.set_input_from_frame(frame);
             }
 
+            let frame_start = self.clock.as_nanos();
             self.frame()?;
             self.capture_recording_frame(&mut current_frame_time, self.room.speed);
+            let frame_end = frame_start + u128::from(1_000_000_000 / self.room.speed);
+            if let Err(e) = self.dump_frame(frame_start, frame_end) {
+                break Err(format!("Error dumping frame {}: {}", frame_count, e).into())
+            }
 
             match self.scene_change {
                 Some(SceneChange::Room(id)) => self.load_room(id)?,
@@ -2410,10 +2538,12 @@ impl Game {
                 },
                 None => (),
             }
+            self.log_state_hash(frame_count)?;
 
             // exit if X pressed or game_end() invoked
             if self.close_requested {
-                break Ok(self.run_game_end_events()?);
+                self.run_game_end_events()?;
+                break Ok(self.replay_summary(frame_count + 1));
             }
 
             // frame limiter
@@ -2440,6 +2570,25 @@ impl Game {
 
             frame_count += 1;
         };
+
+        // The WAV header can only be filled in once all the audio has been written
+        let finished = self.audio.finish_render().and(self.frame_dump.as_mut().map_or(Ok(()), |dump| dump.finish()));
+        match finished {
+            Ok(()) => result,
+            Err(e) => result.and(Err(format!("Error finishing output files: {}", e).into())),
+        }
+    }
+
+    fn replay_summary(&self, frames: usize) -> ReplaySummary {
+        ReplaySummary {
+            frames,
+            room_id: self.room.id,
+            room_name: match self.assets.rooms.get_asset(self.room.id) {
+                Some(room) => self.decode_str(room.name.as_ref()).into_owned(),
+                None => String::new(),
+            },
+            seed: self.rand.seed(),
+        }
     }
 
     fn stop_recording_capture(&mut self, recorder: Child) {
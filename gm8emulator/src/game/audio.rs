@@ -1,10 +1,15 @@
+pub mod effects;
 mod mixer;
 mod mp3;
+pub mod spatial;
+mod wav_writer;
 
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
-    io::Write,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
     process::{self, Child, Stdio},
     sync::{
         atomic::{AtomicU32, Ordering},
@@ -22,8 +27,11 @@ use udon::{
 };
 
 use self::{
+    effects::EffectParams,
     mixer::{Mixer, MixerHandle},
     mp3::Mp3Player,
+    spatial::Sound3D,
+    wav_writer::WavWriter,
 };
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -36,7 +44,7 @@ pub struct Mp3Handle {
 pub struct WavHandle {
     player: WavPlayer,
     params: Arc<SoundParams>,
-    _use_3d: bool,
+    use_3d: bool,
     exclusive: bool,
     id: i32,
 }
@@ -44,6 +52,39 @@ pub struct WavHandle {
 #[derive(Serialize, Deserialize)]
 pub struct SoundParams {
     pub volume: AtomicU32,
+    pub pan: AtomicU32,         // from -1 (left) to 1 (right)
+    pub attenuation: AtomicU32, // how much quieter 3D sounds are, multiplied with volume
+}
+
+/// The layout of WavHandle in version 1 savestates, which didn't have panning or 3D sound.
+#[derive(Serialize, Deserialize)]
+pub struct WavHandleV1 {
+    player: WavPlayer,
+    params: Arc<SoundParamsV1>,
+    use_3d: bool,
+    exclusive: bool,
+    id: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SoundParamsV1 {
+    volume: AtomicU32,
+}
+
+impl From<WavHandleV1> for WavHandle {
+    fn from(handle: WavHandleV1) -> Self {
+        Self {
+            player: handle.player,
+            params: Arc::new(SoundParams {
+                volume: AtomicU32::new(handle.params.volume.load(Ordering::Acquire)),
+                pan: AtomicU32::new(0.0f32.to_bits()),
+                attenuation: AtomicU32::new(1.0f32.to_bits()),
+            }),
+            use_3d: handle.use_3d,
+            exclusive: handle.exclusive,
+            id: handle.id,
+        }
+    }
 }
 
 pub struct AudioManager {
@@ -56,7 +97,18 @@ pub struct AudioManager {
     global_volume: Arc<AtomicU32>,
     end_times: HashMap<i32, Option<u128>>,
     multimedia_end: Option<(i32, Option<u128>)>,
+    effects: HashMap<i32, EffectParams>,
+    fades: HashMap<i32, Fade>,
+    sounds_3d: HashMap<i32, Sound3D>,
     audio_recorder: Option<Child>,
+    wav_render: Option<WavRender>,
+}
+
+/// Mixed audio being written to a WAV file in step with the game clock, rather than played.
+struct WavRender {
+    writer: WavWriter<BufWriter<File>>,
+    start_time: Option<u128>,
+    frames_written: u128,
 }
 
 pub struct InterprocessSource {
@@ -138,7 +190,11 @@ impl AudioManager {
                 global_volume,
                 end_times: HashMap::new(),
                 multimedia_end: None,
+                effects: HashMap::new(),
+                fades: HashMap::new(),
+                sounds_3d: HashMap::new(),
                 audio_recorder: audio_recorder,
+                wav_render: None,
             };
         } else {
             std::thread::spawn(move || {
@@ -157,11 +213,41 @@ impl AudioManager {
                 global_volume,
                 end_times: HashMap::new(),
                 multimedia_end: None,
+                effects: HashMap::new(),
+                fades: HashMap::new(),
+                sounds_3d: HashMap::new(),
                 audio_recorder: audio_recorder,
+                wav_render: None,
             }
         }
     }
 
+    /// Creates an AudioManager which doesn't open an output device, for machines without one.
+    /// Sound end times are still tracked, so sound_isplaying() behaves the same as it does normally.
+    pub fn new_headless() -> Self {
+        let sample_rate = SampleRate::new(48000).unwrap();
+        let channel_count = ChannelCount::new(2).unwrap();
+        let global_volume = Arc::new(AtomicU32::from(1.0f32.to_bits()));
+        let (_, mixer_handle) = Mixer::new(sample_rate, channel_count, global_volume.clone());
+
+        Self {
+            mixer: None,
+            sample_sender: None,
+            mixer_handle,
+            mixer_channel_count: channel_count,
+            mixer_sample_rate: sample_rate,
+            do_output: false,
+            global_volume,
+            end_times: HashMap::new(),
+            multimedia_end: None,
+            effects: HashMap::new(),
+            fades: HashMap::new(),
+            sounds_3d: HashMap::new(),
+            audio_recorder: None,
+            wav_render: None,
+        }
+    }
+
     pub fn capture_audio(&mut self) {
         if let Some(mixer) = &mut self.mixer {
             // samplerate / framerate * channels
@@ -184,6 +270,51 @@ impl AudioManager {
         }
     }
 
+    /// Stops playing audio and mixes it into a WAV file instead, as fast as render_until() is called.
+    /// This works without an output device, so it can be used while headless.
+    pub fn render_to_wav(&mut self, path: &Path) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        let writer = WavWriter::new(writer, self.mixer_sample_rate.into(), self.mixer_channel_count.into())?;
+        // sounds go to a new mixer which only gets run by render_until(), so the output device goes quiet
+        let (mixer, mixer_handle) =
+            Mixer::new(self.mixer_sample_rate, self.mixer_channel_count, self.global_volume.clone());
+        self.mixer = Some(mixer);
+        self.mixer_handle = mixer_handle;
+        self.sample_sender = None;
+        self.do_output = true;
+        self.wav_render = Some(WavRender { writer, start_time: None, frames_written: 0 });
+        Ok(())
+    }
+
+    /// Mixes all the audio up to a point on the game clock into the WAV file, if rendering to one.
+    /// Sounds played since the last call start at the beginning of what's mixed, so calling this at the start of
+    /// every frame puts each sound at the time of the frame it was played in. The first call sets the start time.
+    pub fn render_until(&mut self, time: u128) -> io::Result<()> {
+        if let (Some(render), Some(mixer)) = (&mut self.wav_render, &mut self.mixer) {
+            let start_time = *render.start_time.get_or_insert(time);
+            let sample_rate = u128::from(u32::from(self.mixer_sample_rate));
+            let channels = usize::from(u16::from(self.mixer_channel_count));
+            // worked out from the start every time, so rounding errors don't add up
+            let frames = time.saturating_sub(start_time) * sample_rate / 1_000_000_000;
+            let mut buffer = vec![0.0; (frames.saturating_sub(render.frames_written) as usize) * channels];
+            if !buffer.is_empty() {
+                mixer.write_samples(&mut buffer);
+                render.writer.write_samples(&buffer)?;
+                render.frames_written = frames;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finishes writing the WAV file, if rendering to one. This also happens if the AudioManager is dropped,
+    /// but errors can't be reported then.
+    pub fn finish_render(&mut self) -> io::Result<()> {
+        match self.wav_render.take() {
+            Some(mut render) => render.writer.finish(),
+            None => Ok(()),
+        }
+    }
+
     pub fn stop_audio_capture(&mut self) {
         if let Some(recorder) = self.audio_recorder.take() {
             recorder.wait_with_output().expect("audio recorder should close");
@@ -205,8 +336,12 @@ impl AudioManager {
         WavPlayer::new(file)
             .map(|player| WavHandle {
                 player,
-                params: Arc::new(SoundParams { volume: AtomicU32::new(make_volume(volume).to_bits()) }),
-                _use_3d: use_3d,
+                params: Arc::new(SoundParams {
+                    volume: AtomicU32::new(make_volume(volume).to_bits()),
+                    pan: AtomicU32::new(0.0f32.to_bits()),
+                    attenuation: AtomicU32::new(1.0f32.to_bits()),
+                }),
+                use_3d,
                 exclusive,
                 id: sound_id,
             })
@@ -260,6 +395,7 @@ impl AudioManager {
                     ),
                     handle.params.clone(),
                     handle.id,
+                    self.sound_effects(handle.id),
                 );
             }
         }
@@ -302,6 +438,7 @@ impl AudioManager {
                     )),
                     handle.params.clone(),
                     handle.id,
+                    self.sound_effects(handle.id),
                 );
             }
         }
@@ -352,13 +489,69 @@ impl AudioManager {
             global_volume: self.global_volume.clone(),
             end_times: self.end_times.clone(),
             multimedia_end: self.multimedia_end,
+            effects: self.effects.clone(),
+            fades: self.fades.clone(),
+            sounds_3d: self.sounds_3d.clone(),
         }
     }
 
     pub fn set_state(&mut self, state: AudioState) {
-        self.global_volume = state.global_volume;
+        // the mixer shares the global volume, so it has to be updated rather than replaced
+        self.global_volume.store(state.global_volume.load(Ordering::Acquire), Ordering::Release);
         self.end_times = state.end_times;
         self.multimedia_end = state.multimedia_end;
+        self.fades = state.fades;
+        self.sounds_3d = state.sounds_3d;
+        let old_effects = std::mem::replace(&mut self.effects, state.effects);
+        if self.do_output {
+            for id in old_effects.keys().chain(self.effects.keys()) {
+                let _ = self.mixer_handle.set_effects(*id, self.sound_effects(*id));
+            }
+        }
+    }
+
+    /// Gets the effect parameters for a sound, which are the defaults if none have been set.
+    pub fn effect_params(&self, sound_id: i32) -> EffectParams {
+        self.effects.get(&sound_id).cloned().unwrap_or_default()
+    }
+
+    /// Changes the effect parameters for a sound. Any instances of it which are playing are updated too.
+    pub fn set_effect_params(&mut self, sound_id: i32, params: EffectParams) {
+        self.effects.insert(sound_id, params);
+        if self.do_output {
+            let _ = self.mixer_handle.set_effects(sound_id, self.sound_effects(sound_id));
+        }
+    }
+
+    /// Starts fading a sound's volume from one level to another over a duration in nanoseconds.
+    /// The fade is advanced by update_fades(), which the caller should apply to the sound.
+    pub fn fade(&mut self, sound_id: i32, from: f64, to: f64, start_time: u128, duration: u128) {
+        self.fades.insert(sound_id, Fade { from, to, start_time, duration });
+    }
+
+    pub fn stop_fade(&mut self, sound_id: i32) {
+        self.fades.remove(&sound_id);
+    }
+
+    /// Gets the volume each fading sound should have at the current time. Finished fades are removed.
+    pub fn update_fades(&mut self, current_time: u128) -> Vec<(i32, f64)> {
+        let volumes = self.fades.iter().map(|(id, fade)| (*id, fade.volume_at(current_time))).collect();
+        self.fades.retain(|_, fade| fade.start_time + fade.duration > current_time);
+        volumes
+    }
+
+    /// Gets the 3D settings for a sound, which are the defaults if none have been set.
+    pub fn sound_3d(&self, sound_id: i32) -> Sound3D {
+        self.sounds_3d.get(&sound_id).copied().unwrap_or_default()
+    }
+
+    pub fn set_sound_3d(&mut self, sound_id: i32, sound_3d: Sound3D) {
+        self.sounds_3d.insert(sound_id, sound_3d);
+    }
+
+    // The effects to give the mixer for a sound, if it has any enabled
+    fn sound_effects(&self, sound_id: i32) -> Option<EffectParams> {
+        self.effects.get(&sound_id).filter(|fx| fx.flags != 0).cloned()
     }
 }
 
@@ -366,6 +559,27 @@ impl WavHandle {
     pub fn set_volume(&self, vol: f64) {
         self.params.volume.store(make_volume(vol).to_bits(), Ordering::Release);
     }
+
+    /// Gets the volume as it was last set, between 0 and 1.
+    pub fn volume(&self) -> f64 {
+        unmake_volume(f32::from_bits(self.params.volume.load(Ordering::Acquire)))
+    }
+
+    /// Sets the pan between -1 (left) and 1 (right). 3D sounds are panned by where they are instead.
+    pub fn set_pan(&self, pan: f64) {
+        if !self.use_3d {
+            self.params.pan.store((pan.clamp(-1.0, 1.0) as f32).to_bits(), Ordering::Release);
+        }
+    }
+
+    /// Updates the attenuation and pan of a 3D sound. Does nothing to other sounds.
+    pub fn set_3d(&self, sound_3d: &Sound3D) {
+        if self.use_3d {
+            let gain = sound_3d.distance_gain() * sound_3d.cone_gain(f64::from(make_volume(sound_3d.cone_volume)));
+            self.params.attenuation.store((gain as f32).to_bits(), Ordering::Release);
+            self.params.pan.store((sound_3d.pan() as f32).to_bits(), Ordering::Release);
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -373,6 +587,50 @@ pub struct AudioState {
     global_volume: Arc<AtomicU32>,
     end_times: HashMap<i32, Option<u128>>,
     multimedia_end: Option<(i32, Option<u128>)>,
+    effects: HashMap<i32, EffectParams>,
+    fades: HashMap<i32, Fade>,
+    sounds_3d: HashMap<i32, Sound3D>,
+}
+
+/// The layout of AudioState in version 1 savestates, which didn't have effects, fades or 3D sounds.
+#[derive(Serialize, Deserialize)]
+pub struct AudioStateV1 {
+    global_volume: Arc<AtomicU32>,
+    end_times: HashMap<i32, Option<u128>>,
+    multimedia_end: Option<(i32, Option<u128>)>,
+}
+
+impl From<AudioStateV1> for AudioState {
+    fn from(state: AudioStateV1) -> Self {
+        Self {
+            global_volume: state.global_volume,
+            end_times: state.end_times,
+            multimedia_end: state.multimedia_end,
+            effects: HashMap::new(),
+            fades: HashMap::new(),
+            sounds_3d: HashMap::new(),
+        }
+    }
+}
+
+/// A change in a sound's volume over time, as started by sound_fade().
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct Fade {
+    from: f64,
+    to: f64,
+    start_time: u128,
+    duration: u128,
+}
+
+impl Fade {
+    fn volume_at(&self, time: u128) -> f64 {
+        let elapsed = time.saturating_sub(self.start_time);
+        if elapsed >= self.duration {
+            self.to
+        } else {
+            self.from + (self.to - self.from) * (elapsed as f64 / self.duration as f64)
+        }
+    }
 }
 
 fn length_to_ns(sample_count: usize, sample_rate: u32, channels: u16) -> u128 {
@@ -385,3 +643,15 @@ fn length_to_ns(sample_count: usize, sample_rate: u32, channels: u16) -> u128 {
 fn make_volume(vol: f64) -> f32 {
     1000.0f64.powf(vol.clamp(0.0, 1.0) - 1.0) as f32
 }
+
+// The inverse of make_volume(). The minimum volume comes back as 0.
+fn unmake_volume(vol: f32) -> f64 {
+    (f64::from(vol).log(1000.0) + 1.0).clamp(0.0, 1.0)
+}
+
+// Gets the multipliers for the left and right channels from a pan between -1 and 1.
+// Like the volume, the side being panned away from gets quieter on a logarithmic scale.
+fn make_pan(pan: f32) -> (f32, f32) {
+    let pan = f64::from(pan.clamp(-1.0, 1.0));
+    (make_volume(1.0 - pan.max(0.0)), make_volume(1.0 + pan.min(0.0)))
+}
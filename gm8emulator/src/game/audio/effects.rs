@@ -0,0 +1,679 @@
+//! Sound effects, approximating the DirectX 8 effects GM8 applies with sound_effect_set().
+//!
+//! The parameters have the same meanings, ranges and defaults as DirectX's, since GML passes them straight through.
+//! The processing is much simpler than DirectX's, so the effects won't sound exactly the same, but they're close
+//! enough to recognise and they're deterministic, which matters more for recording.
+
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+use udon::source::Sample;
+
+// Flags for EffectParams::flags, matching the se_* constants
+pub const CHORUS: u32 = 1;
+pub const ECHO: u32 = 2;
+pub const FLANGER: u32 = 4;
+pub const GARGLE: u32 = 8;
+pub const REVERB: u32 = 16;
+pub const COMPRESSOR: u32 = 32;
+pub const EQUALIZER: u32 = 64;
+
+/// Parameters for chorus and flanger, which are the same effect with different delay ranges.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Modulation {
+    pub wetdry: f32,    // 0 to 100, percentage of the output which is processed
+    pub depth: f32,     // 0 to 100, how far the delay is swept
+    pub feedback: f32,  // -99 to 99, percentage of the output fed back in
+    pub frequency: f32, // 0 to 10 Hz (LFO)
+    pub wave: i32,      // 0 = triangle, 1 = sine
+    pub delay: f32,     // 0 to 20 ms for chorus, 0 to 4 ms for flanger
+    pub phase: i32,     // 0 to 4, the LFO phase difference between left and right in steps of 90 degrees from -180
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Echo {
+    pub wetdry: f32,      // 0 to 100
+    pub feedback: f32,    // 0 to 100
+    pub left_delay: f32,  // 1 to 2000 ms
+    pub right_delay: f32, // 1 to 2000 ms
+    pub pan_delay: bool,  // whether echoes swap sides
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gargle {
+    pub rate: f32, // 1 to 1000 Hz
+    pub wave: i32, // 0 = triangle, 1 = square
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Reverb {
+    pub gain: f32,  // -96 to 0 dB, applied to the input
+    pub mix: f32,   // -96 to 0 dB, how loud the reverb is
+    pub time: f32,  // 0.001 to 3000 ms
+    pub ratio: f32, // 0.001 to 0.999, how much faster high frequencies die out
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Compressor {
+    pub gain: f32,      // -60 to 60 dB, applied after compression
+    pub attack: f32,    // 0.01 to 500 ms
+    pub release: f32,   // 50 to 3000 ms
+    pub threshold: f32, // -60 to 0 dB
+    pub ratio: f32,     // 1 to 100
+    pub delay: f32,     // 0 to 4 ms, how far ahead the compressor looks
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Equalizer {
+    pub center: f32,    // 80 to 16000 Hz
+    pub bandwidth: f32, // 1 to 36 semitones
+    pub gain: f32,      // -15 to 15 dB
+}
+
+/// The effects set on a sound, along with the parameters of every effect, including ones which aren't enabled.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EffectParams {
+    pub flags: u32,
+    pub chorus: Modulation,
+    pub echo: Echo,
+    pub flanger: Modulation,
+    pub gargle: Gargle,
+    pub reverb: Reverb,
+    pub compressor: Compressor,
+    pub equalizer: Equalizer,
+}
+
+impl Default for EffectParams {
+    fn default() -> Self {
+        Self {
+            flags: 0,
+            chorus: Modulation {
+                wetdry: 50.0,
+                depth: 10.0,
+                feedback: 25.0,
+                frequency: 1.1,
+                wave: 1,
+                delay: 16.0,
+                phase: 3,
+            },
+            echo: Echo { wetdry: 50.0, feedback: 50.0, left_delay: 500.0, right_delay: 500.0, pan_delay: false },
+            flanger: Modulation {
+                wetdry: 50.0,
+                depth: 100.0,
+                feedback: -50.0,
+                frequency: 0.25,
+                wave: 1,
+                delay: 2.0,
+                phase: 2,
+            },
+            gargle: Gargle { rate: 20.0, wave: 0 },
+            reverb: Reverb { gain: 0.0, mix: 0.0, time: 1000.0, ratio: 0.001 },
+            compressor: Compressor {
+                gain: 0.0,
+                attack: 10.0,
+                release: 200.0,
+                threshold: -20.0,
+                ratio: 3.0,
+                delay: 4.0,
+            },
+            equalizer: Equalizer { center: 8000.0, bandwidth: 12.0, gain: 0.0 },
+        }
+    }
+}
+
+fn db_to_amplitude(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
+
+fn ms_to_samples(ms: f32, sample_rate: f32) -> f32 {
+    ms * sample_rate / 1000.0
+}
+
+// Anything quieter than this (-80dB) counts as silence when waiting for an effect's tail to die away
+const SILENCE: f32 = 1e-4;
+
+/// Processes one frame (one sample per channel) at a time.
+trait Effect {
+    fn process(&mut self, frame: &mut [Sample]);
+
+    /// Changes the effect's parameters without resetting its state.
+    fn set(&mut self, params: &EffectParams);
+
+    /// How many frames of past input the effect holds, so how long it can stay silent before sounding again.
+    fn memory(&self) -> usize {
+        0
+    }
+}
+
+/// A DSP effect chain for one playing sound. Effects are applied in the order of their flags.
+pub struct EffectChain {
+    flags: u32,
+    channels: usize,
+    effects: Vec<Box<dyn Effect + Send>>,
+    draining: bool,
+    silent_frames: usize,
+}
+
+impl EffectChain {
+    pub fn new(params: &EffectParams, sample_rate: u32, channels: u16) -> Self {
+        let rate = sample_rate as f32;
+        let channels = usize::from(channels.max(1));
+        let mut effects: Vec<Box<dyn Effect + Send>> = Vec::new();
+        if params.flags & CHORUS != 0 {
+            effects.push(Box::new(ModulatedDelay::new(CHORUS, params, rate, channels)));
+        }
+        if params.flags & ECHO != 0 {
+            effects.push(Box::new(EchoEffect::new(params, rate, channels)));
+        }
+        if params.flags & FLANGER != 0 {
+            effects.push(Box::new(ModulatedDelay::new(FLANGER, params, rate, channels)));
+        }
+        if params.flags & GARGLE != 0 {
+            effects.push(Box::new(GargleEffect::new(params, rate)));
+        }
+        if params.flags & REVERB != 0 {
+            effects.push(Box::new(ReverbEffect::new(params, rate, channels)));
+        }
+        if params.flags & COMPRESSOR != 0 {
+            effects.push(Box::new(CompressorEffect::new(params, rate, channels)));
+        }
+        if params.flags & EQUALIZER != 0 {
+            effects.push(Box::new(EqualizerEffect::new(params, rate, channels)));
+        }
+        Self { flags: params.flags, channels, effects, draining: false, silent_frames: 0 }
+    }
+
+    /// The flags of the effects in this chain.
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Changes the parameters of the effects in place, so the sound carries on without clicking.
+    /// `params` must have the same flags as the chain, otherwise a new chain is needed.
+    pub fn update(&mut self, params: &EffectParams) {
+        debug_assert_eq!(params.flags, self.flags);
+        for effect in self.effects.iter_mut() {
+            effect.set(params);
+        }
+    }
+
+    /// Processes a buffer of interleaved samples in place.
+    pub fn process(&mut self, buffer: &mut [Sample]) {
+        if self.effects.is_empty() {
+            return
+        }
+        for frame in buffer.chunks_exact_mut(self.channels) {
+            for effect in self.effects.iter_mut() {
+                effect.process(frame);
+            }
+        }
+    }
+
+    /// Processes a buffer which the sound ran out partway through, with silence after the end of the sound.
+    /// Returns whether the effects may still be sounding, in which case the chain should be drained again
+    /// with a buffer of silence.
+    pub fn drain(&mut self, buffer: &mut [Sample]) -> bool {
+        self.draining = true;
+        self.process(buffer);
+        for frame in buffer.chunks_exact(self.channels) {
+            if frame.iter().all(|sample| sample.abs() < SILENCE) {
+                self.silent_frames += 1;
+            } else {
+                self.silent_frames = 0;
+            }
+        }
+        // the effects are in series, so a delay in each one adds up
+        self.silent_frames <= self.effects.iter().map(|effect| effect.memory()).sum()
+    }
+
+    /// Whether the sound has ended and only the effects' tail is left.
+    pub fn is_draining(&self) -> bool {
+        self.draining
+    }
+}
+
+/// A ring buffer of past samples.
+struct DelayLine {
+    buffer: Vec<Sample>,
+    pos: usize,
+}
+
+impl DelayLine {
+    // reading needs at least one sample either side of the last one written
+    const MIN_LEN: usize = 3;
+
+    fn new(len: usize) -> Self {
+        Self { buffer: vec![0.0; len.max(Self::MIN_LEN)], pos: 0 }
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Changes the length of the line, keeping as many of the most recent samples as fit.
+    fn resize(&mut self, len: usize) {
+        let len = len.max(Self::MIN_LEN);
+        let old_len = self.buffer.len();
+        if len == old_len {
+            return
+        }
+        let mut buffer = vec![0.0; len];
+        for i in 0..len.min(old_len) {
+            buffer[len - 1 - i] = self.buffer[(self.pos + old_len - i) % old_len];
+        }
+        self.buffer = buffer;
+        self.pos = len - 1;
+    }
+
+    fn write(&mut self, sample: Sample) {
+        self.pos = (self.pos + 1) % self.buffer.len();
+        self.buffer[self.pos] = sample;
+    }
+
+    /// Reads the sample from `delay` samples before the next one to be written, interpolating between samples.
+    /// So a delay of 1 is the last sample written.
+    fn read(&self, delay: f32) -> Sample {
+        let len = self.buffer.len();
+        let delay = delay.clamp(1.0, (len - 2) as f32);
+        let whole = delay as usize;
+        let fract = delay - whole as f32;
+        let a = self.buffer[(self.pos + len + 1 - whole) % len];
+        let b = self.buffer[(self.pos + len - whole) % len];
+        a + (b - a) * fract
+    }
+}
+
+/// Chorus and flanger: mixes in a copy of the sound with an oscillating delay.
+#[derive(Default)]
+struct ModulatedDelay {
+    flag: u32, // CHORUS or FLANGER
+    sample_rate: f32,
+    wet: f32,
+    depth: f32,
+    feedback: f32,
+    step: f32,
+    sine: bool,
+    delay: f32,
+    phase_offset: f32,
+    phase: f32,
+    lines: Vec<DelayLine>,
+}
+
+impl ModulatedDelay {
+    fn new(flag: u32, params: &EffectParams, sample_rate: f32, channels: usize) -> Self {
+        let lines = (0..channels).map(|_| DelayLine::new(0)).collect();
+        let mut effect = Self { flag, sample_rate, lines, ..Default::default() };
+        effect.set(params);
+        effect
+    }
+
+    fn lfo(&self, phase: f32) -> f32 {
+        let phase = phase.rem_euclid(1.0);
+        if self.sine { (phase * 2.0 * PI).sin() } else { 1.0 - 4.0 * (phase - 0.5).abs() }
+    }
+}
+
+impl Effect for ModulatedDelay {
+    fn process(&mut self, frame: &mut [Sample]) {
+        for (i, sample) in frame.iter_mut().enumerate() {
+            // only the right channel (and any after it) gets the phase offset
+            let phase = if i == 0 { self.phase } else { self.phase + self.phase_offset };
+            let delay = self.delay * (1.0 + self.depth * self.lfo(phase) * 0.5);
+            let line = &mut self.lines[i];
+            let delayed = line.read(delay);
+            line.write(*sample + delayed * self.feedback);
+            *sample = *sample * (1.0 - self.wet) + delayed * self.wet;
+        }
+        self.phase = (self.phase + self.step) % 1.0;
+    }
+
+    fn set(&mut self, params: &EffectParams) {
+        let (params, max_delay) = if self.flag == FLANGER { (params.flanger, 4.0) } else { (params.chorus, 20.0) };
+        self.delay = ms_to_samples(params.delay.clamp(0.0, max_delay), self.sample_rate);
+        self.depth = params.depth.clamp(0.0, 100.0) / 100.0;
+        self.wet = params.wetdry.clamp(0.0, 100.0) / 100.0;
+        self.feedback = params.feedback.clamp(-99.0, 99.0) / 100.0;
+        self.step = params.frequency.clamp(0.0, 10.0) / self.sample_rate;
+        self.sine = params.wave != 0;
+        self.phase_offset = (params.phase.clamp(0, 4) - 2) as f32 * 0.25;
+        // the delay swings by up to half of itself either way
+        let line_len = (self.delay * 1.5) as usize + 2;
+        self.lines.iter_mut().for_each(|line| line.resize(line_len));
+    }
+
+    fn memory(&self) -> usize {
+        self.lines.iter().map(DelayLine::len).max().unwrap_or(0)
+    }
+}
+
+#[derive(Default)]
+struct EchoEffect {
+    sample_rate: f32,
+    wet: f32,
+    feedback: f32,
+    delays: [f32; 2],
+    pan: bool,
+    lines: Vec<DelayLine>,
+    delayed: Vec<Sample>,
+}
+
+impl EchoEffect {
+    fn new(params: &EffectParams, sample_rate: f32, channels: usize) -> Self {
+        let lines = (0..channels).map(|_| DelayLine::new(0)).collect();
+        let mut effect = Self { sample_rate, lines, delayed: vec![0.0; channels], ..Default::default() };
+        effect.set(params);
+        effect
+    }
+}
+
+impl Effect for EchoEffect {
+    fn process(&mut self, frame: &mut [Sample]) {
+        for (i, line) in self.lines.iter().enumerate() {
+            self.delayed[i] = line.read(self.delays[i % 2]);
+        }
+        for (i, sample) in frame.iter_mut().enumerate() {
+            // with pan delay, each side's echoes are fed into the other side
+            let fed_back = if self.pan { self.delayed[i ^ 1] } else { self.delayed[i] };
+            self.lines[i].write(*sample + fed_back * self.feedback);
+            *sample = *sample * (1.0 - self.wet) + self.delayed[i] * self.wet;
+        }
+    }
+
+    fn set(&mut self, params: &EffectParams) {
+        let params = params.echo;
+        let left = ms_to_samples(params.left_delay.clamp(1.0, 2000.0), self.sample_rate);
+        let right = ms_to_samples(params.right_delay.clamp(1.0, 2000.0), self.sample_rate);
+        self.wet = params.wetdry.clamp(0.0, 100.0) / 100.0;
+        self.feedback = params.feedback.clamp(0.0, 100.0) / 100.0;
+        self.delays = [left, right];
+        self.pan = params.pan_delay && self.lines.len() >= 2;
+        for (i, line) in self.lines.iter_mut().enumerate() {
+            line.resize(self.delays[i % 2] as usize + 2);
+        }
+    }
+
+    fn memory(&self) -> usize {
+        self.lines.iter().map(DelayLine::len).max().unwrap_or(0)
+    }
+}
+
+/// Amplitude modulation.
+struct GargleEffect {
+    sample_rate: f32,
+    step: f32,
+    square: bool,
+    phase: f32,
+}
+
+impl GargleEffect {
+    fn new(params: &EffectParams, sample_rate: f32) -> Self {
+        let mut effect = Self { sample_rate, step: 0.0, square: false, phase: 0.0 };
+        effect.set(params);
+        effect
+    }
+}
+
+impl Effect for GargleEffect {
+    fn process(&mut self, frame: &mut [Sample]) {
+        let level = match self.square {
+            true if self.phase < 0.5 => 1.0,
+            true => 0.0,
+            false => 1.0 - (2.0 * self.phase - 1.0).abs(),
+        };
+        frame.iter_mut().for_each(|sample| *sample *= level);
+        self.phase = (self.phase + self.step) % 1.0;
+    }
+
+    fn set(&mut self, params: &EffectParams) {
+        self.step = params.gargle.rate.clamp(1.0, 1000.0) / self.sample_rate;
+        self.square = params.gargle.wave != 0;
+    }
+}
+
+/// A Schroeder reverb: parallel comb filters followed by allpass filters, for each channel.
+struct ReverbEffect {
+    gain: f32,
+    mix: f32,
+    combs: Vec<Vec<(DelayLine, f32, f32)>>, // (line, feedback, lowpass state)
+    allpasses: Vec<Vec<(DelayLine, f32)>>,  // (line, delay)
+    comb_delays: Vec<Vec<f32>>,
+    damping: f32,
+}
+
+const COMB_DELAYS: [f32; 4] = [29.7, 37.1, 41.1, 43.7];
+const ALLPASS_DELAYS: [f32; 2] = [5.0, 1.7];
+const ALLPASS_GAIN: f32 = 0.7;
+const STEREO_SPREAD: f32 = 0.5; // ms added to each delay per channel, so the channels don't sound identical
+
+impl ReverbEffect {
+    fn new(params: &EffectParams, sample_rate: f32, channels: usize) -> Self {
+        let mut combs = Vec::with_capacity(channels);
+        let mut comb_delays = Vec::with_capacity(channels);
+        let mut allpasses = Vec::with_capacity(channels);
+        for channel in 0..channels {
+            let spread = channel as f32 * STEREO_SPREAD;
+            let delays = COMB_DELAYS.iter().map(|ms| ms_to_samples(ms + spread, sample_rate)).collect::<Vec<_>>();
+            combs.push(delays.iter().map(|delay| (DelayLine::new(*delay as usize + 2), 0.0, 0.0)).collect());
+            comb_delays.push(delays);
+            allpasses.push(
+                ALLPASS_DELAYS
+                    .iter()
+                    .map(|ms| {
+                        let delay = ms_to_samples(ms + spread, sample_rate);
+                        (DelayLine::new(delay as usize + 2), delay)
+                    })
+                    .collect(),
+            );
+        }
+        let mut effect = Self { gain: 0.0, mix: 0.0, combs, allpasses, comb_delays, damping: 0.0 };
+        effect.set(params);
+        effect
+    }
+}
+
+impl Effect for ReverbEffect {
+    fn process(&mut self, frame: &mut [Sample]) {
+        for (i, sample) in frame.iter_mut().enumerate() {
+            let input = *sample * self.gain;
+            let mut wet = 0.0;
+            for ((line, feedback, lowpass), delay) in self.combs[i].iter_mut().zip(&self.comb_delays[i]) {
+                let delayed = line.read(*delay);
+                *lowpass = delayed * (1.0 - self.damping) + *lowpass * self.damping;
+                // scaling the input keeps long reverbs from being louder than short ones
+                line.write(input * (1.0 - *feedback) + *lowpass * *feedback);
+                wet += delayed;
+            }
+            wet /= COMB_DELAYS.len() as f32;
+            for (line, delay) in self.allpasses[i].iter_mut() {
+                let delayed = line.read(*delay);
+                line.write(wet + delayed * ALLPASS_GAIN);
+                wet = delayed - wet * ALLPASS_GAIN;
+            }
+            *sample = input + wet * self.mix;
+        }
+    }
+
+    fn set(&mut self, params: &EffectParams) {
+        let params = params.reverb;
+        let time = params.time.clamp(0.001, 3000.0);
+        for (channel, combs) in self.combs.iter_mut().enumerate() {
+            let spread = channel as f32 * STEREO_SPREAD;
+            // each pass through a comb loses enough that the sound falls by 60dB after the reverb time
+            for ((_, feedback, _), ms) in combs.iter_mut().zip(COMB_DELAYS) {
+                *feedback = 0.001f32.powf((ms + spread) / time);
+            }
+        }
+        self.gain = db_to_amplitude(params.gain.clamp(-96.0, 0.0));
+        self.mix = db_to_amplitude(params.mix.clamp(-96.0, 0.0));
+        self.damping = (1.0 - params.ratio.clamp(0.001, 0.999)) * 0.5;
+    }
+
+    fn memory(&self) -> usize {
+        let comb = self.combs.iter().flatten().map(|(line, _, _)| line.len()).max().unwrap_or(0);
+        let allpass = self.allpasses.iter().flatten().map(|(line, _)| line.len()).max().unwrap_or(0);
+        comb + allpass
+    }
+}
+
+/// Turns down anything louder than the threshold. All channels are compressed together.
+#[derive(Default)]
+struct CompressorEffect {
+    sample_rate: f32,
+    gain: f32,
+    attack: f32,
+    release: f32,
+    threshold: f32,
+    slope: f32,
+    delay: f32,
+    envelope: f32,
+    lines: Vec<DelayLine>,
+}
+
+impl CompressorEffect {
+    fn new(params: &EffectParams, sample_rate: f32, channels: usize) -> Self {
+        let lines = (0..channels).map(|_| DelayLine::new(0)).collect();
+        let mut effect = Self { sample_rate, lines, ..Default::default() };
+        effect.set(params);
+        effect
+    }
+}
+
+impl Effect for CompressorEffect {
+    fn process(&mut self, frame: &mut [Sample]) {
+        let level = frame.iter().fold(0.0f32, |level, sample| level.max(sample.abs()));
+        let coefficient = if level > self.envelope { self.attack } else { self.release };
+        self.envelope = level + (self.envelope - level) * coefficient;
+        let over = 20.0 * self.envelope.max(1e-9).log10() - self.threshold;
+        let gain = db_to_amplitude(self.gain - over.max(0.0) * self.slope);
+        for (sample, line) in frame.iter_mut().zip(self.lines.iter_mut()) {
+            // the sample was just written, so it's 1 sample ago
+            line.write(*sample);
+            *sample = line.read(self.delay + 1.0) * gain;
+        }
+    }
+
+    fn set(&mut self, params: &EffectParams) {
+        let params = params.compressor;
+        let sample_rate = self.sample_rate;
+        let coefficient = |ms: f32| (-1.0 / ms_to_samples(ms, sample_rate).max(1.0)).exp();
+        self.gain = params.gain.clamp(-60.0, 60.0);
+        self.attack = coefficient(params.attack.clamp(0.01, 500.0));
+        self.release = coefficient(params.release.clamp(50.0, 3000.0));
+        self.threshold = params.threshold.clamp(-60.0, 0.0);
+        self.slope = 1.0 - 1.0 / params.ratio.clamp(1.0, 100.0);
+        self.delay = ms_to_samples(params.delay.clamp(0.0, 4.0), sample_rate);
+        let line_len = self.delay as usize + 3;
+        self.lines.iter_mut().for_each(|line| line.resize(line_len));
+    }
+
+    fn memory(&self) -> usize {
+        self.lines.iter().map(DelayLine::len).max().unwrap_or(0)
+    }
+}
+
+/// A single peaking filter, from the RBJ audio EQ cookbook.
+struct EqualizerEffect {
+    sample_rate: f32,
+    b: [f32; 3],
+    a: [f32; 2],
+    history: Vec<[f32; 4]>, // x1, x2, y1, y2
+}
+
+impl EqualizerEffect {
+    fn new(params: &EffectParams, sample_rate: f32, channels: usize) -> Self {
+        let mut effect = Self { sample_rate, b: [0.0; 3], a: [0.0; 2], history: vec![[0.0; 4]; channels] };
+        effect.set(params);
+        effect
+    }
+}
+
+impl Effect for EqualizerEffect {
+    fn process(&mut self, frame: &mut [Sample]) {
+        for (sample, [x1, x2, y1, y2]) in frame.iter_mut().zip(self.history.iter_mut()) {
+            let x = *sample;
+            let y = self.b[0] * x + self.b[1] * *x1 + self.b[2] * *x2 - self.a[0] * *y1 - self.a[1] * *y2;
+            (*x2, *x1, *y2, *y1) = (*x1, x, *y1, y);
+            *sample = y;
+        }
+    }
+
+    fn set(&mut self, params: &EffectParams) {
+        let params = params.equalizer;
+        let sample_rate = self.sample_rate;
+        let center = params.center.clamp(80.0, 16000.0f32.min(sample_rate / 2.0 - 1.0));
+        let octaves = params.bandwidth.clamp(1.0, 36.0) / 12.0;
+        let a = 10.0f32.powf(params.gain.clamp(-15.0, 15.0) / 40.0);
+        let w0 = 2.0 * PI * center / sample_rate;
+        let alpha = w0.sin() * (std::f32::consts::LN_2 / 2.0 * octaves * w0 / w0.sin()).sinh();
+        let a0 = 1.0 + alpha / a;
+        self.b = [(1.0 + alpha * a) / a0, -2.0 * w0.cos() / a0, (1.0 - alpha * a) / a0];
+        self.a = [-2.0 * w0.cos() / a0, (1.0 - alpha / a) / a0];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn echo() {
+        let mut params = EffectParams { flags: ECHO, ..Default::default() };
+        params.echo = Echo { wetdry: 50.0, feedback: 0.0, left_delay: 1.0, right_delay: 2.0, pan_delay: false };
+        let mut chain = EffectChain::new(&params, 1000, 2);
+        let mut buffer = [0.0; 8];
+        buffer[0] = 1.0;
+        buffer[1] = 1.0;
+        chain.process(&mut buffer);
+        assert_eq!(buffer, [0.5, 0.5, 0.5, 0.0, 0.0, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn neutral_equalizer() {
+        let mut chain = EffectChain::new(&EffectParams { flags: EQUALIZER, ..Default::default() }, 48000, 1);
+        let mut buffer = [0.25, -0.5, 1.0, 0.0];
+        chain.process(&mut buffer);
+        for (out, expected) in buffer.iter().zip([0.25, -0.5, 1.0, 0.0]) {
+            assert!((out - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn zero_delay() {
+        let mut params = EffectParams { flags: CHORUS | FLANGER, ..Default::default() };
+        params.chorus.delay = 0.0;
+        params.flanger.delay = 0.0;
+        let mut chain = EffectChain::new(&params, 44100, 2);
+        let mut buffer = [1.0; 64];
+        chain.process(&mut buffer);
+        assert!(buffer.iter().all(|x| x.is_finite()));
+    }
+
+    #[test]
+    fn update_keeps_state() {
+        let mut params = EffectParams { flags: ECHO, ..Default::default() };
+        params.echo = Echo { wetdry: 50.0, feedback: 0.0, left_delay: 2.0, right_delay: 2.0, pan_delay: false };
+        let mut chain = EffectChain::new(&params, 1000, 1);
+        let mut buffer = [1.0, 0.0];
+        chain.process(&mut buffer);
+        params.echo.wetdry = 100.0;
+        chain.update(&params);
+        let mut buffer = [0.0, 0.0];
+        chain.process(&mut buffer);
+        assert_eq!(buffer, [1.0, 0.0]);
+    }
+
+    #[test]
+    fn drain() {
+        let mut params = EffectParams { flags: ECHO, ..Default::default() };
+        params.echo = Echo { wetdry: 50.0, feedback: 0.0, left_delay: 10.0, right_delay: 10.0, pan_delay: false };
+        let mut chain = EffectChain::new(&params, 1000, 1);
+        let mut buffer = [0.0; 4];
+        buffer[0] = 1.0;
+        assert!(chain.drain(&mut buffer));
+        assert!(chain.is_draining());
+        let mut buffer = [0.0; 8];
+        assert!(chain.drain(&mut buffer));
+        assert_eq!(buffer[6], 0.5);
+        let mut buffer = [0.0; 16];
+        assert!(!chain.drain(&mut buffer));
+    }
+}
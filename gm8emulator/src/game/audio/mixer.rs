@@ -1,4 +1,7 @@
-use super::SoundParams;
+use super::{
+    effects::{EffectChain, EffectParams},
+    make_pan, SoundParams,
+};
 use std::sync::{
     atomic::{AtomicU32, Ordering},
     mpsc::{self, Receiver, Sender},
@@ -12,7 +15,7 @@ const INIT_CAPACITY: usize = 16;
 pub struct Mixer {
     channels: ChannelCount,
     sample_rate: SampleRate,
-    sources: Vec<(Box<dyn Source + Send + 'static>, Arc<SoundParams>, i32)>,
+    sources: Vec<(Box<dyn Source + Send + 'static>, Arc<SoundParams>, i32, Option<EffectChain>)>,
     exclusive_source: Option<(Box<dyn Source + Send + 'static>, i32)>,
     global_volume: Arc<AtomicU32>,
     input_buffer: Vec<Sample>,
@@ -20,9 +23,10 @@ pub struct Mixer {
 }
 
 enum Command {
-    Add { source: Box<dyn Source + Send + 'static>, params: Arc<SoundParams>, id: i32 },
+    Add { source: Box<dyn Source + Send + 'static>, params: Arc<SoundParams>, id: i32, effects: Option<EffectParams> },
     AddExclusive { source: Box<dyn Source + Send + 'static>, id: i32 },
     Stop(i32),
+    SetEffects { id: i32, effects: Option<EffectParams> },
     StopAll,
 }
 
@@ -61,16 +65,29 @@ impl Source for Mixer {
         // Check for new incoming commands
         while let Ok(cmd) = self.receiver.try_recv() {
             match cmd {
-                Command::Add { source, params, id } => self.sources.push((source, params, id)),
+                Command::Add { source, params, id, effects } => {
+                    let chain = effects.map(|fx| EffectChain::new(&fx, self.sample_rate.into(), self.channels.into()));
+                    self.sources.push((source, params, id, chain));
+                },
                 Command::AddExclusive { source, id } => self.exclusive_source = Some((source, id)),
                 Command::Stop(id) => {
-                    self.sources.retain(|(_, _, x)| *x != id);
+                    self.sources.retain(|(_, _, x, _)| *x != id);
                     if let Some((_, x)) = &self.exclusive_source {
                         if *x == id {
                             self.exclusive_source = None;
                         }
                     }
                 },
+                Command::SetEffects { id, effects } => {
+                    let (sample_rate, channels): (u32, u16) = (self.sample_rate.into(), self.channels.into());
+                    for (_, _, _, chain) in self.sources.iter_mut().filter(|(_, _, x, _)| *x == id) {
+                        match (chain, &effects) {
+                            // keep the effects' state if possible, since starting over would click
+                            (Some(chain), Some(fx)) if chain.flags() == fx.flags => chain.update(fx),
+                            (chain, fx) => *chain = fx.as_ref().map(|fx| EffectChain::new(fx, sample_rate, channels)),
+                        }
+                    }
+                },
                 Command::StopAll => {
                     self.sources.clear();
                     self.exclusive_source = None;
@@ -91,16 +108,40 @@ impl Source for Mixer {
         let input_buffer = &mut self.input_buffer;
         input_buffer.resize_with(buffer.len(), Default::default);
         let global_volume = f32::from_bits(self.global_volume.load(Ordering::Acquire));
+        let channels = usize::from(u16::from(self.channels));
+
+        RetainMut::retain_mut(&mut self.sources, |(source, params, _, effects)| {
+            let volume = f32::from_bits(params.volume.load(Ordering::Acquire))
+                * f32::from_bits(params.attenuation.load(Ordering::Acquire));
+            let (left, right) = make_pan(f32::from_bits(params.pan.load(Ordering::Acquire)));
+            let draining = effects.as_ref().is_some_and(EffectChain::is_draining);
+            let mut count = if draining { 0 } else { source.write_samples(input_buffer) };
+            let mut playing = count == input_buffer.len();
+            if let Some(chain) = effects {
+                if playing {
+                    chain.process(input_buffer);
+                } else {
+                    // the sound has ended, but the effects keep going on silence until their tail dies away
+                    input_buffer[count..].iter_mut().for_each(|x| *x = 0.0);
+                    playing = chain.drain(input_buffer);
+                    count = input_buffer.len();
+                }
+            }
 
-        RetainMut::retain_mut(&mut self.sources, |(source, params, _)| {
-            let volume = f32::from_bits(params.volume.load(Ordering::Acquire));
-            let count = source.write_samples(input_buffer);
-
-            for (in_sample, out_sample) in input_buffer.iter().take(count).copied().zip(buffer.iter_mut()) {
-                *out_sample += in_sample * volume * global_volume;
+            for (i, (in_sample, out_sample)) in
+                input_buffer.iter().take(count).copied().zip(buffer.iter_mut()).enumerate()
+            {
+                // panning only applies to stereo (or more), where the first two channels are left and right
+                let pan = match (channels, i % channels) {
+                    (1, _) => 1.0,
+                    (_, 0) => left,
+                    (_, 1) => right,
+                    _ => 1.0,
+                };
+                *out_sample += in_sample * volume * global_volume * pan;
             }
 
-            count == input_buffer.len()
+            playing
         });
 
         buffer.len()
@@ -120,9 +161,15 @@ impl Source for Mixer {
 }
 
 impl MixerHandle {
-    /// Adds a sound to be mixed, along with its ID and atomic params
-    pub fn add(&self, source: impl Source + Send + 'static, params: Arc<SoundParams>, id: i32) -> Result<(), Error> {
-        let command = Command::Add { source: Box::new(source), params, id };
+    /// Adds a sound to be mixed, along with its ID, atomic params and the effects to apply to it
+    pub fn add(
+        &self,
+        source: impl Source + Send + 'static,
+        params: Arc<SoundParams>,
+        id: i32,
+        effects: Option<EffectParams>,
+    ) -> Result<(), Error> {
+        let command = Command::Add { source: Box::new(source), params, id, effects };
         self.0.send(command).map_err(|_| Error::SendError)
     }
 
@@ -137,6 +184,11 @@ impl MixerHandle {
         self.0.send(Command::Stop(id)).map_err(|_| Error::SendError)
     }
 
+    /// Changes the effects on all playing sounds with a certain ID
+    pub fn set_effects(&self, id: i32, effects: Option<EffectParams>) -> Result<(), Error> {
+        self.0.send(Command::SetEffects { id, effects }).map_err(|_| Error::SendError)
+    }
+
     /// Stops all sounds
     pub fn stop_all(&self) -> Result<(), Error> {
         self.0.send(Command::StopAll).map_err(|_| Error::SendError)
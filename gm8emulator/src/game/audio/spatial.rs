@@ -0,0 +1,117 @@
+//! A simple model of DirectSound's 3D sound, as set up by the sound_3d_* functions.
+//!
+//! GM8 never moves DirectSound's listener, so it's always at the origin, facing along +z with +x to its right.
+
+use serde::{Deserialize, Serialize};
+
+/// Where a 3D sound is and how it's heard. Velocity is kept since GML can set it, but doppler isn't modelled.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sound3D {
+    pub position: [f64; 3],
+    pub velocity: [f64; 3],
+    pub min_distance: f64, // the sound is at full volume this close, and gets quieter further away
+    pub max_distance: f64, // the sound doesn't get any quieter past this distance
+    pub cone_orientation: [f64; 3],
+    pub cone_inside: f64,  // angle in degrees inside which the sound is at full volume
+    pub cone_outside: f64, // angle in degrees outside which the sound is at cone_volume
+    pub cone_volume: f64,  // volume outside the cone, from 0 to 1
+}
+
+impl Default for Sound3D {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3],
+            velocity: [0.0; 3],
+            min_distance: 1.0,
+            max_distance: 1000000000.0,
+            cone_orientation: [0.0, 0.0, 1.0],
+            cone_inside: 360.0,
+            cone_outside: 360.0,
+            cone_volume: 1.0,
+        }
+    }
+}
+
+fn length(v: [f64; 3]) -> f64 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+impl Sound3D {
+    /// How much the listener hears of the sound, as a multiplier for its volume, not counting the cone.
+    pub fn distance_gain(&self) -> f64 {
+        let distance = length(self.position);
+        let min_distance = self.min_distance.max(f64::EPSILON);
+        if distance <= min_distance {
+            1.0
+        } else {
+            // inverse distance, which is 6dB quieter every time the distance doubles
+            min_distance / distance.min(self.max_distance.max(min_distance))
+        }
+    }
+
+    /// How much of the sound is pointed at the listener, between `cone_volume` (which should already be made
+    /// logarithmic) and 1.
+    pub fn cone_gain(&self, cone_volume: f64) -> f64 {
+        let (distance, cone_length) = (length(self.position), length(self.cone_orientation));
+        if distance == 0.0 || cone_length == 0.0 {
+            return 1.0
+        }
+        // the angle between where the cone points and the direction from the sound to the listener
+        let dot = self.cone_orientation.iter().zip(self.position).map(|(o, p)| -o * p).sum::<f64>();
+        let angle = (dot / (distance * cone_length)).clamp(-1.0, 1.0).acos().to_degrees();
+        let inside = self.cone_inside.clamp(0.0, 360.0) / 2.0;
+        let outside = (self.cone_outside.clamp(0.0, 360.0) / 2.0).max(inside);
+        if angle <= inside {
+            1.0
+        } else if angle >= outside {
+            cone_volume
+        } else {
+            1.0 + (cone_volume - 1.0) * (angle - inside) / (outside - inside)
+        }
+    }
+
+    /// Which side the listener hears the sound from, from -1 (left) to 1 (right).
+    pub fn pan(&self) -> f64 {
+        let distance = length(self.position);
+        if distance == 0.0 { 0.0 } else { (self.position[0] / distance).clamp(-1.0, 1.0) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attenuation() {
+        let mut sound =
+            Sound3D { position: [0.0, 0.0, 10.0], min_distance: 5.0, max_distance: 20.0, ..Default::default() };
+        assert_eq!(sound.distance_gain(), 0.5);
+        assert_eq!(sound.pan(), 0.0);
+        sound.position = [40.0, 0.0, 0.0];
+        assert_eq!(sound.distance_gain(), 0.25);
+        assert_eq!(sound.pan(), 1.0);
+        sound.position = [-3.0, 0.0, 0.0];
+        assert_eq!(sound.distance_gain(), 1.0);
+        assert_eq!(sound.pan(), -1.0);
+    }
+
+    #[test]
+    fn cone() {
+        let mut sound = Sound3D {
+            position: [0.0, 0.0, 10.0],
+            cone_orientation: [0.0, 0.0, -1.0],
+            cone_inside: 90.0,
+            cone_outside: 180.0,
+            ..Default::default()
+        };
+        assert_eq!(sound.cone_gain(0.25), 1.0);
+        sound.cone_orientation = [0.0, 0.0, 1.0];
+        assert_eq!(sound.cone_gain(0.25), 0.25);
+        sound.cone_orientation = [1.0, 0.0, -1.0];
+        assert!((sound.cone_gain(0.25) - 1.0).abs() < 1e-9);
+        sound.cone_orientation = [1.0, 0.0, 0.0];
+        assert!((sound.cone_gain(0.25) - 0.25).abs() < 1e-9);
+        sound.cone_orientation = [(67.5f64).to_radians().sin(), 0.0, -(67.5f64).to_radians().cos()];
+        assert!((sound.cone_gain(0.25) - 0.625).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,113 @@
+use byteorder::{WriteBytesExt, LE};
+use std::io::{self, Seek, SeekFrom, Write};
+use udon::source::Sample;
+
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const HEADER_SIZE: u32 = 58;
+// the RIFF size field is 32 bits and covers everything after itself
+const MAX_DATA_SIZE: u64 = u32::MAX as u64 - (HEADER_SIZE as u64 - 8);
+
+/// Writes samples to a 32-bit float WAV file. The header is written with empty sizes first, then filled in by
+/// finish(), so nothing has to be kept in memory. If finish() isn't called, it's called when this is dropped.
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    sample_rate: u32,
+    channels: u16,
+    sample_count: u64,
+    finished: bool,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(writer: W, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        let mut wav = Self { writer, sample_rate, channels, sample_count: 0, finished: false };
+        wav.write_header()?;
+        Ok(wav)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let block_align = self.channels * 4;
+        // write_samples() keeps this below MAX_DATA_SIZE
+        let data_size = (self.sample_count * 4) as u32;
+        let w = &mut self.writer;
+        w.write_all(b"RIFF")?;
+        w.write_u32::<LE>(HEADER_SIZE - 8 + data_size)?;
+        w.write_all(b"WAVE")?;
+        w.write_all(b"fmt ")?;
+        w.write_u32::<LE>(18)?;
+        w.write_u16::<LE>(WAVE_FORMAT_IEEE_FLOAT)?;
+        w.write_u16::<LE>(self.channels)?;
+        w.write_u32::<LE>(self.sample_rate)?;
+        w.write_u32::<LE>(self.sample_rate * u32::from(block_align))?;
+        w.write_u16::<LE>(block_align)?;
+        w.write_u16::<LE>(32)?; // bits per sample
+        w.write_u16::<LE>(0)?; // no extension
+        // non-PCM formats need a fact chunk with the frame count
+        w.write_all(b"fact")?;
+        w.write_u32::<LE>(4)?;
+        w.write_u32::<LE>((self.sample_count / u64::from(self.channels)) as u32)?;
+        w.write_all(b"data")?;
+        w.write_u32::<LE>(data_size)?;
+        Ok(())
+    }
+
+    /// Appends interleaved samples. Their count should be a multiple of the channel count.
+    /// Fails without writing anything if the file would go over the 4 GiB limit of a WAV file.
+    pub fn write_samples(&mut self, samples: &[Sample]) -> io::Result<()> {
+        let sample_count = self.sample_count + samples.len() as u64;
+        if sample_count * 4 > MAX_DATA_SIZE {
+            return Err(io::Error::other("audio is too long for a WAV file (over 4 GiB)"))
+        }
+        for sample in samples {
+            self.writer.write_f32::<LE>(*sample)?;
+        }
+        self.sample_count = sample_count;
+        Ok(())
+    }
+
+    /// Fills in the sizes in the header and flushes the writer.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.finished = true;
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()
+    }
+}
+
+impl<W: Write + Seek> Drop for WavWriter<W> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.finish();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn header() {
+        let mut data = Vec::new();
+        let mut wav = WavWriter::new(Cursor::new(&mut data), 48000, 2).unwrap();
+        wav.write_samples(&[0.5, -0.5, 1.0, 0.0]).unwrap();
+        drop(wav);
+        assert_eq!(data.len(), HEADER_SIZE as usize + 16);
+        assert_eq!(&data[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(data[4..8].try_into().unwrap()), data.len() as u32 - 8);
+        assert_eq!(u32::from_le_bytes(data[46..50].try_into().unwrap()), 2); // frames
+        assert_eq!(&data[50..54], b"data");
+        assert_eq!(u32::from_le_bytes(data[54..58].try_into().unwrap()), 16);
+        assert_eq!(f32::from_le_bytes(data[58..62].try_into().unwrap()), 0.5);
+    }
+
+    #[test]
+    fn size_limit() {
+        let mut wav = WavWriter::new(Cursor::new(Vec::new()), 48000, 2).unwrap();
+        wav.sample_count = MAX_DATA_SIZE / 4 - 2;
+        assert!(wav.write_samples(&[0.0; 2]).is_ok());
+        assert!(wav.write_samples(&[0.0; 2]).is_err());
+        assert_eq!(wav.sample_count, MAX_DATA_SIZE / 4);
+    }
+}
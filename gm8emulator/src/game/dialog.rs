@@ -0,0 +1,705 @@
+//! Message boxes and input dialogs, as opened by show_message, get_string and the like.
+//!
+//! GM8 shows these as real windows and waits for them to close before continuing. Here they're drawn over the game
+//! with the built-in font, styled by the `message_*` settings, and the game waits the same way. When recording, each
+//! result is stored as a replay event, so replays never show dialogs at all.
+
+use crate::{
+    game::{draw, replay::Event, Game, GetAsset, PlayType},
+    gml::{self, Value},
+    input::{Button, MouseButton},
+    math::Real,
+    render::{RendererState, Scaling},
+};
+use ramen::event::Event as WindowEvent;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const PADDING: i32 = 12;
+const SPACING: i32 = 8;
+const BUTTON_MIN_WIDTH: i32 = 75;
+const INPUT_MIN_WIDTH: i32 = 200;
+const SEPARATOR_HEIGHT: i32 = 7;
+
+const C_FACE: i32 = 0xf0f0f0;
+const C_BUTTON: i32 = 0xe1e1e1;
+const C_BORDER: i32 = 0x808080;
+const C_CAPTION: i32 = 0x800000;
+const C_WHITE: i32 = 0xffffff;
+
+/// The settings changed by the message_* functions.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MessageSettings {
+    pub background: i32, // sprite, or -1
+    pub button: i32,     // sprite with normal and hovered frames, or -1
+    pub alpha: Real,
+    pub text_colour: i32,
+    pub button_colour: i32,
+    pub input_text_colour: i32,
+    pub input_colour: i32,
+    pub mouse_colour: i32,
+    pub position: (i32, i32), // -1 to centre
+    pub size: (i32, i32),     // -1 to fit the contents
+    pub show_caption: bool,
+    pub caption: gml::String, // empty to use the room caption
+}
+
+impl Default for MessageSettings {
+    fn default() -> Self {
+        Self {
+            background: -1,
+            button: -1,
+            alpha: Real::from(1.0),
+            text_colour: 0,
+            button_colour: 0,
+            input_text_colour: 0,
+            input_colour: C_WHITE,
+            mouse_colour: 0x800000,
+            position: (-1, -1),
+            size: (-1, -1),
+            show_caption: true,
+            caption: "".into(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Message,
+    Question,
+    MessageExt,
+    GetString,
+    GetInteger,
+    Menu,
+}
+
+impl Kind {
+    /// The replay event which stores a dialog's result.
+    fn event(self, value: Value) -> Event {
+        match self {
+            Self::Message => Event::ShowMessage,
+            Self::Question => Event::ShowQuestion(value),
+            Self::MessageExt => Event::ShowMessageExt(value),
+            Self::GetString => Event::GetString(value),
+            Self::GetInteger => Event::GetInteger(value),
+            Self::Menu => Event::ShowMenu(value),
+        }
+    }
+
+    /// Gets a dialog's result back out of a replay event, if the event is for this kind of dialog.
+    fn result(self, event: Event) -> Option<Value> {
+        match (self, event) {
+            (Self::Message, Event::ShowMessage) => Some(Default::default()),
+            (Self::Question, Event::ShowQuestion(value))
+            | (Self::MessageExt, Event::ShowMessageExt(value))
+            | (Self::GetString, Event::GetString(value))
+            | (Self::GetInteger, Event::GetInteger(value))
+            | (Self::Menu, Event::ShowMenu(value)) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// How a dialog was closed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Outcome {
+    Button(usize),
+    Cancel,
+}
+
+pub struct Dialog {
+    kind: Kind,
+    text: gml::String,
+    /// Button labels, or menu items. Empty buttons aren't shown, and menu items that are "-" are separators.
+    buttons: Vec<gml::String>,
+    /// The result when the dialog is cancelled, or when get_integer gets something that isn't a number.
+    default: Value,
+    input: String,
+    position: Option<(i32, i32)>,
+    selected: Option<usize>,
+    outcome: Option<Outcome>,
+}
+
+impl Dialog {
+    fn new(kind: Kind, text: gml::String, buttons: Vec<gml::String>, default: Value) -> Self {
+        let selected = if kind == Kind::Menu { None } else { Some(0) };
+        Self { kind, text, buttons, default, input: String::new(), position: None, selected, outcome: None }
+    }
+
+    pub fn message(text: gml::String) -> Self {
+        Self::new(Kind::Message, text, vec!["OK".into()], Default::default())
+    }
+
+    pub fn question(text: gml::String) -> Self {
+        Self::new(Kind::Question, text, vec!["Yes".into(), "No".into()], false.into())
+    }
+
+    pub fn message_ext(text: gml::String, buttons: [gml::String; 3]) -> Self {
+        let mut dialog = Self::new(Kind::MessageExt, text, buttons.into(), Default::default());
+        dialog.selected = dialog.buttons.iter().position(|b| !b.as_ref().is_empty());
+        dialog
+    }
+
+    /// `input` is the default, decoded for editing.
+    pub fn get_string(text: gml::String, default: gml::String, input: String) -> Self {
+        let mut dialog = Self::new(Kind::GetString, text, vec!["OK".into(), "Cancel".into()], default.into());
+        dialog.input = input;
+        dialog
+    }
+
+    pub fn get_integer(text: gml::String, default: Real) -> Self {
+        let mut dialog = Self::new(Kind::GetInteger, text, vec!["OK".into(), "Cancel".into()], default.into());
+        dialog.input = default.to_string();
+        dialog
+    }
+
+    /// A popup menu at the given position. Items are separated by '|'.
+    pub fn menu(items: &[u8], default: i32, position: (i32, i32)) -> Self {
+        let items = items.split(|&c| c == b'|').map(gml::String::from).collect();
+        let mut dialog = Self::new(Kind::Menu, "".into(), items, default.into());
+        dialog.position = Some(position);
+        dialog
+    }
+
+    fn has_input(&self) -> bool {
+        matches!(self.kind, Kind::GetString | Kind::GetInteger)
+    }
+
+    fn is_selectable(&self, index: usize) -> bool {
+        match self.buttons.get(index) {
+            Some(label) if self.kind == Kind::Menu => label.as_ref() != b"-",
+            Some(label) => !label.as_ref().is_empty(),
+            None => false,
+        }
+    }
+
+    /// Moves the selection to the next (or previous) button or menu item, wrapping around.
+    fn cycle_selection(&mut self, forward: bool) {
+        let count = self.buttons.len();
+        let mut index = self.selected.unwrap_or(if forward { count - 1 } else { 0 });
+        for _ in 0..count {
+            index = if forward { (index + 1) % count } else { (index + count - 1) % count };
+            if self.is_selectable(index) {
+                self.selected = Some(index);
+                return
+            }
+        }
+    }
+
+    fn key_down(&mut self, key: Button) {
+        let arrows_move = self.kind == Kind::Menu || !self.has_input();
+        match key {
+            Button::Return => {
+                if let Some(index) = self.selected {
+                    self.outcome = Some(Outcome::Button(index));
+                }
+            },
+            Button::Escape => self.outcome = Some(Outcome::Cancel),
+            Button::Tab => self.cycle_selection(true),
+            Button::DownArrow | Button::RightArrow if arrows_move => self.cycle_selection(true),
+            Button::UpArrow | Button::LeftArrow if arrows_move => self.cycle_selection(false),
+            Button::Backspace if self.has_input() => {
+                self.input.pop();
+            },
+            _ => (),
+        }
+    }
+
+    fn char_input(&mut self, chr: char) {
+        if self.has_input() && !chr.is_control() {
+            self.input.push(chr);
+        }
+    }
+
+    fn hover(&mut self, button: Option<usize>) {
+        if button.is_some() || self.kind == Kind::Menu {
+            self.selected = button;
+        }
+    }
+
+    fn click(&mut self, button: Option<usize>, inside: bool) {
+        match button {
+            Some(index) => self.outcome = Some(Outcome::Button(index)),
+            // clicking away from a menu closes it
+            None if self.kind == Kind::Menu && !inside => self.outcome = Some(Outcome::Cancel),
+            None => (),
+        }
+    }
+
+    /// The value the dialog's function returns. `input` is the text box contents, in the game's encoding.
+    fn result(&self, outcome: Outcome, input: gml::String) -> Value {
+        match (self.kind, outcome) {
+            (Kind::Question, Outcome::Button(index)) => (index == 0).into(),
+            (Kind::MessageExt, Outcome::Button(index)) => (index as f64 + 1.0).into(),
+            (Kind::GetString, Outcome::Button(0)) => input.into(),
+            (Kind::GetInteger, Outcome::Button(0)) => match self.input.trim().parse::<f64>() {
+                Ok(number) if number.is_finite() => number.into(),
+                _ => self.default.clone(),
+            },
+            (Kind::Menu, Outcome::Button(index)) => (index as f64).into(),
+            _ => self.default.clone(),
+        }
+    }
+}
+
+type Rect = (i32, i32, i32, i32);
+
+fn contains(rect: Rect, (x, y): (i32, i32)) -> bool {
+    x >= rect.0 && x < rect.2 && y >= rect.1 && y < rect.3
+}
+
+/// Where each part of a dialog goes on screen.
+struct Layout {
+    frame: Rect,
+    caption: Option<Rect>,
+    text: (i32, i32),
+    text_width: i32,
+    input: Option<Rect>,
+    buttons: Vec<Option<Rect>>,
+}
+
+impl Layout {
+    fn button_at(&self, pos: (i32, i32)) -> Option<usize> {
+        self.buttons.iter().position(|rect| rect.map_or(false, |r| contains(r, pos)))
+    }
+}
+
+/// The state saved while something is drawn over the game, so it can be put back afterwards.
+pub struct Overlay {
+    renderer_state: RendererState,
+    font_state: (i32, draw::Halign, draw::Valign),
+    window_size: (u32, u32),
+    framebuffer_size: (u32, u32),
+    scaling: Scaling,
+}
+
+impl Overlay {
+    /// Converts a position in the window to a position on the overlay, undoing the scaling done when presenting.
+    pub fn mouse_position(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        let (window_w, window_h) = (self.window_size.0 as i32, self.window_size.1 as i32);
+        let (fb_w, fb_h) = (self.framebuffer_size.0 as i32, self.framebuffer_size.1 as i32);
+        let (left, top, w, h) = match self.scaling {
+            Scaling::Fixed(scale) => {
+                let (w, h) = ((f64::from(fb_w) * scale) as i32, (f64::from(fb_h) * scale) as i32);
+                ((window_w - w) / 2, (window_h - h) / 2, w, h)
+            },
+            Scaling::Aspect(_) if fb_w > 0 && fb_h > 0 => {
+                let fixed_width = window_h * fb_w / fb_h;
+                if fixed_width < window_w {
+                    ((window_w - fixed_width) / 2, 0, fixed_width, window_h)
+                } else {
+                    let fixed_height = window_w * fb_h / fb_w;
+                    (0, (window_h - fixed_height) / 2, window_w, fixed_height)
+                }
+            },
+            Scaling::Aspect(_) => (0, 0, fb_w, fb_h),
+            Scaling::Full => (0, 0, window_w, window_h),
+        };
+        if w <= 0 || h <= 0 {
+            return (x, y)
+        }
+        ((x - left) * fb_w / w, (y - top) * fb_h / h)
+    }
+}
+
+impl Game {
+    /// Shows a dialog and returns its result. When replaying, the result comes from the replay instead.
+    pub fn run_dialog(&mut self, dialog: Dialog, function: &str) -> gml::Result<Value> {
+        let kind = dialog.kind;
+        match self.play_type {
+            PlayType::Normal => Ok(self.show_dialog(dialog)),
+            PlayType::Record => {
+                let value = self.show_dialog(dialog);
+                self.stored_events.push_back(kind.event(value.clone()));
+                Ok(value)
+            },
+            PlayType::Replay => match self.stored_events.pop_front().and_then(|event| kind.result(event)) {
+                Some(value) => Ok(value),
+                None => Err(gml::Error::ReplayError(function.into())),
+            },
+        }
+    }
+
+    /// Prepares to draw over the current frame, until `end_overlay` is called.
+    pub fn begin_overlay(&mut self) -> Overlay {
+        // while recording, the window is the recording UI's rather than the game's
+        let (window_size, scaling) = match self.play_type {
+            PlayType::Record => (self.ui_window_size, Scaling::Aspect(-1.0)),
+            _ => (self.window_inner_size, self.scaling),
+        };
+        let overlay = Overlay {
+            renderer_state: self.renderer.state(),
+            font_state: (self.draw_font_id, self.draw_halign, self.draw_valign),
+            window_size,
+            framebuffer_size: (self.unscaled_width, self.unscaled_height),
+            scaling,
+        };
+        let (fb_w, fb_h) = (overlay.framebuffer_size.0 as i32, overlay.framebuffer_size.1 as i32);
+        self.draw_font_id = -1;
+        self.draw_halign = draw::Halign::Left;
+        self.draw_valign = draw::Valign::Top;
+        self.renderer.reset_target();
+        self.renderer.set_3d(false);
+        self.renderer.set_view(0, 0, fb_w, fb_h, 0.0, 0, 0, fb_w, fb_h);
+        // keep a copy of the frame, so the overlay can be drawn over it repeatedly and removed afterwards
+        self.renderer.resize_framebuffer(overlay.framebuffer_size.0, overlay.framebuffer_size.1, true);
+        overlay
+    }
+
+    /// Puts back the frame as it was before the overlay was drawn.
+    pub fn clear_overlay(&mut self, overlay: &Overlay) {
+        self.renderer.draw_stored(0, 0, overlay.framebuffer_size.0, overlay.framebuffer_size.1);
+    }
+
+    /// Shows what's been drawn, waits a little, and returns the window events that came in meanwhile.
+    pub fn present_overlay(&mut self, overlay: &Overlay) -> Vec<WindowEvent> {
+        self.renderer.present(overlay.window_size.0, overlay.window_size.1, overlay.scaling);
+        std::thread::sleep(Duration::from_millis(16));
+        match self.window.as_mut() {
+            Some(window) => {
+                window.poll_events();
+                window.events().to_vec()
+            },
+            None => Vec::new(),
+        }
+    }
+
+    /// Removes the overlay and restores the renderer and drawing settings.
+    pub fn end_overlay(&mut self, overlay: Overlay) {
+        self.renderer.resize_framebuffer(overlay.framebuffer_size.0, overlay.framebuffer_size.1, false);
+        self.clear_overlay(&overlay);
+        self.renderer.set_state(&overlay.renderer_state);
+        if let Some(surf) = self.surface_target.and_then(|id| self.surfaces.get(id)) {
+            self.renderer.set_target(surf.atlas_ref);
+        }
+        self.draw_font_id = overlay.font_state.0;
+        self.draw_halign = overlay.font_state.1;
+        self.draw_valign = overlay.font_state.2;
+        if self.play_type == PlayType::Normal {
+            // the game didn't see the keys or clicks that closed the overlay being pressed, so it shouldn't see
+            // them being held or released either
+            self.input.keyboard_clear_all();
+            self.input.mouse_clear_all();
+        }
+    }
+
+    /// Blocks until the user closes the dialog, drawing it over the current frame.
+    fn show_dialog(&mut self, mut dialog: Dialog) -> Value {
+        if self.window.is_none() {
+            return dialog.result(Outcome::Cancel, "".into())
+        }
+        let overlay = self.begin_overlay();
+        let layout = self.dialog_layout(&dialog);
+        let mut mouse = (i32::MIN, i32::MIN);
+        let outcome = loop {
+            self.clear_overlay(&overlay);
+            self.draw_dialog(&dialog, &layout);
+            let events = self.present_overlay(&overlay);
+            if let Some(outcome) = dialog.outcome {
+                break outcome
+            }
+            for event in events {
+                match event {
+                    WindowEvent::KeyboardDown(key) => {
+                        if let Ok(button) = Button::try_from(key) {
+                            dialog.key_down(button);
+                        }
+                    },
+                    WindowEvent::Input(chr) => {
+                        // only accept characters the game can represent
+                        if self.encode_str_maybe(chr.encode_utf8(&mut [0; 4])).is_some() {
+                            dialog.char_input(chr);
+                        }
+                    },
+                    WindowEvent::MouseMove((x, y)) => {
+                        if let (Ok(x), Ok(y)) = (i32::try_from(x), i32::try_from(y)) {
+                            mouse = overlay.mouse_position((x, y));
+                            dialog.hover(layout.button_at(mouse));
+                        }
+                    },
+                    WindowEvent::MouseUp(button) => {
+                        if matches!(MouseButton::try_from(button), Ok(MouseButton::Left)) {
+                            dialog.click(layout.button_at(mouse), contains(layout.frame, mouse));
+                        }
+                    },
+                    WindowEvent::CloseRequest => {
+                        if self.play_type == PlayType::Normal {
+                            self.close_requested = true;
+                        }
+                        dialog.outcome = Some(Outcome::Cancel);
+                    },
+                    _ => (),
+                }
+            }
+        };
+        self.end_overlay(overlay);
+
+        let input = match self.encode_str_maybe(&dialog.input) {
+            Some(bytes) => gml::String::from(bytes.as_ref()),
+            None => "".into(),
+        };
+        dialog.result(outcome, input)
+    }
+
+    fn dialog_layout(&self, dialog: &Dialog) -> Layout {
+        let settings = &self.message_settings;
+        let line_height = self.default_font.tallest_char_height as i32;
+        let (screen_w, screen_h) = (self.unscaled_width as i32, self.unscaled_height as i32);
+        let measure = |string: &gml::String, max_width| self.get_string_size(string.clone(), None, max_width);
+
+        if dialog.kind == Kind::Menu {
+            let row_height = line_height + 6;
+            let width = dialog.buttons.iter().map(|item| measure(item, None).0).max().unwrap_or(0) + 2 * PADDING;
+            let (mut x, mut y) = dialog.position.unwrap_or((0, 0));
+            let height = dialog
+                .buttons
+                .iter()
+                .map(|item| if item.as_ref() == b"-" { SEPARATOR_HEIGHT } else { row_height })
+                .sum::<i32>()
+                + 4;
+            x = x.min(screen_w - width).max(0);
+            y = y.min(screen_h - height).max(0);
+            let mut row_y = y + 2;
+            let buttons = dialog
+                .buttons
+                .iter()
+                .map(|item| {
+                    let top = row_y;
+                    if item.as_ref() == b"-" {
+                        row_y += SEPARATOR_HEIGHT;
+                        None
+                    } else {
+                        row_y += row_height;
+                        Some((x + 2, top, x + width - 2, row_y))
+                    }
+                })
+                .collect();
+            return Layout {
+                frame: (x, y, x + width, y + height),
+                caption: None,
+                text: (x, y),
+                text_width: 0,
+                input: None,
+                buttons,
+            }
+        }
+
+        let max_text_width = match settings.size.0 {
+            w if w > 0 => (w - 2 * PADDING).max(1),
+            _ => (screen_w * 3 / 4).max(INPUT_MIN_WIDTH),
+        };
+        let (text_w, text_h) = measure(&dialog.text, Some(max_text_width));
+        let button_h = line_height + 10;
+        let button_widths = dialog
+            .buttons
+            .iter()
+            .map(|b| {
+                if b.as_ref().is_empty() {
+                    None
+                } else {
+                    Some((measure(b, None).0 + 2 * PADDING).max(BUTTON_MIN_WIDTH))
+                }
+            })
+            .collect::<Vec<_>>();
+        let visible = button_widths.iter().flatten().count() as i32;
+        let buttons_w = button_widths.iter().flatten().sum::<i32>() + SPACING * (visible - 1).max(0);
+        let input_h = if dialog.has_input() { line_height + 8 } else { 0 };
+        let caption_h = if settings.show_caption { line_height + 8 } else { 0 };
+
+        let content_w = text_w.max(buttons_w).max(if dialog.has_input() { INPUT_MIN_WIDTH } else { 0 });
+        let width = if settings.size.0 > 0 { settings.size.0 } else { content_w + 2 * PADDING };
+        let height = match settings.size.1 {
+            h if h > 0 => h,
+            _ => {
+                caption_h
+                    + PADDING
+                    + text_h
+                    + if dialog.has_input() { SPACING + input_h } else { 0 }
+                    + SPACING
+                    + button_h
+                    + PADDING
+            },
+        };
+        let x = if settings.position.0 >= 0 { settings.position.0 } else { (screen_w - width) / 2 };
+        let y = if settings.position.1 >= 0 { settings.position.1 } else { (screen_h - height) / 2 };
+
+        let text = (x + PADDING, y + caption_h + PADDING);
+        let input = if dialog.has_input() {
+            let top = text.1 + text_h + SPACING;
+            Some((x + PADDING, top, x + width - PADDING, top + input_h))
+        } else {
+            None
+        };
+        let button_y = y + height - PADDING - button_h;
+        let mut button_x = x + (width - buttons_w) / 2;
+        let buttons = button_widths
+            .iter()
+            .map(|w| {
+                w.map(|w| {
+                    let rect = (button_x, button_y, button_x + w, button_y + button_h);
+                    button_x += w + SPACING;
+                    rect
+                })
+            })
+            .collect();
+        Layout {
+            frame: (x, y, x + width, y + height),
+            caption: if settings.show_caption { Some((x, y, x + width, y + caption_h)) } else { None },
+            text,
+            text_width: max_text_width,
+            input,
+            buttons,
+        }
+    }
+
+    /// Draws one of the message sprites stretched over a rectangle. Returns false if the sprite doesn't exist.
+    fn draw_dialog_sprite(&mut self, sprite: i32, image_index: i32, rect: Rect, alpha: f64) -> bool {
+        let sprite = match self.assets.sprites.get_asset(sprite) {
+            Some(sprite) if sprite.width > 0 && sprite.height > 0 => sprite,
+            _ => return false,
+        };
+        let image_index = image_index.min(sprite.frames.len() as i32 - 1);
+        let atlas_ref = match sprite.get_atlas_ref(image_index) {
+            Some(atlas_ref) => atlas_ref,
+            None => return false,
+        };
+        let xscale = f64::from(rect.2 - rect.0) / f64::from(sprite.width);
+        let yscale = f64::from(rect.3 - rect.1) / f64::from(sprite.height);
+        let x = f64::from(rect.0) + f64::from(sprite.origin_x) * xscale;
+        let y = f64::from(rect.1) + f64::from(sprite.origin_y) * yscale;
+        self.renderer.draw_sprite(atlas_ref, x, y, xscale, yscale, 0.0, C_WHITE, alpha);
+        true
+    }
+
+    pub(super) fn draw_dialog_rect(&mut self, rect: Rect, colour: i32, alpha: f64, outline: bool) {
+        let (x1, y1, x2, y2) = (f64::from(rect.0), f64::from(rect.1), f64::from(rect.2 - 1), f64::from(rect.3 - 1));
+        if outline {
+            self.renderer.draw_rectangle_outline(x1, y1, x2, y2, colour, alpha);
+        } else {
+            self.renderer.draw_rectangle(x1, y1, x2, y2, colour, alpha);
+        }
+    }
+
+    pub(super) fn draw_dialog_text(
+        &mut self,
+        x: i32,
+        y: i32,
+        text: gml::String,
+        max_width: Option<i32>,
+        colour: i32,
+        alpha: f64,
+    ) {
+        let one = Real::from(1.0);
+        let colours = Some((colour, colour, colour, colour));
+        self.draw_string(x.into(), y.into(), text, None, max_width, one, one, Real::from(0.0), colours, alpha.into());
+    }
+
+    fn draw_dialog(&mut self, dialog: &Dialog, layout: &Layout) {
+        let settings = self.message_settings.clone();
+        let alpha = settings.alpha.into_inner();
+        let (x1, y1, x2, _) = layout.frame;
+        let line_height = self.default_font.tallest_char_height as i32;
+
+        if !self.draw_dialog_sprite(settings.background, 0, layout.frame, alpha) {
+            let colour = if dialog.kind == Kind::Menu { C_WHITE } else { C_FACE };
+            self.draw_dialog_rect(layout.frame, colour, alpha, false);
+            self.draw_dialog_rect(layout.frame, C_BORDER, alpha, true);
+        }
+        if let Some(rect) = layout.caption {
+            self.draw_dialog_rect(rect, C_CAPTION, alpha, false);
+            let caption = match settings.caption.as_ref() {
+                b"" => self.room.caption.clone(),
+                _ => settings.caption,
+            };
+            self.draw_dialog_text(rect.0 + 6, rect.1 + 4, caption, None, C_WHITE, alpha);
+        }
+        if dialog.kind != Kind::Menu {
+            let (tx, ty) = layout.text;
+            self.draw_dialog_text(tx, ty, dialog.text.clone(), Some(layout.text_width), settings.text_colour, alpha);
+        }
+
+        if let Some(rect) = layout.input {
+            self.draw_dialog_rect(rect, settings.input_colour, alpha, false);
+            self.draw_dialog_rect(rect, C_BORDER, alpha, true);
+            let text = match self.encode_str_maybe(&format!("{}|", dialog.input)) {
+                Some(bytes) => gml::String::from(bytes.as_ref()),
+                None => "|".into(),
+            };
+            self.draw_dialog_text(rect.0 + 4, rect.1 + 4, text, None, settings.input_text_colour, alpha);
+        }
+
+        for (i, rect) in layout.buttons.iter().enumerate() {
+            let selected = dialog.selected == Some(i);
+            let rect = match *rect {
+                Some(rect) => rect,
+                None => continue,
+            };
+            let label = dialog.buttons[i].clone();
+            if dialog.kind == Kind::Menu {
+                if selected {
+                    self.draw_dialog_rect(rect, C_BUTTON, alpha, false);
+                }
+                let colour = if selected { settings.mouse_colour } else { settings.text_colour };
+                self.draw_dialog_text(rect.0 + PADDING - 2, rect.1 + 3, label, None, colour, alpha);
+                continue
+            }
+            if !self.draw_dialog_sprite(settings.button, selected as i32, rect, alpha) {
+                self.draw_dialog_rect(rect, C_BUTTON, alpha, false);
+                self.draw_dialog_rect(rect, if selected { settings.mouse_colour } else { C_BORDER }, alpha, true);
+            }
+            let label_w = self.get_string_size(label.clone(), None, None).0;
+            let (x, y) = (rect.0 + (rect.2 - rect.0 - label_w) / 2, rect.1 + (rect.3 - rect.1 - line_height) / 2);
+            let colour = if selected { settings.mouse_colour } else { settings.button_colour };
+            self.draw_dialog_text(x, y, label, None, colour, alpha);
+        }
+
+        // separators between menu items
+        if dialog.kind == Kind::Menu {
+            let mut row_y = y1 + 2;
+            for (item, rect) in dialog.buttons.iter().zip(&layout.buttons) {
+                match rect {
+                    Some((_, _, _, bottom)) => row_y = *bottom,
+                    None if item.as_ref() == b"-" => {
+                        let line_y = row_y + SEPARATOR_HEIGHT / 2;
+                        self.draw_dialog_rect((x1 + 4, line_y, x2 - 4, line_y + 1), C_BORDER, alpha, false);
+                        row_y += SEPARATOR_HEIGHT;
+                    },
+                    None => (),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results() {
+        let mut dialog = Dialog::question("?".into());
+        dialog.key_down(Button::Tab);
+        dialog.key_down(Button::Return);
+        assert_eq!(dialog.outcome, Some(Outcome::Button(1)));
+        assert_eq!(dialog.result(Outcome::Button(1), "".into()), Value::from(false));
+
+        let dialog = Dialog::message_ext("".into(), ["".into(), "A".into(), "B".into()]);
+        assert_eq!(dialog.selected, Some(1));
+        assert_eq!(dialog.result(Outcome::Button(2), "".into()), Value::from(3.0));
+        assert_eq!(dialog.result(Outcome::Cancel, "".into()), Value::from(0.0));
+
+        let mut dialog = Dialog::get_integer("".into(), Real::from(5.0));
+        dialog.key_down(Button::Backspace);
+        "12.5".chars().for_each(|c| dialog.char_input(c));
+        assert_eq!(dialog.result(Outcome::Button(0), "".into()), Value::from(12.5));
+        dialog.char_input('x');
+        assert_eq!(dialog.result(Outcome::Button(0), "".into()), Value::from(5.0));
+
+        let mut dialog = Dialog::menu(b"New|-|Quit", -1, (0, 0));
+        dialog.key_down(Button::DownArrow);
+        dialog.key_down(Button::DownArrow);
+        assert_eq!(dialog.selected, Some(2));
+        assert_eq!(dialog.result(Outcome::Button(2), "".into()), Value::from(2.0));
+        assert_eq!(dialog.result(Outcome::Cancel, "".into()), Value::from(-1.0));
+    }
+}
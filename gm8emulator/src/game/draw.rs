@@ -219,7 +219,9 @@ impl Game {
         // Apply room caption
         let title = self.get_window_title();
         if self.play_type != PlayType::Record {
-            self.window.set_title(title.as_ref());
+            if let Some(window) = &self.window {
+                window.set_title(title.as_ref());
+            }
         }
 
         Ok(())
@@ -426,7 +428,6 @@ impl Game {
     /// Splits the string into line-width pairs.
     fn split_string<'a>(&self, string: gml::String, max_width: Option<i32>, font: &'a Font) -> LineIterator<'a> {
         let encoded_text = match self.gm_version {
-            Version::GameMaker8_0 => string.as_ref().to_vec(),
             Version::GameMaker8_1 => {
                 let encoding = font.get_encoding(self.encoding);
                 let string = string.decode_utf8();
@@ -450,6 +451,7 @@ impl Game {
                     encoded_text.into_owned()
                 }
             },
+            _ => string.as_ref().to_vec(),
         };
         LineIterator { text: encoded_text, pos: 0, font, max_width, word_buf: Vec::new(), word_width: 0 }
     }
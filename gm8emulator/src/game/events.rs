@@ -426,6 +426,24 @@ impl Game {
             self.run_object_event(gml::ev::MOUSE, 61, None)?;
         }
 
+        // Joystick events, which run every step the stick is pushed or the button is held
+        for (id, first_sub) in [(1, 16), (2, 31)] {
+            let joystick = match self.input.joystick(id) {
+                Some(joystick) => *joystick,
+                None => continue,
+            };
+            let (dx, dy) = joystick.pushed();
+            let directions = [dx < 0, dx > 0, dy < 0, dy > 0]; // left, right, up, down
+            for (sub, _) in (first_sub..).zip(directions).filter(|(_, pushed)| *pushed) {
+                self.run_object_event(gml::ev::MOUSE, sub, None)?;
+            }
+            for button in 1..=8 {
+                if joystick.button(button) {
+                    self.run_object_event(gml::ev::MOUSE, first_sub + 4 + button as u32, None)?;
+                }
+            }
+        }
+
         Ok(())
     }
 
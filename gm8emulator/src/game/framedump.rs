@@ -0,0 +1,161 @@
+//! Dumps every frame of a replay to disk, for encoding videos without dropping frames.
+//!
+//! Frames are written at a fixed frame rate, which is the room speed when the first frame is dumped. If the room
+//! speed changes, frames are repeated or skipped to keep the video in step with the game clock, which is also
+//! what --audio-out follows, so the two line up when muxed together.
+
+use crate::game::Game;
+use image::RgbaImage;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+pub enum Format {
+    /// Numbered PNG files in a directory
+    Png(PathBuf),
+    /// A raw YUV4MPEG2 stream. Every frame has to be the same size, so later frames are cropped or padded to fit.
+    Y4m(BufWriter<File>),
+}
+
+pub struct FrameDump {
+    format: Format,
+    scale: u32,
+    fps: u32,
+    size: Option<(u32, u32)>,
+    start_time: Option<u128>,
+    frames_written: u128,
+}
+
+impl FrameDump {
+    /// Creates a frame dump at the given path, which is a Y4M file if it ends in .y4m or a directory otherwise.
+    /// Frames are scaled up by a whole number using nearest neighbour.
+    pub fn new(path: &Path, scale: u32) -> io::Result<Self> {
+        let format = if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("y4m")) {
+            Format::Y4m(BufWriter::new(File::create(path)?))
+        } else {
+            fs::create_dir_all(path)?;
+            Format::Png(path.to_path_buf())
+        };
+        Ok(Self { format, scale: scale.max(1), fps: 0, size: None, start_time: None, frames_written: 0 })
+    }
+
+    /// Writes a frame which is shown from `start_time` to `end_time` on the game clock, as many times as it takes to
+    /// keep up with the clock. `fps` is only used for the first frame.
+    pub fn write_frame(&mut self, image: RgbaImage, fps: u32, start_time: u128, end_time: u128) -> io::Result<()> {
+        if self.start_time.is_none() {
+            self.start_time = Some(start_time);
+            self.fps = fps.max(1);
+        }
+        let frames = frames_at(end_time.saturating_sub(self.start_time.unwrap_or(start_time)), self.fps);
+        if frames <= self.frames_written {
+            return Ok(())
+        }
+        let image = scale(&image, self.scale);
+        let (width, height) = *self.size.get_or_insert(image.dimensions());
+        match &mut self.format {
+            Format::Png(dir) => {
+                for frame in self.frames_written..frames {
+                    image.save(dir.join(format!("{:06}.png", frame))).map_err(io::Error::other)?;
+                }
+            },
+            Format::Y4m(file) => {
+                if self.frames_written == 0 {
+                    writeln!(file, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444", width, height, self.fps)?;
+                }
+                let planes = to_yuv444(&image, width, height);
+                for _ in self.frames_written..frames {
+                    file.write_all(b"FRAME\n")?;
+                    file.write_all(&planes)?;
+                }
+            },
+        }
+        self.frames_written = frames;
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> io::Result<()> {
+        match &mut self.format {
+            Format::Png(_) => Ok(()),
+            Format::Y4m(file) => file.flush(),
+        }
+    }
+}
+
+/// How many frames at the given frame rate fit into `elapsed` nanoseconds. This is rounded rather than truncated,
+/// because the game clock advances by a whole number of nanoseconds each frame, so at most frame rates the length
+/// of a frame is slightly short.
+fn frames_at(elapsed: u128, fps: u32) -> u128 {
+    (elapsed * u128::from(fps) + 500_000_000) / 1_000_000_000
+}
+
+/// Scales an image up by a whole number.
+fn scale(image: &RgbaImage, scale: u32) -> RgbaImage {
+    if scale == 1 {
+        return image.clone()
+    }
+    RgbaImage::from_fn(image.width() * scale, image.height() * scale, |x, y| *image.get_pixel(x / scale, y / scale))
+}
+
+/// Converts to Y, U and V planes with BT.601 limited range. The image is placed at the top left of a frame of the
+/// given size, with black filling any space left over.
+fn to_yuv444(image: &RgbaImage, width: u32, height: u32) -> Vec<u8> {
+    let len = (width * height) as usize;
+    let mut planes = vec![16; len];
+    planes.resize(len * 3, 128);
+    for (x, y, pixel) in image.enumerate_pixels().filter(|(x, y, _)| *x < width && *y < height) {
+        let [r, g, b, _] = pixel.0.map(f32::from);
+        let i = (y * width + x) as usize;
+        planes[i] = (16.0 + 0.257 * r + 0.504 * g + 0.098 * b).round() as u8;
+        planes[len + i] = (128.0 - 0.148 * r - 0.291 * g + 0.439 * b).round() as u8;
+        planes[len * 2 + i] = (128.0 + 0.439 * r - 0.368 * g - 0.071 * b).round() as u8;
+    }
+    planes
+}
+
+impl Game {
+    /// Dumps what's on screen, if dumping frames. Should be called after a frame has been drawn, with the times it
+    /// started and ends on the game clock.
+    pub fn dump_frame(&mut self, start_time: u128, end_time: u128) -> io::Result<()> {
+        if let Some(mut dump) = self.frame_dump.take() {
+            self.renderer.flush_queue();
+            let (width, height) = (self.unscaled_width, self.unscaled_height);
+            let rgba = self.renderer.get_pixels(0, 0, width as _, height as _);
+            let result = match RgbaImage::from_vec(width, height, rgba.into_vec()) {
+                Some(image) => dump.write_frame(image, self.room.speed, start_time, end_time),
+                None => Err(io::Error::new(io::ErrorKind::InvalidData, "renderer returned the wrong number of pixels")),
+            };
+            self.frame_dump = Some(dump);
+            result
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yuv() {
+        let image = RgbaImage::from_raw(2, 1, vec![255, 255, 255, 255, 0, 0, 0, 255]).unwrap();
+        assert_eq!(to_yuv444(&image, 2, 2), vec![235, 16, 16, 16, 128, 128, 128, 128, 128, 128, 128, 128]);
+        let scaled = scale(&image, 2);
+        assert_eq!(scaled.dimensions(), (4, 2));
+        assert_eq!(scaled.get_pixel(1, 1).0, [255; 4]);
+        assert_eq!(scaled.get_pixel(2, 0).0, [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn frame_count() {
+        for fps in [30, 50, 60] {
+            // the same frame length the game clock uses
+            let frame_time = 1_000_000_000 / u128::from(fps);
+            for frame in 1..=fps * 120 {
+                assert_eq!(frames_at(u128::from(frame) * frame_time, fps), u128::from(frame));
+            }
+        }
+    }
+}
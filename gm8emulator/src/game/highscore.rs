@@ -0,0 +1,276 @@
+//! The highscore table, and the screen it's shown on.
+//!
+//! Like GM8, the table is kept in the registry under the key named after the game ID, which is why giving a game a
+//! new ID starts it with an empty table. Its places are the values `Name1`-`Name10` and `Score1`-`Score10`. Keeping it
+//! in the emulated registry means it persists between sessions, savestates restore it along with the rest of the
+//! registry, and replays start with the table they were recorded with. When a score makes it onto the table, the name
+//! typed in for it is stored as a replay event, and replays insert that name without showing anything.
+
+use crate::{
+    game::{
+        registry::{self, Registry, Root},
+        replay::Event,
+        Game, GetAsset, PlayType,
+    },
+    gml::{self, Value},
+    input::Button,
+    math::Real,
+};
+use ramen::event::Event as WindowEvent;
+use serde::{Deserialize, Serialize};
+
+/// How many entries the table has.
+pub const TABLE_SIZE: usize = 10;
+
+/// The longest name that can be typed in, in characters.
+const MAX_NAME_LENGTH: usize = 20;
+
+/// How the highscore screen looks, as changed by the highscore_set_* functions.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HighscoreSettings {
+    pub background: i32, // background asset, or -1
+    pub border: bool,
+    pub caption: gml::String,
+    pub nobody: gml::String, // the name of empty places
+    pub escape: gml::String, // the hint at the bottom
+    pub back_colour: i32,
+    pub new_colour: i32,
+    pub other_colour: i32,
+}
+
+impl Default for HighscoreSettings {
+    fn default() -> Self {
+        Self {
+            background: -1,
+            border: true,
+            caption: "Top Ten Players".into(),
+            nobody: "<nobody>".into(),
+            escape: "press <Escape> to close".into(),
+            back_colour: 0xe0ffff,
+            new_colour: 0x0000ff,
+            other_colour: 0,
+        }
+    }
+}
+
+pub type Table = Vec<(gml::String, Real)>;
+
+/// Finds where a score would go in the table. It has to beat a score already there, so ties go below.
+pub fn place_for(table: &Table, score: Real) -> Option<usize> {
+    table.iter().position(|(_, s)| score > *s)
+}
+
+/// Inserts an entry, pushing the last one off the end.
+pub fn insert(table: &mut Table, place: usize, name: gml::String, score: Real) {
+    table.insert(place, (name, score));
+    table.truncate(TABLE_SIZE);
+}
+
+/// Reads a game's table from the registry. Places that were never written are filled with `nobody`.
+fn read_table(registry: &Registry, game_id: i32, nobody: &gml::String) -> Table {
+    let key = registry::game_key(game_id).into_bytes();
+    (1..=TABLE_SIZE)
+        .map(|place| {
+            let name = match registry.read(Root::CurrentUser, &key, format!("Name{}", place).as_bytes()) {
+                Some(Value::Str(name)) => name.clone(),
+                _ => nobody.clone(),
+            };
+            let score = match registry.read(Root::CurrentUser, &key, format!("Score{}", place).as_bytes()) {
+                Some(Value::Real(score)) => *score,
+                _ => Real::from(0.0),
+            };
+            (name, score)
+        })
+        .collect()
+}
+
+fn write_table(registry: &mut Registry, game_id: i32, table: &Table) {
+    let key = registry::game_key(game_id).into_bytes();
+    for (i, (name, score)) in table.iter().enumerate() {
+        registry.write(Root::CurrentUser, &key, format!("Name{}", i + 1).as_bytes(), Value::Str(name.clone()));
+        registry.write(Root::CurrentUser, &key, format!("Score{}", i + 1).as_bytes(), Value::Real(*score));
+    }
+}
+
+impl Game {
+    pub fn highscore_table(&self) -> Table {
+        read_table(&self.registry, self.game_id, &self.highscore_settings.nobody)
+    }
+
+    pub fn set_highscore_table(&mut self, table: &Table) {
+        write_table(&mut self.registry, self.game_id, table);
+    }
+
+    /// Adds a score to the table if it's high enough, asking for a name if so, then shows the table.
+    /// `function` is the GML function doing this, for replay errors.
+    pub fn show_highscore(&mut self, score: Real, settings: &HighscoreSettings, function: &str) -> gml::Result<()> {
+        let mut table = self.highscore_table();
+        let place = match place_for(&table, score) {
+            Some(place) => place,
+            None => {
+                if self.play_type != PlayType::Replay {
+                    self.show_highscore_screen(&table, None, settings);
+                }
+                return Ok(())
+            },
+        };
+        let name = match self.play_type {
+            PlayType::Normal => self.show_highscore_screen(&table, Some((place, score)), settings),
+            PlayType::Record => {
+                let name = self.show_highscore_screen(&table, Some((place, score)), settings);
+                self.stored_events.push_back(Event::HighscoreName(name.clone().into()));
+                name
+            },
+            PlayType::Replay => match self.stored_events.pop_front() {
+                Some(Event::HighscoreName(Value::Str(name))) => name,
+                _ => return Err(gml::Error::ReplayError(function.into())),
+            },
+        };
+        insert(&mut table, place, name, score);
+        self.set_highscore_table(&table);
+        Ok(())
+    }
+
+    /// Shows the table until it's closed. If `entry` is given, a name is typed in at that place first.
+    /// Returns the name, or nobody if none was typed.
+    fn show_highscore_screen(
+        &mut self,
+        table: &Table,
+        entry: Option<(usize, Real)>,
+        settings: &HighscoreSettings,
+    ) -> gml::String {
+        if self.window.is_none() {
+            return settings.nobody.clone()
+        }
+        let overlay = self.begin_overlay();
+        let mut name = String::new();
+        let mut entering = entry.is_some();
+        loop {
+            self.clear_overlay(&overlay);
+            let mut shown = table.clone();
+            if let Some((place, score)) = entry {
+                let cursor = if entering { "|" } else { "" };
+                let typed = self.encode_str_maybe(&format!("{}{}", name, cursor)).map(|s| s.as_ref().into());
+                insert(&mut shown, place, typed.unwrap_or_else(|| "".into()), score);
+            }
+            self.draw_highscore_screen(&shown, entry.map(|(place, _)| place), settings);
+
+            let mut done = false;
+            for event in self.present_overlay(&overlay) {
+                match event {
+                    WindowEvent::KeyboardDown(key) => match Button::try_from(key) {
+                        Ok(Button::Return) if entering => entering = false,
+                        Ok(Button::Backspace) if entering => {
+                            name.pop();
+                        },
+                        Ok(Button::Escape) | Ok(Button::Return) => done = true,
+                        _ => (),
+                    },
+                    WindowEvent::Input(chr) if entering => {
+                        let fits = name.chars().count() < MAX_NAME_LENGTH;
+                        if fits && !chr.is_control() && self.encode_str_maybe(chr.encode_utf8(&mut [0; 4])).is_some() {
+                            name.push(chr);
+                        }
+                    },
+                    WindowEvent::MouseUp(_) if !entering => done = true,
+                    WindowEvent::CloseRequest => {
+                        if self.play_type == PlayType::Normal {
+                            self.close_requested = true;
+                        }
+                        done = true;
+                    },
+                    _ => (),
+                }
+            }
+            if done {
+                break
+            }
+        }
+        self.end_overlay(overlay);
+
+        match self.encode_str_maybe(name.trim()) {
+            Some(bytes) if !bytes.is_empty() => bytes.as_ref().into(),
+            _ => settings.nobody.clone(),
+        }
+    }
+
+    fn draw_highscore_screen(&mut self, table: &Table, new_place: Option<usize>, settings: &HighscoreSettings) {
+        let line_height = self.default_font.tallest_char_height as i32;
+        let row_height = line_height + 4;
+        let (screen_w, screen_h) = (self.unscaled_width as i32, self.unscaled_height as i32);
+        let width = 360.min(screen_w - 16).max(160);
+        let height = row_height * (TABLE_SIZE as i32 + 4);
+        let (x1, y1) = ((screen_w - width) / 2, (screen_h - height) / 2);
+        let rect = (x1, y1, x1 + width, y1 + height);
+
+        let background = self.assets.backgrounds.get_asset(settings.background).and_then(|b| {
+            Some((b.atlas_ref?, f64::from(width) / f64::from(b.width), f64::from(height) / f64::from(b.height)))
+        });
+        match background {
+            Some((atlas_ref, xscale, yscale)) => {
+                self.renderer.draw_sprite(atlas_ref, x1.into(), y1.into(), xscale, yscale, 0.0, 0xffffff, 1.0)
+            },
+            None => self.draw_dialog_rect(rect, settings.back_colour, 1.0, false),
+        }
+        if settings.border {
+            self.draw_dialog_rect(rect, settings.other_colour, 1.0, true);
+            self.draw_dialog_rect((rect.0 + 1, rect.1 + 1, rect.2 - 1, rect.3 - 1), settings.other_colour, 1.0, true);
+        }
+
+        let caption_w = self.get_string_size(settings.caption.clone(), None, None).0;
+        let (caption_x, caption_y) = (x1 + (width - caption_w) / 2, y1 + row_height / 2);
+        self.draw_dialog_text(caption_x, caption_y, settings.caption.clone(), None, settings.other_colour, 1.0);
+        for (i, (name, score)) in table.iter().enumerate() {
+            let colour = if new_place == Some(i) { settings.new_colour } else { settings.other_colour };
+            let y = y1 + row_height * (i as i32 + 2);
+            let mut label = format!("{}. ", i + 1).into_bytes();
+            label.extend_from_slice(name.as_ref());
+            self.draw_dialog_text(x1 + 24, y, label.into(), Some(width - 100), colour, 1.0);
+            let score = gml::String::from(score.round().to_string());
+            let score_w = self.get_string_size(score.clone(), None, None).0;
+            self.draw_dialog_text(x1 + width - 24 - score_w, y, score, None, colour, 1.0);
+        }
+        let escape_w = self.get_string_size(settings.escape.clone(), None, None).0;
+        let (escape_x, escape_y) = (x1 + (width - escape_w) / 2, y1 + height - row_height - row_height / 2);
+        self.draw_dialog_text(escape_x, escape_y, settings.escape.clone(), None, settings.other_colour, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placement() {
+        let mut table: Table = (0..TABLE_SIZE).map(|i| ("a".into(), Real::from(100.0 - i as f64 * 10.0))).collect();
+        assert_eq!(place_for(&table, Real::from(5.0)), None);
+        assert_eq!(place_for(&table, Real::from(10.0)), None);
+        assert_eq!(place_for(&table, Real::from(95.0)), Some(1));
+        insert(&mut table, 1, "b".into(), Real::from(95.0));
+        assert_eq!(table.len(), TABLE_SIZE);
+        assert_eq!(table[1], ("b".into(), Real::from(95.0)));
+        assert_eq!(table[9].1, Real::from(20.0));
+        assert_eq!(place_for(&table, Real::from(95.0)), Some(2));
+    }
+
+    #[test]
+    fn storage() {
+        let mut registry = Registry::default();
+        let nobody = gml::String::from("<nobody>");
+        let mut table = read_table(&registry, 123, &nobody);
+        assert_eq!(table.len(), TABLE_SIZE);
+        assert!(table.iter().all(|entry| *entry == (nobody.clone(), Real::from(0.0))));
+
+        insert(&mut table, 0, "a".into(), Real::from(50.0));
+        write_table(&mut registry, 123, &table);
+        // the same values registry_read_string and registry_read_real see
+        let key = b"Software\\Game Maker\\GM123";
+        assert_eq!(registry.read(Root::CurrentUser, key, b"Name1"), Some(&Value::Str("a".into())));
+        assert_eq!(registry.read(Root::CurrentUser, key, b"Score1"), Some(&Value::Real(Real::from(50.0))));
+        assert_eq!(registry.read(Root::CurrentUser, key, b"Name10"), Some(&Value::Str(nobody.clone())));
+        assert_eq!(registry.read(Root::CurrentUser, key, b"Score10"), Some(&Value::Real(Real::from(0.0))));
+        assert_eq!(registry.save_state().values().count(), 2 * TABLE_SIZE);
+        assert_eq!(read_table(&registry, 123, &nobody), table);
+        assert_eq!(read_table(&registry, 456, &nobody)[0].0, nobody);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::game::vfs::Vfs;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
@@ -20,7 +21,12 @@ pub enum ExportSetting {
 }
 
 impl IncludedFile {
-    pub fn export(&mut self, temp_directory: PathBuf, program_directory: PathBuf) -> std::io::Result<()> {
+    pub fn export(
+        &mut self,
+        vfs: &mut Vfs,
+        temp_directory: PathBuf,
+        program_directory: PathBuf,
+    ) -> std::io::Result<()> {
         if self.data.is_some() {
             if let Some(mut export_path) = match self.export_settings.clone() {
                 ExportSetting::NoExport => None,
@@ -29,16 +35,21 @@ impl IncludedFile {
                 ExportSetting::CustomFolder(dir) => Some(dir.clone().into()),
             } {
                 export_path.push(&self.name);
-                self.export_to(&export_path)?;
+                self.export_to(vfs, &export_path)?;
             }
         }
         Ok(())
     }
 
-    pub fn export_to(&mut self, path: &Path) -> std::io::Result<()> {
+    pub fn export_to(&mut self, vfs: &mut Vfs, path: &Path) -> std::io::Result<()> {
         if let Some(data) = self.data.as_ref() {
-            if self.overwrite || !path.exists() {
-                std::fs::write(path, &data)?;
+            let path = path.to_string_lossy();
+            if self.overwrite || !vfs.file_exists(&path) {
+                vfs.write(&path, data.to_vec())?;
+            }
+            // host code such as extension DLLs can't see the overlay, so sandboxed files go on the disk too
+            if vfs.is_sandboxed(&path) && (self.overwrite || !Path::new(&*path).is_file()) {
+                std::fs::write(&*path, data)?;
             }
             if self.free_after_export {
                 self.data = None;
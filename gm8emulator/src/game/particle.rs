@@ -92,6 +92,11 @@ impl Manager {
         self.systems.get_asset_mut(id)
     }
 
+    /// Iterates over the IDs of every particle system which exists.
+    pub fn system_ids(&self) -> impl Iterator<Item = i32> + '_ {
+        self.systems.iter().enumerate().filter(|(_, system)| system.is_some()).map(|(id, _)| id as i32)
+    }
+
     pub fn auto_update_systems(&mut self, rand: &mut Random) {
         for ps in self.systems.iter_mut().filter_map(|x| x.as_mut().filter(|s| s.auto_update)) {
             ps.update(rand, &self.types);
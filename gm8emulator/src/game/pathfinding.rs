@@ -4,6 +4,7 @@ use crate::{
     math::Real,
 };
 use serde::{Deserialize, Serialize};
+use std::{cell::Cell, collections::VecDeque};
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct PotentialStepSettings {
@@ -63,6 +64,95 @@ impl MpGrid {
     pub fn set(&mut self, x: usize, y: usize, val: i32) {
         self.mpgrid[x][y] = val;
     }
+
+    /// Gets the cell containing the given position, if it's inside the grid.
+    pub fn cell_at(&self, x: Real, y: Real) -> Option<(usize, usize)> {
+        let cell_x = ((x - self.left.into()) / self.cellwidth.into()).floor();
+        let cell_y = ((y - self.top.into()) / self.cellheight.into()).floor();
+        if cell_x >= 0.into()
+            && cell_y >= 0.into()
+            && cell_x < Real::from(self.hcells as i32)
+            && cell_y < Real::from(self.vcells as i32)
+        {
+            Some((cell_x.to_i32() as usize, cell_y.to_i32() as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Gets the position of the centre of the given cell.
+    pub fn cell_centre(&self, x: usize, y: usize) -> (Real, Real) {
+        (
+            Real::from(self.left + x as i32 * self.cellwidth) + Real::from(self.cellwidth) / 2.into(),
+            Real::from(self.top + y as i32 * self.cellheight) + Real::from(self.cellheight) / 2.into(),
+        )
+    }
+
+    /// Finds a path with the fewest moves between two cells, returning every cell along it (including both ends).
+    /// Diagonal moves are only taken when both cells beside the corner are free, so paths never cut corners.
+    /// When several paths are equally short, the one taken depends only on the order neighbours are checked in:
+    /// right, up, left, down, then the diagonals. That order is this emulator's own choice. It hasn't been compared
+    /// with paths made by GM8, so ties may be broken differently there. It's pinned by a test so that recordings keep
+    /// replaying the same way, and changing it to GM8's should be done alongside a GM8-made reference path.
+    pub fn find_path(
+        &self,
+        start: (usize, usize),
+        goal: (usize, usize),
+        allow_diag: bool,
+    ) -> Option<Vec<(usize, usize)>> {
+        const NEIGHBOURS: [(isize, isize); 8] = [(1, 0), (0, -1), (-1, 0), (0, 1), (1, -1), (-1, -1), (-1, 1), (1, 1)];
+        if self.get(start.0, start.1) < 0 || self.get(goal.0, goal.1) < 0 {
+            return None
+        }
+        let free = |x: isize, y: isize| {
+            x >= 0
+                && y >= 0
+                && (x as usize) < self.hcells
+                && (y as usize) < self.vcells
+                && self.get(x as usize, y as usize) >= 0
+        };
+        // the neighbours of a cell which can be moved into from it
+        let moves = |(x, y): (usize, usize)| {
+            let (x, y) = (x as isize, y as isize);
+            NEIGHBOURS
+                .iter()
+                .take(if allow_diag { 8 } else { 4 })
+                .filter(move |(dx, dy)| free(x + dx, y + dy) && free(x + dx, y) && free(x, y + dy))
+                .map(move |(dx, dy)| ((x + dx) as usize, (y + dy) as usize))
+        };
+
+        // flood fill outwards from the start, recording how many moves it takes to reach each cell
+        let mut distances = vec![vec![usize::MAX; self.vcells]; self.hcells];
+        let mut queue = VecDeque::new();
+        distances[start.0][start.1] = 0;
+        queue.push_back(start);
+        while let Some(cell) = queue.pop_front() {
+            if cell == goal {
+                break
+            }
+            let distance = distances[cell.0][cell.1] + 1;
+            for (x, y) in moves(cell) {
+                if distances[x][y] == usize::MAX {
+                    distances[x][y] = distance;
+                    queue.push_back((x, y));
+                }
+            }
+        }
+        if distances[goal.0][goal.1] == usize::MAX {
+            return None
+        }
+
+        // walk back from the goal, always taking the first neighbour which is one move closer to the start
+        let mut cells = vec![goal];
+        let mut cell = goal;
+        while cell != start {
+            let distance = distances[cell.0][cell.1];
+            cell = moves(cell).find(|&(x, y)| distances[x][y] == distance - 1)?;
+            cells.push(cell);
+        }
+        cells.reverse();
+        Some(cells)
+    }
 }
 
 /// Performs a step straight towards the given destination, stopping when a wall is reached.
@@ -177,6 +267,47 @@ pub enum PathGenResult {
     NotDone,
 }
 
+/// Makes a path by taking potential steps towards the given destination.
+/// Gives up once the path gets longer than `factor` times the straight-line distance, or if the instance gets stuck.
+pub fn potential_path(
+    x: Real,
+    y: Real,
+    step_size: Real,
+    factor: Real,
+    settings: &PotentialStepSettings,
+    inst: &Instance,
+    path: &mut Path,
+    coll: impl Fn() -> bool,
+) -> bool {
+    let max_length = factor.into_inner() * (x - inst.x.get()).into_inner().hypot((y - inst.y.get()).into());
+    // when rotating on the spot, it might take a full turn before there's anywhere to go
+    let max_stuck_steps = if settings.rotate_on_spot && settings.max_rotation > 0.into() {
+        (Real::from(360) / settings.max_rotation).ceil().to_i32().max(1)
+    } else {
+        1
+    };
+    let length = Cell::new(0.0);
+    let stuck_steps = Cell::new(0);
+    make_path(inst, path, |inst| {
+        let (old_x, old_y) = (inst.x.get(), inst.y.get());
+        if potential_step(x, y, step_size, settings, inst, &coll) {
+            return PathGenResult::Done
+        }
+        let moved = (inst.x.get() - old_x).into_inner().hypot((inst.y.get() - old_y).into());
+        if moved == 0.0 {
+            stuck_steps.set(stuck_steps.get() + 1);
+        } else {
+            stuck_steps.set(0);
+            length.set(length.get() + moved);
+        }
+        if stuck_steps.get() >= max_stuck_steps || length.get() > max_length {
+            PathGenResult::Failed
+        } else {
+            PathGenResult::NotDone
+        }
+    })
+}
+
 pub fn make_path(inst: &Instance, path: &mut Path, func: impl Fn(&Instance) -> PathGenResult) -> bool {
     let (old_x, old_y, old_direction) = (inst.x.get(), inst.y.get(), inst.direction.get());
     path.curve = false;
@@ -194,3 +325,62 @@ pub fn make_path(inst: &Instance, path: &mut Path, func: impl Fn(&Instance) -> P
     inst.bbox_is_stale.set(true);
     result == PathGenResult::Done
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_path() {
+        // . . . .
+        // . # # .
+        // . # . .
+        let mut grid = MpGrid::new(0, 0, 4, 3, 16, 16);
+        grid.set(1, 1, -1);
+        grid.set(2, 1, -1);
+        grid.set(1, 2, -1);
+
+        let path = grid.find_path((0, 2), (2, 2), false).unwrap();
+        assert_eq!(path, vec![(0, 2), (0, 1), (0, 0), (1, 0), (2, 0), (3, 0), (3, 1), (3, 2), (2, 2)]);
+        // the only diagonals on the way would cut the corners of walls
+        assert_eq!(grid.find_path((0, 2), (2, 2), true), Some(path));
+        let open = MpGrid::new(0, 0, 4, 3, 16, 16);
+        assert_eq!(open.find_path((0, 0), (3, 2), true).unwrap(), vec![(0, 0), (1, 1), (2, 2), (3, 2)]);
+
+        grid.set(3, 1, -1);
+        assert_eq!(grid.find_path((0, 2), (2, 2), true), None);
+        assert_eq!(grid.cell_at(63.9.into(), 0.into()), Some((3, 0)));
+        assert_eq!(grid.cell_at(64.into(), 0.into()), None);
+    }
+
+    #[test]
+    fn grid_path_order() {
+        // ties between equally short paths go to whichever neighbour comes first walking back from the goal:
+        // right, up, left, down, then the diagonals. These expectations come from that order (see find_path),
+        // not from GM8, and only keep it from changing by accident.
+        let open = MpGrid::new(0, 0, 3, 3, 16, 16);
+        assert_eq!(open.find_path((0, 0), (2, 2), false).unwrap(), vec![(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(open.find_path((2, 2), (0, 0), false).unwrap(), vec![(2, 2), (2, 1), (2, 0), (1, 0), (0, 0)]);
+        assert_eq!(open.find_path((0, 2), (2, 0), false).unwrap(), vec![(0, 2), (0, 1), (0, 0), (1, 0), (2, 0)]);
+        // a diagonal move counts the same as a straight one
+        assert_eq!(open.find_path((0, 0), (2, 2), true).unwrap(), vec![(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(open.find_path((0, 0), (2, 1), true).unwrap(), vec![(0, 0), (1, 1), (2, 1)]);
+
+        // . # .
+        // . . .
+        // . . .
+        let mut grid = MpGrid::new(0, 0, 3, 3, 16, 16);
+        grid.set(1, 0, -1);
+        // a diagonal can't pass a wall on either side
+        assert_eq!(grid.find_path((0, 0), (1, 1), true).unwrap(), vec![(0, 0), (0, 1), (1, 1)]);
+        assert_eq!(grid.find_path((0, 1), (2, 0), true).unwrap(), vec![(0, 1), (1, 1), (2, 1), (2, 0)]);
+        assert_eq!(grid.find_path((2, 1), (0, 0), true).unwrap(), vec![(2, 1), (1, 1), (0, 1), (0, 0)]);
+
+        // . . .
+        // . # .
+        // . . .
+        let mut grid = MpGrid::new(0, 0, 3, 3, 16, 16);
+        grid.set(1, 1, -1);
+        assert_eq!(grid.find_path((0, 0), (2, 2), true).unwrap(), vec![(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)]);
+    }
+}
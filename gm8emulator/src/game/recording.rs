@@ -1,3 +1,5 @@
+mod branch_window;
+mod branches;
 mod console;
 mod control_window;
 mod game_window;
@@ -10,6 +12,8 @@ mod menu_bar;
 mod popup_dialog;
 mod savestate_window;
 mod set_mouse_dialog;
+mod watch_window;
+mod watches;
 mod window;
 
 use crate::{
@@ -23,14 +27,18 @@ use crate::{
         Game, GameClock, SceneChange,
     },
     imgui_utils::*,
-    input,
+    input::{self, Joystick, JOYSTICK_COUNT},
     render::{atlas::AtlasRef, PrimitiveType, RendererState},
     types::Colour,
 };
 use imgui::{self, internal::RawWrapper, DrawCmd};
 use ramen::{event::Event, input::Key};
-use serde::{Deserialize, Serialize};
-use std::{fs::File, path::PathBuf, time::Instant};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
 use super::replay::FrameRng;
 const GRID_COLOUR_GOOD: Colour = Colour::new(0.25, 0.625, 0.38671875);
@@ -92,6 +100,9 @@ struct UIState<'g> {
     /// Mouse position set by the user to be taken into use next time they advance a frame
     new_mouse_pos: Option<(i32, i32)>,
 
+    /// Joystick states set by the user to be taken into use next time they advance a frame
+    new_joysticks: Option<[Joystick; JOYSTICK_COUNT]>,
+
     /// Whether the user is currently in the process of setting a mouse position
     /// If so, mouse inputs should be "eaten" by this process and not sent to imgui windows
     setting_mouse_pos: bool,
@@ -121,6 +132,12 @@ struct UIState<'g> {
     /// Cached reports on the current state of any instances the user is "watching"
     instance_reports: Vec<(i32, Option<InstanceReport>)>,
 
+    /// Watch expressions and breakpoints, evaluated after every frame
+    watches: watches::Watches,
+
+    /// Tree of branches saved in the project, kept in its own file in the branches folder
+    branches: branches::BranchTree,
+
     /// Until which frame the game should advance
     run_until_frame: Option<usize>,
 
@@ -432,6 +449,8 @@ pub enum WindowKind {
     Keybindings,
     Macro(usize),
     Console(usize),
+    Branches,
+    Watches,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -496,6 +515,34 @@ impl ProjectConfig {
     }
 }
 
+/// Reads one of the project's files other than the config, or returns the default if it doesn't exist yet.
+/// These are kept apart from project.cfg so that adding to them doesn't stop older project configs from loading.
+fn load_project_file<T: DeserializeOwned + Default>(path: &Path, description: &str) -> T {
+    if !path.exists() {
+        return T::default()
+    }
+    match File::open(path).map_err(bincode::Error::from).and_then(bincode::deserialize_from) {
+        Ok(value) => value,
+        Err(e) => {
+            println!("Warning: Couldn't read the {} from {}: {}", description, path.to_string_lossy(), e);
+            T::default()
+        },
+    }
+}
+
+/// Saves one of the project's files other than the config. If that failed it will return a description of the
+/// error, otherwise None
+fn save_project_file<T: Serialize>(path: &Path, value: &T, description: &str) -> Option<String> {
+    path.parent()
+        .map(fs::create_dir_all)
+        .unwrap_or(Ok(()))
+        .and_then(|()| File::create(path))
+        .map_err(bincode::Error::from)
+        .and_then(|f| bincode::serialize_into(f, value))
+        .err()
+        .map(|e| format!("The {} was not saved to disk because of an error: {}", description, e))
+}
+
 impl Game {
     pub fn record(&mut self, project_path: PathBuf, pause: bool, start_save_path: Option<&PathBuf>) {
         let mut save_buffer = savestate::Buffer::new();
@@ -508,6 +555,8 @@ impl Game {
         };
         let mut config = ProjectConfig::from_file_or_default(&config_path);
         let mut replay = Replay::new(if let GameClock::SpoofedNanos(t) = self.clock { t } else { 0 }, self.rand.seed());
+        replay.seeded_files = self.vfs.is_seeded();
+        replay.start_registry = self.registry.save_state();
 
         let mut ini_filename = project_path.clone();
         ini_filename.push("imgui.ini");
@@ -608,8 +657,10 @@ impl Game {
                             if pause {
                                 self.rand.set_seed(backup_replay.start_seed);
                                 self.clock = GameClock::SpoofedNanos(backup_replay.start_time);
+                                self.registry.load_state(backup_replay.start_registry.clone());
                                 replay.start_seed = backup_replay.start_seed;
                                 replay.start_time = backup_replay.start_time;
+                                replay.start_registry = backup_replay.start_registry.clone();
                             }
 
                             if backup_replay.contains_part(&replay) {
@@ -755,11 +806,13 @@ impl Game {
             }
         }
 
+        let window = self.window.as_mut().expect("record mode requires a window");
         if config.ui_maximised {
-            self.window.set_maximised(true);
+            window.set_maximised(true);
         } else {
-            self.window.set_size((config.ui_width, config.ui_height));
+            window.set_size((config.ui_width, config.ui_height));
         }
+        self.ui_window_size = (config.ui_width.into(), config.ui_height.into());
 
         for (i, state) in keyboard_state.iter_mut().enumerate() {
             if self.input.keyboard_check_direct(i as u8) {
@@ -776,6 +829,9 @@ impl Game {
         keybind_path.push("keybindings.cfg");
 
         let instance_reports = config.watched_ids.iter().map(|id| (*id, InstanceReport::new(&*self, *id))).collect();
+        let watch_list: watches::WatchList = load_project_file(&watches::list_path(&project_path), "watches");
+        let watches = watches::Watches::new(self, &watch_list.watches, &watch_list.breakpoints);
+        let branches = load_project_file(&branches::tree_path(&project_path), "branch tree");
         let keybindings = keybinds::Keybindings::from_file_or_default(&keybind_path);
 
         let mut windows: Vec<(Box<dyn Window>, bool)> = vec![
@@ -792,6 +848,8 @@ impl Game {
                 WindowKind::Keybindings => windows.push((Box::new(keybinds::KeybindWindow::open(0)), false)),
                 WindowKind::Macro(id) => windows.push((Box::new(macro_window::MacroWindow::open(*id)), false)),
                 WindowKind::Console(id) => windows.push((Box::new(console::ConsoleWindow::open(*id)), false)),
+                WindowKind::Branches => windows.push((Box::new(branch_window::BranchWindow::open(0)), false)),
+                WindowKind::Watches => windows.push((Box::new(watch_window::WatchWindow::open(0)), false)),
                 WindowKind::Control
                 | WindowKind::Game
                 | WindowKind::InstanceReports
@@ -817,6 +875,7 @@ impl Game {
             keyboard_state,
             mouse_state,
             new_mouse_pos: None,
+            new_joysticks: None,
             setting_mouse_pos: false,
             ui_renderer_state,
             cached_savestate: savestate,
@@ -831,6 +890,8 @@ impl Game {
             grid_colour: GRID_COLOUR_GOOD,
             grid_colour_background: CLEAR_COLOUR_GOOD,
             instance_reports,
+            watches,
+            branches,
             new_rand: None,
             save_paths,
             keybind_path,
@@ -996,8 +1057,9 @@ impl UIState<'_> {
     /// Polls new window events from operating system and updates config, imgui and renderer accordingly.
     /// Returns false if the program should exit (eg. the 'X' button was pressed), otherwise true.
     fn poll_window_events(&mut self, io: &mut imgui::Io) -> bool {
-        self.game.window.poll_events();
-        for event in self.game.window.events().into_iter().copied() {
+        let window = self.game.window.as_mut().expect("record mode requires a window");
+        window.poll_events();
+        for event in window.events().into_iter().copied() {
             match event {
                 ev @ Event::KeyboardDown(key) | ev @ Event::KeyboardUp(key) => {
                     let state = matches!(ev, Event::KeyboardDown(_));
@@ -1044,6 +1106,7 @@ impl UIState<'_> {
                 Event::Resize((width, height)) => {
                     self.config.ui_width = u16::try_from(width).unwrap_or(u16::MAX);
                     self.config.ui_height = u16::try_from(height).unwrap_or(u16::MAX);
+                    self.game.ui_window_size = (width.into(), height.into());
                     io.display_size = [f32::from(width), f32::from(height)];
                     self.game.renderer.resize_framebuffer(width as _, height as _, false);
                     self.clear_context_menu = true;
@@ -1079,6 +1142,7 @@ impl UIState<'_> {
             game_running: &mut self.game_running,
             setting_mouse_pos: &mut self.setting_mouse_pos,
             new_mouse_pos: &mut self.new_mouse_pos,
+            new_joysticks: &mut self.new_joysticks,
             new_rand: &mut self.new_rand,
             err_string: &mut self.err_string,
             replay: &mut self.replay,
@@ -1090,6 +1154,8 @@ impl UIState<'_> {
             renderer_state: &mut self.game_renderer_state,
             save_buffer: &mut self.lz4_buffer,
             instance_reports: &mut self.instance_reports,
+            watches: &mut self.watches,
+            branches: &mut self.branches,
 
             clean_state: &mut self.clean_state,
             run_until_frame: &mut self.run_until_frame,
@@ -0,0 +1,220 @@
+use crate::{
+    game::recording::{
+        branches::ReplayDiff,
+        window::{EmulatorContext, Openable, Window},
+    },
+    gml::datetime::DateTime,
+};
+use imgui::TreeNodeFlags;
+
+// How many differing frame ranges to list before giving up
+const MAX_DIFF_RANGES: usize = 32;
+
+pub struct BranchWindow {
+    is_open: bool,
+    new_label: String,
+    edit_label: String,
+    selected: Option<u64>,
+    compare: (Option<u64>, Option<u64>),
+    diff: Option<ReplayDiff>,
+}
+
+impl Openable<Self> for BranchWindow {
+    fn window_name() -> &'static str {
+        "Branches"
+    }
+
+    fn open(_id: usize) -> Self {
+        Self::new()
+    }
+}
+
+impl Window for BranchWindow {
+    fn stored_kind(&self) -> Option<super::WindowKind> {
+        Some(super::WindowKind::Branches)
+    }
+
+    fn name(&self) -> String {
+        "Branches".to_owned()
+    }
+
+    fn show_window(&mut self, info: &mut EmulatorContext) {
+        let frame = info.frame;
+        let mut is_open = self.is_open;
+        frame.window("Branches").opened(&mut is_open).build(|| {
+            frame.input_text("##newbranchlabel", &mut self.new_label).hint("Label").build();
+            if frame.is_item_focused() {
+                info.keybindings.disable_bindings();
+            }
+            frame.same_line();
+            if frame.button("New Branch") && *info.game_running && info.branch_create(self.new_label.clone()) {
+                self.new_label.clear();
+                self.select(info, info.branches.current);
+            }
+
+            frame.separator();
+            if info.branches.is_empty() {
+                frame.text("No branches yet");
+            } else {
+                self.show_tree(info, None);
+            }
+
+            if let Some(id) = self.selected {
+                frame.separator();
+                self.show_selected(info, id);
+            }
+
+            frame.separator();
+            self.show_diff(info);
+        });
+        self.is_open = is_open;
+    }
+
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+}
+
+impl BranchWindow {
+    pub fn new() -> Self {
+        Self {
+            is_open: true,
+            new_label: String::new(),
+            edit_label: String::new(),
+            selected: None,
+            compare: (None, None),
+            diff: None,
+        }
+    }
+
+    fn select(&mut self, info: &EmulatorContext, id: Option<u64>) {
+        self.selected = id;
+        self.edit_label = id.and_then(|id| info.branches.get(id)).map(|b| b.info.label.clone()).unwrap_or_default();
+    }
+
+    fn branch_name(info: &EmulatorContext, id: u64) -> String {
+        match info.branches.get(id) {
+            Some(branch) if !branch.info.label.is_empty() => format!("#{} {}", id, branch.info.label),
+            _ => format!("#{}", id),
+        }
+    }
+
+    fn show_tree(&mut self, info: &mut EmulatorContext, parent: Option<u64>) {
+        let frame = info.frame;
+        let children = info.branches.children(parent).map(|b| b.id).collect::<Vec<_>>();
+        for id in children {
+            let has_children = info.branches.children(Some(id)).next().is_some();
+            let mut label = Self::branch_name(info, id);
+            if info.branches.current == Some(id) {
+                label.push_str(" (current)");
+            }
+            let node = frame
+                .tree_node_config(format!("{}###branch{}", label, id))
+                .flags(TreeNodeFlags::OPEN_ON_ARROW | TreeNodeFlags::DEFAULT_OPEN | TreeNodeFlags::SPAN_AVAIL_WIDTH)
+                .leaf(!has_children)
+                .selected(self.selected == Some(id))
+                .push();
+            if frame.is_item_clicked() {
+                self.select(info, Some(id));
+            }
+            if let Some(node) = node {
+                self.show_tree(info, Some(id));
+                node.end();
+            }
+        }
+    }
+
+    fn show_selected(&mut self, info: &mut EmulatorContext, id: u64) {
+        let frame = info.frame;
+        let branch = match info.branches.get(id) {
+            Some(branch) => branch.info.clone(),
+            None => {
+                self.select(info, None);
+                return
+            },
+        };
+        frame.text(format!("Frame: {}", branch.frame));
+        frame.text(format!("Re-records: {}", branch.rerecords));
+        frame.text(format!("Saved: {}", DateTime::from_nanos(branch.timestamp).datetime_string()));
+        match branch.parent {
+            Some(parent) => frame.text(format!("Parent: {}", Self::branch_name(info, parent))),
+            None => frame.text("Parent: none"),
+        }
+
+        frame.input_text("##branchlabel", &mut self.edit_label).build();
+        if frame.is_item_focused() {
+            info.keybindings.disable_bindings();
+        }
+        frame.same_line();
+        if frame.button("Rename") {
+            if let Some(branch) = info.branches.get_mut(id) {
+                branch.info.label = self.edit_label.clone();
+                info.save_branches();
+            }
+        }
+
+        if frame.button("Restore") && *info.startup_successful {
+            info.branch_restore(id);
+        }
+        frame.same_line();
+        if frame.button("Compare as A") {
+            self.compare.0 = Some(id);
+            self.diff = None;
+        }
+        frame.same_line();
+        if frame.button("Compare as B") {
+            self.compare.1 = Some(id);
+            self.diff = None;
+        }
+        frame.same_line();
+        if frame.button("Delete") {
+            info.branch_delete(id);
+            self.select(info, None);
+            if self.compare.0 == Some(id) || self.compare.1 == Some(id) {
+                self.compare = (None, None);
+                self.diff = None;
+            }
+        }
+    }
+
+    fn show_diff(&mut self, info: &mut EmulatorContext) {
+        let frame = info.frame;
+        let (a, b) = match self.compare {
+            (Some(a), Some(b)) => (a, b),
+            _ => {
+                frame.text("Pick two branches to compare their replays");
+                return
+            },
+        };
+        frame.text(format!("A: {}", Self::branch_name(info, a)));
+        frame.text(format!("B: {}", Self::branch_name(info, b)));
+        if frame.button("Compare") {
+            self.diff = match (info.branch_replay(a), info.branch_replay(b)) {
+                (Some(a), Some(b)) => Some(ReplayDiff::new(&a, &b)),
+                _ => None,
+            };
+        }
+        if let Some(diff) = &self.diff {
+            if diff.is_empty() {
+                frame.text("The replays are the same");
+                return
+            }
+            if diff.startup_differs {
+                frame.text("The replays start differently");
+            }
+            if let Some(first) = diff.first_difference() {
+                frame.text(format!("{} frames differ, starting at frame {}", diff.frame_count(), first));
+                for range in diff.frames.iter().take(MAX_DIFF_RANGES) {
+                    if range.len() == 1 {
+                        frame.bullet_text(format!("Frame {}", range.start));
+                    } else {
+                        frame.bullet_text(format!("Frames {} to {}", range.start, range.end - 1));
+                    }
+                }
+                if diff.frames.len() > MAX_DIFF_RANGES {
+                    frame.text(format!("...and {} more", diff.frames.len() - MAX_DIFF_RANGES));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! A tree of branches for a project, so alternative routes can be kept around and compared.
+//!
+//! Each branch is a savestate plus the whole replay that was loaded when it was made, which can be longer than the
+//! savestate's own replay in read-only mode. They're stored in the project's branches folder, along with the tree
+//! itself so it can be shown without loading every savestate.
+
+use crate::game::{replay::Replay, savestate::SaveInfo};
+use serde::{Deserialize, Serialize};
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Branch {
+    pub id: u64,
+    pub info: SaveInfo,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct BranchTree {
+    branches: Vec<Branch>,
+    next_id: u64,
+    /// The branch that was last made or restored, which new branches and savestates are children of
+    pub current: Option<u64>,
+}
+
+impl BranchTree {
+    /// Adds a branch and makes it the current one, returning its id.
+    pub fn add(&mut self, info: SaveInfo) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.branches.push(Branch { id, info });
+        self.current = Some(id);
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Branch> {
+        self.branches.iter().find(|b| b.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut Branch> {
+        self.branches.iter_mut().find(|b| b.id == id)
+    }
+
+    /// Iterates over the branches with the given parent, or the roots of the tree if it's None.
+    pub fn children(&self, parent: Option<u64>) -> impl Iterator<Item = &Branch> {
+        self.branches.iter().filter(move |b| b.info.parent == parent)
+    }
+
+    /// Removes a branch, moving its children up to its parent. Returns whether it existed.
+    pub fn remove(&mut self, id: u64) -> bool {
+        if let Some(index) = self.branches.iter().position(|b| b.id == id) {
+            let branch = self.branches.remove(index);
+            for child in self.branches.iter_mut().filter(|b| b.info.parent == Some(id)) {
+                child.info.parent = branch.info.parent;
+            }
+            if self.current == Some(id) {
+                self.current = branch.info.parent;
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.branches.is_empty()
+    }
+}
+
+pub fn tree_path(project_path: &Path) -> PathBuf {
+    project_path.join("branches").join("tree.bin")
+}
+
+pub fn savestate_path(project_path: &Path, id: u64) -> PathBuf {
+    project_path.join("branches").join(format!("{}.bin", id))
+}
+
+pub fn replay_path(project_path: &Path, id: u64) -> PathBuf {
+    project_path.join("branches").join(format!("{}.gmtas", id))
+}
+
+/// How two replays differ.
+pub struct ReplayDiff {
+    /// Whether the start time, seed or startup events differ
+    pub startup_differs: bool,
+    /// Ranges of frames which differ, including frames that only one of the replays has
+    pub frames: Vec<Range<usize>>,
+}
+
+impl ReplayDiff {
+    pub fn new(a: &Replay, b: &Replay) -> Self {
+        let startup_differs =
+            a.start_time != b.start_time || a.start_seed != b.start_seed || a.startup_events != b.startup_events;
+        let mut frames: Vec<Range<usize>> = Vec::new();
+        for i in 0..a.frame_count().max(b.frame_count()) {
+            if a.get_frame(i) != b.get_frame(i) {
+                match frames.last_mut() {
+                    Some(range) if range.end == i => range.end += 1,
+                    _ => frames.push(i..i + 1),
+                }
+            }
+        }
+        Self { startup_differs, frames }
+    }
+
+    pub fn first_difference(&self) -> Option<usize> {
+        self.frames.first().map(|r| r.start)
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.iter().map(|r| r.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.startup_differs && self.frames.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree() {
+        let mut tree = BranchTree::default();
+        let root = tree.add(SaveInfo::default());
+        let child = tree.add(SaveInfo { parent: Some(root), ..Default::default() });
+        let grandchild = tree.add(SaveInfo { parent: Some(child), ..Default::default() });
+        assert_eq!(tree.current, Some(grandchild));
+        assert!(tree.remove(child));
+        assert!(!tree.remove(child));
+        assert_eq!(tree.get(grandchild).unwrap().info.parent, Some(root));
+        assert_eq!(tree.children(Some(root)).count(), 1);
+        tree.current = Some(root);
+        assert!(tree.remove(root));
+        assert_eq!(tree.current, None);
+        assert_eq!(tree.children(None).map(|b| b.id).collect::<Vec<_>>(), vec![grandchild]);
+    }
+
+    #[test]
+    fn diff() {
+        let mut a = Replay::new(0, 0);
+        for _ in 0..6 {
+            a.new_frame();
+        }
+        let mut b = a.clone();
+        b.get_frame_mut(1).unwrap().mouse_x = 5;
+        b.get_frame_mut(2).unwrap().mouse_x = 5;
+        b.get_frame_mut(4).unwrap().mouse_y = 5;
+        b.new_frame();
+        let diff = ReplayDiff::new(&a, &b);
+        assert!(!diff.startup_differs);
+        assert_eq!(diff.frames, vec![1..3, 4..5, 6..7]);
+        assert_eq!(diff.first_difference(), Some(1));
+        assert_eq!(diff.frame_count(), 4);
+        assert!(ReplayDiff::new(&a, &a).is_empty());
+    }
+}
@@ -47,10 +47,18 @@ impl Window for ControlWindow {
                 || run_until_frame
               ) && *info.game_running
                 && info.err_string.is_none()
+                && info.watches.hit.is_none()
             {
                 self.advance_frame(info);
             }
 
+            if let Some(hit) = &info.watches.hit {
+                info.frame.text_colored([1.0, 0.5, 0.5, 1.0], hit);
+                if info.frame.button_with_size("Continue", [content_width, 20.0]) {
+                    info.watches.hit = None;
+                }
+            }
+
             if (info.frame.button_with_size("Quick Save", [content_width, 20.0])
                 || info.keybind_pressed(Binding::Quicksave)
               ) && *info.game_running
@@ -275,6 +283,10 @@ impl ControlWindow {
                 new_frame.mouse_y = y;
             }
 
+            if let Some(joysticks) = *info.new_joysticks {
+                new_frame.joysticks = joysticks;
+            }
+
             if let Some(rand) = &*info.new_rand {
                 new_frame.new_seed = Some(rand.clone());
             }
@@ -287,6 +299,8 @@ impl ControlWindow {
         if let Some(error) = self.run_frame(info.game, info.renderer_state) {
             *info.err_string = Some(error);
             *info.game_running = false;
+        } else if let Err(e) = info.game.log_state_hash(info.config.current_frame) {
+            *info.err_string = Some(format!("Failed to write state hash log: {}", e));
         }
 
         info.config.current_frame += 1;
@@ -337,8 +351,10 @@ impl ControlWindow {
         info.clear_context_menu();
         *info.new_rand = None;
         *info.new_mouse_pos = None;
+        *info.new_joysticks = None;
 
         info.update_instance_reports();
+        info.update_watches(true);
     }
 
     fn update_keyboard_state(&self, keyboard_state: &mut [KeyState; 256], frame: &mut Frame) {
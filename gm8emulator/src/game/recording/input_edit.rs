@@ -8,10 +8,10 @@ use crate::{
         replay::{FrameRng, Input, Replay},
     },
     imgui_utils::*,
-    input::Button,
+    input::{Button, Joystick, JOYSTICK_COUNT},
 };
 
-use super::popup_dialog::{string_input::RNGSelect, Dialog, DialogState};
+use super::popup_dialog::{joystick_input::JoystickSelect, string_input::RNGSelect, Dialog, DialogState};
 use imgui::*;
 
 #[derive(PartialEq, Eq)]
@@ -55,6 +55,12 @@ pub struct InputEditWindow {
     context_menu_keystate: KeyState,
 
     rng_select: RNGSelect,
+
+    joystick_select: JoystickSelect,
+    /// The frame to request the joystick dialog for once the table is drawn
+    joystick_select_request: Option<usize>,
+    /// The frames the joystick dialog edits: the first frame, and the last one if a range was selected
+    joystick_frames: (usize, Option<usize>),
 }
 
 struct RowColorStack<'a> {
@@ -120,6 +126,7 @@ impl Drop for RowColorStack<'_> {
 const INPUT_TABLE_WIDTH: f32 = 50.0;
 const INPUT_TABLE_RNG_WIDTH: f32 = INPUT_TABLE_WIDTH * 1.5;
 const INPUT_TABLE_MOUSE_WIDTH: f32 = INPUT_TABLE_WIDTH * 1.5;
+const INPUT_TABLE_JOYSTICK_WIDTH: f32 = INPUT_TABLE_WIDTH * 1.5;
 const INPUT_TABLE_HEIGHT: f32 = 20.0;
 const INPUT_TABLE_YPOS: f32 = 44.0;
 const TABLE_PADDING: f32 = 2.0;
@@ -222,6 +229,11 @@ impl Window for InputEditWindow {
                     TableColumnFlags::NO_REORDER | TableColumnFlags::WIDTH_FIXED,
                     INPUT_TABLE_MOUSE_WIDTH,
                 ));
+                info.frame.table_setup_column_with(TableColumnSetup::with_flags_and_init_width_or_weight(
+                    "Joystick",
+                    TableColumnFlags::NO_REORDER | TableColumnFlags::WIDTH_FIXED,
+                    INPUT_TABLE_JOYSTICK_WIDTH,
+                ));
                 info.frame.table_setup_scroll_freeze(0, 1); // freeze header row
                 info.frame.table_headers_row();
 
@@ -242,6 +254,15 @@ impl Window for InputEditWindow {
             }
             cell_padding_style_var.end();
 
+            if let Some(frame_index) = self.joystick_select_request.take() {
+                if let Some(replay_frame) = info.replay.get_frame(frame_index) {
+                    let joysticks = replay_frame.joysticks;
+                    info.request_modal(&mut self.joystick_select);
+                    self.joystick_select.set_joysticks(joysticks);
+                    self.joystick_frames = (frame_index, None);
+                }
+            }
+
             let hovered_text = if self.is_selecting != MouseSelection::None {
                 let count = self.selection_start_index.abs_diff(self.selection_end_index) + 1;
                 Some(match self.hovered_text {
@@ -318,6 +339,25 @@ impl Window for InputEditWindow {
             _ => self.is_selecting = MouseSelection::None, // Once the dialog is closed, stop displaying the selection (Closed, Cancelled, Invalid, etc)
         };
 
+        match self.joystick_select.show(info) {
+            DialogState::Submit => {
+                let (start, end) = self.joystick_frames;
+                if start >= info.config.current_frame {
+                    let joysticks = self.joystick_select.get_result();
+                    self.update_joysticks_for_frame(start, end, joysticks, info.replay);
+                }
+                self.is_selecting = MouseSelection::None;
+            },
+            DialogState::Open => {
+                any_open = true;
+                if self.joystick_frames.1.is_some() {
+                    // Same as above, keep showing the selected frames while the dialog is open
+                    self.is_selecting = MouseSelection::Fixed;
+                }
+            },
+            _ => (),
+        }
+
         any_open
     }
 }
@@ -333,7 +373,7 @@ impl Openable<Self> for InputEditWindow {
 }
 
 impl InputEditWindow {
-    const ADDITIONAL_COLUMNS: usize = 3; // Frame counter, RNG Seed and Mouse columns
+    const ADDITIONAL_COLUMNS: usize = 4; // Frame counter, RNG Seed, Mouse and Joystick columns
     const ADDITIONAL_COLUMNS_INFRONT: usize = 1; // How many of the above column are in front of all the key buttons (only Frame column)
 
     fn new() -> Self {
@@ -361,6 +401,35 @@ impl InputEditWindow {
             context_menu_keystate: KeyState::Neutral,
 
             rng_select: RNGSelect::new("Pick RNG"),
+
+            joystick_select: JoystickSelect::new("Set Joysticks"),
+            joystick_select_request: None,
+            joystick_frames: (0, None),
+        }
+    }
+
+    fn update_joysticks_for_frame(
+        &mut self,
+        frame: usize,
+        end_frame: Option<usize>,
+        joysticks: [Joystick; JOYSTICK_COUNT],
+        replay: &mut Replay,
+    ) {
+        // Like the mouse, this updates a range of frames if one was selected, and otherwise this frame and all
+        // following frames which had the same joystick states. Joysticks are always held, so there's no single frame mode.
+        if let Some(replay_frame) = replay.get_frame_mut(frame) {
+            let old_joysticks = replay_frame.joysticks;
+            replay_frame.joysticks = joysticks;
+
+            let mut current_frame = frame + 1;
+            while let Some(next_frame) = replay.get_frame_mut(current_frame) {
+                if end_frame.map_or(next_frame.joysticks == old_joysticks, |end| current_frame <= end) {
+                    next_frame.joysticks = joysticks;
+                    current_frame += 1;
+                } else {
+                    break
+                }
+            }
         }
     }
 
@@ -472,6 +541,14 @@ impl InputEditWindow {
                     self.setting_mouse_pos_end_frame = Some(end);
                 }
                 self.context_menu = false;
+            } else if frame.menu_item("Set Joysticks") {
+                if let Some(current_frame) = replay.get_frame(start) {
+                    let joysticks = current_frame.joysticks;
+                    info.request_modal(&mut self.joystick_select);
+                    self.joystick_select.set_joysticks(joysticks);
+                    self.joystick_frames = (start, Some(end));
+                }
+                self.context_menu = false;
             } else if frame.menu_item("Run until last selected frame") {
                 *info.run_until_frame = Some(end);
                 self.context_menu = false;
@@ -835,6 +912,32 @@ impl InputEditWindow {
                 }
             }
 
+            // Joystick Column
+            frame.table_set_column_index(self.keys.len() + Self::ADDITIONAL_COLUMNS_INFRONT + 2);
+            let prev_joysticks = prev_frame.map_or([Joystick::new(); JOYSTICK_COUNT], |f| f.joysticks);
+            // Only show which joysticks changed, the states themselves don't fit in a cell
+            let changed_joysticks = (0..JOYSTICK_COUNT)
+                .filter(|&id| current_frame.joysticks[id] != prev_joysticks[id])
+                .map(|id| format!("J{}", id + 1))
+                .collect::<Vec<_>>();
+            let joystick_text =
+                if changed_joysticks.is_empty() { String::from("-") } else { changed_joysticks.join(" ") };
+
+            frame.button_with_size(&joystick_text, [INPUT_TABLE_JOYSTICK_WIDTH, INPUT_TABLE_HEIGHT]);
+            let joystick_hovered = frame.is_item_hovered();
+            if joystick_hovered {
+                any_button_hovered = true;
+            }
+
+            // If we clicked on a joystick input we haven't reached yet
+            if i >= config.current_frame && joystick_hovered {
+                if frame.is_mouse_clicked(imgui::MouseButton::Left) {
+                    self.joystick_select_request = Some(i);
+                } else if frame.is_mouse_clicked(imgui::MouseButton::Middle) {
+                    self.update_joysticks_for_frame(i, None, prev_joysticks, *replay);
+                }
+            }
+
             // If we aren't hovering any of the key buttons, check if we are hovering the current row
             if frame.is_mouse_clicked(imgui::MouseButton::Right)
                 && !any_button_hovered
@@ -2,6 +2,7 @@ use crate::{
     game::recording::{
         self,
         keybinds::Binding,
+        popup_dialog::joystick_input::render_joystick_editor,
         window::{EmulatorContext, Window},
         InputMode, KeyState,
     },
@@ -9,7 +10,7 @@ use crate::{
     input,
     types::Colour,
 };
-use imgui::StyleColor;
+use imgui::{StyleColor, TreeNodeFlags};
 use ramen::input::Key;
 
 pub enum ContextMenuType {
@@ -81,6 +82,12 @@ impl InputWindows {
             .size([300.0, 138.0], imgui::Condition::Always)
             .build(|| self.render_mouse_window(info));
 
+        frame
+            .window("Joystick")
+            .position([304.0, 210.0], imgui::Condition::FirstUseEver)
+            .size([260.0, 400.0], imgui::Condition::FirstUseEver)
+            .build(|| self.render_joystick_window(info));
+
         if self.request_context_menu {
             if !info.request_context_menu() {
                 self.context_menu_type = None;
@@ -465,6 +472,25 @@ impl InputWindows {
         }
     }
 
+    /// Renders the virtual joystick state menu into an imgui window
+    fn render_joystick_window(&mut self, info: &mut EmulatorContext) {
+        let frame = info.frame;
+        let edited = info.new_joysticks.is_some();
+        let mut joysticks = info.new_joysticks.unwrap_or_else(|| info.game.input.joysticks());
+        let mut changed = false;
+
+        for (id, joystick) in joysticks.iter_mut().enumerate() {
+            let label = if edited { format!("Joystick {}*", id + 1) } else { format!("Joystick {}", id + 1) };
+            if frame.collapsing_header(format!("{}###joystick{}", label, id), TreeNodeFlags::DEFAULT_OPEN) {
+                changed |= render_joystick_editor(frame, id, joystick);
+            }
+        }
+
+        if changed {
+            *info.new_joysticks = Some(joysticks);
+        }
+    }
+
     /// Renders a single keyboard control button
     fn render_keyboard_button(
         &mut self,
@@ -1,6 +1,6 @@
 use crate::game::recording::{
-    console::ConsoleWindow, input_edit::InputEditWindow, keybinds::KeybindWindow, macro_window::MacroWindow,
-    window::Openable, UIState,
+    branch_window::BranchWindow, console::ConsoleWindow, input_edit::InputEditWindow, keybinds::KeybindWindow, macro_window::MacroWindow,
+    watch_window::WatchWindow, window::Openable, UIState,
 };
 
 impl UIState<'_> {
@@ -72,6 +72,8 @@ impl UIState<'_> {
                         single InputEditWindow,
                         multi ConsoleWindow,
                         multi MacroWindow,
+                        single BranchWindow,
+                        single WatchWindow,
                     }
 
                     open_menu_token.end();
@@ -1,3 +1,4 @@
+pub mod joystick_input;
 pub mod string_input;
 
 use super::window::EmulatorContext;
@@ -0,0 +1,112 @@
+use super::{Dialog, DialogState};
+use crate::{
+    game::recording::window::EmulatorContext,
+    input::{self, Joystick, JOYSTICK_COUNT},
+    math::Real,
+};
+
+pub struct JoystickSelect {
+    name: &'static str,
+    joysticks: [Joystick; JOYSTICK_COUNT],
+}
+
+impl Dialog for JoystickSelect {
+    fn show(&mut self, info: &mut EmulatorContext) -> DialogState {
+        let frame = info.frame;
+        let mut state: DialogState = DialogState::Closed;
+
+        if let Some(token) = frame.begin_popup(&self.name) {
+            state = DialogState::Open;
+
+            for (id, joystick) in self.joysticks.iter_mut().enumerate() {
+                frame.text(format!("Joystick {}", id + 1));
+                render_joystick_editor(frame, id, joystick);
+                frame.separator();
+            }
+
+            if frame.button_with_size("Submit", [50.0, 20.0]) {
+                frame.close_current_popup();
+                state = DialogState::Submit;
+            }
+
+            frame.same_line_with_spacing(0.0, 5.0);
+            if frame.button_with_size("Cancel", [50.0, 20.0]) {
+                frame.close_current_popup();
+                state = DialogState::Cancelled;
+            }
+            token.end();
+        }
+
+        state
+    }
+
+    fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    fn reset(&mut self) {
+        self.joysticks = [Joystick::new(); JOYSTICK_COUNT];
+    }
+}
+
+impl JoystickSelect {
+    pub fn new(name: &'static str) -> JoystickSelect {
+        JoystickSelect { name, joysticks: [Joystick::new(); JOYSTICK_COUNT] }
+    }
+
+    /// Sets the state to start editing from. Call this after requesting the dialog, since that resets it.
+    pub fn set_joysticks(&mut self, joysticks: [Joystick; JOYSTICK_COUNT]) {
+        self.joysticks = joysticks;
+    }
+
+    /// Gets the joysticks as edited. Only meaningful if show() returned DialogState::Submit.
+    pub fn get_result(&self) -> [Joystick; JOYSTICK_COUNT] {
+        self.joysticks
+    }
+}
+
+/// Renders the controls for one virtual joystick, returning whether anything was changed.
+/// `id` is the joystick's index, used to keep the controls of different joysticks apart.
+pub fn render_joystick_editor(frame: &imgui::Ui, id: usize, joystick: &mut Joystick) -> bool {
+    let mut changed = false;
+
+    let mut connected = joystick.connected;
+    if frame.checkbox(format!("Connected##joy{}", id), &mut connected) {
+        // Disconnecting resets everything else, so disconnected joysticks all compare equal
+        *joystick = Joystick { connected, ..Joystick::new() };
+        changed = true;
+    }
+    if !joystick.connected {
+        return changed
+    }
+
+    for (name, axis) in input::JOYSTICK_AXES.iter().zip(joystick.axes.iter_mut()) {
+        let mut value = axis.into_inner() as f32;
+        frame.set_next_item_width(150.0);
+        if frame.slider(format!("{}##joy{}", name, id), -1.0, 1.0, &mut value) {
+            // Rounded so text replays don't fill up with float noise
+            *axis = Real::from((f64::from(value) * 1000.0).round() / 1000.0);
+            changed = true;
+        }
+    }
+
+    for button in 1..=input::JOYSTICK_BUTTONS as i32 {
+        if (button - 1) % 8 != 0 {
+            frame.same_line();
+        }
+        let mut held = joystick.button(button);
+        if frame.checkbox(format!("{}##joy{}", button, id), &mut held) {
+            joystick.set_button(button, held);
+            changed = true;
+        }
+    }
+
+    let mut pov = joystick.pov;
+    frame.set_next_item_width(150.0);
+    if frame.input_int(format!("POV##joy{}", id), &mut pov).step(45).build() {
+        joystick.pov = if pov < 0 { -1 } else { pov % 360 };
+        changed = true;
+    }
+
+    changed
+}
@@ -0,0 +1,116 @@
+use crate::game::recording::{
+    watches::Breakpoint,
+    window::{EmulatorContext, Openable, Window},
+};
+
+const ERROR_COLOUR: [f32; 4] = [1.0, 0.5, 0.5, 1.0];
+
+pub struct WatchWindow {
+    is_open: bool,
+    new_watch: String,
+    new_breakpoint: String,
+}
+
+impl Openable<Self> for WatchWindow {
+    fn window_name() -> &'static str {
+        "Watches"
+    }
+
+    fn open(_id: usize) -> Self {
+        Self::new()
+    }
+}
+
+impl Window for WatchWindow {
+    fn stored_kind(&self) -> Option<super::WindowKind> {
+        Some(super::WindowKind::Watches)
+    }
+
+    fn name(&self) -> String {
+        "Watches".to_owned()
+    }
+
+    fn show_window(&mut self, info: &mut EmulatorContext) {
+        let frame = info.frame;
+        let mut is_open = self.is_open;
+        frame.window("Watches").opened(&mut is_open).build(|| {
+            self.show_watches(info);
+            frame.separator();
+            self.show_breakpoints(info);
+        });
+        self.is_open = is_open;
+    }
+
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+}
+
+impl WatchWindow {
+    pub fn new() -> Self {
+        Self { is_open: true, new_watch: String::new(), new_breakpoint: String::new() }
+    }
+
+    fn show_watches(&mut self, info: &mut EmulatorContext) {
+        let frame = info.frame;
+        let mut remove = None;
+        for (i, watch) in info.watches.watches.iter().enumerate() {
+            if frame.button(format!("X##removewatch{}", i)) {
+                remove = Some(i);
+            }
+            frame.same_line();
+            frame.text(format!("{} = {}", watch.source, watch.value));
+        }
+        if let Some(i) = remove {
+            info.watches.watches.remove(i);
+            info.save_watches();
+        }
+
+        frame.input_text("##newwatch", &mut self.new_watch).hint("Expression").build();
+        if frame.is_item_focused() {
+            info.keybindings.disable_bindings();
+        }
+        frame.same_line();
+        if frame.button("Add Watch") && !self.new_watch.is_empty() {
+            info.watches.add_watch(info.game, std::mem::take(&mut self.new_watch));
+            info.save_watches();
+        }
+    }
+
+    fn show_breakpoints(&mut self, info: &mut EmulatorContext) {
+        let frame = info.frame;
+        let mut remove = None;
+        for (i, state) in info.watches.breakpoints.iter().enumerate() {
+            if frame.button(format!("X##removebreakpoint{}", i)) {
+                remove = Some(i);
+            }
+            frame.same_line();
+            frame.text(format!("Break {}", state.breakpoint.description()));
+            if let Some(error) = state.error() {
+                frame.text_colored(ERROR_COLOUR, format!("    {}", error));
+            }
+        }
+        if let Some(i) = remove {
+            info.watches.breakpoints.remove(i);
+            info.save_watches();
+        }
+
+        frame.input_text("##newbreakpoint", &mut self.new_breakpoint).hint("Condition or object name").build();
+        if frame.is_item_focused() {
+            info.keybindings.disable_bindings();
+        }
+        let mut breakpoint = None;
+        if frame.button("Break When True") {
+            breakpoint = Some(Breakpoint::Condition(self.new_breakpoint.clone()));
+        }
+        frame.same_line();
+        if frame.button("Break On Create") {
+            breakpoint = Some(Breakpoint::InstanceCreate(self.new_breakpoint.clone()));
+        }
+        if let Some(breakpoint) = breakpoint.filter(|_| !self.new_breakpoint.is_empty()) {
+            info.watches.add_breakpoint(info.game, breakpoint);
+            info.save_watches();
+            self.new_breakpoint.clear();
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Watch expressions and breakpoints, which are evaluated after every frame in record mode.
+//!
+//! Evaluating them mustn't change the game state or the recording would desync, so expressions which call scripts or
+//! functions that can change the game state or depend on the outside world are refused when they're added.
+
+use crate::{
+    game::{Game, GetAsset},
+    gml::{
+        runtime::{ArrayAccessor, InstanceIdentifier, Node},
+        Context, Value,
+    },
+    instance::Instance,
+    types::ID,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Breakpoint {
+    /// Stops when a GML expression goes from false to true
+    Condition(String),
+    /// Stops when an instance of the named object, or one of its children, is created
+    InstanceCreate(String),
+}
+
+impl Breakpoint {
+    pub fn description(&self) -> String {
+        match self {
+            Self::Condition(source) => format!("when {}", source),
+            Self::InstanceCreate(object) => format!("when {} is created", object),
+        }
+    }
+}
+
+/// The watches and breakpoints saved in a project.
+#[derive(Default, Serialize, Deserialize)]
+pub struct WatchList {
+    pub watches: Vec<String>,
+    pub breakpoints: Vec<Breakpoint>,
+}
+
+pub fn list_path(project_path: &Path) -> PathBuf {
+    project_path.join("watches.cfg")
+}
+
+enum Compiled {
+    Condition(Node),
+    InstanceCreate(ID),
+}
+
+pub struct Watch {
+    pub source: String,
+    node: Result<Node, String>,
+    pub value: String,
+}
+
+pub struct BreakpointState {
+    pub breakpoint: Breakpoint,
+    compiled: Result<Compiled, String>,
+    was_true: bool,
+}
+
+impl BreakpointState {
+    pub fn error(&self) -> Option<&str> {
+        self.compiled.as_ref().err().map(String::as_str)
+    }
+}
+
+pub struct Watches {
+    pub watches: Vec<Watch>,
+    pub breakpoints: Vec<BreakpointState>,
+    last_instance_id: ID,
+    /// Why frame advance was stopped, if it was. Frames can't be advanced until this is cleared.
+    pub hit: Option<String>,
+}
+
+/// Whether an expression can be evaluated without changing the game state.
+fn is_read_only(node: &Node) -> bool {
+    let array = |array: &ArrayAccessor| match array {
+        ArrayAccessor::None => true,
+        ArrayAccessor::Single(index) => is_read_only(index),
+        ArrayAccessor::Double(index1, index2) => is_read_only(index1) && is_read_only(index2),
+    };
+    let owner = |owner: &InstanceIdentifier| match owner {
+        InstanceIdentifier::Expression(node) => is_read_only(node),
+        _ => true,
+    };
+    match node {
+        Node::Constant { .. } | Node::Literal { .. } | Node::RuntimeError { .. } => true,
+        Node::Variable { accessor } => array(&accessor.array) && owner(&accessor.owner),
+        Node::Field { accessor } => array(&accessor.array) && owner(&accessor.owner),
+        // volatile functions can depend on the outside world, which isn't the same during a replay
+        Node::RoutineFunction { args, function } => !function.is_volatile() && args.iter().all(is_read_only),
+        Node::ValueFunction { args, .. } => args.iter().all(is_read_only),
+        Node::ContextFunction { .. }
+        | Node::StateFunction { .. }
+        | Node::Script { .. }
+        | Node::ExtensionFunction { .. } => false,
+        Node::Unary { child, .. } => is_read_only(child),
+        Node::Binary { left, right, .. } => is_read_only(left) && is_read_only(right),
+    }
+}
+
+fn compile(game: &mut Game, source: &str) -> Result<Node, String> {
+    let node = game.compiler.compile_expression(source.as_bytes()).map_err(|e| e.message)?;
+    if is_read_only(&node) {
+        Ok(node)
+    } else {
+        Err("can't call scripts or functions which change the game state or depend on the outside world".into())
+    }
+}
+
+fn compile_breakpoint(game: &mut Game, breakpoint: &Breakpoint) -> Result<Compiled, String> {
+    match breakpoint {
+        Breakpoint::Condition(source) => compile(game, source).map(Compiled::Condition),
+        Breakpoint::InstanceCreate(name) => game
+            .assets
+            .objects
+            .iter()
+            .position(|object| object.as_ref().is_some_and(|o| o.name.as_ref() == name.as_bytes()))
+            .map(|index| Compiled::InstanceCreate(index as ID))
+            .ok_or_else(|| format!("there's no object called {}", name)),
+    }
+}
+
+/// Evaluates an expression as a dummy instance, the same way constants are.
+fn eval(game: &mut Game, node: &Node) -> Result<Value, String> {
+    let dummy_instance =
+        game.room.instance_list.insert_dummy(Instance::new_dummy(game.assets.objects.get_asset(0).map(|x| x.as_ref())));
+    let result = game.eval(node, &mut Context::with_single_instance(dummy_instance));
+    game.room.instance_list.remove_dummy(dummy_instance);
+    result.map_err(|e| e.to_string())
+}
+
+impl Watches {
+    pub fn new(game: &mut Game, watches: &[String], breakpoints: &[Breakpoint]) -> Self {
+        let mut new =
+            Self { watches: Vec::new(), breakpoints: Vec::new(), last_instance_id: game.last_instance_id, hit: None };
+        for source in watches {
+            new.add_watch(game, source.clone());
+        }
+        for breakpoint in breakpoints {
+            new.add_breakpoint(game, breakpoint.clone());
+        }
+        new
+    }
+
+    pub fn add_watch(&mut self, game: &mut Game, source: String) {
+        let node = compile(game, &source);
+        let mut watch = Watch { source, node, value: String::new() };
+        Self::update_watch(game, &mut watch);
+        self.watches.push(watch);
+    }
+
+    pub fn add_breakpoint(&mut self, game: &mut Game, breakpoint: Breakpoint) {
+        let compiled = compile_breakpoint(game, &breakpoint);
+        let mut state = BreakpointState { breakpoint, compiled, was_true: false };
+        if let Ok(Compiled::Condition(node)) = &state.compiled {
+            state.was_true = eval(game, node).is_ok_and(|v| v.is_truthy());
+        }
+        self.breakpoints.push(state);
+    }
+
+    /// Every watch expression and breakpoint, for storing in the project.
+    pub fn list(&self) -> WatchList {
+        WatchList {
+            watches: self.watches.iter().map(|w| w.source.clone()).collect(),
+            breakpoints: self.breakpoints.iter().map(|b| b.breakpoint.clone()).collect(),
+        }
+    }
+
+    fn update_watch(game: &mut Game, watch: &mut Watch) {
+        watch.value = match &watch.node {
+            Ok(node) => match eval(game, node) {
+                Ok(value) => value.to_string(),
+                Err(e) => format!("error: {}", e),
+            },
+            Err(e) => format!("invalid: {}", e),
+        };
+    }
+
+    /// Evaluates every watch and breakpoint. If `check_breakpoints` is set, breakpoints which trigger set `hit`,
+    /// otherwise they're only brought up to date, such as after loading a savestate.
+    pub fn update(&mut self, game: &mut Game, check_breakpoints: bool) {
+        for watch in &mut self.watches {
+            Self::update_watch(game, watch);
+        }
+        let mut hits = Vec::new();
+        for state in &mut self.breakpoints {
+            match &state.compiled {
+                Ok(Compiled::Condition(node)) => {
+                    let is_true = eval(game, node).is_ok_and(|v| v.is_truthy());
+                    if is_true && !state.was_true {
+                        hits.push(state.breakpoint.description());
+                    }
+                    state.was_true = is_true;
+                },
+                Ok(Compiled::InstanceCreate(object)) => {
+                    // instance IDs only go up, so anything above the last one seen is new
+                    let list = &game.room.instance_list;
+                    let mut iter = list.iter_by_identity(*object);
+                    while let Some(handle) = iter.next(list) {
+                        let id = list.get(handle).id.get();
+                        if id > self.last_instance_id {
+                            hits.push(format!("{} (instance {})", state.breakpoint.description(), id));
+                            break
+                        }
+                    }
+                },
+                Err(_) => (),
+            }
+        }
+        self.last_instance_id = game.last_instance_id;
+        if check_breakpoints && !hits.is_empty() {
+            self.hit = Some(format!("Breakpoint hit: {}", hits.join(", ")));
+        }
+    }
+}
@@ -1,19 +1,24 @@
 use crate::{
     game::{
         recording::{
+            branches::{self, BranchTree},
             instance_report::InstanceReport,
             keybinds::{Binding, Keybindings},
             popup_dialog::Dialog,
+            save_project_file,
+            watches::{self, Watches},
             KeyState, ProjectConfig, WindowKind,
         },
         replay::{FrameRng, Replay},
         savestate::{self, SaveState},
         Game,
     },
+    gml::datetime,
     imgui_utils::Vec2,
+    input::{Joystick, JOYSTICK_COUNT},
     render::RendererState,
 };
-use std::path::PathBuf;
+use std::{fs, io, path::PathBuf};
 
 pub struct EmulatorContext<'a> {
     pub game: &'a mut Game,
@@ -21,6 +26,7 @@ pub struct EmulatorContext<'a> {
     pub game_running: &'a mut bool,
     pub setting_mouse_pos: &'a mut bool,
     pub new_mouse_pos: &'a mut Option<(i32, i32)>,
+    pub new_joysticks: &'a mut Option<[Joystick; JOYSTICK_COUNT]>,
     pub new_rand: &'a mut Option<FrameRng>,
     pub config: &'a mut ProjectConfig,
     pub err_string: &'a mut Option<String>,
@@ -32,6 +38,8 @@ pub struct EmulatorContext<'a> {
     pub renderer_state: &'a mut RendererState,
     pub save_buffer: &'a mut savestate::Buffer,
     pub instance_reports: &'a mut Vec<(i32, Option<InstanceReport>)>,
+    pub watches: &'a mut Watches,
+    pub branches: &'a mut BranchTree,
 
     pub clean_state: &'a mut bool,
     pub run_until_frame: &'a mut Option<usize>,
@@ -117,6 +125,32 @@ impl EmulatorContext<'_> {
             self.config.watched_ids.iter().map(|id| (*id, InstanceReport::new(self.game, *id))).collect();
     }
 
+    /// Re-evaluates the watch expressions and breakpoints. If `check_breakpoints` is set and one of them triggers,
+    /// frame advance stops until the user continues.
+    pub fn update_watches(&mut self, check_breakpoints: bool) {
+        if !*self.game_running {
+            return
+        }
+        self.watches.update(self.game, check_breakpoints);
+        if self.watches.hit.is_some() {
+            *self.run_until_frame = None;
+        }
+    }
+
+    /// Saves the current watches and breakpoints into the project.
+    pub fn save_watches(&mut self) {
+        if let Some(err) = save_project_file(&watches::list_path(self.project_path), &self.watches.list(), "watches") {
+            *self.err_string = Some(err);
+        }
+    }
+
+    /// Saves the branch tree into the project's branches folder.
+    pub fn save_branches(&mut self) {
+        if let Some(err) = save_project_file(&branches::tree_path(self.project_path), &*self.branches, "branch tree") {
+            *self.err_string = Some(err);
+        }
+    }
+
     pub fn keybind_pressed(&self, binding: Binding) -> bool {
         self.keybindings.keybind_pressed(binding, self.frame)
     }
@@ -129,12 +163,7 @@ impl EmulatorContext<'_> {
         if slot >= self.save_paths.len() {
             false
         } else {
-            let mut savestate_replay = self.replay.clone();
-
-            // make sure the saved replay is only up to the savestate.
-            savestate_replay.truncate_frames(self.config.current_frame);
-
-            let state = SaveState::from(self.game, savestate_replay, self.renderer_state.clone(), *self.clean_state);
+            let state = self.create_savestate();
             let result = self.savestate_save_to_file(slot, &state);
             if slot == self.config.quicksave_slot {
                 *self.savestate = state;
@@ -143,6 +172,20 @@ impl EmulatorContext<'_> {
         }
     }
 
+    /// Makes a savestate of the current frame, with its info filled in from the project.
+    fn create_savestate(&mut self) -> SaveState {
+        let mut savestate_replay = self.replay.clone();
+
+        // make sure the saved replay is only up to the savestate.
+        savestate_replay.truncate_frames(self.config.current_frame);
+
+        let mut state = SaveState::from(self.game, savestate_replay, self.renderer_state.clone(), *self.clean_state);
+        state.info.parent = self.branches.current;
+        state.info.rerecords = self.config.rerecords;
+        state.info.timestamp = datetime::now_as_nanos();
+        state
+    }
+
     pub fn savestate_load(&mut self, slot: usize) -> bool {
         *self.run_until_frame = None;
         if slot == self.config.quicksave_slot {
@@ -202,6 +245,9 @@ impl EmulatorContext<'_> {
                         savestate::ReadError::DeserializeErr(err) => {
                             format!("Error deserializing {}:\n\n{}", filename, err)
                         },
+                        savestate::ReadError::UnknownVersion(version) => {
+                            format!("{} is savestate version {}, which this version can't load", filename, version)
+                        },
                     });
                     None
                 },
@@ -235,6 +281,7 @@ impl EmulatorContext<'_> {
         self.clear_context_menu();
         *self.new_rand = None;
         *self.new_mouse_pos = None;
+        *self.new_joysticks = None;
         *self.err_string = None;
         *self.game_running = true;
 
@@ -252,6 +299,86 @@ impl EmulatorContext<'_> {
         self.config.save();
 
         self.update_instance_reports();
+        self.watches.hit = None;
+        self.update_watches(false);
+    }
+
+    /// Makes a new branch from the current frame, as a child of the current branch.
+    /// The whole replay is kept with it, so in read-only mode it includes the frames after the current one too.
+    pub fn branch_create(&mut self, label: String) -> bool {
+        let mut state = self.create_savestate();
+        state.info.label = label;
+        let id = self.branches.add(state.info.clone());
+        let state_path = branches::savestate_path(self.project_path, id);
+        let result = match state_path.parent().map(fs::create_dir_all).unwrap_or(Ok(())) {
+            Ok(()) => match state.save_to_file(&state_path, self.save_buffer) {
+                Ok(()) => self
+                    .replay
+                    .to_file(&branches::replay_path(self.project_path, id))
+                    .map_err(|e| format!("Failed to save the replay for branch #{}: {:?}", id, e)),
+                Err(e) => Err(format!("Failed to save the savestate for branch #{}: {:?}", id, e)),
+            },
+            Err(e) => Err(format!("Failed to create the branches folder: {}", e)),
+        };
+        match result {
+            Ok(()) => {
+                self.save_branches();
+                true
+            },
+            Err(err) => {
+                self.branch_delete(id);
+                *self.err_string = Some(err);
+                false
+            },
+        }
+    }
+
+    /// Loads a branch's savestate and swaps in its replay, making it the current branch.
+    pub fn branch_restore(&mut self, id: u64) -> bool {
+        let state = match SaveState::from_file(&branches::savestate_path(self.project_path, id), self.save_buffer) {
+            Ok(state) => state,
+            Err(e) => {
+                *self.err_string = Some(format!("Failed to load the savestate for branch #{}: {:?}", id, e));
+                return false
+            },
+        };
+        let replay = match self.branch_replay(id) {
+            Some(replay) => replay,
+            None => return false,
+        };
+        *self.run_until_frame = None;
+        // set the replay first so loading in read-only mode doesn't complain that the state isn't part of it,
+        // then again afterwards since read/write mode replaces it with the savestate's replay
+        *self.replay = replay.clone();
+        self.savestate_load_from_state(state);
+        *self.replay = replay;
+        self.branches.current = Some(id);
+        self.save_branches();
+        true
+    }
+
+    /// Loads the whole replay that was kept with a branch.
+    pub fn branch_replay(&mut self, id: u64) -> Option<Replay> {
+        match Replay::from_file(&branches::replay_path(self.project_path, id)) {
+            Ok(replay) => Some(replay),
+            Err(e) => {
+                *self.err_string = Some(format!("Failed to load the replay for branch #{}: {:?}", id, e));
+                None
+            },
+        }
+    }
+
+    /// Deletes a branch and its files. Its children are moved up to its parent.
+    pub fn branch_delete(&mut self, id: u64) {
+        for path in [branches::savestate_path(self.project_path, id), branches::replay_path(self.project_path, id)] {
+            if let Err(e) = fs::remove_file(&path) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    *self.err_string = Some(format!("Failed to delete {}: {}", path.to_string_lossy(), e));
+                }
+            }
+        }
+        self.branches.remove(id);
+        self.save_branches();
     }
 
     pub fn clear_context_menu(&mut self) {
@@ -0,0 +1,156 @@
+//! An emulated Windows registry, for games which keep settings or save data in it.
+//!
+//! Nothing touches the real registry. Values are kept in memory, and every change is mirrored to a file in the
+//! game's temp directory, which is inside the project folder when recording, so they persist between sessions.
+//! Since a replay won't have that file, recordings store what the registry held when they started.
+
+use crate::gml::Value;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, ErrorKind},
+    path::PathBuf,
+};
+
+/// The name of the file the registry is mirrored to.
+pub const FILE_NAME: &str = "registry.gmreg";
+
+/// One of the predefined keys registry paths are relative to. The discriminants match the GML arguments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Root {
+    CurrentUser = 0,
+    LocalMachine = 1,
+    ClassesRoot = 2,
+    Users = 3,
+}
+
+impl Root {
+    pub fn from_gml(root: i32) -> Option<Self> {
+        match root {
+            0 => Some(Self::CurrentUser),
+            1 => Some(Self::LocalMachine),
+            2 => Some(Self::ClassesRoot),
+            3 => Some(Self::Users),
+            _ => None,
+        }
+    }
+}
+
+/// The contents of the registry, as stored in savestates, replays and the registry file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistryState {
+    pub root: Root, // used by the _ext functions
+    // key and value names are case-insensitive, so they're stored lowercased
+    keys: BTreeMap<(Root, Vec<u8>), BTreeMap<Vec<u8>, Value>>,
+}
+
+impl Default for RegistryState {
+    fn default() -> Self {
+        Self { root: Root::CurrentUser, keys: BTreeMap::new() }
+    }
+}
+
+impl RegistryState {
+    /// Every value, ordered by root, key and name.
+    pub fn values(&self) -> impl Iterator<Item = (Root, &[u8], &[u8], &Value)> {
+        self.keys.iter().flat_map(|((root, key), values)| {
+            values.iter().map(move |(name, value)| (*root, key.as_slice(), name.as_slice(), value))
+        })
+    }
+
+    pub fn insert(&mut self, root: Root, key: &[u8], name: &[u8], value: Value) {
+        self.keys.entry((root, normalize(key))).or_default().insert(normalize(name), value);
+    }
+}
+
+#[derive(Default)]
+pub struct Registry {
+    state: RegistryState,
+    file: Option<PathBuf>,
+}
+
+/// Lowercases a key or value name and normalizes its backslashes.
+fn normalize(name: &[u8]) -> Vec<u8> {
+    let parts = name.split(|&c| c == b'\\').filter(|part| !part.is_empty()).collect::<Vec<_>>();
+    parts.join(&b'\\').to_ascii_lowercase()
+}
+
+impl Registry {
+    /// Sets the file to mirror the registry to, loading it first if it already exists.
+    pub fn open(&mut self, path: PathBuf) -> io::Result<()> {
+        match fs::read(&path) {
+            Ok(bytes) => {
+                self.state = bincode::deserialize(&bytes).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+            },
+            Err(e) if e.kind() == ErrorKind::NotFound => (),
+            Err(e) => return Err(e),
+        }
+        self.file = Some(path);
+        Ok(())
+    }
+
+    /// Mirrors the registry to its file. A failure is only reported, as the file is just a cache of what the game
+    /// sees and mustn't change how it runs.
+    fn flush_or_warn(&self) {
+        if let Err(e) = self.flush() {
+            eprintln!("Failed to write registry file: {}", e);
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        match &self.file {
+            Some(path) => {
+                let bytes = bincode::serialize(&self.state).map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+                fs::write(path, bytes)
+            },
+            None => Ok(()),
+        }
+    }
+
+    pub fn save_state(&self) -> RegistryState {
+        self.state.clone()
+    }
+
+    pub fn load_state(&mut self, state: RegistryState) {
+        self.state = state;
+        self.flush_or_warn();
+    }
+
+    /// The root used by the `_ext` functions.
+    pub fn root(&self) -> Root {
+        self.state.root
+    }
+
+    pub fn set_root(&mut self, root: Root) {
+        self.state.root = root;
+    }
+
+    pub fn read(&self, root: Root, key: &[u8], name: &[u8]) -> Option<&Value> {
+        self.state.keys.get(&(root, normalize(key))).and_then(|values| values.get(&normalize(name)))
+    }
+
+    pub fn write(&mut self, root: Root, key: &[u8], name: &[u8], value: Value) {
+        self.state.insert(root, key, name, value);
+        self.flush_or_warn();
+    }
+}
+
+/// The key that the functions without `_ext` use, which GM8 names after the game ID.
+pub fn game_key(game_id: i32) -> String {
+    format!("Software\\Game Maker\\GM{}", game_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_insensitive() {
+        let mut registry = Registry::default();
+        registry.write(Root::CurrentUser, b"Software\\Test\\", b"Name", Value::from(1.0));
+        assert!(registry.read(Root::CurrentUser, b"\\software\\TEST", b"name").is_some());
+        assert!(registry.read(Root::LocalMachine, b"Software\\Test", b"Name").is_none());
+        assert!(registry.read(Root::CurrentUser, b"Software", b"Name").is_none());
+    }
+}
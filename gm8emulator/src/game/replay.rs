@@ -1,4 +1,11 @@
-use crate::gml::Value;
+pub mod libtas;
+pub mod text;
+
+use crate::{
+    game::registry::RegistryState,
+    gml::Value,
+    input::{Joystick, JOYSTICK_COUNT},
+};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use lzzzz::lz4;
 use serde::{Deserialize, Serialize};
@@ -8,6 +15,11 @@ use std::{
     path::PathBuf,
 };
 
+// The version written at the start of gmtas files. Version 1 replays were written before joysticks were added,
+// version 2 replays didn't record whether the game's files were seeded, and version 3 replays didn't store the
+// registry's starting contents.
+const FILE_VERSION: u32 = 4;
+
 // Represents an entire replay (TAS) file
 #[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct Replay {
@@ -21,6 +33,12 @@ pub struct Replay {
     // Special list of stored events used during startup (before frame 0)
     pub startup_events: Vec<Event>,
 
+    // Whether the sandboxed game could read the real files in its directories (--seed-files).
+    pub seeded_files: bool,
+
+    // The contents of the emulated registry at the beginning of this replay.
+    pub start_registry: RegistryState,
+
     // List of frames in this replay.
     frames: Vec<Frame>,
 }
@@ -60,17 +78,20 @@ pub struct Frame {
     pub events: Vec<Event>,
     pub new_seed: Option<FrameRng>,
     pub new_time: Option<u128>,
+    pub joysticks: [Joystick; JOYSTICK_COUNT],
 }
 
 // Stored events for certain things which must always happen the same way during replay
 #[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub enum Event {
-    GetInteger(Value),   // value returned from get_integer()
-    GetString(Value),    // value returned from get_string()
-    Randomize(i32),      // value assigned to seed by randomize()
-    ShowMenu(Value),     // value returned from show_menu()
-    ShowMessage,         // acknowledges that a show_message() does not need to be shown during replay
-    ShowQuestion(Value), // value returned from show_question()
+    GetInteger(Value),     // value returned from get_integer()
+    GetString(Value),      // value returned from get_string()
+    Randomize(i32),        // value assigned to seed by randomize()
+    ShowMenu(Value),       // value returned from show_menu()
+    ShowMessage,           // acknowledges that a show_message() does not need to be shown during replay
+    ShowQuestion(Value),   // value returned from show_question()
+    ShowMessageExt(Value), // value returned from show_message_ext()
+    HighscoreName(Value),  // name entered for a new highscore
 }
 
 // An input event which takes place during a frame
@@ -84,12 +105,100 @@ pub enum Input {
     MouseWheelDown,
 }
 
+// The layout of version 1 replays, which didn't have joysticks
+#[derive(Serialize, Deserialize)]
+pub struct ReplayV1 {
+    start_time: u128,
+    start_seed: i32,
+    startup_events: Vec<Event>,
+    frames: Vec<FrameV1>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FrameV1 {
+    mouse_x: i32,
+    mouse_y: i32,
+    inputs: Vec<Input>,
+    events: Vec<Event>,
+    new_seed: Option<FrameRng>,
+    new_time: Option<u128>,
+}
+
+// The layout of version 2 replays, which didn't record whether files were seeded
+#[derive(Serialize, Deserialize)]
+pub struct ReplayV2 {
+    start_time: u128,
+    start_seed: i32,
+    startup_events: Vec<Event>,
+    frames: Vec<Frame>,
+}
+
+impl From<ReplayV1> for ReplayV2 {
+    fn from(replay: ReplayV1) -> Self {
+        let frames = replay
+            .frames
+            .into_iter()
+            .map(|f| Frame {
+                mouse_x: f.mouse_x,
+                mouse_y: f.mouse_y,
+                inputs: f.inputs,
+                events: f.events,
+                new_seed: f.new_seed,
+                new_time: f.new_time,
+                joysticks: Default::default(),
+            })
+            .collect();
+        Self {
+            start_time: replay.start_time,
+            start_seed: replay.start_seed,
+            startup_events: replay.startup_events,
+            frames,
+        }
+    }
+}
+
+// The layout of version 3 replays, which didn't store the registry
+#[derive(Serialize, Deserialize)]
+pub struct ReplayV3 {
+    start_time: u128,
+    start_seed: i32,
+    startup_events: Vec<Event>,
+    seeded_files: bool,
+    frames: Vec<Frame>,
+}
+
+impl From<ReplayV2> for ReplayV3 {
+    fn from(replay: ReplayV2) -> Self {
+        Self {
+            start_time: replay.start_time,
+            start_seed: replay.start_seed,
+            startup_events: replay.startup_events,
+            seeded_files: false,
+            frames: replay.frames,
+        }
+    }
+}
+
+impl From<ReplayV3> for Replay {
+    fn from(replay: ReplayV3) -> Self {
+        Self {
+            start_time: replay.start_time,
+            start_seed: replay.start_seed,
+            startup_events: replay.startup_events,
+            seeded_files: replay.seeded_files,
+            start_registry: Default::default(),
+            frames: replay.frames,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ReadError {
     IOErr(io::Error),
     DecompressErr(lzzzz::Error),
     DeserializeErr(Box<bincode::ErrorKind>),
     UnknownVersion(u32),
+    ParseErr(text::ParseError),
 }
 
 #[derive(Debug)]
@@ -101,7 +210,14 @@ pub enum WriteError {
 
 impl Replay {
     pub fn new(start_time: u128, start_seed: i32) -> Self {
-        Self { start_time, start_seed, startup_events: Vec::new(), frames: Vec::new() }
+        Self {
+            start_time,
+            start_seed,
+            startup_events: Vec::new(),
+            seeded_files: false,
+            start_registry: Default::default(),
+            frames: Vec::new(),
+        }
     }
 
     // Loads a Replay from a gmtas-format file (doesn't check the file extension)
@@ -111,7 +227,7 @@ impl Replay {
         let mut file = File::open(path).map_err(ReadError::IOErr)?;
 
         match file.read_u32::<LE>() {
-            Ok(1) => {
+            Ok(version @ 1..=FILE_VERSION) => {
                 let init_size = file.metadata().map(|m| m.len() as usize + 1).unwrap_or(0);
                 lz4_buf.reserve(init_size);
                 match file.read_to_end(&mut lz4_buf) {
@@ -122,8 +238,15 @@ impl Replay {
                             match lz4::decompress(block, bin_buf.as_mut_slice()) {
                                 Ok(len) => {
                                     unsafe { bin_buf.set_len(len) };
-                                    bincode::deserialize::<'_, Self>(bin_buf.as_slice())
-                                        .map_err(ReadError::DeserializeErr)
+                                    match version {
+                                        1 => bincode::deserialize::<'_, ReplayV1>(bin_buf.as_slice())
+                                            .map(|replay| Self::from(ReplayV3::from(ReplayV2::from(replay)))),
+                                        2 => bincode::deserialize::<'_, ReplayV2>(bin_buf.as_slice())
+                                            .map(|replay| Self::from(ReplayV3::from(replay))),
+                                        3 => bincode::deserialize::<'_, ReplayV3>(bin_buf.as_slice()).map(Self::from),
+                                        _ => bincode::deserialize::<'_, Self>(bin_buf.as_slice()),
+                                    }
+                                    .map_err(ReadError::DeserializeErr)
                                 },
                                 Err(err) => Err(ReadError::DecompressErr(err)),
                             }
@@ -139,6 +262,17 @@ impl Replay {
         }
     }
 
+    // Loads a Replay from a text-format file (doesn't check the file extension)
+    pub fn from_text_file(path: &PathBuf) -> Result<Self, ReadError> {
+        let text = std::fs::read_to_string(path).map_err(ReadError::IOErr)?;
+        Self::from_text(&text).map_err(ReadError::ParseErr)
+    }
+
+    // Writes this replay into a file in the text format
+    pub fn to_text_file(&self, path: &PathBuf) -> Result<(), WriteError> {
+        std::fs::write(path, self.to_text()).map_err(WriteError::IOErr)
+    }
+
     // Serializes this replay into a file
     pub fn to_file(&self, path: &PathBuf) -> Result<(), WriteError> {
         let mut lz4_buf = Vec::new();
@@ -147,7 +281,7 @@ impl Replay {
             Ok(()) => match lz4::compress_to_vec(bin_buf.as_slice(), lz4_buf.as_mut(), lz4::ACC_LEVEL_DEFAULT) {
                 Ok(_length) => {
                     match OpenOptions::new().create(true).write(true).truncate(true).open(path).and_then(|mut f| {
-                        f.write_u32::<LE>(1).and_then(|_| {
+                        f.write_u32::<LE>(FILE_VERSION).and_then(|_| {
                             f.write_u64::<LE>(bin_buf.len() as u64).and_then(|_| f.write_all(lz4_buf.as_slice()))
                         })
                     }) {
@@ -162,12 +296,12 @@ impl Replay {
     }
 
     // Adds a new frame of input to the end of the replay.
-    // Mouse position will be the same as the previous frame unless this is the first frame,
-    // in which case it will be (0, 0)
+    // Mouse position and joysticks will be the same as the previous frame unless this is the first frame,
+    // in which case the mouse will be at (0, 0) and the joysticks disconnected
     pub fn new_frame(&mut self) -> &mut Frame {
-        let (mouse_x, mouse_y) = match self.frames.last() {
-            Some(frame) => (frame.mouse_x, frame.mouse_y),
-            None => (0, 0),
+        let (mouse_x, mouse_y, joysticks) = match self.frames.last() {
+            Some(frame) => (frame.mouse_x, frame.mouse_y, frame.joysticks),
+            None => (0, 0, Default::default()),
         };
         self.frames.push(Frame {
             mouse_x,
@@ -176,17 +310,18 @@ impl Replay {
             events: Vec::new(),
             new_seed: None,
             new_time: None,
+            joysticks,
         });
         self.frames.last_mut().unwrap() // Last cannot be None since we just pushed an element
     }
 
     // Adds a new frame of input to the specified position of the replay.
-    // Mouse position will be the same as the previous frame unless this is the first frame,
-    // in which case it will be (0, 0)
+    // Mouse position and joysticks will be the same as the previous frame unless this is the first frame,
+    // in which case the mouse will be at (0, 0) and the joysticks disconnected
     pub fn insert_new_frame(&mut self, index: usize) -> &mut Frame {
-        let (mouse_x, mouse_y) = match self.frames.get(index-1) {
-            Some(frame) => (frame.mouse_x, frame.mouse_y),
-            None => (0, 0),
+        let (mouse_x, mouse_y, joysticks) = match self.frames.get(index-1) {
+            Some(frame) => (frame.mouse_x, frame.mouse_y, frame.joysticks),
+            None => (0, 0, Default::default()),
         };
         self.frames.insert(index, Frame {
             mouse_x,
@@ -195,6 +330,7 @@ impl Replay {
             events: Vec::new(),
             new_seed: None,
             new_time: None,
+            joysticks,
         });
         self.frames.get_mut(index).unwrap()
     }
@@ -0,0 +1,199 @@
+//! Importing input logs recorded with libTAS.
+//!
+//! libTAS stores inputs in a plain text `inputs` file (inside the .ltm archive) with one line per frame, like
+//! `|K61:ff51|M320:240:A:1....|`. The `K` section lists the X11 keysyms held that frame, in hex, and the `M`
+//! section has the mouse position, whether it's (A)bsolute or (R)elative, and which of the five buttons are held.
+//! Other sections (controllers, flags, framerate) don't apply to GM8 games and are skipped.
+//!
+//! Since libTAS records which inputs are held rather than when they change, presses and releases are generated
+//! by comparing each frame to the one before it.
+
+use super::{text::ParseError, Input, Replay};
+use crate::input::{Button, MouseButton};
+use std::collections::{BTreeSet, HashMap};
+
+/// Maps X11 keysyms, as used by libTAS, to the Windows virtual key codes GM8 uses.
+pub struct KeyMap(HashMap<u32, u8>);
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let mut map = HashMap::new();
+        // Latin-1 keysyms match ASCII, so letters (either case), digits and space line up with their VKs
+        for c in b'0'..=b'9' {
+            map.insert(u32::from(c), c);
+        }
+        for c in b'A'..=b'Z' {
+            map.insert(u32::from(c), c);
+            map.insert(u32::from(c.to_ascii_lowercase()), c);
+        }
+        for i in 0..10 {
+            map.insert(0xffb0 + i, Button::Keypad0 as u8 + i as u8);
+        }
+        for i in 0..12 {
+            map.insert(0xffbe + i, Button::F1 as u8 + i as u8);
+        }
+        let keys = [
+            (0x0020, Button::Space),
+            (0xff08, Button::Backspace),
+            (0xff09, Button::Tab),
+            (0xff0d, Button::Return),
+            (0xff13, Button::Pause),
+            (0xff1b, Button::Escape),
+            (0xff50, Button::Home),
+            (0xff51, Button::LeftArrow),
+            (0xff52, Button::UpArrow),
+            (0xff53, Button::RightArrow),
+            (0xff54, Button::DownArrow),
+            (0xff55, Button::PageUp),
+            (0xff56, Button::PageDown),
+            (0xff57, Button::End),
+            (0xff63, Button::Insert),
+            (0xff8d, Button::Return),
+            (0xffe1, Button::LeftShift),
+            (0xffe2, Button::RightShift),
+            (0xffe3, Button::LeftControl),
+            (0xffe4, Button::RightControl),
+            (0xffe5, Button::CapsLock),
+            (0xffe9, Button::LeftAlt),
+            (0xffea, Button::RightAlt),
+            (0xffff, Button::Delete),
+        ];
+        map.extend(keys.iter().map(|(keysym, button)| (*keysym, *button as u8)));
+        Self(map)
+    }
+}
+
+impl KeyMap {
+    /// Adds or replaces mappings from a text file with one `keysym vk` pair per line.
+    /// Keysyms are hex, as in libTAS input files. Key codes are decimal, or hex with a `0x` prefix.
+    pub fn load_overrides(&mut self, text: &str) -> Result<(), ParseError> {
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let error = || ParseError { line: i + 1, message: format!("expected 'keysym keycode', found '{}'", line) };
+            let (keysym, vk) = line.split_once(char::is_whitespace).ok_or_else(error)?;
+            let vk = vk.trim();
+            let vk = match vk.strip_prefix("0x") {
+                Some(hex) => u8::from_str_radix(hex, 16),
+                None => vk.parse(),
+            };
+            self.0.insert(u32::from_str_radix(keysym, 16).map_err(|_| error())?, vk.map_err(|_| error())?);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, keysym: u32) -> Option<u8> {
+        self.0.get(&keysym).copied()
+    }
+}
+
+/// The result of importing a libTAS input file.
+pub struct LibTasImport {
+    pub replay: Replay,
+    /// Keysyms which were pressed but aren't in the key map, so they were left out.
+    pub unmapped_keys: BTreeSet<u32>,
+}
+
+impl Replay {
+    /// Converts the contents of a libTAS `inputs` file into a replay.
+    pub fn from_libtas(
+        inputs: &str,
+        keymap: &KeyMap,
+        start_time: u128,
+        start_seed: i32,
+    ) -> Result<LibTasImport, ParseError> {
+        let mut replay = Self::new(start_time, start_seed);
+        let mut unmapped_keys = BTreeSet::new();
+        let mut held_keys = BTreeSet::new();
+        let mut held_buttons = BTreeSet::new();
+        let mut mouse = (0, 0);
+        for (i, line) in inputs.lines().enumerate() {
+            let error = |message: String| ParseError { line: i + 1, message };
+            let line = line.trim();
+            if line.is_empty() {
+                continue
+            }
+            if !line.starts_with('|') {
+                return Err(error(format!("expected a frame starting with '|', found '{}'", line)))
+            }
+
+            let mut keys = BTreeSet::new();
+            let mut buttons = BTreeSet::new();
+            for section in line.split('|').filter(|s| !s.is_empty()) {
+                if let Some(keysyms) = section.strip_prefix('K') {
+                    for keysym in keysyms.split(':').filter(|s| !s.is_empty()) {
+                        let keysym = u32::from_str_radix(keysym, 16)
+                            .map_err(|_| error(format!("invalid keysym '{}'", keysym)))?;
+                        if let Some(vk) = keymap.get(keysym) {
+                            keys.insert(vk);
+                        } else {
+                            unmapped_keys.insert(keysym);
+                        }
+                    }
+                } else if let Some(fields) = section.strip_prefix('M') {
+                    let fields = fields.split(':').collect::<Vec<_>>();
+                    let (x, y, mode, held) = match fields.as_slice() {
+                        [x, y, mode, held, ..] => (x, y, *mode, held.as_bytes()),
+                        _ => return Err(error(format!("invalid mouse input '{}'", section))),
+                    };
+                    let x = x.parse::<i32>().map_err(|_| error(format!("invalid mouse x '{}'", x)))?;
+                    let y = y.parse::<i32>().map_err(|_| error(format!("invalid mouse y '{}'", y)))?;
+                    mouse = match mode {
+                        "A" => (x, y),
+                        "R" => (mouse.0 + x, mouse.1 + y),
+                        _ => return Err(error(format!("invalid mouse mode '{}'", mode))),
+                    };
+                    // X11 numbers the middle button 2 and the right button 3, and buttons 4 and 5 don't exist in GM8
+                    let x11_buttons =
+                        [(b'1', MouseButton::Left), (b'2', MouseButton::Middle), (b'3', MouseButton::Right)];
+                    for (flag, button) in x11_buttons {
+                        if held.contains(&flag) {
+                            buttons.insert(button as i8);
+                        }
+                    }
+                }
+            }
+
+            let frame = replay.new_frame();
+            frame.mouse_x = mouse.0;
+            frame.mouse_y = mouse.1;
+            frame.inputs.extend(held_keys.difference(&keys).map(|&vk| Input::KeyRelease(vk)));
+            frame.inputs.extend(keys.difference(&held_keys).map(|&vk| Input::KeyPress(vk)));
+            frame.inputs.extend(held_buttons.difference(&buttons).map(|&b| Input::MouseRelease(b)));
+            frame.inputs.extend(buttons.difference(&held_buttons).map(|&b| Input::MousePress(b)));
+            held_keys = keys;
+            held_buttons = buttons;
+        }
+        Ok(LibTasImport { replay, unmapped_keys })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import() {
+        let mut keymap = KeyMap::default();
+        keymap.load_overrides("# jump on z\n7a 0x20\n").unwrap();
+        let inputs = "|K|M10:20:A:.....|\n|K7a:ff51:1234|M5:-5:R:1....|\n|Kff51|M5:-5:R:..3..|F1|\n|K|M0:0:A:.....|\n";
+        let import = Replay::from_libtas(inputs, &keymap, 0, 0).unwrap();
+        assert_eq!(import.unmapped_keys.into_iter().collect::<Vec<_>>(), vec![0x1234]);
+
+        let replay = import.replay;
+        assert_eq!(replay.frame_count(), 4);
+        let frame = replay.get_frame(0).unwrap();
+        assert_eq!((frame.mouse_x, frame.mouse_y), (10, 20));
+        assert!(frame.inputs.is_empty());
+        let frame = replay.get_frame(1).unwrap();
+        assert_eq!((frame.mouse_x, frame.mouse_y), (15, 15));
+        assert_eq!(frame.inputs, vec![Input::KeyPress(0x20), Input::KeyPress(0x25), Input::MousePress(1)]);
+        let frame = replay.get_frame(2).unwrap();
+        assert_eq!((frame.mouse_x, frame.mouse_y), (20, 10));
+        assert_eq!(frame.inputs, vec![Input::KeyRelease(0x20), Input::MouseRelease(1), Input::MousePress(2)]);
+        let frame = replay.get_frame(3).unwrap();
+        assert_eq!(frame.inputs, vec![Input::KeyRelease(0x25), Input::MouseRelease(2)]);
+    }
+}
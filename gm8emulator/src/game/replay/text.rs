@@ -0,0 +1,433 @@
+//! A human-readable replay format, so replays can be reviewed and merged like any other text file.
+//!
+//! The header gives the start time, start seed and any startup events, and has a `seeded_files` line if the game
+//! could read the real files in its directories. The registry's starting contents are given as one
+//! `registry ROOT KEY NAME VALUE` line per value, and a `registry_root` line if the `_ext` functions didn't start out
+//! using the current user. Then there's one line per frame:
+//!
+//! ```text
+//! start_time 1600000000000000000
+//! start_seed 12345
+//! seeded_files
+//! registry 0 "software\\game maker\\gm123" "volume" 0.5
+//! startup show_message
+//! 0: 320,240 kp:37 mp:1
+//! 1: 320,240 kr:37 mr:1 wu seed+2 get_string:"hello world"
+//! ```
+//!
+//! Each frame starts with its index and mouse position, followed by its inputs in order:
+//! `kp`/`kr` are key presses/releases by virtual key code, `mp`/`mr` are mouse button presses/releases, and
+//! `wu`/`wd` are mouse wheel movements. `seed+N` advances the RNG N times, `seed=N` overrides the seed, and
+//! `time=N` sets the clock. Joysticks keep their state between frames, so they're only written when they change:
+//! `j1:X,Y,Z,R,U,V:BUTTONS:POV` gives joystick 1's six axes, the held buttons joined with `+` (or `-` for none),
+//! and its POV angle, and `j1:off` disconnects it. Stored events come last. Real values are written in full precision
+//! and strings are quoted, with `\"`, `\\` and `\xNN` escapes. Blank lines and lines starting with `#` are ignored.
+
+use super::{Event, Frame, FrameRng, Input, Replay};
+use crate::{
+    game::registry::Root,
+    gml::Value,
+    input::{Joystick, JOYSTICK_AXES, JOYSTICK_BUTTONS, JOYSTICK_COUNT},
+    math::Real,
+};
+use std::fmt::{self, Write};
+
+/// An error encountered while reading a text replay, along with the (1-based) line it was on.
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Replay {
+    /// Writes this replay in the text format.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        // writing to a String can't fail
+        self.write_text(&mut out).unwrap();
+        out
+    }
+
+    fn write_text(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "start_time {}", self.start_time)?;
+        writeln!(out, "start_seed {}", self.start_seed)?;
+        if self.seeded_files {
+            writeln!(out, "seeded_files")?;
+        }
+        if self.start_registry.root != Root::CurrentUser {
+            writeln!(out, "registry_root {}", self.start_registry.root as i32)?;
+        }
+        for (root, key, name, value) in self.start_registry.values() {
+            writeln!(out, "registry {} {} {} {}", root as i32, StringText(key), StringText(name), ValueText(value))?;
+        }
+        for event in &self.startup_events {
+            writeln!(out, "startup {}", EventText(event))?;
+        }
+        let mut joysticks = [Joystick::new(); JOYSTICK_COUNT];
+        for (i, frame) in self.frames.iter().enumerate() {
+            write!(out, "{}: {},{}", i, frame.mouse_x, frame.mouse_y)?;
+            for input in &frame.inputs {
+                match input {
+                    Input::KeyPress(key) => write!(out, " kp:{}", key)?,
+                    Input::KeyRelease(key) => write!(out, " kr:{}", key)?,
+                    Input::MousePress(button) => write!(out, " mp:{}", button)?,
+                    Input::MouseRelease(button) => write!(out, " mr:{}", button)?,
+                    Input::MouseWheelUp => write!(out, " wu")?,
+                    Input::MouseWheelDown => write!(out, " wd")?,
+                }
+            }
+            match &frame.new_seed {
+                Some(FrameRng::Increment(count)) => write!(out, " seed+{}", count)?,
+                Some(FrameRng::Override(seed)) => write!(out, " seed={}", seed)?,
+                None => (),
+            }
+            if let Some(time) = frame.new_time {
+                write!(out, " time={}", time)?;
+            }
+            for (id, (joystick, previous)) in frame.joysticks.iter().zip(&joysticks).enumerate() {
+                if joystick != previous {
+                    write!(out, " j{}:{}", id + 1, JoystickText(joystick))?;
+                }
+            }
+            joysticks = frame.joysticks;
+            for event in &frame.events {
+                write!(out, " {}", EventText(event))?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a replay written by `to_text()`.
+    pub fn from_text(text: &str) -> Result<Self, ParseError> {
+        let mut start_time = None;
+        let mut start_seed = None;
+        let mut replay = Self::new(0, 0);
+        for (i, line) in text.lines().enumerate() {
+            let error = |message: String| ParseError { line: i + 1, message };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let tokens = tokenize(line).map_err(error)?;
+            match tokens[0] {
+                "start_time" if tokens.len() == 2 => start_time = Some(parse_number(tokens[1]).map_err(error)?),
+                "start_seed" if tokens.len() == 2 => start_seed = Some(parse_number(tokens[1]).map_err(error)?),
+                "startup" if tokens.len() == 2 => replay.startup_events.push(parse_event(tokens[1]).map_err(error)?),
+                "start_time" | "start_seed" | "startup" => {
+                    return Err(error(format!("{} takes exactly one value", tokens[0])))
+                },
+                "seeded_files" if tokens.len() == 1 => replay.seeded_files = true,
+                "seeded_files" => return Err(error("seeded_files doesn't take a value".into())),
+                "registry_root" if tokens.len() == 2 => {
+                    replay.start_registry.root = parse_root(tokens[1]).map_err(error)?;
+                },
+                "registry" if tokens.len() == 5 => {
+                    let root = parse_root(tokens[1]).map_err(error)?;
+                    let key = parse_string(tokens[2]).map_err(error)?;
+                    let name = parse_string(tokens[3]).map_err(error)?;
+                    let value = parse_value(tokens[4]).map_err(error)?;
+                    replay.start_registry.insert(root, &key, &name, value);
+                },
+                "registry_root" => return Err(error("registry_root takes exactly one value".into())),
+                "registry" => return Err(error("registry takes a root, key, name and value".into())),
+                index => {
+                    let index = index
+                        .strip_suffix(':')
+                        .and_then(|n| n.parse::<usize>().ok())
+                        .ok_or_else(|| error(format!("expected a frame number, found '{}'", index)))?;
+                    if index != replay.frames.len() {
+                        return Err(error(format!("expected frame {}, found frame {}", replay.frames.len(), index)))
+                    }
+                    let joysticks = replay.frames.last().map(|f| f.joysticks).unwrap_or_default();
+                    replay.frames.push(parse_frame(&tokens[1..], joysticks).map_err(error)?);
+                },
+            }
+        }
+        replay.start_time = start_time.ok_or(ParseError { line: 0, message: "missing start_time".into() })?;
+        replay.start_seed = start_seed.ok_or(ParseError { line: 0, message: "missing start_seed".into() })?;
+        Ok(replay)
+    }
+}
+
+struct EventText<'a>(&'a Event);
+
+impl fmt::Display for EventText<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Event::GetInteger(value) => write!(f, "get_integer:{}", ValueText(value)),
+            Event::GetString(value) => write!(f, "get_string:{}", ValueText(value)),
+            Event::HighscoreName(value) => write!(f, "highscore_name:{}", ValueText(value)),
+            Event::Randomize(seed) => write!(f, "randomize:{}", seed),
+            Event::ShowMenu(value) => write!(f, "show_menu:{}", ValueText(value)),
+            Event::ShowMessage => write!(f, "show_message"),
+            Event::ShowMessageExt(value) => write!(f, "show_message_ext:{}", ValueText(value)),
+            Event::ShowQuestion(value) => write!(f, "show_question:{}", ValueText(value)),
+        }
+    }
+}
+
+struct JoystickText<'a>(&'a Joystick);
+
+impl fmt::Display for JoystickText<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let joystick = self.0;
+        if !joystick.connected {
+            return f.write_str("off")
+        }
+        let axes = joystick.axes.iter().map(|axis| axis.into_inner().to_string()).collect::<Vec<_>>();
+        let buttons =
+            (1..=JOYSTICK_BUTTONS as i32).filter(|&b| joystick.button(b)).map(|b| b.to_string()).collect::<Vec<_>>();
+        let buttons = if buttons.is_empty() { "-".to_string() } else { buttons.join("+") };
+        write!(f, "{}:{}:{}", axes.join(","), buttons, joystick.pov)
+    }
+}
+
+struct ValueText<'a>(&'a Value);
+
+impl fmt::Display for ValueText<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            // Debug formatting for f64 always round-trips, and always has a decimal point or exponent
+            Value::Real(real) => write!(f, "{:?}", real.into_inner()),
+            Value::Str(string) => write!(f, "{}", StringText(string.as_ref())),
+        }
+    }
+}
+
+struct StringText<'a>(&'a [u8]);
+
+impl fmt::Display for StringText<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for &byte in self.0 {
+            match byte {
+                b'"' => f.write_str("\\\"")?,
+                b'\\' => f.write_str("\\\\")?,
+                b' '..=b'~' => f.write_char(char::from(byte))?,
+                _ => write!(f, "\\x{:02x}", byte)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// Splits a line on whitespace, except for whitespace inside quoted strings.
+fn tokenize(line: &str) -> Result<Vec<&str>, String> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => (),
+            }
+        } else if c.is_whitespace() {
+            if let Some(start) = start.take() {
+                tokens.push(&line[start..i]);
+            }
+        } else {
+            start.get_or_insert(i);
+            in_string = c == '"';
+        }
+    }
+    if in_string {
+        return Err("unterminated string".into())
+    }
+    if let Some(start) = start {
+        tokens.push(&line[start..]);
+    }
+    Ok(tokens)
+}
+
+fn parse_number<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("invalid number '{}'", s))
+}
+
+fn parse_frame(tokens: &[&str], joysticks: [Joystick; JOYSTICK_COUNT]) -> Result<Frame, String> {
+    let (mouse_x, mouse_y) = tokens
+        .first()
+        .and_then(|pos| pos.split_once(','))
+        .ok_or_else(|| "expected mouse position after frame number".to_string())?;
+    let mut frame = Frame {
+        mouse_x: parse_number(mouse_x)?,
+        mouse_y: parse_number(mouse_y)?,
+        inputs: Vec::new(),
+        events: Vec::new(),
+        new_seed: None,
+        new_time: None,
+        joysticks,
+    };
+    for &token in &tokens[1..] {
+        if let Some(count) = token.strip_prefix("seed+") {
+            frame.new_seed = Some(FrameRng::Increment(parse_number(count)?));
+        } else if let Some(seed) = token.strip_prefix("seed=") {
+            frame.new_seed = Some(FrameRng::Override(parse_number(seed)?));
+        } else if let Some(time) = token.strip_prefix("time=") {
+            frame.new_time = Some(parse_number(time)?);
+        } else {
+            let (name, arg) = token.split_once(':').unwrap_or((token, ""));
+            match name {
+                "kp" => frame.inputs.push(Input::KeyPress(parse_number(arg)?)),
+                "kr" => frame.inputs.push(Input::KeyRelease(parse_number(arg)?)),
+                "mp" => frame.inputs.push(Input::MousePress(parse_number(arg)?)),
+                "mr" => frame.inputs.push(Input::MouseRelease(parse_number(arg)?)),
+                "wu" if arg.is_empty() => frame.inputs.push(Input::MouseWheelUp),
+                "wd" if arg.is_empty() => frame.inputs.push(Input::MouseWheelDown),
+                "j1" => frame.joysticks[0] = parse_joystick(arg)?,
+                "j2" => frame.joysticks[1] = parse_joystick(arg)?,
+                _ => frame.events.push(parse_event(token)?),
+            }
+        }
+    }
+    Ok(frame)
+}
+
+fn parse_joystick(s: &str) -> Result<Joystick, String> {
+    let mut joystick = Joystick::new();
+    if s == "off" {
+        return Ok(joystick)
+    }
+    joystick.connected = true;
+    let (axes, buttons, pov) = match s.split(':').collect::<Vec<_>>().as_slice() {
+        [axes, buttons, pov] => (*axes, *buttons, *pov),
+        _ => return Err(format!("expected 'axes:buttons:pov' for joystick, found '{}'", s)),
+    };
+    let axes = axes.split(',').map(|axis| parse_number::<f64>(axis).map(Real::from)).collect::<Result<Vec<_>, _>>()?;
+    if axes.len() != JOYSTICK_AXES.len() {
+        return Err(format!("expected {} joystick axes, found {}", JOYSTICK_AXES.len(), axes.len()))
+    }
+    joystick.axes.copy_from_slice(&axes);
+    if buttons != "-" {
+        for button in buttons.split('+') {
+            let button = parse_number(button)?;
+            if !(1..=JOYSTICK_BUTTONS as i32).contains(&button) {
+                return Err(format!("invalid joystick button {}", button))
+            }
+            joystick.set_button(button, true);
+        }
+    }
+    joystick.pov = parse_number(pov)?;
+    Ok(joystick)
+}
+
+fn parse_event(token: &str) -> Result<Event, String> {
+    let (name, arg) = token.split_once(':').unwrap_or((token, ""));
+    match name {
+        "get_integer" => Ok(Event::GetInteger(parse_value(arg)?)),
+        "get_string" => Ok(Event::GetString(parse_value(arg)?)),
+        "highscore_name" => Ok(Event::HighscoreName(parse_value(arg)?)),
+        "randomize" => Ok(Event::Randomize(parse_number(arg)?)),
+        "show_menu" => Ok(Event::ShowMenu(parse_value(arg)?)),
+        "show_message" if arg.is_empty() => Ok(Event::ShowMessage),
+        "show_message_ext" => Ok(Event::ShowMessageExt(parse_value(arg)?)),
+        "show_question" => Ok(Event::ShowQuestion(parse_value(arg)?)),
+        _ => Err(format!("unknown input or event '{}'", token)),
+    }
+}
+
+fn parse_root(s: &str) -> Result<Root, String> {
+    Root::from_gml(parse_number(s)?).ok_or_else(|| format!("invalid registry root {}", s))
+}
+
+fn parse_value(s: &str) -> Result<Value, String> {
+    if s.starts_with('"') {
+        Ok(Value::Str(parse_string(s)?.into()))
+    } else {
+        Ok(Value::Real(Real::from(parse_number::<f64>(s)?)))
+    }
+}
+
+fn parse_string(s: &str) -> Result<Vec<u8>, String> {
+    let string = match s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        Some(string) => string,
+        None => return Err(format!("expected a quoted string, found {}", s)),
+    };
+    let mut bytes = Vec::with_capacity(string.len());
+    let mut iter = string.bytes();
+    while let Some(byte) = iter.next() {
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue
+        }
+        match iter.next() {
+            Some(b'x') => {
+                let hex = [iter.next(), iter.next()];
+                let byte = match hex {
+                    [Some(hi), Some(lo)] => {
+                        std::str::from_utf8(&[hi, lo]).ok().and_then(|h| u8::from_str_radix(h, 16).ok())
+                    },
+                    _ => None,
+                };
+                bytes.push(byte.ok_or_else(|| format!("invalid escape sequence in {}", s))?);
+            },
+            Some(escaped @ (b'"' | b'\\')) => bytes.push(escaped),
+            _ => return Err(format!("invalid escape sequence in {}", s)),
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut replay = Replay::new(1_600_000_000_000_000_000, -42);
+        replay.startup_events.push(Event::ShowMessage);
+        replay.startup_events.push(Event::GetInteger(Value::Real(Real::from(0.1))));
+        replay.seeded_files = true;
+        replay.start_registry.root = Root::Users;
+        replay.start_registry.insert(Root::CurrentUser, b"Software\\Game Maker\\GM1", b"Score", Value::from(10.5));
+        replay.start_registry.insert(Root::LocalMachine, b"Software\\Test", b"Name", Value::Str("\"a b\"".into()));
+        replay.new_frame();
+        let frame = replay.new_frame();
+        frame.mouse_x = -5;
+        frame.mouse_y = 300;
+        frame.inputs = vec![
+            Input::KeyPress(37),
+            Input::MousePress(1),
+            Input::MouseWheelDown,
+            Input::KeyRelease(37),
+            Input::MouseRelease(1),
+            Input::MouseWheelUp,
+        ];
+        frame.new_seed = Some(FrameRng::Increment(3));
+        frame.new_time = Some(12345);
+        frame.events = vec![
+            Event::GetString(Value::Str("say \"hi\" \\ \x01\u{e9}".into())),
+            Event::Randomize(-7),
+            Event::ShowQuestion(Value::Real(Real::from(1.0))),
+            Event::ShowMenu(Value::Real(Real::from(-1e300))),
+            Event::ShowMessageExt(Value::Real(Real::from(2.0))),
+            Event::HighscoreName(Value::Str("<nobody>".into())),
+        ];
+        frame.joysticks[1].connected = true;
+        frame.joysticks[1].axes[0] = Real::from(-0.25);
+        frame.joysticks[1].set_button(1, true);
+        frame.joysticks[1].set_button(16, true);
+        frame.joysticks[1].pov = 270;
+        replay.new_frame().new_seed = Some(FrameRng::Override(i32::MIN));
+        replay.new_frame().joysticks[1] = Joystick::new();
+
+        let text = replay.to_text();
+        assert_eq!(Replay::from_text(&text).unwrap(), replay);
+    }
+
+    #[test]
+    fn bad_frame_order() {
+        let err = Replay::from_text("start_time 0\nstart_seed 0\n0: 0,0\n2: 0,0\n").unwrap_err();
+        assert_eq!(err.line, 4);
+    }
+}
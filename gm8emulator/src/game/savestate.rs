@@ -1,8 +1,11 @@
+mod legacy;
+
 use crate::{
     game::{
-        audio::AudioState, draw, external, includedfile::IncludedFile, model::Model, particle,
-        pathfinding::PotentialStepSettings, surface::Surface, transition::UserTransition, Assets,
-        Game, GameClock, Replay, RoomState, Version,
+        audio::AudioState, dialog::MessageSettings, draw, external, highscore::HighscoreSettings,
+        includedfile::IncludedFile, model::Model, particle,
+        pathfinding::PotentialStepSettings, registry::RegistryState, surface::Surface, transition::UserTransition,
+        vfs::VfsState, Assets, Game, GameClock, Replay, RoomState, Version,
     },
     gml::{self, ds, rand::Random, Compiler},
     handleman::HandleList,
@@ -25,6 +28,15 @@ use std::{
     rc::Rc,
 };
 
+use self::legacy::{SaveStateV1, SaveStateV2};
+
+/// The version written at the start of savestate files. This has to be bumped whenever the serialized layout of
+/// SaveState changes, including anything inside it, with a copy of the old layout kept in `legacy` to load it from.
+pub const FILE_VERSION: u32 = 3;
+
+/// Versioned savestates start with this. Unversioned ones start with the length of their data, which is never this big.
+const MAGIC: &[u8; 8] = b"GM8SAVE\0";
+
 /// Represents a savestate. Very similar to the Game struct, but without things which aren't serialized.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct SaveState {
@@ -39,6 +51,8 @@ pub struct SaveState {
     pub textures: Vec<Option<SavedTexture>>,
 
     pub externals: external::ExternalState,
+    pub registry: RegistryState,
+    pub vfs: VfsState,
     pub surface_fix: bool,
 
     pub view_current: usize,
@@ -81,6 +95,8 @@ pub struct SaveState {
     pub uninit_args_are_zero: bool,
 
     pub potential_step_settings: PotentialStepSettings,
+    pub message_settings: MessageSettings,
+    pub highscore_settings: HighscoreSettings,
 
     pub fps: u32,
     pub frame_counter: u32,
@@ -120,14 +136,28 @@ pub struct SaveState {
     replay: Replay,
     screenshot: Box<[u8]>,
     zbuffer: Box<[f32]>,
+
+    pub info: SaveInfo,
+}
+
+/// Where a savestate came from, so branches of a recording can be told apart.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SaveInfo {
+    pub parent: Option<u64>, // the branch this was saved from, if any
+    pub frame: usize,
+    pub rerecords: u64,
+    pub timestamp: u128, // when this was saved, in real nanoseconds since the unix epoch
+    pub label: String,
 }
 
 impl SaveState {
     /// Creates a new SaveState from the given components.
+    /// Its info only has the frame number set, the caller can fill in the rest.
     pub fn from(game: &Game, replay: Replay, renderer_state: RendererState, clean_state: bool) -> Self {
         let (window_width, window_height) = game.renderer.stored_size();
         let screenshot = game.renderer.stored_pixels();
         let zbuffer = game.renderer.stored_zbuffer();
+        let info = SaveInfo { frame: replay.frame_count(), ..Default::default() };
 
         Self {
             compiler: game.compiler.clone(),
@@ -139,6 +169,8 @@ impl SaveState {
             background_colour: game.background_colour,
             textures: game.renderer.dump_dynamic_textures(),
             externals: game.externals.save_state(),
+            registry: game.registry.save_state(),
+            vfs: game.vfs.save_state(),
             surface_fix: game.surface_fix.clone(),
             view_current: game.view_current,
             last_instance_id: game.last_instance_id.clone(),
@@ -172,6 +204,8 @@ impl SaveState {
             uninit_fields_are_zero: game.uninit_fields_are_zero.clone(),
             uninit_args_are_zero: game.uninit_args_are_zero.clone(),
             potential_step_settings: game.potential_step_settings.clone(),
+            message_settings: game.message_settings.clone(),
+            highscore_settings: game.highscore_settings.clone(),
             fps: game.fps,
             frame_counter: game.frame_counter,
             transition_kind: game.transition_kind.clone(),
@@ -205,6 +239,7 @@ impl SaveState {
             screenshot,
             zbuffer,
             clean_state,
+            info,
         }
     }
 
@@ -221,6 +256,8 @@ impl SaveState {
         }
 
         game.externals.load_state(self.externals);
+        game.registry.load_state(self.registry);
+        game.vfs.load_state(self.vfs);
 
         game.surface_fix = self.surface_fix;
 
@@ -262,6 +299,8 @@ impl SaveState {
         game.uninit_fields_are_zero = self.uninit_fields_are_zero;
         game.uninit_args_are_zero = self.uninit_args_are_zero;
         game.potential_step_settings = self.potential_step_settings;
+        game.message_settings = self.message_settings;
+        game.highscore_settings = self.highscore_settings;
         game.fps = self.fps;
         game.frame_counter = self.frame_counter;
         game.transition_kind = self.transition_kind;
@@ -297,38 +336,48 @@ impl SaveState {
         self.replay
     }
 
-    /// Loads a SaveState from a file. The format will always match the one used by `save_to_file()`.
+    /// Loads a SaveState from a file, upgrading it first if it was saved by an older version.
     pub fn from_file(path: &PathBuf, buffer: &mut Buffer) -> Result<Self, ReadError> {
-        match File::open(path).map(|f| (f.metadata().map(|m| m.len() as usize + 1).unwrap_or(0), f)) {
-            Ok((init_size, mut file)) => {
-                buffer.lz4_buf.clear();
-                buffer.lz4_buf.reserve(init_size);
-                match file.read_to_end(&mut buffer.lz4_buf) {
-                    Ok(_) => {
-                        match (buffer.lz4_buf.as_slice().read_u64::<LE>().map(|x| x as usize), buffer.lz4_buf.get(8..))
-                        {
-                            (Ok(len), Some(block)) => {
-                                buffer.bin_buf.clear();
-                                buffer.bin_buf.reserve(len);
-                                unsafe { buffer.bin_buf.set_len(len) };
-                                match lz4::decompress(block, buffer.bin_buf.as_mut_slice()) {
-                                    Ok(len) => {
-                                        unsafe { buffer.bin_buf.set_len(len) };
-                                        bincode::deserialize::<'_, SaveState>(buffer.bin_buf.as_slice())
-                                            .map_err(ReadError::DeserializeErr)
-                                    },
-                                    Err(err) => Err(ReadError::DecompressErr(err)),
-                                }
-                            },
-                            (Ok(_), None) => Err(ReadError::IOErr(io::Error::from(io::ErrorKind::UnexpectedEof))),
-                            (Err(err), _) => Err(ReadError::IOErr(err)),
-                        }
-                    },
-                    Err(err) => Err(ReadError::IOErr(err)),
-                }
-            },
-            Err(err) => Err(ReadError::IOErr(err)),
+        Self::from_file_versioned(path, buffer).map(|(state, _)| state)
+    }
+
+    /// Loads a SaveState from a file, also returning the version the file was saved as.
+    pub fn from_file_versioned(path: &PathBuf, buffer: &mut Buffer) -> Result<(Self, u32), ReadError> {
+        let mut file = File::open(path).map_err(ReadError::IOErr)?;
+        let init_size = file.metadata().map(|m| m.len() as usize + 1).unwrap_or(0);
+        buffer.lz4_buf.clear();
+        buffer.lz4_buf.reserve(init_size);
+        file.read_to_end(&mut buffer.lz4_buf).map_err(ReadError::IOErr)?;
+
+        // files from before versioning was added start with the length of their data straight away
+        let (version, mut data) = match buffer.lz4_buf.strip_prefix(MAGIC) {
+            Some(mut data) => (data.read_u32::<LE>().map_err(ReadError::IOErr)?, data),
+            None => (1, buffer.lz4_buf.as_slice()),
+        };
+        if version == 0 || version > FILE_VERSION {
+            return Err(ReadError::UnknownVersion(version))
+        }
+
+        let len = data.read_u64::<LE>().map_err(ReadError::IOErr)? as usize;
+        buffer.bin_buf.clear();
+        buffer.bin_buf.reserve(len);
+        unsafe { buffer.bin_buf.set_len(len) };
+        match lz4::decompress(data, buffer.bin_buf.as_mut_slice()) {
+            Ok(len) => unsafe { buffer.bin_buf.set_len(len) },
+            Err(err) => return Err(ReadError::DecompressErr(err)),
+        }
+
+        let data = buffer.bin_buf.as_slice();
+        let mut state = match version {
+            1 => bincode::deserialize::<'_, SaveStateV1>(data).map(|state| Self::from(SaveStateV2::from(state))),
+            2 => bincode::deserialize::<'_, SaveStateV2>(data).map(Self::from),
+            _ => bincode::deserialize::<'_, SaveState>(data),
+        }
+        .map_err(ReadError::DeserializeErr)?;
+        if version < 2 {
+            state.info.frame = state.replay.frame_count();
         }
+        Ok((state, version))
     }
 
     /// Saves a SaveState to a file. The SaveState object is formatted with Serde/bincode and compressed with lz4.
@@ -341,8 +390,10 @@ impl SaveState {
                 match lz4::compress_to_vec(buffer.bin_buf.as_slice(), buffer.lz4_buf.as_mut(), lz4::ACC_LEVEL_DEFAULT) {
                     Ok(_length) => {
                         match OpenOptions::new().create(true).write(true).truncate(true).open(path).and_then(|mut f| {
-                            f.write_u64::<LE>(buffer.bin_buf.len() as u64)
-                                .and_then(|_| f.write_all(buffer.lz4_buf.as_slice()))
+                            f.write_all(MAGIC)?;
+                            f.write_u32::<LE>(FILE_VERSION)?;
+                            f.write_u64::<LE>(buffer.bin_buf.len() as u64)?;
+                            f.write_all(buffer.lz4_buf.as_slice())
                         }) {
                             Ok(()) => Ok(()),
                             Err(e) => Err(WriteError::IOErr(e)),
@@ -372,6 +423,7 @@ pub enum ReadError {
     IOErr(io::Error),
     DecompressErr(lzzzz::Error),
     DeserializeErr(Box<bincode::ErrorKind>),
+    UnknownVersion(u32),
 }
 
 #[derive(Debug)]
@@ -0,0 +1,493 @@
+//! The layouts of savestates saved by older versions, and conversions from each one to the next.
+//!
+//! These are copies of the old types, since the current ones can't load old data. Types whose layout hasn't changed
+//! since are shared with SaveState, so when one of them changes, its old layout has to be copied here (or next to it,
+//! if it has private fields) before the version is bumped.
+
+use super::{SaveInfo, SaveState};
+use crate::{
+    asset::{sound::SoundV1, Background, Font, Object, Path, Room, Script, Sprite, Timeline, Trigger},
+    game::{
+        audio::{AudioState, AudioStateV1},
+        dialog::MessageSettings,
+        draw, external,
+        highscore::HighscoreSettings,
+        includedfile::IncludedFile,
+        model::Model,
+        particle,
+        pathfinding::PotentialStepSettings,
+        registry::RegistryState,
+        replay::{ReplayV1, ReplayV2, ReplayV3},
+        surface::Surface,
+        transition::UserTransition,
+        vfs::VfsState,
+        Assets, GameClock, RoomState, Version,
+    },
+    gml::{self, ds, rand::Random, Compiler},
+    handleman::HandleList,
+    input::{Input, InputV1},
+    instance::DummyFieldHolder,
+    math::Real,
+    render::{RendererState, SavedTexture, Scaling},
+    types::{Colour, ID},
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+/// The layout of savestates from before versioning was added.
+#[derive(Serialize, Deserialize)]
+pub struct SaveStateV1 {
+    compiler: Compiler,
+    rand: Random,
+    input: InputV1,
+    assets: AssetsV1,
+    event_holders: [IndexMap<u32, Rc<RefCell<Vec<ID>>>>; 12],
+    custom_draw_objects: HashSet<ID>,
+
+    background_colour: Colour,
+    textures: Vec<Option<SavedTexture>>,
+
+    externals: external::ExternalState,
+    surface_fix: bool,
+
+    view_current: usize,
+
+    last_instance_id: ID,
+    last_tile_id: ID,
+
+    particles: particle::Manager,
+
+    room: RoomState,
+    stored_rooms: Vec<RoomState>,
+    room_order: Box<[i32]>,
+    user_transitions: HashMap<i32, UserTransition>,
+
+    globals: DummyFieldHolder,
+    globalvars: HashSet<usize>,
+    game_start: bool,
+
+    stacks: HandleList<ds::Stack>,
+    queues: HandleList<ds::Queue>,
+    lists: HandleList<ds::List>,
+    maps: HandleList<ds::Map>,
+    priority_queues: HandleList<ds::Priority>,
+    grids: HandleList<ds::Grid>,
+    ds_precision: Real,
+
+    draw_font_id: ID,
+    draw_colour: Colour,
+    draw_alpha: Real,
+    draw_halign: draw::Halign,
+    draw_valign: draw::Valign,
+    surfaces: HandleList<Surface>,
+    surface_target: Option<i32>,
+    models: HandleList<Model>,
+    model_matrix_stack: Vec<[f32; 16]>,
+    auto_draw: bool,
+    renderer_state: RendererState,
+
+    uninit_fields_are_zero: bool,
+    uninit_args_are_zero: bool,
+
+    potential_step_settings: PotentialStepSettings,
+
+    fps: u32,
+    frame_counter: u32,
+    transition_kind: i32,
+    transition_steps: i32,
+    cursor_sprite: i32,
+    cursor_sprite_frame: u32,
+    score: i32,
+    score_capt: gml::String,
+    score_capt_d: bool,
+    has_set_show_score: bool,
+    lives: i32,
+    lives_capt: gml::String,
+    lives_capt_d: bool,
+    health: Real,
+    health_capt: gml::String,
+    health_capt_d: bool,
+    error_occurred: bool,
+    error_last: gml::String,
+
+    game_id: i32,
+    program_directory: gml::String,
+    included_files: Vec<IncludedFile>,
+    gm_version: Version,
+    clock: GameClock,
+
+    clean_state: bool,
+
+    scaling: Scaling,
+    unscaled_width: u32,
+    unscaled_height: u32,
+    window_width: u32,
+    window_height: u32,
+
+    audio_state: AudioStateV1,
+
+    replay: ReplayV1,
+    screenshot: Box<[u8]>,
+    zbuffer: Box<[f32]>,
+}
+
+/// The layout of Assets in version 1 savestates, whose sounds didn't have panning or 3D sound.
+#[derive(Serialize, Deserialize)]
+pub struct AssetsV1 {
+    backgrounds: Vec<Option<Box<Background>>>,
+    fonts: Vec<Option<Box<Font>>>,
+    objects: Vec<Option<Box<Object>>>,
+    paths: Vec<Option<Box<Path>>>,
+    rooms: Vec<Option<Box<Room>>>,
+    scripts: Vec<Option<Box<Script>>>,
+    sounds: Vec<Option<Box<SoundV1>>>,
+    sprites: Vec<Option<Box<Sprite>>>,
+    timelines: Vec<Option<Box<Timeline>>>,
+    triggers: Vec<Option<Box<Trigger>>>,
+}
+
+impl From<AssetsV1> for Assets {
+    fn from(assets: AssetsV1) -> Self {
+        Self {
+            backgrounds: assets.backgrounds,
+            fonts: assets.fonts,
+            objects: assets.objects,
+            paths: assets.paths,
+            rooms: assets.rooms,
+            scripts: assets.scripts,
+            sounds: assets.sounds.into_iter().map(|sound| sound.map(|sound| Box::new((*sound).into()))).collect(),
+            sprites: assets.sprites,
+            timelines: assets.timelines,
+            triggers: assets.triggers,
+        }
+    }
+}
+
+/// Version 2 added the registry, the VFS, message and highscore settings, joysticks, sound effects, fades and
+/// 3D sounds, and SaveInfo.
+#[derive(Serialize, Deserialize)]
+pub struct SaveStateV2 {
+    compiler: Compiler,
+    rand: Random,
+    input: Input,
+    assets: Assets,
+    event_holders: [IndexMap<u32, Rc<RefCell<Vec<ID>>>>; 12],
+    custom_draw_objects: HashSet<ID>,
+
+    background_colour: Colour,
+    textures: Vec<Option<SavedTexture>>,
+
+    externals: external::ExternalState,
+    registry: RegistryState,
+    vfs: VfsState,
+    surface_fix: bool,
+
+    view_current: usize,
+
+    last_instance_id: ID,
+    last_tile_id: ID,
+
+    particles: particle::Manager,
+
+    room: RoomState,
+    stored_rooms: Vec<RoomState>,
+    room_order: Box<[i32]>,
+    user_transitions: HashMap<i32, UserTransition>,
+
+    globals: DummyFieldHolder,
+    globalvars: HashSet<usize>,
+    game_start: bool,
+
+    stacks: HandleList<ds::Stack>,
+    queues: HandleList<ds::Queue>,
+    lists: HandleList<ds::List>,
+    maps: HandleList<ds::Map>,
+    priority_queues: HandleList<ds::Priority>,
+    grids: HandleList<ds::Grid>,
+    ds_precision: Real,
+
+    draw_font_id: ID,
+    draw_colour: Colour,
+    draw_alpha: Real,
+    draw_halign: draw::Halign,
+    draw_valign: draw::Valign,
+    surfaces: HandleList<Surface>,
+    surface_target: Option<i32>,
+    models: HandleList<Model>,
+    model_matrix_stack: Vec<[f32; 16]>,
+    auto_draw: bool,
+    renderer_state: RendererState,
+
+    uninit_fields_are_zero: bool,
+    uninit_args_are_zero: bool,
+
+    potential_step_settings: PotentialStepSettings,
+    message_settings: MessageSettings,
+    highscore_settings: HighscoreSettings,
+
+    fps: u32,
+    frame_counter: u32,
+    transition_kind: i32,
+    transition_steps: i32,
+    cursor_sprite: i32,
+    cursor_sprite_frame: u32,
+    score: i32,
+    score_capt: gml::String,
+    score_capt_d: bool,
+    has_set_show_score: bool,
+    lives: i32,
+    lives_capt: gml::String,
+    lives_capt_d: bool,
+    health: Real,
+    health_capt: gml::String,
+    health_capt_d: bool,
+    error_occurred: bool,
+    error_last: gml::String,
+
+    game_id: i32,
+    program_directory: gml::String,
+    included_files: Vec<IncludedFile>,
+    gm_version: Version,
+    clock: GameClock,
+
+    clean_state: bool,
+
+    scaling: Scaling,
+    unscaled_width: u32,
+    unscaled_height: u32,
+    window_width: u32,
+    window_height: u32,
+
+    audio_state: AudioState,
+
+    replay: ReplayV2,
+    screenshot: Box<[u8]>,
+    zbuffer: Box<[f32]>,
+
+    info: SaveInfo,
+}
+
+/// The frame number in SaveInfo gets filled in from the replay after loading.
+impl From<SaveStateV1> for SaveStateV2 {
+    fn from(state: SaveStateV1) -> Self {
+        Self {
+            compiler: state.compiler,
+            rand: state.rand,
+            input: state.input.into(),
+            assets: state.assets.into(),
+            event_holders: state.event_holders,
+            custom_draw_objects: state.custom_draw_objects,
+            background_colour: state.background_colour,
+            textures: state.textures,
+            externals: state.externals,
+            registry: Default::default(),
+            vfs: Default::default(),
+            surface_fix: state.surface_fix,
+            view_current: state.view_current,
+            last_instance_id: state.last_instance_id,
+            last_tile_id: state.last_tile_id,
+            particles: state.particles,
+            room: state.room,
+            stored_rooms: state.stored_rooms,
+            room_order: state.room_order,
+            user_transitions: state.user_transitions,
+            globals: state.globals,
+            globalvars: state.globalvars,
+            game_start: state.game_start,
+            stacks: state.stacks,
+            queues: state.queues,
+            lists: state.lists,
+            maps: state.maps,
+            priority_queues: state.priority_queues,
+            grids: state.grids,
+            ds_precision: state.ds_precision,
+            draw_font_id: state.draw_font_id,
+            draw_colour: state.draw_colour,
+            draw_alpha: state.draw_alpha,
+            draw_halign: state.draw_halign,
+            draw_valign: state.draw_valign,
+            surfaces: state.surfaces,
+            surface_target: state.surface_target,
+            models: state.models,
+            model_matrix_stack: state.model_matrix_stack,
+            auto_draw: state.auto_draw,
+            renderer_state: state.renderer_state,
+            uninit_fields_are_zero: state.uninit_fields_are_zero,
+            uninit_args_are_zero: state.uninit_args_are_zero,
+            potential_step_settings: state.potential_step_settings,
+            message_settings: Default::default(),
+            highscore_settings: Default::default(),
+            fps: state.fps,
+            frame_counter: state.frame_counter,
+            transition_kind: state.transition_kind,
+            transition_steps: state.transition_steps,
+            cursor_sprite: state.cursor_sprite,
+            cursor_sprite_frame: state.cursor_sprite_frame,
+            score: state.score,
+            score_capt: state.score_capt,
+            score_capt_d: state.score_capt_d,
+            has_set_show_score: state.has_set_show_score,
+            lives: state.lives,
+            lives_capt: state.lives_capt,
+            lives_capt_d: state.lives_capt_d,
+            health: state.health,
+            health_capt: state.health_capt,
+            health_capt_d: state.health_capt_d,
+            error_occurred: state.error_occurred,
+            error_last: state.error_last,
+            game_id: state.game_id,
+            program_directory: state.program_directory,
+            included_files: state.included_files,
+            gm_version: state.gm_version,
+            clock: state.clock,
+            clean_state: state.clean_state,
+            scaling: state.scaling,
+            unscaled_width: state.unscaled_width,
+            unscaled_height: state.unscaled_height,
+            window_width: state.window_width,
+            window_height: state.window_height,
+            audio_state: state.audio_state.into(),
+            replay: state.replay.into(),
+            screenshot: state.screenshot,
+            zbuffer: state.zbuffer,
+            info: Default::default(),
+        }
+    }
+}
+
+/// Version 3 added whether files were seeded and the registry's starting contents to the replay.
+impl From<SaveStateV2> for SaveState {
+    fn from(state: SaveStateV2) -> Self {
+        Self {
+            compiler: state.compiler,
+            rand: state.rand,
+            input: state.input,
+            assets: state.assets,
+            event_holders: state.event_holders,
+            custom_draw_objects: state.custom_draw_objects,
+            background_colour: state.background_colour,
+            textures: state.textures,
+            externals: state.externals,
+            registry: state.registry,
+            vfs: state.vfs,
+            surface_fix: state.surface_fix,
+            view_current: state.view_current,
+            last_instance_id: state.last_instance_id,
+            last_tile_id: state.last_tile_id,
+            particles: state.particles,
+            room: state.room,
+            stored_rooms: state.stored_rooms,
+            room_order: state.room_order,
+            user_transitions: state.user_transitions,
+            globals: state.globals,
+            globalvars: state.globalvars,
+            game_start: state.game_start,
+            stacks: state.stacks,
+            queues: state.queues,
+            lists: state.lists,
+            maps: state.maps,
+            priority_queues: state.priority_queues,
+            grids: state.grids,
+            ds_precision: state.ds_precision,
+            draw_font_id: state.draw_font_id,
+            draw_colour: state.draw_colour,
+            draw_alpha: state.draw_alpha,
+            draw_halign: state.draw_halign,
+            draw_valign: state.draw_valign,
+            surfaces: state.surfaces,
+            surface_target: state.surface_target,
+            models: state.models,
+            model_matrix_stack: state.model_matrix_stack,
+            auto_draw: state.auto_draw,
+            renderer_state: state.renderer_state,
+            uninit_fields_are_zero: state.uninit_fields_are_zero,
+            uninit_args_are_zero: state.uninit_args_are_zero,
+            potential_step_settings: state.potential_step_settings,
+            message_settings: state.message_settings,
+            highscore_settings: state.highscore_settings,
+            fps: state.fps,
+            frame_counter: state.frame_counter,
+            transition_kind: state.transition_kind,
+            transition_steps: state.transition_steps,
+            cursor_sprite: state.cursor_sprite,
+            cursor_sprite_frame: state.cursor_sprite_frame,
+            score: state.score,
+            score_capt: state.score_capt,
+            score_capt_d: state.score_capt_d,
+            has_set_show_score: state.has_set_show_score,
+            lives: state.lives,
+            lives_capt: state.lives_capt,
+            lives_capt_d: state.lives_capt_d,
+            health: state.health,
+            health_capt: state.health_capt,
+            health_capt_d: state.health_capt_d,
+            error_occurred: state.error_occurred,
+            error_last: state.error_last,
+            game_id: state.game_id,
+            program_directory: state.program_directory,
+            included_files: state.included_files,
+            gm_version: state.gm_version,
+            clock: state.clock,
+            clean_state: state.clean_state,
+            scaling: state.scaling,
+            unscaled_width: state.unscaled_width,
+            unscaled_height: state.unscaled_height,
+            window_width: state.window_width,
+            window_height: state.window_height,
+            audio_state: state.audio_state,
+            replay: ReplayV3::from(state.replay).into(),
+            screenshot: state.screenshot,
+            zbuffer: state.zbuffer,
+            info: state.info,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::savestate::Buffer;
+    use byteorder::{WriteBytesExt, LE};
+    use lzzzz::lz4;
+    use std::{fs::File, io::Write};
+
+    #[test]
+    fn load_v1() {
+        // every type here decodes zeroes as an empty value, so this makes a blank savestate in the old layout
+        let mut state = bincode::deserialize::<SaveStateV1>(&[0; 1 << 16]).unwrap();
+        state.game_id = 1234;
+        state.clean_state = true;
+        state.window_width = 800;
+        state.screenshot = vec![1, 2, 3].into();
+        state.zbuffer = vec![0.5].into();
+
+        // written the way savestates were before they had a version
+        let data = bincode::serialize(&state).unwrap();
+        let mut compressed = Vec::new();
+        lz4::compress_to_vec(&data, &mut compressed, lz4::ACC_LEVEL_DEFAULT).unwrap();
+        let path = std::env::temp_dir().join("gm8emulator_savestate_v1_test.bin");
+        let mut file = File::create(&path).unwrap();
+        file.write_u64::<LE>(data.len() as u64).unwrap();
+        file.write_all(&compressed).unwrap();
+        drop(file);
+
+        let loaded = SaveState::from_file_versioned(&path, &mut Buffer::new());
+        std::fs::remove_file(&path).unwrap();
+        let (state, version) = loaded.unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(state.game_id, 1234);
+        assert!(state.clean_state);
+        assert_eq!(state.window_width, 800);
+        assert_eq!(&*state.screenshot, &[1, 2, 3]);
+        assert_eq!(&*state.zbuffer, &[0.5]);
+        assert_eq!(state.highscore_settings.nobody, "<nobody>".into());
+        assert!(state.input.joysticks().iter().all(|j| !j.connected && j.pov == -1));
+        assert_eq!(state.info.frame, 0);
+        assert!(!state.replay.seeded_files);
+    }
+}
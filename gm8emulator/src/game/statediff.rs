@@ -0,0 +1,379 @@
+//! Structured comparison of two savestates, for narrowing down desyncs and checking that two routes converge.
+//!
+//! Unlike the hashes in statehash, this says exactly what differs, down to single fields of single instances.
+
+use crate::{
+    game::{savestate::SaveState, GetAsset},
+    gml::Value,
+    handleman::HandleList,
+    instance::{DummyFieldHolder, Field, Instance},
+    types::ID,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::{self, Display},
+    hash::Hash,
+};
+
+/// One part of the game state and the differences found in it.
+pub struct Section {
+    pub name: &'static str,
+    pub lines: Vec<String>,
+}
+
+/// Every difference between two savestates, grouped into sections. Sections without differences are left out.
+pub struct StateDiff {
+    pub sections: Vec<Section>,
+}
+
+/// Compares by serialized bytes, so it works on anything serializable and NaNs compare equal to themselves.
+fn same<T: Serialize + ?Sized>(a: &T, b: &T) -> bool {
+    bincode::serialize(a).ok() == bincode::serialize(b).ok()
+}
+
+/// Pushes a line if a value differs, written as "name: a -> b".
+fn compare<T: Serialize + Display>(lines: &mut Vec<String>, name: impl Display, a: T, b: T) {
+    if !same(&a, &b) {
+        lines.push(format!("{}: {} -> {}", name, a, b));
+    }
+}
+
+/// Describes the first difference between two sequences of values, if any.
+fn compare_values<'a>(
+    mut a: impl ExactSizeIterator<Item = &'a Value>,
+    mut b: impl ExactSizeIterator<Item = &'a Value>,
+) -> Option<String> {
+    let (len_a, len_b) = (a.len(), b.len());
+    let mut index = 0;
+    loop {
+        match (a.next(), b.next()) {
+            (Some(x), Some(y)) if x != y => return Some(format!("index {}: {} -> {}", index, x, y)),
+            (Some(_), Some(_)) => index += 1,
+            (None, None) => return None,
+            _ => return Some(format!("size {} -> {}", len_a, len_b)),
+        }
+    }
+}
+
+/// Compares the handles in two handle lists, describing the ones which only exist in one, or which differ.
+fn compare_handles<T: Serialize>(
+    lines: &mut Vec<String>,
+    name: &str,
+    a: &HandleList<T>,
+    b: &HandleList<T>,
+    describe: impl Fn(&T, &T) -> Option<String>,
+) {
+    let ids = a.iter().chain(b.iter()).map(|(id, _)| id).collect::<BTreeSet<_>>();
+    for id in ids {
+        match (a.get(id), b.get(id)) {
+            (Some(x), Some(y)) if !same(x, y) => {
+                let description = describe(x, y).unwrap_or_else(|| "contents differ".into());
+                lines.push(format!("{} {}: {}", name, id, description));
+            },
+            (Some(_), None) => lines.push(format!("{} {}: only in first", name, id)),
+            (None, Some(_)) => lines.push(format!("{} {}: only in second", name, id)),
+            _ => (),
+        }
+    }
+}
+
+impl StateDiff {
+    pub fn new(a: &SaveState, b: &SaveState) -> Self {
+        let mut diff = Self { sections: Vec::new() };
+        diff.section("room", Self::room(a, b));
+        diff.section("rng", {
+            let mut lines = Vec::new();
+            compare(&mut lines, "seed", a.rand.seed(), b.rand.seed());
+            lines
+        });
+        diff.section("instances", Self::instances(a, b));
+        diff.section("globals", Self::globals(a, b));
+        diff.section("data structures", Self::data_structures(a, b));
+        diff.section("surfaces", Self::surfaces(a, b));
+        diff.section("particles", Self::particles(a, b));
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    fn section(&mut self, name: &'static str, lines: Vec<String>) {
+        if !lines.is_empty() {
+            self.sections.push(Section { name, lines });
+        }
+    }
+
+    fn room(a: &SaveState, b: &SaveState) -> Vec<String> {
+        let mut lines = Vec::new();
+        let (a, b) = (&a.room, &b.room);
+        compare(&mut lines, "id", a.id, b.id);
+        compare(&mut lines, "width", a.width, b.width);
+        compare(&mut lines, "height", a.height, b.height);
+        compare(&mut lines, "speed", a.speed, b.speed);
+        compare(&mut lines, "persistent", a.persistent, b.persistent);
+        compare(&mut lines, "caption", &a.caption, &b.caption);
+        compare(&mut lines, "show_colour", a.show_colour, b.show_colour);
+        compare(&mut lines, "views_enabled", a.views_enabled, b.views_enabled);
+        if !same(&a.colour, &b.colour) {
+            lines.push("colour differs".into());
+        }
+        for (i, (x, y)) in a.views.iter().zip(&b.views).enumerate() {
+            if !same(x, y) {
+                lines.push(format!("view {} differs", i));
+            }
+        }
+        for (i, (x, y)) in a.backgrounds.iter().zip(&b.backgrounds).enumerate() {
+            if !same(x, y) {
+                lines.push(format!("background {} differs", i));
+            }
+        }
+        if !same(&a.tile_list, &b.tile_list) {
+            lines.push("tiles differ".into());
+        }
+        lines
+    }
+
+    /// Collects every instance which hasn't been deleted, by ID.
+    fn instance_map(state: &SaveState) -> BTreeMap<ID, &Instance> {
+        let list = &state.room.instance_list;
+        let mut instances = BTreeMap::new();
+        let mut iter = list.iter_by_drawing();
+        while let Some(handle) = iter.next(list) {
+            let instance = list.get(handle);
+            instances.insert(instance.id.get(), instance);
+        }
+        let mut iter = list.iter_inactive();
+        while let Some(handle) = iter.next(list) {
+            let instance = list.get(handle);
+            instances.insert(instance.id.get(), instance);
+        }
+        instances
+    }
+
+    fn instances(a: &SaveState, b: &SaveState) -> Vec<String> {
+        let mut lines = Vec::new();
+        let (map_a, map_b) = (Self::instance_map(a), Self::instance_map(b));
+        let object_name = |object_index: ID| match a.assets.objects.get_asset(object_index) {
+            Some(object) => object.name.to_string(),
+            None => format!("object {}", object_index),
+        };
+        let ids = map_a.keys().chain(map_b.keys()).copied().collect::<BTreeSet<_>>();
+        for id in ids {
+            let (x, y) = match (map_a.get(&id), map_b.get(&id)) {
+                (Some(x), Some(y)) => (x, y),
+                (Some(x), None) => {
+                    lines.push(format!("{} ({}): only in first", id, object_name(x.object_index.get())));
+                    continue
+                },
+                (None, Some(y)) => {
+                    lines.push(format!("{} ({}): only in second", id, object_name(y.object_index.get())));
+                    continue
+                },
+                (None, None) => continue,
+            };
+            let mut vars = Vec::new();
+            if x.state.get() != y.state.get() {
+                vars.push(format!("state: {:?} -> {:?}", x.state.get(), y.state.get()));
+            }
+            macro_rules! compare_vars {
+                ($($var:ident),* $(,)?) => {
+                    $(compare(&mut vars, stringify!($var), x.$var.get(), y.$var.get());)*
+                };
+            }
+            compare_vars! {
+                object_index, solid, visible, persistent, depth, sprite_index, image_alpha, image_blend, image_index,
+                image_speed, image_xscale, image_yscale, image_angle, mask_index, direction, friction, gravity,
+                gravity_direction, hspeed, vspeed, speed, x, y, xprevious, yprevious, xstart, ystart, path_index,
+                path_position, path_positionprevious, path_speed, path_scale, path_orientation, path_endaction,
+                path_xstart, path_ystart, timeline_index, timeline_running, timeline_speed, timeline_position,
+                timeline_loop,
+            }
+            Self::fields(&mut vars, &*x.fields.borrow(), &*y.fields.borrow(), |id| Self::field_name(a, *id));
+            let (alarms_a, alarms_b) = (x.alarms.borrow(), y.alarms.borrow());
+            let alarms = alarms_a.keys().chain(alarms_b.keys()).collect::<BTreeSet<_>>();
+            for alarm in alarms {
+                // alarms which haven't been set yet count as -1, the same as ones which have gone off
+                let (alarm_a, alarm_b) =
+                    (alarms_a.get(alarm).copied().unwrap_or(-1), alarms_b.get(alarm).copied().unwrap_or(-1));
+                compare(&mut vars, format!("alarm[{}]", alarm), alarm_a, alarm_b);
+            }
+            let name = object_name(x.object_index.get());
+            lines.extend(vars.into_iter().map(|var| format!("{} ({}): {}", id, name, var)));
+        }
+        lines
+    }
+
+    fn field_name(state: &SaveState, id: usize) -> String {
+        state.compiler.get_field_name(id).unwrap_or_else(|| format!("field {}", id))
+    }
+
+    /// Compares two sets of fields, such as an instance's variables, naming them with the given function.
+    fn fields<K: Ord + Hash>(
+        lines: &mut Vec<String>,
+        a: &HashMap<K, Field>,
+        b: &HashMap<K, Field>,
+        name: impl Fn(&K) -> String,
+    ) {
+        let keys = a.keys().chain(b.keys()).collect::<BTreeSet<_>>();
+        for key in keys {
+            match (a.get(key), b.get(key)) {
+                (Some(Field::Single(x)), Some(Field::Single(y))) => compare(lines, name(key), x, y),
+                (Some(Field::Array(x)), Some(Field::Array(y))) => {
+                    let indices = x.keys().chain(y.keys()).collect::<BTreeSet<_>>();
+                    for index in indices {
+                        let label = format!("{}[{}]", name(key), index);
+                        match (x.get(index), y.get(index)) {
+                            (Some(x), Some(y)) => compare(lines, label, x, y),
+                            (Some(x), None) => lines.push(format!("{}: {} -> unset", label, x)),
+                            (None, Some(y)) => lines.push(format!("{}: unset -> {}", label, y)),
+                            (None, None) => (),
+                        }
+                    }
+                },
+                (Some(Field::Single(x)), Some(Field::Array(_))) => lines.push(format!("{}: {} -> array", name(key), x)),
+                (Some(Field::Array(_)), Some(Field::Single(y))) => lines.push(format!("{}: array -> {}", name(key), y)),
+                (Some(_), None) => lines.push(format!("{}: only in first", name(key))),
+                (None, Some(_)) => lines.push(format!("{}: only in second", name(key))),
+                (None, None) => (),
+            }
+        }
+    }
+
+    fn globals(a: &SaveState, b: &SaveState) -> Vec<String> {
+        let mut lines = Vec::new();
+        let (DummyFieldHolder { fields: fields_a, vars: vars_a }, DummyFieldHolder { fields: fields_b, vars: vars_b }) =
+            (&a.globals, &b.globals);
+        Self::fields(&mut lines, fields_a, fields_b, |id| Self::field_name(a, *id));
+        // built-in variables don't have an ordering, so sort them by name
+        let vars_a: HashMap<_, _> = vars_a.iter().map(|(var, field)| (format!("{:?}", var), field.clone())).collect();
+        let vars_b: HashMap<_, _> = vars_b.iter().map(|(var, field)| (format!("{:?}", var), field.clone())).collect();
+        Self::fields(&mut lines, &vars_a, &vars_b, |name| name.clone());
+        for var in a.globalvars.symmetric_difference(&b.globalvars).collect::<BTreeSet<_>>() {
+            let which = if a.globalvars.contains(var) { "first" } else { "second" };
+            lines.push(format!("globalvar {} only in {}", Self::field_name(a, *var), which));
+        }
+        lines
+    }
+
+    fn data_structures(a: &SaveState, b: &SaveState) -> Vec<String> {
+        let mut lines = Vec::new();
+        compare(&mut lines, "ds_set_precision", a.ds_precision, b.ds_precision);
+        compare_handles(&mut lines, "ds_stack", &a.stacks, &b.stacks, |x, y| compare_values(x.iter(), y.iter()));
+        compare_handles(&mut lines, "ds_queue", &a.queues, &b.queues, |x, y| compare_values(x.iter(), y.iter()));
+        compare_handles(&mut lines, "ds_list", &a.lists, &b.lists, |x, y| compare_values(x.iter(), y.iter()));
+        compare_handles(&mut lines, "ds_map", &a.maps, &b.maps, |x, y| {
+            compare_values(x.keys.iter(), y.keys.iter())
+                .map(|d| format!("keys {}", d))
+                .or_else(|| compare_values(x.values.iter(), y.values.iter()).map(|d| format!("values {}", d)))
+        });
+        compare_handles(&mut lines, "ds_priority", &a.priority_queues, &b.priority_queues, |x, y| {
+            compare_values(x.values.iter(), y.values.iter()).map(|d| format!("values {}", d)).or_else(|| {
+                compare_values(x.priorities.iter(), y.priorities.iter()).map(|d| format!("priorities {}", d))
+            })
+        });
+        compare_handles(&mut lines, "ds_grid", &a.grids, &b.grids, |x, y| {
+            if (x.width(), x.height()) != (y.width(), y.height()) {
+                return Some(format!("size {}x{} -> {}x{}", x.width(), x.height(), y.width(), y.height()))
+            }
+            let (width, height) = (x.width() as i32, x.height() as i32);
+            (0..width).flat_map(|i| (0..height).map(move |j| (i, j))).find_map(|(i, j)| {
+                match (x.get(i, j), y.get(i, j)) {
+                    (Some(v1), Some(v2)) if v1 != v2 => Some(format!("[{}, {}]: {} -> {}", i, j, v1, v2)),
+                    _ => None,
+                }
+            })
+        });
+        lines
+    }
+
+    fn surfaces(a: &SaveState, b: &SaveState) -> Vec<String> {
+        let mut lines = Vec::new();
+        compare_handles(&mut lines, "surface", &a.surfaces, &b.surfaces, |x, y| {
+            if (x.width, x.height) != (y.width, y.height) {
+                Some(format!("size {}x{} -> {}x{}", x.width, x.height, y.width, y.height))
+            } else {
+                Some("texture differs".into())
+            }
+        });
+        compare(&mut lines, "surface_target", a.surface_target.unwrap_or(-1), b.surface_target.unwrap_or(-1));
+        // the contents of surfaces are saved along with every other texture made at runtime
+        compare(&mut lines, "dynamic texture count", a.textures.len(), b.textures.len());
+        for (i, (x, y)) in a.textures.iter().zip(&b.textures).enumerate() {
+            if !same(x, y) {
+                lines.push(format!("dynamic texture {}: contents differ", i));
+            }
+        }
+        lines
+    }
+
+    fn particles(a: &SaveState, b: &SaveState) -> Vec<String> {
+        let mut lines = Vec::new();
+        let (a, b) = (&a.particles, &b.particles);
+        let ids = a.system_ids().chain(b.system_ids()).collect::<BTreeSet<_>>();
+        for id in ids {
+            match (a.get_system(id), b.get_system(id)) {
+                (Some(x), Some(y)) if !same(x, y) => {
+                    let mut vars = Vec::new();
+                    compare(&mut vars, "particle count", x.particles.len(), y.particles.len());
+                    compare(&mut vars, "x", x.x, y.x);
+                    compare(&mut vars, "y", x.y, y.y);
+                    compare(&mut vars, "depth", x.depth, y.depth);
+                    compare(&mut vars, "auto_update", x.auto_update, y.auto_update);
+                    compare(&mut vars, "auto_draw", x.auto_draw, y.auto_draw);
+                    if vars.is_empty() {
+                        vars.push("particles or emitters differ".into());
+                    }
+                    lines.extend(vars.into_iter().map(|var| format!("system {}: {}", id, var)));
+                },
+                (Some(_), None) => lines.push(format!("system {}: only in first", id)),
+                (None, Some(_)) => lines.push(format!("system {}: only in second", id)),
+                _ => (),
+            }
+        }
+        if !same(a, b) && lines.is_empty() {
+            lines.push("particle types differ".into());
+        }
+        lines
+    }
+}
+
+impl Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no differences found")
+        }
+        for section in &self.sections {
+            writeln!(f, "{}:", section.name)?;
+            for line in &section.lines {
+                writeln!(f, "    {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Real;
+
+    #[test]
+    fn values() {
+        let (one, two) = (Value::Real(Real::from(1)), Value::Real(Real::from(2)));
+        assert_eq!(compare_values([&one, &two].into_iter(), [&one, &two].into_iter()), None);
+        assert_eq!(compare_values([&one, &two].into_iter(), [&one, &one].into_iter()).unwrap(), "index 1: 2 -> 1");
+        assert_eq!(compare_values([&one].into_iter(), [&one, &one].into_iter()).unwrap(), "size 1 -> 2");
+    }
+
+    #[test]
+    fn fields() {
+        let one = Value::Real(Real::from(1));
+        let a = HashMap::from([(0, Field::Single(one.clone())), (1, Field::Array(HashMap::from([(2, one.clone())])))]);
+        let b = HashMap::from([(0, Field::Single(Value::Real(Real::from(3)))), (2, Field::Single(one))]);
+        let mut lines = Vec::new();
+        StateDiff::fields(&mut lines, &a, &b, |id| format!("v{}", id));
+        assert_eq!(lines, vec!["v0: 1 -> 3", "v1: only in first", "v2: only in second"]);
+    }
+}
@@ -0,0 +1,314 @@
+//! Per-frame hashes of the game's deterministic state, for finding the first frame where two runs diverge.
+//!
+//! A hash log is a text file with one line per frame: the frame number followed by one hash per subsystem.
+//! When recording, rewinding means a frame can be logged more than once - the last line for a frame wins.
+
+use crate::{
+    game::Game,
+    instance::{Field, Instance},
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+/// A part of the game state which gets its own hash, so a mismatch can be narrowed down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subsystem {
+    Room,
+    Random,
+    Instances,
+    Globals,
+    DataStructures,
+}
+
+impl Subsystem {
+    /// Every subsystem, in the order their hashes are written.
+    pub const ALL: [Self; 5] = [Self::Room, Self::Random, Self::Instances, Self::Globals, Self::DataStructures];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Room => "room",
+            Self::Random => "rng",
+            Self::Instances => "instances",
+            Self::Globals => "globals",
+            Self::DataStructures => "ds",
+        }
+    }
+}
+
+/// The hashes of every subsystem at the end of a single frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameHash([u64; Subsystem::ALL.len()]);
+
+impl FrameHash {
+    pub fn of(game: &Game) -> Self {
+        Self(Subsystem::ALL.map(|subsystem| {
+            let mut hasher = StateHasher::new();
+            hasher.subsystem(game, subsystem);
+            hasher.0
+        }))
+    }
+
+    /// Lists the subsystems whose hashes don't match.
+    pub fn diff(&self, other: &Self) -> Vec<Subsystem> {
+        Subsystem::ALL.iter().zip(self.0.iter().zip(&other.0)).filter(|(_, (a, b))| a != b).map(|(s, _)| *s).collect()
+    }
+}
+
+impl fmt::Display for FrameHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, hash) in self.0.iter().enumerate() {
+            if i != 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{:016x}", hash)?;
+        }
+        Ok(())
+    }
+}
+
+/// FNV-1a. Unlike the std hashers, its output is guaranteed to be the same across builds and platforms.
+struct StateHasher(u64);
+
+impl StateHasher {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn value<T: Serialize + ?Sized>(&mut self, value: &T) {
+        bincode::serialize_into(&mut *self, value).expect("hashing game state shouldn't fail");
+    }
+
+    /// Hashes the entries of a HashMap or HashSet sorted by key, since their iteration order changes between runs.
+    fn unordered<K: Serialize, V>(
+        &mut self,
+        entries: impl Iterator<Item = (K, V)>,
+        mut hash_value: impl FnMut(&mut Self, V),
+    ) {
+        let mut entries = entries
+            .map(|(k, v)| (bincode::serialize(&k).expect("hashing game state shouldn't fail"), v))
+            .collect::<Vec<_>>();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.value(&entries.len());
+        for (key, value) in entries {
+            self.hash_bytes(&key);
+            hash_value(self, value);
+        }
+    }
+
+    fn hash_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn field(&mut self, field: &Field) {
+        match field {
+            Field::Single(value) => {
+                self.value(&0u8);
+                self.value(value);
+            },
+            Field::Array(array) => {
+                self.value(&1u8);
+                self.unordered(array.iter(), |h, value| h.value(value));
+            },
+        }
+    }
+
+    fn instance(&mut self, instance: &Instance) {
+        // the hashmaps get hashed separately, and parents come from the object so they're skipped entirely
+        let mut instance = instance.clone();
+        let fields = instance.fields.take();
+        let alarms = instance.alarms.take();
+        instance.parents = Default::default();
+        self.value(&instance);
+        self.unordered(fields.iter(), |h, field| h.field(field));
+        self.unordered(alarms.iter(), |h, alarm| h.value(alarm));
+    }
+
+    fn subsystem(&mut self, game: &Game, subsystem: Subsystem) {
+        match subsystem {
+            Subsystem::Room => {
+                let room = &game.room;
+                self.value(&(room.id, room.width, room.height, room.speed, room.persistent));
+                self.value(&(room.colour, room.show_colour, room.views_enabled, &room.caption));
+                self.value(&room.views);
+                self.value(&room.backgrounds);
+                self.value(&room.tile_list);
+            },
+            Subsystem::Random => self.value(&game.rand.seed()),
+            Subsystem::Instances => {
+                let list = &game.room.instance_list;
+                let mut iter = list.iter_by_drawing();
+                while let Some(handle) = iter.next(list) {
+                    self.instance(list.get(handle));
+                }
+                let mut iter = list.iter_inactive();
+                while let Some(handle) = iter.next(list) {
+                    self.instance(list.get(handle));
+                }
+            },
+            Subsystem::Globals => {
+                self.unordered(game.globals.fields.iter(), |h, field| h.field(field));
+                self.unordered(game.globals.vars.iter(), |h, field| h.field(field));
+                self.unordered(game.globalvars.iter().map(|var| (var, ())), |_, _| ());
+            },
+            Subsystem::DataStructures => {
+                self.value(&(&game.stacks, &game.queues, &game.lists));
+                self.value(&(&game.maps, &game.priority_queues, &game.grids));
+                self.value(&game.ds_precision);
+            },
+        }
+    }
+}
+
+impl Write for StateHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hash_bytes(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes the hash of each frame to a log file as it's played.
+pub struct HashLog {
+    file: BufWriter<File>,
+}
+
+impl HashLog {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        write!(file, "# frame")?;
+        for subsystem in Subsystem::ALL {
+            write!(file, " {}", subsystem.name())?;
+        }
+        writeln!(file)?;
+        Ok(Self { file })
+    }
+
+    /// Logs the current state of the game as the state at the end of the given frame.
+    pub fn write_frame(&mut self, frame: usize, game: &Game) -> io::Result<()> {
+        writeln!(self.file, "{} {}", frame, FrameHash::of(game))?;
+        // flush every frame so the log is still useful if the game crashes
+        self.file.flush()
+    }
+}
+
+/// Reads a hash log written by HashLog.
+pub fn read_log(path: &Path) -> io::Result<BTreeMap<usize, FrameHash>> {
+    let invalid = |line_number: usize| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("malformed hash log entry on line {}", line_number + 1))
+    };
+    let mut frames = BTreeMap::new();
+    for (line_number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue
+        }
+        let mut parts = line.split_whitespace();
+        let frame = parts.next().and_then(|s| s.parse::<usize>().ok()).ok_or_else(|| invalid(line_number))?;
+        let mut hashes = [0u64; Subsystem::ALL.len()];
+        for hash in hashes.iter_mut() {
+            *hash = parts.next().and_then(|s| u64::from_str_radix(s, 16).ok()).ok_or_else(|| invalid(line_number))?;
+        }
+        if parts.next().is_some() {
+            return Err(invalid(line_number))
+        }
+        frames.insert(frame, FrameHash(hashes));
+    }
+    Ok(frames)
+}
+
+/// The result of comparing two hash logs.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Comparison {
+    /// Every frame matched. Contains the number of frames compared.
+    Identical(usize),
+    /// The logs disagree about the state at the end of this frame.
+    Mismatch { frame: usize, subsystems: Vec<Subsystem> },
+    /// All frames before this one matched, but only one log has this frame.
+    Missing { frame: usize, in_first: bool },
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Identical(frames) => write!(f, "no differences found in {} frames", frames),
+            Self::Mismatch { frame, subsystems } => {
+                write!(f, "first mismatch on frame {}, differing in:", frame)?;
+                for subsystem in subsystems {
+                    write!(f, " {}", subsystem.name())?;
+                }
+                Ok(())
+            },
+            Self::Missing { frame, in_first } => write!(
+                f,
+                "no mismatches, but frame {} is only in the {} log",
+                frame,
+                if *in_first { "first" } else { "second" }
+            ),
+        }
+    }
+}
+
+/// Finds the first frame where two hash logs disagree.
+pub fn compare(first: &BTreeMap<usize, FrameHash>, second: &BTreeMap<usize, FrameHash>) -> Comparison {
+    let mut frames = first.keys().chain(second.keys()).copied().collect::<Vec<_>>();
+    frames.sort_unstable();
+    frames.dedup();
+    for &frame in &frames {
+        match (first.get(&frame), second.get(&frame)) {
+            (Some(a), Some(b)) if a != b => return Comparison::Mismatch { frame, subsystems: a.diff(b) },
+            (Some(_), Some(_)) => (),
+            (a, _) => return Comparison::Missing { frame, in_first: a.is_some() },
+        }
+    }
+    Comparison::Identical(frames.len())
+}
+
+impl Game {
+    /// Writes the current state to the hash log as the end of the given frame, if there is a hash log.
+    pub fn log_state_hash(&mut self, frame: usize) -> io::Result<()> {
+        if let Some(mut log) = self.state_hash_log.take() {
+            let result = log.write_frame(frame, self);
+            self.state_hash_log = Some(log);
+            result
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(entries: &[(usize, [u64; 5])]) -> BTreeMap<usize, FrameHash> {
+        entries.iter().map(|(frame, hashes)| (*frame, FrameHash(*hashes))).collect()
+    }
+
+    #[test]
+    fn compare_logs() {
+        let a = log(&[(0, [1, 2, 3, 4, 5]), (1, [1, 2, 3, 4, 5]), (2, [6, 2, 3, 4, 5])]);
+        assert_eq!(compare(&a, &a), Comparison::Identical(3));
+
+        let b = log(&[(0, [1, 2, 3, 4, 5]), (1, [1, 7, 3, 4, 8]), (2, [6, 2, 3, 4, 5])]);
+        assert_eq!(compare(&a, &b), Comparison::Mismatch {
+            frame: 1,
+            subsystems: vec![Subsystem::Random, Subsystem::DataStructures],
+        });
+
+        let c = log(&[(0, [1, 2, 3, 4, 5]), (1, [1, 2, 3, 4, 5])]);
+        assert_eq!(compare(&a, &c), Comparison::Missing { frame: 2, in_first: true });
+        assert_eq!(compare(&c, &a), Comparison::Missing { frame: 2, in_first: false });
+    }
+}
@@ -0,0 +1,513 @@
+//! A copy-on-write virtual filesystem over the game and temp directories.
+//!
+//! When recording or replaying, anything a game writes under its own directory or its temp directory goes into an
+//! in-memory overlay instead of the disk, so save files are rolled back along with everything else when a savestate
+//! is loaded. Paths anywhere else still go straight to the host filesystem.
+//!
+//! The overlay starts out empty apart from the exported included files, so a replay can't be affected by whatever
+//! save files happen to be lying around. If it's seeded, files the game hasn't touched yet are read from the real
+//! directories instead, but they're never written to. Replays record whether they were seeded.
+//!
+//! Included files are the one exception: host code like extension DLLs can only see the real disk, so they're
+//! exported there as well as into the overlay. The game itself still reads the overlay copy.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fs,
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
+
+/// A file in the overlay, or a record that it was deleted.
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    path: PathBuf, // as first written, for file_find
+    data: Option<Vec<u8>>,
+}
+
+/// The overlay, as stored in savestates.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct VfsState {
+    // paths are case-insensitive on Windows, so they're keyed lowercased
+    files: BTreeMap<String, Entry>,
+    dirs: BTreeMap<String, PathBuf>,
+}
+
+type Buffer = Rc<RefCell<Vec<u8>>>;
+
+struct LiveEntry {
+    path: PathBuf,
+    // shared with any open handles, so writes show up in the overlay immediately
+    data: Option<Buffer>,
+}
+
+/// Nothing is sandboxed until `set_roots` is called, so the default passes everything through to the disk.
+#[derive(Default)]
+pub struct Vfs {
+    files: BTreeMap<String, LiveEntry>,
+    dirs: BTreeMap<String, PathBuf>,
+    roots: Vec<String>,
+    seeded: bool,
+}
+
+/// An open file, either on the host or in the overlay.
+#[derive(Debug)]
+pub enum File {
+    Host(fs::File),
+    Virtual(VirtualFile),
+}
+
+#[derive(Debug)]
+pub struct VirtualFile {
+    data: Buffer,
+    pos: u64,
+    append: bool,
+}
+
+/// Mirrors `std::fs::OpenOptions`, for opening files through the VFS.
+#[derive(Clone, Default)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+}
+
+/// Makes a path absolute and removes `.` and `..` components, without touching the disk.
+fn resolve(path: &Path) -> PathBuf {
+    let mut out = if path.is_absolute() { PathBuf::new() } else { std::env::current_dir().unwrap_or_default() };
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                out.pop();
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn key(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/").trim_end_matches('/').to_lowercase()
+}
+
+/// Whether a key is somewhere below another one.
+fn is_inside(key: &str, dir: &str) -> bool {
+    key.strip_prefix(dir).is_some_and(|rest| rest.starts_with('/'))
+}
+
+fn parent_key(key: &str) -> &str {
+    key.rsplit_once('/').map_or("", |(parent, _)| parent)
+}
+
+impl Vfs {
+    /// Sandboxes everything under the given directories. If `seeded`, files which aren't in the overlay are read
+    /// from the disk.
+    pub fn set_roots(&mut self, roots: &[&Path], seeded: bool) {
+        self.roots = roots.iter().map(|root| key(&resolve(root))).filter(|root| !root.is_empty()).collect();
+        self.seeded = seeded;
+    }
+
+    /// Whether anything is sandboxed, or everything goes to the disk.
+    pub fn is_enabled(&self) -> bool {
+        !self.roots.is_empty()
+    }
+
+    /// Whether untouched files are read from the disk.
+    pub fn is_seeded(&self) -> bool {
+        self.seeded
+    }
+
+    /// Whether a path goes to the overlay rather than the disk.
+    pub fn is_sandboxed(&self, path: &str) -> bool {
+        self.sandboxed(path).is_some()
+    }
+
+    /// Returns the overlay key and host path for a path, or None if it isn't sandboxed.
+    fn sandboxed(&self, path: &str) -> Option<(String, PathBuf)> {
+        if self.roots.is_empty() {
+            return None
+        }
+        let path = resolve(path.as_ref());
+        let key = key(&path);
+        self.roots.iter().any(|root| key == *root || is_inside(&key, root)).then_some((key, path))
+    }
+
+    /// Finds a sandboxed file's contents, either in the overlay or (if seeded) on the disk.
+    fn lookup(&self, key: &str, path: &Path) -> io::Result<Option<Buffer>> {
+        match self.files.get(key) {
+            Some(entry) => Ok(entry.data.clone()),
+            None if self.seeded && path.is_file() => Ok(Some(Rc::new(RefCell::new(fs::read(path)?)))),
+            None => Ok(None),
+        }
+    }
+
+    /// Puts a sandboxed file into the overlay, or replaces its contents in place if it's already there.
+    fn store(&mut self, key: String, path: PathBuf, data: Vec<u8>) -> Buffer {
+        match self.files.get_mut(&key) {
+            Some(LiveEntry { data: Some(buffer), .. }) => {
+                *buffer.borrow_mut() = data;
+                buffer.clone()
+            },
+            _ => {
+                let buffer = Rc::new(RefCell::new(data));
+                self.files.insert(key, LiveEntry { path, data: Some(buffer.clone()) });
+                buffer
+            },
+        }
+    }
+
+    pub fn open(&mut self, path: &str, options: &OpenOptions) -> io::Result<File> {
+        let (key, host_path) = match self.sandboxed(path) {
+            Some(x) => x,
+            None => return options.to_std().open(path).map(File::Host),
+        };
+        let existing = self.lookup(&key, &host_path)?;
+        let data = match existing {
+            Some(_) if options.create_new => return Err(ErrorKind::AlreadyExists.into()),
+            None if !(options.create || options.create_new) => return Err(ErrorKind::NotFound.into()),
+            Some(buffer) if !(options.write || options.append) => buffer,
+            existing => {
+                let in_overlay = self.files.get(&key).is_some_and(|entry| entry.data.is_some());
+                let buffer = match existing {
+                    Some(buffer) if in_overlay => buffer,
+                    existing => {
+                        let data = existing.map(|buffer| buffer.borrow().clone()).unwrap_or_default();
+                        self.store(key, host_path, data)
+                    },
+                };
+                if options.truncate {
+                    buffer.borrow_mut().clear();
+                }
+                buffer
+            },
+        };
+        Ok(File::Virtual(VirtualFile { data, pos: 0, append: options.append }))
+    }
+
+    pub fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        match self.sandboxed(path) {
+            Some((key, host_path)) => match self.lookup(&key, &host_path)? {
+                Some(buffer) => Ok(buffer.borrow().clone()),
+                None => Err(ErrorKind::NotFound.into()),
+            },
+            None => fs::read(path),
+        }
+    }
+
+    pub fn write(&mut self, path: &str, data: Vec<u8>) -> io::Result<()> {
+        match self.sandboxed(path) {
+            Some((key, host_path)) => {
+                self.store(key, host_path, data);
+                Ok(())
+            },
+            None => fs::write(path, data),
+        }
+    }
+
+    pub fn file_exists(&self, path: &str) -> bool {
+        match self.sandboxed(path) {
+            Some((key, host_path)) => match self.files.get(&key) {
+                Some(entry) => entry.data.is_some(),
+                None => self.seeded && host_path.is_file(),
+            },
+            None => Path::new(path).is_file(),
+        }
+    }
+
+    pub fn dir_exists(&self, path: &str) -> bool {
+        match self.sandboxed(path) {
+            Some((key, host_path)) => {
+                self.roots.contains(&key)
+                    || self.dirs.contains_key(&key)
+                    || self.files.iter().any(|(k, e)| e.data.is_some() && is_inside(k, &key))
+                    || (self.seeded && host_path.is_dir())
+            },
+            None => Path::new(path).is_dir(),
+        }
+    }
+
+    /// Deletes a file if it exists.
+    pub fn delete(&mut self, path: &str) -> io::Result<()> {
+        match self.sandboxed(path) {
+            Some((key, host_path)) => {
+                if self.file_exists(path) {
+                    self.files.insert(key, LiveEntry { path: host_path, data: None });
+                }
+                Ok(())
+            },
+            None if Path::new(path).exists() => fs::remove_file(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Renames a file, unless there's already something at the destination.
+    pub fn rename(&mut self, from: &str, to: &str) -> io::Result<()> {
+        match (self.sandboxed(from), self.sandboxed(to)) {
+            (None, None) if !Path::new(to).exists() => fs::rename(from, to),
+            (None, None) => Ok(()),
+            _ if self.file_exists(to) => Ok(()),
+            _ => {
+                let data = self.read(from)?;
+                self.write(to, data)?;
+                self.delete(from)
+            },
+        }
+    }
+
+    pub fn copy(&mut self, from: &str, to: &str) -> io::Result<()> {
+        match (self.sandboxed(from), self.sandboxed(to)) {
+            (None, None) => fs::copy(from, to).map(|_| ()),
+            _ => {
+                let data = self.read(from)?;
+                self.write(to, data)
+            },
+        }
+    }
+
+    /// Creates a directory along with any missing parents.
+    pub fn create_dir(&mut self, path: &str) -> io::Result<()> {
+        match self.sandboxed(path) {
+            Some((_, mut host_path)) => loop {
+                let key = key(&host_path);
+                if !self.roots.iter().any(|root| is_inside(&key, root)) {
+                    break Ok(())
+                }
+                self.dirs.insert(key, host_path.clone());
+                if !host_path.pop() {
+                    break Ok(())
+                }
+            },
+            None => fs::create_dir_all(path),
+        }
+    }
+
+    /// Lists the files and directories in a sandboxed directory whose names match a wildcard pattern, as
+    /// (name, is_dir). Returns None if the pattern isn't sandboxed.
+    pub fn find(&self, pattern: &str) -> Option<Vec<(PathBuf, bool)>> {
+        let (key, host_path) = self.sandboxed(pattern)?;
+        let dir_key = parent_key(&key);
+        let options = glob::MatchOptions { case_sensitive: false, ..Default::default() };
+        let name_pattern = glob::Pattern::new(key[dir_key.len()..].trim_start_matches('/')).ok()?;
+        let mut found = BTreeMap::new();
+        if let Some(entries) = host_path.parent().filter(|_| self.seeded).and_then(|dir| fs::read_dir(dir).ok()) {
+            for entry in entries.filter_map(Result::ok) {
+                let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+                found.insert(entry.file_name().to_string_lossy().to_lowercase(), (entry.file_name().into(), is_dir));
+            }
+        }
+        let in_dir = |k: &String| parent_key(k) == dir_key;
+        for (k, path) in self.dirs.iter().filter(|(k, _)| in_dir(k)) {
+            found.insert(k[dir_key.len() + 1..].to_string(), (path.file_name().unwrap_or_default().into(), true));
+        }
+        for (k, entry) in self.files.iter().filter(|(k, _)| in_dir(k)) {
+            let name = k[dir_key.len() + 1..].to_string();
+            match entry.data {
+                Some(_) => found.insert(name, (entry.path.file_name().unwrap_or_default().into(), false)),
+                None => found.remove(&name),
+            };
+        }
+        Some(found.into_iter().filter(|(name, _)| name_pattern.matches_with(name, options)).map(|(_, x)| x).collect())
+    }
+
+    pub fn save_state(&self) -> VfsState {
+        let files = self
+            .files
+            .iter()
+            .map(|(key, entry)| {
+                let data = entry.data.as_ref().map(|buffer| buffer.borrow().clone());
+                (key.clone(), Entry { path: entry.path.clone(), data })
+            })
+            .collect();
+        VfsState { files, dirs: self.dirs.clone() }
+    }
+
+    pub fn load_state(&mut self, state: VfsState) {
+        let mut old_files = std::mem::take(&mut self.files);
+        for (key, entry) in state.files {
+            // files which are still open keep pointing at the overlay if they were in it before
+            let data = match (old_files.remove(&key).and_then(|old| old.data), entry.data) {
+                (Some(buffer), Some(data)) => {
+                    *buffer.borrow_mut() = data;
+                    Some(buffer)
+                },
+                (_, data) => data.map(|data| Rc::new(RefCell::new(data))),
+            };
+            self.files.insert(key, LiveEntry { path: entry.path, data });
+        }
+        self.dirs = state.dirs;
+    }
+}
+
+impl OpenOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    pub fn open(&self, vfs: &mut Vfs, path: &str) -> io::Result<File> {
+        vfs.open(path, self)
+    }
+
+    fn to_std(&self) -> fs::OpenOptions {
+        let mut options = fs::OpenOptions::new();
+        options
+            .read(self.read)
+            .write(self.write)
+            .append(self.append)
+            .truncate(self.truncate)
+            .create(self.create)
+            .create_new(self.create_new);
+        options
+    }
+}
+
+impl File {
+    pub fn set_len(&mut self, size: u64) -> io::Result<()> {
+        match self {
+            Self::Host(f) => f.set_len(size),
+            Self::Virtual(f) => {
+                f.data.borrow_mut().resize(size as usize, 0);
+                Ok(())
+            },
+        }
+    }
+
+    /// Whether writes should be buffered. Virtual files are already in memory and have to stay up to date for
+    /// savestates, so they aren't.
+    pub fn buffer_capacity(&self) -> usize {
+        match self {
+            Self::Host(_) => 8192,
+            Self::Virtual(_) => 0,
+        }
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Host(f) => f.read(buf),
+            Self::Virtual(f) => {
+                let data = f.data.borrow();
+                let start = (f.pos as usize).min(data.len());
+                let count = buf.len().min(data.len() - start);
+                buf[..count].copy_from_slice(&data[start..start + count]);
+                f.pos += count as u64;
+                Ok(count)
+            },
+        }
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Host(f) => f.write(buf),
+            Self::Virtual(f) => {
+                let mut data = f.data.borrow_mut();
+                if f.append {
+                    f.pos = data.len() as u64;
+                }
+                let start = f.pos as usize;
+                if data.len() < start + buf.len() {
+                    data.resize(start + buf.len(), 0);
+                }
+                data[start..start + buf.len()].copy_from_slice(buf);
+                f.pos += buf.len() as u64;
+                Ok(buf.len())
+            },
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Host(f) => f.flush(),
+            Self::Virtual(_) => Ok(()),
+        }
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::Host(f) => f.seek(pos),
+            Self::Virtual(f) => {
+                let new_pos = match pos {
+                    SeekFrom::Start(offset) => Some(offset),
+                    SeekFrom::End(offset) => (f.data.borrow().len() as u64).checked_add_signed(offset),
+                    SeekFrom::Current(offset) => f.pos.checked_add_signed(offset),
+                };
+                f.pos = new_pos.ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "seek before start of file"))?;
+                Ok(f.pos)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlay() {
+        let root = std::env::temp_dir().join("gm8emulator_vfs_test");
+        let path = root.join("SAVE.dat").to_string_lossy().into_owned();
+        let other = root.join("save2.dat").to_string_lossy().into_owned();
+        let mut vfs = Vfs::default();
+        vfs.set_roots(&[&root], false);
+
+        assert!(!vfs.file_exists(&path));
+        let mut file = OpenOptions::new().write(true).create(true).open(&mut vfs, &path).unwrap();
+        file.write_all(b"hello").unwrap();
+        assert_eq!(vfs.read(&path.to_lowercase()).unwrap(), b"hello");
+        assert!(!Path::new(&path).exists());
+        let state = vfs.save_state();
+
+        file.write_all(b" world").unwrap();
+        assert_eq!(vfs.read(&path).unwrap(), b"hello world");
+        vfs.load_state(state);
+        assert_eq!(vfs.read(&path).unwrap(), b"hello");
+        file.seek(SeekFrom::End(0)).unwrap();
+        file.write_all(b"!").unwrap();
+        assert_eq!(vfs.read(&path).unwrap(), b"hello!");
+
+        vfs.rename(&path, &other).unwrap();
+        assert!(!vfs.file_exists(&path));
+        assert_eq!(vfs.find(&root.join("*.DAT").to_string_lossy()).unwrap(), vec![("save2.dat".into(), false)]);
+    }
+}
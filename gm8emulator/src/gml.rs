@@ -110,6 +110,13 @@ impl FunctionTryFromEnum for ValueFunctionPtr {
     fn addr(&self) -> *const () { *self as _ }
 }
 
+impl RoutineFunction {
+    /// Whether this is a Volatile function rather than a Constant one, since they compile to the same node.
+    pub fn is_volatile(&self) -> bool {
+        mappings::FUNCTIONS.values().any(|x| matches!(x, Function::Volatile(_)) && x.addr() == self.0.addr())
+    }
+}
+
 impl<T: FunctionTryFromEnum> Serialize for FunctionPtr<T> {
     fn serialize<S>(&self, s: S) -> std::result::Result<S::Ok, S::Error>
     where
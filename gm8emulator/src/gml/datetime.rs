@@ -44,6 +44,18 @@ fn i32_to_month(m: i32) -> Option<time::Month> {
     })
 }
 
+/// Compares two datetimes like Delphi's CompareDateTime, treating them as equal if they're within a millisecond.
+pub fn compare(a: Real, b: Real) -> i32 {
+    let (a, b) = (a.into_inner(), b.into_inner());
+    if (a - b).abs() < 1.0 / 86400000.0 {
+        0
+    } else if a < b {
+        -1
+    } else {
+        1
+    }
+}
+
 pub struct DateTime(PrimitiveDateTime);
 
 impl DateTime {
@@ -130,6 +142,55 @@ impl DateTime {
     pub fn weekday(&self) -> u32 {
         self.0.weekday().number_from_sunday().into()
     }
+
+    pub fn days_in_month(&self) -> u32 {
+        time::util::days_in_year_month(self.year(), self.0.month()).into()
+    }
+
+    pub fn days_in_year(&self) -> u32 {
+        time::util::days_in_year(self.year()).into()
+    }
+
+    pub fn is_leap_year(&self) -> bool {
+        time::util::is_leap_year(self.year())
+    }
+
+    /// Adds a number of months, keeping the time. Like Delphi's IncMonth, the day is clamped to the new month's length.
+    pub fn inc_month(&self, months: i32) -> Option<Self> {
+        let month_index = self.year().checked_mul(12)?.checked_add(self.month() as i32 - 1)?.checked_add(months)?;
+        let (year, month) = (month_index.div_euclid(12), i32_to_month(month_index.rem_euclid(12) + 1)?);
+        let day = self.0.day().min(time::util::days_in_year_month(year, month));
+        time::Date::from_calendar_date(year, month, day).ok().map(|d| Self(d.with_time(self.0.time())))
+    }
+
+    /// The absolute time between two datetimes in days, including any fraction of a day.
+    pub fn span(&self, other: &Self) -> f64 {
+        (self.0 - other.0).abs().whole_milliseconds() as f64 / 86400000.0
+    }
+
+    pub fn is_same_day(&self, other: &Self) -> bool {
+        self.0.date() == other.0.date()
+    }
+
+    /// Formats the date part. GM8 uses the system's short date format for this, but that would make the result
+    /// depend on where the game is run, so this is always year-month-day.
+    pub fn date_string(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year(), self.month(), self.day())
+    }
+
+    /// Formats the time part, always as 24-hour time.
+    pub fn time_string(&self) -> String {
+        format!("{:02}:{:02}:{:02}", self.hour(), self.minute(), self.second())
+    }
+
+    /// Formats both parts. Like Delphi's DateTimeToStr, the time is left out if it's exactly midnight.
+    pub fn datetime_string(&self) -> String {
+        if self.0.time() == time::Time::MIDNIGHT {
+            self.date_string()
+        } else {
+            format!("{} {}", self.date_string(), self.time_string())
+        }
+    }
 }
 
 impl From<DateTime> for Real {
@@ -156,3 +217,27 @@ impl From<Real> for DateTime {
         Self(epoch() + days + if dt > 0.into() { ms } else { -ms })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn months() {
+        let dt = DateTime::from_ymdhms(2020, 1, 31, 12, 30, 0).unwrap();
+        assert_eq!(dt.inc_month(1).unwrap().datetime_string(), "2020-02-29 12:30:00");
+        assert_eq!(dt.inc_month(-13).unwrap().date_string(), "2018-12-31");
+        assert_eq!(dt.inc_month(12 * 3 + 1).unwrap().date_string(), "2023-02-28");
+        assert_eq!(DateTime::from_ymd(2000, 1, 1).unwrap().datetime_string(), "2000-01-01");
+        assert_eq!(dt.days_in_month(), 31);
+        assert!(dt.is_leap_year());
+    }
+
+    #[test]
+    fn span() {
+        let a = DateTime::from_ymdhms(1899, 12, 29, 12, 0, 0).unwrap();
+        let b = DateTime::from_ymdhms(1899, 12, 31, 6, 0, 0).unwrap();
+        assert_eq!(a.span(&b), 1.75);
+        assert_eq!(DateTime::from(Real::from(a)).span(&DateTime::from(Real::from(b))), 1.75);
+    }
+}
@@ -1,7 +1,7 @@
-use image::{codecs::gif::GifDecoder, AnimationDecoder, ImageError, ImageFormat, Pixel, RgbaImage};
+use crate::game::vfs::{File, OpenOptions, Vfs};
+use image::{codecs::gif::GifDecoder, AnimationDecoder, ImageError, ImageFormat, ImageReader, Pixel, RgbaImage};
 use std::{
-    fs::{File, OpenOptions},
-    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    io::{self, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write},
     path::Path,
 };
 
@@ -97,7 +97,7 @@ pub fn to_path(path: &str) -> std::borrow::Cow<str> {
 }
 
 impl TextHandle {
-    pub fn open(path: &str, mode: AccessMode) -> io::Result<Self> {
+    pub fn open(vfs: &mut Vfs, path: &str, mode: AccessMode) -> io::Result<Self> {
         #[rustfmt::skip]
         let (read, write, append) = match mode {
             AccessMode::Read    => (true,  false, false),
@@ -111,11 +111,13 @@ impl TextHandle {
             .write(write)
             .append(append)
             .truncate(write && !append)
-            .open(path)?;
+            .open(vfs, path)?;
 
         Ok(match mode {
             AccessMode::Read => TextHandle::Read(BufReader::new(file)),
-            AccessMode::Write | AccessMode::Special => TextHandle::Write(BufWriter::new(file)),
+            AccessMode::Write | AccessMode::Special => {
+                TextHandle::Write(BufWriter::with_capacity(file.buffer_capacity(), file))
+            },
         })
     }
 
@@ -200,11 +202,11 @@ impl TextHandle {
 }
 
 impl BinaryHandle {
-    pub fn open(path: &str, mode: AccessMode) -> io::Result<Self> {
-        let file = Self::_open(path, mode)?;
+    pub fn open(vfs: &mut Vfs, path: &str, mode: AccessMode) -> io::Result<Self> {
+        let file = Self::_open(vfs, path, mode)?;
         match mode {
             AccessMode::Read => Ok(Self::Read(BufReader::new(file))),
-            AccessMode::Write => Ok(Self::Write(BufWriter::new(file))),
+            AccessMode::Write => Ok(Self::Write(BufWriter::with_capacity(file.buffer_capacity(), file))),
             AccessMode::Special => Ok(Self::ReadWrite(file)),
         }
     }
@@ -218,7 +220,7 @@ impl BinaryHandle {
     // same name between testing and opening, and also would require an additional
     // function call every time. Instead, when a read-only or write-only mode was
     // requested for a file, we try first to create it and fail if it's exists.
-    fn _open(path: &str, mode: AccessMode) -> io::Result<File> {
+    fn _open(vfs: &mut Vfs, path: &str, mode: AccessMode) -> io::Result<File> {
         let mut opts = OpenOptions::new();
 
         #[rustfmt::skip]
@@ -231,7 +233,7 @@ impl BinaryHandle {
         if !(read && write) {
             // We don't return on other errors (that is, not AlreadyExists) here
             // because the second call to .open() may give us a more exact one.
-            if let r @ Ok(_) = opts.create_new(true).read(true).write(true).open(path) {
+            if let r @ Ok(_) = opts.create_new(true).read(true).write(true).open(vfs, path) {
                 return r
             };
 
@@ -244,7 +246,7 @@ impl BinaryHandle {
         opts.create(write) // not .create(true), read the initial comment why!
             .read(read)
             .write(write)
-            .open(path)
+            .open(vfs, path)
     }
 
     fn get_reader(&mut self) -> Result<&mut dyn Read> {
@@ -380,50 +382,19 @@ pub fn skip_line(f: impl Read) -> io::Result<()> {
     Ok(())
 }
 
-pub fn file_exists(path: &str) -> bool {
-    Path::new(path).is_file()
+pub fn load_image(vfs: &Vfs, path: &str) -> Result<RgbaImage> {
+    let data = vfs.read(path)?;
+    Ok(ImageReader::with_format(Cursor::new(data), ImageFormat::from_path(path)?).decode()?.into_rgba8())
 }
 
-pub fn rename(from: &str, to: &str) -> Result<()> {
-    if !Path::new(to).exists() {
-        std::fs::rename(from, to)?;
-    }
-    Ok(())
-}
-
-pub fn copy(from: &str, to: &str) -> Result<()> {
-    std::fs::copy(from, to)?;
-    Ok(())
-}
-
-pub fn dir_exists(path: &str) -> bool {
-    Path::new(path).is_dir()
-}
-
-pub fn dir_create(path: &str) -> Result<()> {
-    std::fs::create_dir_all(path)?;
-    Ok(())
-}
-
-pub fn delete(path: &str) -> Result<()> {
-    if Path::new(path).exists() {
-        std::fs::remove_file(path)?;
-    }
-    Ok(())
-}
-
-pub fn load_image(path: &str) -> Result<RgbaImage> {
-    Ok(image::open(path)?.into_rgba8())
-}
-
-pub fn load_animation(path: &str, imgnumb: usize) -> Result<Vec<RgbaImage>> {
+pub fn load_animation(vfs: &Vfs, path: &str, imgnumb: usize) -> Result<Vec<RgbaImage>> {
     if ImageFormat::from_path(path)? == ImageFormat::Gif {
-        GifDecoder::new(BufReader::new(File::open(path)?))?
+        GifDecoder::new(Cursor::new(vfs.read(path)?))?
             .into_frames()
             .map(|r| r.map(|f| f.into_buffer()).map_err(Error::from))
             .collect()
     } else {
-        let image = load_image(path.as_ref())?;
+        let image = load_image(vfs, path)?;
         let sprite_width = image.width() as usize / imgnumb;
         let sprite_height = image.height() as usize;
         // get pixel data for each frame
@@ -445,12 +416,14 @@ pub fn load_animation(path: &str, imgnumb: usize) -> Result<Vec<RgbaImage>> {
     }
 }
 
-pub fn save_image<P: AsRef<Path>>(path: P, image: RgbaImage) -> Result<()> {
+pub fn save_image(vfs: &mut Vfs, path: &str, image: RgbaImage) -> Result<()> {
     // save to png if the filename is .png otherwise bmp regardless of filename
-    if path.as_ref().extension().and_then(|s| s.to_str()).map(|s| s.eq_ignore_ascii_case("png")).unwrap_or(false) {
-        image.save_with_format(path, ImageFormat::Png)?;
+    let mut data = Cursor::new(Vec::new());
+    if Path::new(path).extension().and_then(|s| s.to_str()).map(|s| s.eq_ignore_ascii_case("png")).unwrap_or(false) {
+        image.write_to(&mut data, ImageFormat::Png)?;
     } else {
-        image.save_with_format(path, ImageFormat::Bmp)?;
+        image.write_to(&mut data, ImageFormat::Bmp)?;
     }
+    vfs.write(path, data.into_inner())?;
     Ok(())
 }
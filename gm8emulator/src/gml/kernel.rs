@@ -5,8 +5,14 @@
 use crate::{
     action, asset,
     game::{
-        draw, external, gm_save::GMSave, model, particle, pathfinding, platform, replay, surface::Surface,
-        transition::UserTransition, view::View, Game, GameClock, GetAsset, PlayType, SceneChange, Version,
+        audio::{
+            effects::{self, EffectParams},
+            spatial::Sound3D,
+        },
+        dialog::Dialog, draw, external, gm_save::GMSave, highscore, model, particle, pathfinding, platform, registry,
+        replay,
+        surface::Surface,
+        transition::UserTransition, vfs::Vfs, view::View, Game, GameClock, GetAsset, PlayType, SceneChange, Version,
     },
     gml::{
         self,
@@ -16,7 +22,7 @@ use crate::{
         network, Context, Value,
     },
     handleman::HandleManager,
-    input::MouseButton,
+    input::{self, MouseButton},
     instance::{Field, Instance, InstanceState},
     math::Real,
     render::{BlendType, Fog, Light, Renderer, Scaling},
@@ -185,7 +191,9 @@ impl Game {
 
     pub fn window_set_visible(&mut self, args: &[Value]) -> gml::Result<Value> {
         let visible = expect_args!(args, [bool])?;
-        self.window.set_visible(visible);
+        if let Some(window) = &mut self.window {
+            window.set_visible(visible);
+        }
         Ok(Default::default())
     }
 
@@ -212,7 +220,9 @@ impl Game {
         if show_border != self.window_border {
             self.window_border = show_border;
             if self.play_type != PlayType::Record {
-                self.window.set_borderless(!show_border);
+                if let Some(window) = &mut self.window {
+                    window.set_borderless(!show_border);
+                }
             }
         }
         Ok(Default::default())
@@ -256,7 +266,9 @@ impl Game {
         if sizeable != self.window_sizeable {
             self.window_sizeable = sizeable;
             if self.play_type != PlayType::Record {
-                self.window.set_resizable(self.window_sizeable);
+                if let Some(window) = &mut self.window {
+                    window.set_resizable(self.window_sizeable);
+                }
             }
         }
         Ok(Default::default())
@@ -270,7 +282,9 @@ impl Game {
     pub fn window_set_caption(&mut self, args: &[Value]) -> gml::Result<Value> {
         let caption = expect_args!(args, [string])?;
         if self.play_type == PlayType::Record {
-            self.window.set_title(caption.as_ref());
+            if let Some(window) = &mut self.window {
+                window.set_title(caption.as_ref());
+            }
         }
         self.window_caption = caption.into_owned();
         Ok(Default::default())
@@ -313,7 +327,9 @@ impl Game {
             },
         };
         if self.play_type == PlayType::Normal {
-            self.window.set_cursor(cursor);
+            if let Some(window) = &mut self.window {
+                window.set_cursor(cursor);
+            }
         }
         self.window_cursor_gml = code;
         Ok(Default::default())
@@ -345,7 +361,9 @@ impl Game {
         let (width, height) = expect_args!(args, [int, int])?;
         if width > 0 && height > 0 {
             self.window_inner_size = (width as u32, height as u32);
-            self.window.set_size((width as _, height as _));
+            if let Some(window) = &mut self.window {
+                window.set_size((width as _, height as _));
+            }
         }
         Ok(Default::default())
     }
@@ -421,7 +439,9 @@ impl Game {
                 (region_w, region_h)
             };
             self.window_inner_size = (width, height);
-            self.window.set_size((width as _, height as _));
+            if let Some(window) = &mut self.window {
+                window.set_size((width as _, height as _));
+            }
         }
         Ok(Default::default())
     }
@@ -525,7 +545,7 @@ impl Game {
         let rgba = self.renderer.get_pixels(0, 0, width as _, height as _);
         let mut image = RgbaImage::from_vec(width, height, rgba.into()).unwrap();
         asset::sprite::process_image(&mut image, false, false, true);
-        match file::save_image(file::to_path(&fname).as_ref(), image) {
+        match file::save_image(&mut self.vfs, file::to_path(&fname).as_ref(), image) {
             Ok(()) => Ok(Default::default()),
             Err(e) => Err(gml::Error::FunctionError("screen_save".into(), e.to_string())),
         }
@@ -541,7 +561,7 @@ impl Game {
         let rgba = self.renderer.get_pixels(x, y, w, h);
         let mut image = RgbaImage::from_vec(w as _, h as _, rgba.into()).unwrap();
         asset::sprite::process_image(&mut image, false, false, true);
-        match file::save_image(file::to_path(&fname).as_ref(), image) {
+        match file::save_image(&mut self.vfs, file::to_path(&fname).as_ref(), image) {
             Ok(()) => Ok(Default::default()),
             Err(e) => Err(gml::Error::FunctionError("screen_save_part".into(), e.to_string())),
         }
@@ -680,7 +700,7 @@ impl Game {
 
     pub fn draw_clear(&mut self, args: &[Value]) -> gml::Result<Value> {
         let col = expect_args!(args, [int])?;
-        if self.gm_version == Version::GameMaker8_0 && !self.surface_fix {
+        if self.gm_version != Version::GameMaker8_1 && !self.surface_fix {
             self.renderer.clear_view_no_zbuf((col as u32).into(), 1.0);
         } else {
             self.renderer.clear_view((col as u32).into(), 1.0);
@@ -690,7 +710,7 @@ impl Game {
 
     pub fn draw_clear_alpha(&mut self, args: &[Value]) -> gml::Result<Value> {
         let (col, alpha) = expect_args!(args, [int, real])?;
-        if self.gm_version == Version::GameMaker8_0 && !self.surface_fix {
+        if self.gm_version != Version::GameMaker8_1 && !self.surface_fix {
             self.renderer.clear_view_no_zbuf((col as u32).into(), alpha.into());
         } else {
             self.renderer.clear_view((col as u32).into(), alpha.into());
@@ -2282,7 +2302,7 @@ impl Game {
         // reset viewport to top left of room because lol
         self.renderer.reset_target();
         self.surface_target = None;
-        if self.gm_version == Version::GameMaker8_0 {
+        if self.gm_version != Version::GameMaker8_1 {
             self.renderer.set_zbuf_trashed(!self.surface_fix);
         }
         if self.surface_fix && self.room.views_enabled {
@@ -2442,7 +2462,7 @@ impl Game {
             let mut image =
                 RgbaImage::from_vec(surf.width, surf.height, self.renderer.dump_sprite(surf.atlas_ref).into()).unwrap();
             asset::sprite::process_image(&mut image, false, false, true);
-            match file::save_image(file::to_path(&fname).as_ref(), image) {
+            match file::save_image(&mut self.vfs, file::to_path(&fname).as_ref(), image) {
                 Ok(()) => Ok(Default::default()),
                 Err(e) => Err(gml::Error::FunctionError("surface_save".into(), e.to_string())),
             }
@@ -2465,7 +2485,7 @@ impl Game {
                 RgbaImage::from_vec(w as _, h as _, self.renderer.dump_sprite_part(surf.atlas_ref, x, y, w, h).into())
                     .unwrap();
             asset::sprite::process_image(&mut image, false, false, true);
-            match file::save_image(file::to_path(&fname).as_ref(), image) {
+            match file::save_image(&mut self.vfs, file::to_path(&fname).as_ref(), image) {
                 Ok(()) => Ok(Default::default()),
                 Err(e) => Err(gml::Error::FunctionError("surface_save_part".into(), e.to_string())),
             }
@@ -3305,9 +3325,17 @@ impl Game {
         self.draw_text(&[x.into(), y.into(), format!("{}{}", caption, self.score).into()])
     }
 
-    pub fn action_highscore_show(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function action_highscore_show")
+    pub fn action_highscore_show(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (background, border, new_colour, other_colour, _font) = expect_args!(args, [int, bool, int, int, any])?;
+        let settings = highscore::HighscoreSettings {
+            background,
+            border,
+            new_colour,
+            other_colour,
+            ..self.highscore_settings.clone()
+        };
+        self.show_highscore(self.score.into(), &settings, "action_highscore_show")?;
+        Ok(Default::default())
     }
 
     pub fn action_set_life(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -3622,7 +3650,9 @@ impl Game {
         } else {
             Cursor::Blank
         };
-        self.window.set_cursor(cursor);
+        if let Some(window) = &mut self.window {
+            window.set_cursor(cursor);
+        }
         Ok(Default::default())
     }
 
@@ -4125,8 +4155,8 @@ impl Game {
     pub fn string_length(&self, args: &[Value]) -> gml::Result<Value> {
         let string = expect_args!(args, [bytes])?;
         match self.gm_version {
-            Version::GameMaker8_0 => Ok(Value::Real((string.as_ref().len() as f64).into())),
             Version::GameMaker8_1 => Ok(Value::Real((self.decode_str(string.as_ref()).chars().count() as f64).into())),
+            _ => Ok(Value::Real((string.as_ref().len() as f64).into())),
         }
     }
 
@@ -4144,7 +4174,14 @@ impl Game {
 
     pub fn string_pos(&self, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [bytes, bytes]).map(|(query, main_string)| match self.gm_version {
-            Version::GameMaker8_0 => {
+            Version::GameMaker8_1 => {
+                let query = self.decode_str(query.as_ref());
+                let main_string = self.decode_str(main_string.as_ref());
+                Value::Real(Real::from(
+                    main_string.as_ref().find(query.as_ref()).map(|p| p + 1).unwrap_or_default() as f64
+                ))
+            },
+            _ => {
                 let query = query.as_ref();
                 Value::Real(Real::from(
                     main_string
@@ -4155,13 +4192,6 @@ impl Game {
                         .unwrap_or_default() as f64,
                 ))
             },
-            Version::GameMaker8_1 => {
-                let query = self.decode_str(query.as_ref());
-                let main_string = self.decode_str(main_string.as_ref());
-                Value::Real(Real::from(
-                    main_string.as_ref().find(query.as_ref()).map(|p| p + 1).unwrap_or_default() as f64
-                ))
-            },
         })
     }
 
@@ -4171,23 +4201,21 @@ impl Game {
         let len = len.max(0) as usize;
         let s = s.as_ref();
         Ok(match self.gm_version {
-            Version::GameMaker8_0 => s.iter().skip(start).take(len).copied().collect::<Vec<_>>().into(),
             Version::GameMaker8_1 => self.decode_str(s).chars().skip(start).take(len).collect::<String>().into(),
+            _ => s.iter().skip(start).take(len).copied().collect::<Vec<_>>().into(),
         })
     }
 
     pub fn string_char_at(&self, args: &[Value]) -> gml::Result<Value> {
         let (string, pos) = expect_args!(args, [bytes, int])?;
         match self.gm_version {
-            Version::GameMaker8_0 => {
-                Ok(string.as_ref().get((pos as isize - 1).max(0) as usize).map_or("".into(), |ch| vec![*ch].into()))
-            },
             Version::GameMaker8_1 => Ok(Value::Str(
                 self.decode_str(string.as_ref())
                     .chars()
                     .nth((pos as isize - 1).max(0) as usize)
                     .map_or("".to_string().into(), |ch| ch.to_string().into()),
             )),
+            _ => Ok(string.as_ref().get((pos as isize - 1).max(0) as usize).map_or("".into(), |ch| vec![*ch].into())),
         }
     }
 
@@ -4199,9 +4227,6 @@ impl Game {
         };
         let s = s.as_ref();
         Ok(match self.gm_version {
-            Version::GameMaker8_0 => {
-                s.iter().take(start).chain(s.iter().skip(start + len)).copied().collect::<Vec<_>>().into()
-            },
             Version::GameMaker8_1 => self
                 .decode_str(s)
                 .chars()
@@ -4209,6 +4234,7 @@ impl Game {
                 .filter_map(|(i, x)| if (start..start + len).contains(&i) { None } else { Some(x) })
                 .collect::<String>()
                 .into(),
+            _ => s.iter().take(start).chain(s.iter().skip(start + len)).copied().collect::<Vec<_>>().into(),
         })
     }
 
@@ -4223,15 +4249,15 @@ impl Game {
 
     pub fn string_lower(&self, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [bytes]).map(|s| match self.gm_version {
-            Version::GameMaker8_0 => Value::Str(s.as_ref().to_ascii_lowercase().into()),
             Version::GameMaker8_1 => Value::Str(self.decode_str(s.as_ref()).to_lowercase().into()),
+            _ => Value::Str(s.as_ref().to_ascii_lowercase().into()),
         })
     }
 
     pub fn string_upper(&self, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [bytes]).map(|s| match self.gm_version {
-            Version::GameMaker8_0 => Value::Str(s.as_ref().to_ascii_uppercase().into()),
             Version::GameMaker8_1 => Value::Str(self.decode_str(s.as_ref()).to_uppercase().into()),
+            _ => Value::Str(s.as_ref().to_ascii_uppercase().into()),
         })
     }
 
@@ -4855,36 +4881,54 @@ impl Game {
         .into())
     }
 
-    pub fn mp_linear_path(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
-        let (path_id, xg, yg, step_size, checkall) = expect_args!(args, [int, real, real, real, bool])?;
-        // we use a closure that needs a &Game for the collision calls, so we can't have a &mut Path
-        // so this function needs to own the path while that closure's being used
-        if let Some(mut path) =
-            usize::try_from(path_id).ok().and_then(|id| self.assets.paths.get_mut(id)).and_then(Option::take)
-        {
-            let inst = self.room.instance_list.get(context.this);
-            let coll = || {
-                if checkall {
-                    self.check_collision_any(context.this).is_some()
-                } else {
-                    self.check_collision_solid(context.this).is_some()
-                }
-            };
-            pathfinding::make_path(inst, &mut path, |inst| {
+    /// Runs `f` on the given path, returning what it does.
+    /// The path generators use closures that need a &Game for the collision calls, so we can't have a &mut Path
+    /// at the same time. Instead the path is taken out of the asset list while `f` runs, and put back after.
+    fn with_path<T>(&mut self, path_id: i32, f: impl FnOnce(&Self, &mut asset::Path) -> T) -> gml::Result<T> {
+        match usize::try_from(path_id).ok().and_then(|id| self.assets.paths.get_mut(id)).and_then(Option::take) {
+            Some(mut path) => {
+                let result = f(self, &mut path);
+                self.assets.paths[path_id as usize] = Some(path);
+                Ok(result)
+            },
+            None => Err(gml::Error::NonexistentAsset(asset::Type::Path, path_id)),
+        }
+    }
+
+    /// Makes a path by taking linear steps towards the given destination until it's reached or the instance gets
+    /// stuck, returning whether it was reached.
+    fn linear_path(
+        &mut self,
+        context: &Context,
+        path_id: i32,
+        (xg, yg): (Real, Real),
+        step_size: Real,
+        coll: impl Fn(&Self) -> bool,
+    ) -> gml::Result<Value> {
+        self.with_path(path_id, |game, path| {
+            pathfinding::make_path(game.room.instance_list.get(context.this), path, |inst| {
                 let (old_x, old_y) = (inst.x.get(), inst.y.get());
-                if pathfinding::linear_step(xg, yg, step_size, inst, coll) {
+                if pathfinding::linear_step(xg, yg, step_size, inst, || coll(game)) {
                     pathfinding::PathGenResult::Done
                 } else if inst.x.get() == old_x && inst.y.get() == old_y {
                     pathfinding::PathGenResult::Failed
                 } else {
                     pathfinding::PathGenResult::NotDone
                 }
-            });
-            self.assets.paths[path_id as usize] = Some(path);
-            Ok(Default::default())
-        } else {
-            Err(gml::Error::NonexistentAsset(asset::Type::Path, path_id))
-        }
+            })
+            .into()
+        })
+    }
+
+    pub fn mp_linear_path(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, xg, yg, step_size, checkall) = expect_args!(args, [int, real, real, real, bool])?;
+        self.linear_path(context, path_id, (xg, yg), step_size, |game| {
+            if checkall {
+                game.check_collision_any(context.this).is_some()
+            } else {
+                game.check_collision_solid(context.this).is_some()
+            }
+        })
     }
 
     pub fn mp_linear_step_object(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -4897,9 +4941,13 @@ impl Game {
         .into())
     }
 
-    pub fn mp_linear_path_object(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function mp_linear_path_object")
+    pub fn mp_linear_path_object(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, xg, yg, step_size, obj) = expect_args!(args, [int, real, real, real, int])?;
+        self.linear_path(context, path_id, (xg, yg), step_size, |game| match obj {
+            gml::SELF => false,
+            gml::OTHER => game.check_collision(context.this, context.other),
+            obj => game.find_instance_with(obj, |handle| game.check_collision(context.this, handle)).is_some(),
+        })
     }
 
     pub fn mp_potential_settings(&mut self, args: &[Value]) -> gml::Result<Value> {
@@ -4932,9 +4980,27 @@ impl Game {
         .into())
     }
 
-    pub fn mp_potential_path(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function mp_potential_path")
+    pub fn mp_potential_path(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, xg, yg, step_size, factor, checkall) = expect_args!(args, [int, real, real, real, real, bool])?;
+        self.with_path(path_id, |game, path| {
+            pathfinding::potential_path(
+                xg,
+                yg,
+                step_size,
+                factor,
+                &game.potential_step_settings,
+                game.room.instance_list.get(context.this),
+                path,
+                || {
+                    if checkall {
+                        game.check_collision_any(context.this).is_some()
+                    } else {
+                        game.check_collision_solid(context.this).is_some()
+                    }
+                },
+            )
+            .into()
+        })
     }
 
     pub fn mp_potential_step_object(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
@@ -4954,9 +5020,25 @@ impl Game {
         .into())
     }
 
-    pub fn mp_potential_path_object(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function mp_potential_path_object")
+    pub fn mp_potential_path_object(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (path_id, xg, yg, step_size, factor, obj) = expect_args!(args, [int, real, real, real, real, int])?;
+        self.with_path(path_id, |game, path| {
+            pathfinding::potential_path(
+                xg,
+                yg,
+                step_size,
+                factor,
+                &game.potential_step_settings,
+                game.room.instance_list.get(context.this),
+                path,
+                || match obj {
+                    gml::SELF => false,
+                    gml::OTHER => game.check_collision(context.this, context.other),
+                    obj => game.find_instance_with(obj, |handle| game.check_collision(context.this, handle)).is_some(),
+                },
+            )
+            .into()
+        })
     }
 
     pub fn mp_grid_create(&mut self, args: &[Value]) -> gml::Result<Value> {
@@ -5089,14 +5171,103 @@ impl Game {
         }
     }
 
-    pub fn mp_grid_add_instances(&mut self, _context: &mut Context, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function mp_grid_add_instances")
+    pub fn mp_grid_add_instances(&mut self, context: &mut Context, args: &[Value]) -> gml::Result<Value> {
+        let (id, obj, precise) = expect_args!(args, [int, int, bool])?;
+        let mpgrid = match self.mpgrids.get(id) {
+            Some(mpgrid) => mpgrid,
+            None => {
+                return Err(gml::Error::FunctionError(
+                    "mp_grid_add_instances".into(),
+                    pathfinding::Error::NonexistentStructure(id).into(),
+                ))
+            },
+        };
+        let handles = match obj {
+            gml::SELF => vec![context.this],
+            gml::OTHER => vec![context.other],
+            obj => {
+                let handles = std::cell::RefCell::new(Vec::new());
+                self.find_instance_with(obj, |handle| {
+                    handles.borrow_mut().push(handle);
+                    false
+                });
+                handles.into_inner()
+            },
+        };
+        if mpgrid.cellwidth <= 0 || mpgrid.cellheight <= 0 {
+            return Ok(Default::default())
+        }
+        // the cells from `low` to `high` along one axis, limited to the grid
+        let cell_range = |low: i32, high: i32, start: i32, size: i32, count: usize| {
+            let first = (low - start).div_euclid(size).max(0) as usize;
+            let last = (high - start).div_euclid(size);
+            if last < 0 { 0..0 } else { first..(last as usize + 1).min(count) }
+        };
+        let mut cells = Vec::new();
+        for handle in handles {
+            // only the cells under the bounding box can touch the instance, so there's no need to check the rest
+            let inst = self.room.instance_list.get(handle);
+            inst.update_bbox(self.get_instance_mask_sprite(handle));
+            let xs =
+                cell_range(inst.bbox_left.get(), inst.bbox_right.get(), mpgrid.left, mpgrid.cellwidth, mpgrid.hcells);
+            let ys =
+                cell_range(inst.bbox_top.get(), inst.bbox_bottom.get(), mpgrid.top, mpgrid.cellheight, mpgrid.vcells);
+            for x in xs {
+                for y in ys.clone() {
+                    let x1 = mpgrid.left + x as i32 * mpgrid.cellwidth;
+                    let y1 = mpgrid.top + y as i32 * mpgrid.cellheight;
+                    let (x2, y2) = (x1 + mpgrid.cellwidth - 1, y1 + mpgrid.cellheight - 1);
+                    if self.check_collision_rectangle(handle, x1, y1, x2, y2, precise) {
+                        cells.push((x, y));
+                    }
+                }
+            }
+        }
+        let mpgrid = self.mpgrids.get_mut(id).unwrap();
+        for (x, y) in cells {
+            mpgrid.set(x, y, -1);
+        }
+        Ok(Default::default())
     }
 
-    pub fn mp_grid_path(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 7
-        unimplemented!("Called unimplemented kernel function mp_grid_path")
+    pub fn mp_grid_path(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (id, path_id, xstart, ystart, xgoal, ygoal, allow_diag) =
+            expect_args!(args, [int, int, real, real, real, real, bool])?;
+        let mpgrid = match self.mpgrids.get(id) {
+            Some(mpgrid) => mpgrid,
+            None => {
+                return Err(gml::Error::FunctionError(
+                    "mp_grid_path".into(),
+                    pathfinding::Error::NonexistentStructure(id).into(),
+                ))
+            },
+        };
+        let path = match usize::try_from(path_id).ok().and_then(|id| self.assets.paths.get_mut(id)) {
+            Some(Some(path)) => path,
+            _ => return Err(gml::Error::NonexistentAsset(asset::Type::Path, path_id)),
+        };
+        let cells = match (mpgrid.cell_at(xstart, ystart), mpgrid.cell_at(xgoal, ygoal)) {
+            (Some(start), Some(goal)) => mpgrid.find_path(start, goal, allow_diag),
+            _ => None,
+        };
+        // the path is left alone if there's no way to reach the goal
+        Ok(match cells {
+            Some(cells) => {
+                path.curve = false;
+                path.closed = false;
+                path.points.clear();
+                path.points.push(asset::path::Point { x: xstart, y: ystart, speed: 100.into() });
+                // the first and last cells are replaced by the exact start and goal positions
+                for &(x, y) in cells.iter().skip(1).take(cells.len().saturating_sub(2)) {
+                    let (x, y) = mpgrid.cell_centre(x, y);
+                    path.points.push(asset::path::Point { x, y, speed: 100.into() });
+                }
+                path.points.push(asset::path::Point { x: xgoal, y: ygoal, speed: 100.into() });
+                path.update();
+                true.into()
+            },
+            None => false.into(),
+        })
     }
 
     pub fn mp_grid_draw(&mut self, args: &[Value]) -> gml::Result<Value> {
@@ -5771,15 +5942,13 @@ impl Game {
     pub fn game_save(&mut self, args: &[Value]) -> gml::Result<Value> {
         let fname = expect_args!(args, [string])?;
         let save = GMSave::from_game(self);
-        let mut file = std::fs::File::create(file::to_path(&fname).as_ref())
-            .map(std::io::BufWriter::new)
-            .map_err(|e| gml::Error::FunctionError("game_save".into(), format!("{}", e)))?;
         // write magic number (0x21c in GM8)
-        file.write(&[0x1d, 0x02, 0x00, 0x00])
-            .map_err(|e| gml::Error::FunctionError("game_save".into(), format!("{}", e)))?;
+        let mut file = vec![0x1d, 0x02, 0x00, 0x00];
         bincode::serialize_into(&mut file, &save)
             .map_err(|e| gml::Error::FunctionError("game_save".into(), format!("{}", e)))?;
-        file.flush().map_err(|e| gml::Error::FunctionError("game_save".into(), e.to_string()))?;
+        self.vfs
+            .write(file::to_path(&fname).as_ref(), file)
+            .map_err(|e| gml::Error::FunctionError("game_save".into(), e.to_string()))?;
         Ok(Default::default())
     }
 
@@ -5894,7 +6063,9 @@ impl Game {
             1 => file::AccessMode::Write,
             2 | _ => file::AccessMode::Special,
         };
-        match self.binary_files.add_from(|| Ok(file::BinaryHandle::open(file::to_path(&filename).as_ref(), mode)?)) {
+        let vfs = &mut self.vfs;
+        match self.binary_files.add_from(|| Ok(file::BinaryHandle::open(vfs, file::to_path(&filename).as_ref(), mode)?))
+        {
             Ok(i) => Ok((i + 1).into()),
             Err(e) => Err(gml::Error::FunctionError("file_bin_open".into(), e.to_string())),
         }
@@ -5972,7 +6143,7 @@ impl Game {
         let filename = expect_args!(args, [string])?;
         use std::error::Error as _; // for .source() trait method
 
-        match self.text_files.add_from(|| Ok(file::TextHandle::open(file::to_path(&filename).as_ref(), file::AccessMode::Read)?)) {
+        match self.text_files.add_from(|| Ok(file::TextHandle::open(&mut self.vfs, file::to_path(&filename).as_ref(), file::AccessMode::Read)?)) {
             Ok(i) => Ok((i + 1).into()),
             Err(e)
                 if e.source()
@@ -5987,7 +6158,7 @@ impl Game {
 
     pub fn file_text_open_write(&mut self, args: &[Value]) -> gml::Result<Value> {
         let filename = expect_args!(args, [string])?;
-        match self.text_files.add_from(|| Ok(file::TextHandle::open(file::to_path(&filename).as_ref(), file::AccessMode::Write)?)) {
+        match self.text_files.add_from(|| Ok(file::TextHandle::open(&mut self.vfs, file::to_path(&filename).as_ref(), file::AccessMode::Write)?)) {
             Ok(i) => Ok((i + 1).into()),
             Err(e) => Err(gml::Error::FunctionError("file_text_open_write".into(), e.to_string())),
         }
@@ -5995,7 +6166,7 @@ impl Game {
 
     pub fn file_text_open_append(&mut self, args: &[Value]) -> gml::Result<Value> {
         let filename = expect_args!(args, [string])?;
-        match self.text_files.add_from(|| Ok(file::TextHandle::open(file::to_path(&filename).as_ref(), file::AccessMode::Special)?)) {
+        match self.text_files.add_from(|| Ok(file::TextHandle::open(&mut self.vfs, file::to_path(&filename).as_ref(), file::AccessMode::Special)?)) {
             Ok(i) => Ok((i + 1).into()),
             Err(e) => Err(gml::Error::FunctionError("file_text_open_append".into(), e.to_string())),
         }
@@ -6094,7 +6265,7 @@ impl Game {
 
     pub fn file_open_read(&mut self, args: &[Value]) -> gml::Result<Value> {
         let filename = expect_args!(args, [string])?;
-        match file::TextHandle::open(file::to_path(&filename).as_ref(), file::AccessMode::Read) {
+        match file::TextHandle::open(&mut self.vfs, file::to_path(&filename).as_ref(), file::AccessMode::Read) {
             Ok(f) => {
                 self.open_file.replace(f);
             },
@@ -6110,7 +6281,7 @@ impl Game {
 
     pub fn file_open_write(&mut self, args: &[Value]) -> gml::Result<Value> {
         let filename = expect_args!(args, [string])?;
-        match file::TextHandle::open(file::to_path(&filename).as_ref(), file::AccessMode::Write) {
+        match file::TextHandle::open(&mut self.vfs, file::to_path(&filename).as_ref(), file::AccessMode::Write) {
             Ok(f) => {
                 self.open_file.replace(f);
                 Ok(Default::default())
@@ -6124,7 +6295,7 @@ impl Game {
 
     pub fn file_open_append(&mut self, args: &[Value]) -> gml::Result<Value> {
         let filename = expect_args!(args, [string])?;
-        match file::TextHandle::open(file::to_path(&filename).as_ref(), file::AccessMode::Special) {
+        match file::TextHandle::open(&mut self.vfs, file::to_path(&filename).as_ref(), file::AccessMode::Special) {
             Ok(f) => {
                 self.open_file.replace(f);
                 Ok(Default::default())
@@ -6213,31 +6384,31 @@ impl Game {
 
     pub fn file_exists(&self, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [any]).map(|x| match x {
-            Value::Str(s) => file::file_exists(file::to_path(&self.decode_str(s.as_ref())).as_ref()).into(),
+            Value::Str(s) => self.vfs.file_exists(file::to_path(&self.decode_str(s.as_ref())).as_ref()).into(),
             Value::Real(_) => gml::FALSE.into(),
         })
     }
 
-    pub fn file_delete(&self, args: &[Value]) -> gml::Result<Value> {
+    pub fn file_delete(&mut self, args: &[Value]) -> gml::Result<Value> {
         let filename = expect_args!(args, [string])?;
-        match file::delete(file::to_path(&filename).as_ref()) {
+        match self.vfs.delete(file::to_path(&filename).as_ref()) {
             Ok(()) => Ok(Default::default()),
             Err(e) => Err(gml::Error::FunctionError("file_delete".into(), e.to_string())),
         }
     }
 
-    pub fn file_rename(&self, args: &[Value]) -> gml::Result<Value> {
+    pub fn file_rename(&mut self, args: &[Value]) -> gml::Result<Value> {
         let (from, to) = expect_args!(args, [string, string])?;
-        if file::rename(file::to_path(&from).as_ref(), file::to_path(&to).as_ref()).is_err() {
+        if self.vfs.rename(file::to_path(&from).as_ref(), file::to_path(&to).as_ref()).is_err() {
             // Fail silently
             eprintln!("Warning (file_rename): could not rename {} to {}", from, to);
         }
         Ok(Default::default())
     }
 
-    pub fn file_copy(&self, args: &[Value]) -> gml::Result<Value> {
+    pub fn file_copy(&mut self, args: &[Value]) -> gml::Result<Value> {
         let (from, to) = expect_args!(args, [string, string])?;
-        if file::copy(file::to_path(&from).as_ref(), file::to_path(&to).as_ref()).is_err() {
+        if self.vfs.copy(file::to_path(&from).as_ref(), file::to_path(&to).as_ref()).is_err() {
             // Fail silently
             eprintln!("Warning (file_copy): could not copy {} to {}", from, to);
         }
@@ -6246,14 +6417,14 @@ impl Game {
 
     pub fn directory_exists(&self, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [any]).map(|x| match x {
-            Value::Str(s) => file::dir_exists(file::to_path(&self.decode_str(s.as_ref())).as_ref()).into(),
+            Value::Str(s) => self.vfs.dir_exists(file::to_path(&self.decode_str(s.as_ref())).as_ref()).into(),
             Value::Real(_) => gml::FALSE.into(),
         })
     }
 
-    pub fn directory_create(&self, args: &[Value]) -> gml::Result<Value> {
+    pub fn directory_create(&mut self, args: &[Value]) -> gml::Result<Value> {
         let path = expect_args!(args, [string])?;
-        match file::dir_create(file::to_path(&path).as_ref()) {
+        match self.vfs.create_dir(file::to_path(&path).as_ref()) {
             Ok(()) => Ok(Default::default()),
             Err(e) => Err(gml::Error::FunctionError("directory_create".into(), e.to_string())),
         }
@@ -6274,6 +6445,12 @@ impl Game {
         let include_volume_id = (attribs & 8) != 0;
         let include_directory = (attribs & 16) != 0;
         let include_archive = (attribs & 32) != 0;
+        if let Some(entries) = self.vfs.find(&file::to_path(path)) {
+            self.file_finder = Some(Box::new(
+                entries.into_iter().filter(move |(_, is_dir)| include_directory || !is_dir).map(|(name, _)| name),
+            ));
+            return self.file_find_next(&[])
+        }
         match glob::glob_with(path, glob::MatchOptions { case_sensitive: false, ..Default::default() }) {
             Ok(paths) => {
                 // add . and .. to start if necessary
@@ -6389,7 +6566,7 @@ impl Game {
         let program_directory = self.decode_str(self.program_directory.as_ref()).into_owned().into();
         if let Some(file) = self.included_files.iter_mut().filter(|i| name.eq_ignore_ascii_case(i.name.as_ref())).next()
         {
-            match file.export(temp_directory, program_directory) {
+            match file.export(&mut self.vfs, temp_directory, program_directory) {
                 Ok(()) => Ok(Default::default()),
                 Err(e) => Err(gml::Error::FunctionError("export_include_file".into(), e.to_string())),
             }
@@ -6403,7 +6580,7 @@ impl Game {
         if let Some(file) = self.included_files.iter_mut().filter(|i| name.eq_ignore_ascii_case(i.name.as_ref())).next()
         {
             let path_ref: &str = path.as_ref();
-            match file.export_to(path_ref.as_ref()) {
+            match file.export_to(&mut self.vfs, path_ref.as_ref()) {
                 Ok(()) => Ok(Default::default()),
                 Err(e) => Err(gml::Error::FunctionError("export_include_file_location".into(), e.to_string())),
             }
@@ -6516,66 +6693,96 @@ impl Game {
         Ok(env.as_ref().into())
     }
 
-    pub fn registry_write_string(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function registry_write_string")
+    pub fn registry_write_string(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (name, value) = expect_args!(args, [bytes, bytes])?;
+        let key = registry::game_key(self.game_id);
+        self.registry.write(registry::Root::CurrentUser, key.as_bytes(), name.as_ref(), value.into());
+        Ok(Default::default())
     }
 
-    pub fn registry_write_real(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function registry_write_real")
+    pub fn registry_write_real(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (name, value) = expect_args!(args, [bytes, real])?;
+        let key = registry::game_key(self.game_id);
+        self.registry.write(registry::Root::CurrentUser, key.as_bytes(), name.as_ref(), value.into());
+        Ok(Default::default())
     }
 
-    pub fn registry_read_string(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function registry_read_string")
+    pub fn registry_read_string(&self, args: &[Value]) -> gml::Result<Value> {
+        let name = expect_args!(args, [bytes])?;
+        let key = registry::game_key(self.game_id);
+        match self.registry.read(registry::Root::CurrentUser, key.as_bytes(), name.as_ref()) {
+            Some(value @ Value::Str(_)) => Ok(value.clone()),
+            _ => Ok("".into()),
+        }
     }
 
-    pub fn registry_read_real(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function registry_read_real")
+    pub fn registry_read_real(&self, args: &[Value]) -> gml::Result<Value> {
+        let name = expect_args!(args, [bytes])?;
+        let key = registry::game_key(self.game_id);
+        match self.registry.read(registry::Root::CurrentUser, key.as_bytes(), name.as_ref()) {
+            Some(value @ Value::Real(_)) => Ok(value.clone()),
+            _ => Ok(Default::default()),
+        }
     }
 
-    pub fn registry_exists(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function registry_exists")
+    pub fn registry_exists(&self, args: &[Value]) -> gml::Result<Value> {
+        let name = expect_args!(args, [bytes])?;
+        let key = registry::game_key(self.game_id);
+        Ok(self.registry.read(registry::Root::CurrentUser, key.as_bytes(), name.as_ref()).is_some().into())
     }
 
-    pub fn registry_write_string_ext(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function registry_write_string_ext")
+    pub fn registry_write_string_ext(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (key, name, value) = expect_args!(args, [bytes, bytes, bytes])?;
+        self.registry.write(self.registry.root(), key.as_ref(), name.as_ref(), value.into());
+        Ok(Default::default())
     }
 
-    pub fn registry_write_real_ext(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function registry_write_real_ext")
+    pub fn registry_write_real_ext(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (key, name, value) = expect_args!(args, [bytes, bytes, real])?;
+        self.registry.write(self.registry.root(), key.as_ref(), name.as_ref(), value.into());
+        Ok(Default::default())
     }
 
-    pub fn registry_read_string_ext(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function registry_read_string_ext")
+    pub fn registry_read_string_ext(&self, args: &[Value]) -> gml::Result<Value> {
+        let (key, name) = expect_args!(args, [bytes, bytes])?;
+        match self.registry.read(self.registry.root(), key.as_ref(), name.as_ref()) {
+            Some(value @ Value::Str(_)) => Ok(value.clone()),
+            _ => Ok("".into()),
+        }
     }
 
-    pub fn registry_read_real_ext(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function registry_read_real_ext")
+    pub fn registry_read_real_ext(&self, args: &[Value]) -> gml::Result<Value> {
+        let (key, name) = expect_args!(args, [bytes, bytes])?;
+        match self.registry.read(self.registry.root(), key.as_ref(), name.as_ref()) {
+            Some(value @ Value::Real(_)) => Ok(value.clone()),
+            _ => Ok(Default::default()),
+        }
     }
 
-    pub fn registry_exists_ext(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function registry_exists_ext")
+    pub fn registry_exists_ext(&self, args: &[Value]) -> gml::Result<Value> {
+        let (key, name) = expect_args!(args, [bytes, bytes])?;
+        Ok(self.registry.read(self.registry.root(), key.as_ref(), name.as_ref()).is_some().into())
     }
 
-    pub fn registry_set_root(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function registry_set_root")
+    pub fn registry_set_root(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let root = expect_args!(args, [int])?;
+        match registry::Root::from_gml(root) {
+            Some(root) => {
+                self.registry.set_root(root);
+                Ok(Default::default())
+            },
+            None => Err(gml::Error::FunctionError("registry_set_root".into(), format!("invalid root {}", root))),
+        }
     }
 
     pub fn ini_open(&mut self, args: &[Value]) -> gml::Result<Value> {
         let name = expect_args!(args, [bytes])?;
         let name_str = self.decode_str(name.as_ref());
-        if file::file_exists(&file::to_path(&name_str)) {
-            match ini::Ini::load_from_file(name_str.as_ref()) {
+        let path = file::to_path(&name_str);
+        if self.vfs.file_exists(&path) {
+            let ini =
+                self.vfs.read(&path).map_err(ini::Error::Io).and_then(|data| ini::Ini::read_from(&mut data.as_slice()));
+            match ini {
                 Ok(ini) => {
                     self.open_ini = Some((ini, name));
                     Ok(Default::default())
@@ -6591,12 +6798,16 @@ impl Game {
     pub fn ini_close(&mut self, args: &[Value]) -> gml::Result<Value> {
         expect_args!(args, [])?;
         match self.open_ini.as_ref() {
-            Some((ini, path)) => match ini.write_to_file(self.decode_str(path.as_ref()).as_ref()) {
-                Ok(()) => {
-                    self.open_ini = None;
-                    Ok(Default::default())
-                },
-                Err(e) => Err(gml::Error::FunctionError("ini_close".into(), format!("{}", e))),
+            Some((ini, path)) => {
+                let path = file::to_path(&self.decode_str(path.as_ref())).into_owned();
+                let mut data = Vec::new();
+                match ini.write_to(&mut data).and_then(|()| self.vfs.write(&path, data)) {
+                    Ok(()) => {
+                        self.open_ini = None;
+                        Ok(Default::default())
+                    },
+                    Err(e) => Err(gml::Error::FunctionError("ini_close".into(), format!("{}", e))),
+                }
             },
             None => Ok(Default::default()),
         }
@@ -6852,14 +7063,15 @@ impl Game {
         unimplemented!("Called unimplemented kernel function show_text")
     }
 
-    pub fn show_message(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function show_message")
+    pub fn show_message(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let text = expect_args!(args, [bytes])?;
+        self.run_dialog(Dialog::message(text), "show_message")?;
+        Ok(Default::default())
     }
 
-    pub fn show_question(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function show_question")
+    pub fn show_question(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let text = expect_args!(args, [bytes])?;
+        self.run_dialog(Dialog::question(text), "show_question")
     }
 
     pub fn show_error(&mut self, args: &[Value]) -> gml::Result<Value> {
@@ -6877,178 +7089,220 @@ impl Game {
         unimplemented!("Called unimplemented kernel function load_info")
     }
 
-    pub fn highscore_show(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function highscore_show")
+    pub fn highscore_show(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let score = expect_args!(args, [real])?;
+        let settings = self.highscore_settings.clone();
+        self.show_highscore(score, &settings, "highscore_show")?;
+        Ok(Default::default())
     }
 
-    pub fn highscore_set_background(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function highscore_set_background")
+    pub fn highscore_set_background(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let background = expect_args!(args, [int])?;
+        self.highscore_settings.background = background;
+        Ok(Default::default())
     }
 
-    pub fn highscore_set_border(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function highscore_set_border")
+    pub fn highscore_set_border(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let border = expect_args!(args, [bool])?;
+        self.highscore_settings.border = border;
+        Ok(Default::default())
     }
 
-    pub fn highscore_set_font(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function highscore_set_font")
+    pub fn highscore_set_font(&mut self, args: &[Value]) -> gml::Result<Value> {
+        // the highscore screen always uses the built-in font
+        expect_args!(args, [any, any, any])?;
+        Ok(Default::default())
     }
 
-    pub fn highscore_set_strings(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function highscore_set_strings")
+    pub fn highscore_set_strings(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (caption, nobody, escape) = expect_args!(args, [bytes, bytes, bytes])?;
+        self.highscore_settings.caption = caption;
+        self.highscore_settings.nobody = nobody;
+        self.highscore_settings.escape = escape;
+        Ok(Default::default())
     }
 
-    pub fn highscore_set_colors(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function highscore_set_colors")
+    pub fn highscore_set_colors(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (back_colour, new_colour, other_colour) = expect_args!(args, [int, int, int])?;
+        self.highscore_settings.back_colour = back_colour;
+        self.highscore_settings.new_colour = new_colour;
+        self.highscore_settings.other_colour = other_colour;
+        Ok(Default::default())
     }
 
-    pub fn highscore_show_ext(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 7
-        unimplemented!("Called unimplemented kernel function highscore_show_ext")
+    pub fn highscore_show_ext(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (score, background, border, new_colour, other_colour, _font, _size) =
+            expect_args!(args, [real, int, bool, int, int, any, any])?;
+        let settings = highscore::HighscoreSettings {
+            background,
+            border,
+            new_colour,
+            other_colour,
+            ..self.highscore_settings.clone()
+        };
+        self.show_highscore(score, &settings, "highscore_show_ext")?;
+        Ok(Default::default())
     }
 
-    pub fn highscore_clear(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function highscore_clear")
+    pub fn highscore_clear(&mut self, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        let table = vec![(self.highscore_settings.nobody.clone(), Real::from(0.0)); highscore::TABLE_SIZE];
+        self.set_highscore_table(&table);
+        Ok(Default::default())
     }
 
-    pub fn highscore_add(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function highscore_add")
+    pub fn highscore_add(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (name, score) = expect_args!(args, [bytes, real])?;
+        let mut table = self.highscore_table();
+        if let Some(place) = highscore::place_for(&table, score) {
+            highscore::insert(&mut table, place, name, score);
+            self.set_highscore_table(&table);
+        }
+        Ok(Default::default())
     }
 
-    pub fn highscore_add_current(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 0
-        unimplemented!("Called unimplemented kernel function highscore_add_current")
+    pub fn highscore_add_current(&mut self, args: &[Value]) -> gml::Result<Value> {
+        expect_args!(args, [])?;
+        let settings = self.highscore_settings.clone();
+        self.show_highscore(self.score.into(), &settings, "highscore_add_current")?;
+        Ok(Default::default())
     }
 
-    pub fn highscore_value(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function highscore_value")
+    pub fn highscore_value(&self, args: &[Value]) -> gml::Result<Value> {
+        let place = expect_args!(args, [int])?;
+        if (1..=highscore::TABLE_SIZE as i32).contains(&place) {
+            let (_, score) = self.highscore_table().swap_remove(place as usize - 1);
+            Ok(score.into())
+        } else {
+            Ok((-1).into())
+        }
     }
 
-    pub fn highscore_name(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function highscore_name")
+    pub fn highscore_name(&self, args: &[Value]) -> gml::Result<Value> {
+        let place = expect_args!(args, [int])?;
+        if (1..=highscore::TABLE_SIZE as i32).contains(&place) {
+            let (name, _) = self.highscore_table().swap_remove(place as usize - 1);
+            Ok(name.into())
+        } else {
+            Ok("".into())
+        }
     }
 
-    pub fn draw_highscore(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function draw_highscore")
+    pub fn draw_highscore(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (x1, y1, x2, y2) = expect_args!(args, [real, real, real, real])?;
+        let halign = self.draw_halign;
+        let row_height = (y2 - y1) / Real::from(highscore::TABLE_SIZE as f64);
+        for (i, (name, score)) in self.highscore_table().into_iter().enumerate() {
+            let y = y1 + row_height * Real::from(i as f64);
+            let score = score.round().to_string().into();
+            self.draw_halign = draw::Halign::Left;
+            self.draw_string(x1, y, name, None, None, 1.into(), 1.into(), 0.into(), None, self.draw_alpha.into());
+            self.draw_halign = draw::Halign::Right;
+            self.draw_string(x2, y, score, None, None, 1.into(), 1.into(), 0.into(), None, self.draw_alpha.into());
+        }
+        self.draw_halign = halign;
+        Ok(Default::default())
     }
 
-    pub fn show_message_ext(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function show_message_ext")
+    pub fn show_message_ext(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (text, button1, button2, button3) = expect_args!(args, [bytes, bytes, bytes, bytes])?;
+        self.run_dialog(Dialog::message_ext(text, [button1, button2, button3]), "show_message_ext")
     }
 
-    pub fn message_background(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function message_background")
-        // TODO
+    pub fn message_background(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let sprite = expect_args!(args, [int])?;
+        self.message_settings.background = sprite;
         Ok(Default::default())
     }
 
-    pub fn message_button(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function message_button")
-        // TODO
+    pub fn message_button(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let sprite = expect_args!(args, [int])?;
+        self.message_settings.button = sprite;
         Ok(Default::default())
     }
 
-    pub fn message_alpha(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function message_alpha")
-        // TODO
+    pub fn message_alpha(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let alpha = expect_args!(args, [real])?;
+        self.message_settings.alpha = alpha;
         Ok(Default::default())
     }
 
-    pub fn message_text_font(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        //unimplemented!("Called unimplemented kernel function message_text_font")
-        // TODO
+    pub fn message_text_font(&mut self, args: &[Value]) -> gml::Result<Value> {
+        // only the built-in font is available, so just the colour is used
+        let (_name, _size, colour, _style) = expect_args!(args, [any, any, int, any])?;
+        self.message_settings.text_colour = colour;
         Ok(Default::default())
     }
 
-    pub fn message_button_font(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        //unimplemented!("Called unimplemented kernel function message_button_font")
-        // TODO
+    pub fn message_button_font(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (_name, _size, colour, _style) = expect_args!(args, [any, any, int, any])?;
+        self.message_settings.button_colour = colour;
         Ok(Default::default())
     }
 
-    pub fn message_input_font(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        //unimplemented!("Called unimplemented kernel function message_input_font")
-        // TODO
+    pub fn message_input_font(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (_name, _size, colour, _style) = expect_args!(args, [any, any, int, any])?;
+        self.message_settings.input_text_colour = colour;
         Ok(Default::default())
     }
 
-    pub fn message_text_charset(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        //unimplemented!("Called unimplemented kernel function message_text_charset")
-        // TODO
+    pub fn message_text_charset(&mut self, args: &[Value]) -> gml::Result<Value> {
+        // the built-in font covers the game's encoding, so there's nothing to change
+        expect_args!(args, [any, any])?;
         Ok(Default::default())
     }
 
-    pub fn message_mouse_color(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function message_mouse_color")
-        // TODO
+    pub fn message_mouse_color(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let colour = expect_args!(args, [int])?;
+        self.message_settings.mouse_colour = colour;
         Ok(Default::default())
     }
 
-    pub fn message_input_color(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function message_input_color")
-        // TODO
+    pub fn message_input_color(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let colour = expect_args!(args, [int])?;
+        self.message_settings.input_colour = colour;
         Ok(Default::default())
     }
 
-    pub fn message_position(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        //unimplemented!("Called unimplemented kernel function message_position")
-        // TODO
+    pub fn message_position(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (x, y) = expect_args!(args, [int, int])?;
+        self.message_settings.position = (x, y);
         Ok(Default::default())
     }
 
-    pub fn message_size(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        //unimplemented!("Called unimplemented kernel function message_size")
-        // TODO
+    pub fn message_size(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (width, height) = expect_args!(args, [int, int])?;
+        self.message_settings.size = (width, height);
         Ok(Default::default())
     }
 
-    pub fn message_caption(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        //unimplemented!("Called unimplemented kernel function message_caption")
-        // TODO
+    pub fn message_caption(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (show, caption) = expect_args!(args, [bool, bytes])?;
+        self.message_settings.show_caption = show;
+        self.message_settings.caption = caption;
         Ok(Default::default())
     }
 
-    pub fn show_menu(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function show_menu")
+    pub fn show_menu(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (items, default) = expect_args!(args, [bytes, int])?;
+        let position = (self.input.mouse_x(), self.input.mouse_y());
+        self.run_dialog(Dialog::menu(items.as_ref(), default, position), "show_menu")
     }
 
-    pub fn show_menu_pos(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function show_menu_pos")
+    pub fn show_menu_pos(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (x, y, items, default) = expect_args!(args, [int, int, bytes, int])?;
+        self.run_dialog(Dialog::menu(items.as_ref(), default, (x, y)), "show_menu_pos")
     }
 
-    pub fn get_integer(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function get_integer")
+    pub fn get_integer(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (text, default) = expect_args!(args, [bytes, real])?;
+        self.run_dialog(Dialog::get_integer(text, default), "get_integer")
     }
 
-    pub fn get_string(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function get_string")
+    pub fn get_string(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (text, default) = expect_args!(args, [bytes, bytes])?;
+        let input = self.decode_str(default.as_ref()).into_owned();
+        self.run_dialog(Dialog::get_string(text, default, input), "get_string")
     }
 
     pub fn get_color(&mut self, _args: &[Value]) -> gml::Result<Value> {
@@ -7195,102 +7449,74 @@ impl Game {
         Ok(self.input.mouse_wheel_down().into())
     }
 
-    pub fn joystick_exists(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_exists")
-        // TODO
-        Ok(gml::FALSE.into())
+    pub fn joystick_exists(&self, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input.joystick(id).is_some().into())
     }
 
-    pub fn joystick_direction(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_direction")
-        // TODO
-        Ok(101.into())
+    pub fn joystick_direction(&self, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input.joystick(id).map_or(101, |j| i32::from(j.direction())).into())
     }
 
-    pub fn joystick_name(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_name")
-        // TODO
-        Ok("".into())
+    pub fn joystick_name(&self, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(if self.input.joystick(id).is_some() { "Virtual Joystick" } else { "" }.into())
     }
 
-    pub fn joystick_axes(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_axes")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_axes(&self, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input.joystick(id).map_or(0, |_| input::JOYSTICK_AXES.len() as i32).into())
     }
 
-    pub fn joystick_buttons(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_buttons")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_buttons(&self, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input.joystick(id).map_or(0, |_| input::JOYSTICK_BUTTONS as i32).into())
     }
 
-    pub fn joystick_has_pov(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_has_pov")
-        // TODO
-        Ok(gml::FALSE.into())
+    pub fn joystick_has_pov(&self, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input.joystick(id).is_some().into())
     }
 
-    pub fn joystick_check_button(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        //unimplemented!("Called unimplemented kernel function joystick_check_button")
-        // TODO
-        Ok(gml::FALSE.into())
+    pub fn joystick_check_button(&self, args: &[Value]) -> gml::Result<Value> {
+        let (id, button) = expect_args!(args, [int, int])?;
+        Ok(self.input.joystick(id).map_or(false, |j| j.button(button)).into())
     }
 
-    pub fn joystick_xpos(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_xpos")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_xpos(&self, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input.joystick(id).map_or(Real::ZERO, |j| j.axes[0]).into())
     }
 
-    pub fn joystick_ypos(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_ypos")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_ypos(&self, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input.joystick(id).map_or(Real::ZERO, |j| j.axes[1]).into())
     }
 
-    pub fn joystick_zpos(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_zpos")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_zpos(&self, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input.joystick(id).map_or(Real::ZERO, |j| j.axes[2]).into())
     }
 
-    pub fn joystick_rpos(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_rpos")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_rpos(&self, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input.joystick(id).map_or(Real::ZERO, |j| j.axes[3]).into())
     }
 
-    pub fn joystick_upos(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_upos")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_upos(&self, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input.joystick(id).map_or(Real::ZERO, |j| j.axes[4]).into())
     }
 
-    pub fn joystick_vpos(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_vpos")
-        // TODO
-        Ok(0.into())
+    pub fn joystick_vpos(&self, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input.joystick(id).map_or(Real::ZERO, |j| j.axes[5]).into())
     }
 
-    pub fn joystick_pov(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        //unimplemented!("Called unimplemented kernel function joystick_pov")
-        // TODO
-        Ok((-1).into())
+    pub fn joystick_pov(&self, args: &[Value]) -> gml::Result<Value> {
+        let id = expect_args!(args, [int])?;
+        Ok(self.input.joystick(id).map_or(-1, |j| j.pov).into())
     }
 
     pub fn keyboard_clear(&mut self, args: &[Value]) -> gml::Result<Value> {
@@ -7533,8 +7759,8 @@ impl Game {
             (args.get(0), args.get(1), args.get(2), args.get(3), args.get(4))
         {
             let encoding = match self.gm_version {
-                Version::GameMaker8_0 => self.encoding,
                 Version::GameMaker8_1 => encoding_rs::UTF_8,
+                _ => self.encoding,
             };
             let gm_dll = gml::String::from(dll_name.clone());
             let dll = gm_dll.decode(encoding);
@@ -7590,8 +7816,8 @@ impl Game {
     pub fn external_free(&mut self, args: &[Value]) -> gml::Result<Value> {
         let dll_name = expect_args!(args, [bytes])?;
         let encoding = match self.gm_version {
-            Version::GameMaker8_0 => self.encoding,
             Version::GameMaker8_1 => encoding_rs::UTF_8,
+            _ => self.encoding,
         };
         let dll = gml::String::from(dll_name);
         self.externals.free(&*dll.decode(encoding));
@@ -7723,7 +7949,7 @@ impl Game {
             for (src, dest) in args.iter().zip(new_args.iter_mut()) {
                 *dest = src.clone();
             }
-            match std::fs::read(self.decode_str(path.as_ref()).as_ref()) {
+            match self.vfs.read(self.decode_str(path.as_ref()).as_ref()) {
                 Ok(code) => {
                     new_args[0] = code.into();
                     self.execute_string(context, &new_args)
@@ -7740,11 +7966,11 @@ impl Game {
         Ok({
             #[cfg(target_os = "windows")]
             {
-                self.window.hwnd() as u64 as f64
+                self.window.as_ref().map_or(0.0, |window| window.hwnd() as u64 as f64)
             }
             #[cfg(unix)]
             {
-                self.window.xid()
+                self.window.as_ref().map_or(Default::default(), |window| window.xid())
             }
         }.into())
     }
@@ -7968,14 +8194,14 @@ impl Game {
         Ok((((0..24).contains(&h) && (0..60).contains(&m) && (0..60).contains(&s)) || (h, m, s) == (24, 0, 0)).into())
     }
 
-    pub fn date_inc_year(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function date_inc_year")
+    pub fn date_inc_year(args: &[Value]) -> gml::Result<Value> {
+        let (datetime, amount) = expect_args!(args, [real, int])?;
+        Ok(DateTime::from(datetime).inc_month(amount.saturating_mul(12)).map(Real::from).unwrap_or(datetime).into())
     }
 
-    pub fn date_inc_month(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function date_inc_month")
+    pub fn date_inc_month(args: &[Value]) -> gml::Result<Value> {
+        let (datetime, amount) = expect_args!(args, [real, int])?;
+        Ok(DateTime::from(datetime).inc_month(amount).map(Real::from).unwrap_or(datetime).into())
     }
 
     pub fn date_inc_week(args: &[Value]) -> gml::Result<Value> {
@@ -8067,99 +8293,100 @@ impl Game {
         Ok(DateTime::from(datetime).second_of_year().into())
     }
 
-    pub fn date_year_span(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function date_year_span")
+    pub fn date_year_span(args: &[Value]) -> gml::Result<Value> {
+        let (datetime1, datetime2) = expect_args!(args, [real, real])?;
+        // Delphi's average year and month lengths are used, so partial spans come out as fractions
+        Ok((DateTime::from(datetime1).span(&DateTime::from(datetime2)) / 365.25).into())
     }
 
-    pub fn date_month_span(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function date_month_span")
+    pub fn date_month_span(args: &[Value]) -> gml::Result<Value> {
+        let (datetime1, datetime2) = expect_args!(args, [real, real])?;
+        Ok((DateTime::from(datetime1).span(&DateTime::from(datetime2)) / 30.4375).into())
     }
 
-    pub fn date_week_span(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function date_week_span")
+    pub fn date_week_span(args: &[Value]) -> gml::Result<Value> {
+        let (datetime1, datetime2) = expect_args!(args, [real, real])?;
+        Ok((DateTime::from(datetime1).span(&DateTime::from(datetime2)) / 7.0).into())
     }
 
-    pub fn date_day_span(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function date_day_span")
+    pub fn date_day_span(args: &[Value]) -> gml::Result<Value> {
+        let (datetime1, datetime2) = expect_args!(args, [real, real])?;
+        Ok(DateTime::from(datetime1).span(&DateTime::from(datetime2)).into())
     }
 
-    pub fn date_hour_span(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function date_hour_span")
+    pub fn date_hour_span(args: &[Value]) -> gml::Result<Value> {
+        let (datetime1, datetime2) = expect_args!(args, [real, real])?;
+        Ok((DateTime::from(datetime1).span(&DateTime::from(datetime2)) * 24.0).into())
     }
 
-    pub fn date_minute_span(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function date_minute_span")
+    pub fn date_minute_span(args: &[Value]) -> gml::Result<Value> {
+        let (datetime1, datetime2) = expect_args!(args, [real, real])?;
+        Ok((DateTime::from(datetime1).span(&DateTime::from(datetime2)) * 1440.0).into())
     }
 
-    pub fn date_second_span(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function date_second_span")
+    pub fn date_second_span(args: &[Value]) -> gml::Result<Value> {
+        let (datetime1, datetime2) = expect_args!(args, [real, real])?;
+        Ok((DateTime::from(datetime1).span(&DateTime::from(datetime2)) * 86400.0).into())
     }
 
-    pub fn date_compare_datetime(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function date_compare_datetime")
+    pub fn date_compare_datetime(args: &[Value]) -> gml::Result<Value> {
+        let (datetime1, datetime2) = expect_args!(args, [real, real])?;
+        Ok(datetime::compare(datetime1, datetime2).into())
     }
 
-    pub fn date_compare_date(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function date_compare_date")
+    pub fn date_compare_date(args: &[Value]) -> gml::Result<Value> {
+        let (datetime1, datetime2) = expect_args!(args, [real, real])?;
+        Ok(datetime::compare(datetime1.trunc(), datetime2.trunc()).into())
     }
 
-    pub fn date_compare_time(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function date_compare_time")
+    pub fn date_compare_time(args: &[Value]) -> gml::Result<Value> {
+        let (datetime1, datetime2) = expect_args!(args, [real, real])?;
+        Ok(datetime::compare(datetime1.fract(), datetime2.fract()).into())
     }
 
-    pub fn date_date_of(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function date_date_of")
+    pub fn date_date_of(args: &[Value]) -> gml::Result<Value> {
+        let datetime = expect_args!(args, [real])?;
+        Ok(datetime.trunc().into())
     }
 
-    pub fn date_time_of(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function date_time_of")
+    pub fn date_time_of(args: &[Value]) -> gml::Result<Value> {
+        let datetime = expect_args!(args, [real])?;
+        Ok(datetime.fract().into())
     }
 
-    pub fn date_datetime_string(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function date_datetime_string")
+    pub fn date_datetime_string(args: &[Value]) -> gml::Result<Value> {
+        let datetime = expect_args!(args, [real])?;
+        Ok(DateTime::from(datetime).datetime_string().into())
     }
 
-    pub fn date_date_string(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function date_date_string")
+    pub fn date_date_string(args: &[Value]) -> gml::Result<Value> {
+        let datetime = expect_args!(args, [real])?;
+        Ok(DateTime::from(datetime).date_string().into())
     }
 
-    pub fn date_time_string(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function date_time_string")
+    pub fn date_time_string(args: &[Value]) -> gml::Result<Value> {
+        let datetime = expect_args!(args, [real])?;
+        Ok(DateTime::from(datetime).time_string().into())
     }
 
-    pub fn date_days_in_month(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function date_days_in_month")
+    pub fn date_days_in_month(args: &[Value]) -> gml::Result<Value> {
+        let datetime = expect_args!(args, [real])?;
+        Ok(DateTime::from(datetime).days_in_month().into())
     }
 
-    pub fn date_days_in_year(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function date_days_in_year")
+    pub fn date_days_in_year(args: &[Value]) -> gml::Result<Value> {
+        let datetime = expect_args!(args, [real])?;
+        Ok(DateTime::from(datetime).days_in_year().into())
     }
 
-    pub fn date_leap_year(_args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function date_leap_year")
+    pub fn date_leap_year(args: &[Value]) -> gml::Result<Value> {
+        let datetime = expect_args!(args, [real])?;
+        Ok(DateTime::from(datetime).is_leap_year().into())
     }
 
-    pub fn date_is_today(&self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 1
-        unimplemented!("Called unimplemented kernel function date_is_today")
+    pub fn date_is_today(&self, args: &[Value]) -> gml::Result<Value> {
+        let datetime = expect_args!(args, [real])?;
+        Ok(DateTime::from(datetime).is_same_day(&self.clock.measure()).into())
     }
 
     pub fn sprite_exists(&self, args: &[Value]) -> gml::Result<Value> {
@@ -8269,23 +8496,23 @@ impl Game {
                     // TODO: delete sprite when this is safe for sprite fonts
                     // self.renderer.delete_sprite(dst_frame.atlas_ref);
                     match self.gm_version {
-                        Version::GameMaker8_0 => {
+                        Version::GameMaker8_1 => {
                             for (dst_row, src_row) in dst_data
                                 .chunks_mut(dst_frame.width as usize * 4)
                                 .zip(src_data.chunks(src_frame.width as usize * 4))
                             {
                                 for (dst_col, src_col) in dst_row.chunks_mut(4).zip(src_row.chunks(4)) {
-                                    dst_col[3] = ((src_col[..3].iter().map(|&x| u32::from(x)).sum::<u32>() * u32::from(dst_col[3])) / (3*255)) as u8;
+                                    dst_col[3] = (src_col[..3].iter().map(|&x| u16::from(x)).sum::<u16>() / 3u16) as u8;
                                 }
                             }
                         },
-                        Version::GameMaker8_1 => {
+                        _ => {
                             for (dst_row, src_row) in dst_data
                                 .chunks_mut(dst_frame.width as usize * 4)
                                 .zip(src_data.chunks(src_frame.width as usize * 4))
                             {
                                 for (dst_col, src_col) in dst_row.chunks_mut(4).zip(src_row.chunks(4)) {
-                                    dst_col[3] = (src_col[..3].iter().map(|&x| u16::from(x)).sum::<u16>() / 3u16) as u8;
+                                    dst_col[3] = ((src_col[..3].iter().map(|&x| u32::from(x)).sum::<u32>() * u32::from(dst_col[3])) / (3*255)) as u8;
                                 }
                             }
                         },
@@ -8310,8 +8537,8 @@ impl Game {
         let (x, y, width, height, transparency, smooth, origin_x, origin_y) =
             expect_args!(args, [int, int, int, int, int, bool, int, int])?;
         let (removeback, fill_transparent) = match self.gm_version {
-            Version::GameMaker8_0 => (transparency != 0, true),
             Version::GameMaker8_1 => (transparency == 1, transparency != 2),
+            _ => (transparency != 0, true),
         };
         // i know we're downloading the thing and reuploading it instead of doing it all in one go
         // but we need the pixel data to make the colliders
@@ -8417,8 +8644,8 @@ impl Game {
             let width = width.min(surf.width as i32 - x);
             let height = height.min(surf.height as i32 - y);
             let (removeback, fill_transparent) = match self.gm_version {
-                Version::GameMaker8_0 => (transparency != 0, true),
                 Version::GameMaker8_1 => (transparency == 1, transparency != 2),
+                _ => (transparency != 0, true),
             };
             let rgba = self.renderer.dump_sprite_part(surf.atlas_ref, x, y, width, height);
             let mut image = RgbaImage::from_vec(width as _, height as _, rgba.into_vec()).unwrap();
@@ -8519,10 +8746,18 @@ impl Game {
     }
 
     pub fn sprite_add(&mut self, args: &[Value]) -> gml::Result<Value> {
-        let (fname, imgnumb, removeback, smooth, origin_x, origin_y) =
-            expect_args!(args, [string, int, bool, bool, int, int])?;
+        // before 8.0, this also took whether the sprite is precise and whether to preload it
+        let (fname, imgnumb, precise, removeback, smooth, origin_x, origin_y) = if self.gm_version.is_pre_8_0() {
+            let (fname, imgnumb, precise, removeback, smooth, _preload, origin_x, origin_y) =
+                expect_args!(args, [string, int, bool, bool, bool, bool, int, int])?;
+            (fname, imgnumb, precise, removeback, smooth, origin_x, origin_y)
+        } else {
+            let (fname, imgnumb, removeback, smooth, origin_x, origin_y) =
+                expect_args!(args, [string, int, bool, bool, int, int])?;
+            (fname, imgnumb, true, removeback, smooth, origin_x, origin_y)
+        };
         let imgnumb = imgnumb.max(1) as usize;
-        let mut images = match file::load_animation(file::to_path(&fname).as_ref(), imgnumb) {
+        let mut images = match file::load_animation(&self.vfs, file::to_path(&fname).as_ref(), imgnumb) {
             Ok(frames) => frames,
             Err(e) => {
                 eprintln!("Warning: sprite_add on {} failed: {}", fname, e);
@@ -8534,7 +8769,11 @@ impl Game {
         }
         let (width, height) = images[0].dimensions();
         // make colliders
-        let colliders = asset::sprite::make_colliders_precise(&images, 0, false);
+        let colliders = if precise {
+            asset::sprite::make_colliders_precise(&images, 0, false)
+        } else {
+            asset::sprite::make_colliders_shaped(&images, 0, false, None, Some(asset::sprite::ColliderShape::Rectangle))
+        };
         // collect atlas refs
         // yes i know it's a new texture for every frame like in gm8 but it's fine
         let frames = images
@@ -8569,14 +8808,23 @@ impl Game {
     }
 
     pub fn sprite_replace(&mut self, args: &[Value]) -> gml::Result<Value> {
-        let (sprite_id, fname, imgnumb, removeback, smooth, origin_x, origin_y) =
-            expect_args!(args, [int, string, int, bool, bool, int, int])?;
+        // before 8.0, this also took whether the sprite is precise and whether to preload it
+        let (sprite_id, fname, imgnumb, precise, removeback, smooth, origin_x, origin_y) =
+            if self.gm_version.is_pre_8_0() {
+                let (sprite_id, fname, imgnumb, precise, removeback, smooth, _preload, origin_x, origin_y) =
+                    expect_args!(args, [int, string, int, bool, bool, bool, bool, int, int])?;
+                (sprite_id, fname, imgnumb, precise, removeback, smooth, origin_x, origin_y)
+            } else {
+                let (sprite_id, fname, imgnumb, removeback, smooth, origin_x, origin_y) =
+                    expect_args!(args, [int, string, int, bool, bool, int, int])?;
+                (sprite_id, fname, imgnumb, true, removeback, smooth, origin_x, origin_y)
+            };
         if let Some(sprite) = self.assets.sprites.get_asset_mut(sprite_id) {
             for frame in &sprite.frames {
                 self.renderer.delete_sprite(frame.atlas_ref);
             }
             let imgnumb = imgnumb.max(1) as usize;
-            let mut images = match file::load_animation(file::to_path(&fname).as_ref(), imgnumb) {
+            let mut images = match file::load_animation(&self.vfs, file::to_path(&fname).as_ref(), imgnumb) {
                 Ok(frames) => frames,
                 Err(e) => {
                     eprintln!("Warning: sprite_replace on {} failed: {}", fname, e);
@@ -8588,7 +8836,12 @@ impl Game {
             }
             let (width, height) = images[0].dimensions();
             // make colliders
-            let colliders = asset::sprite::make_colliders_precise(&images, 0, false);
+            let colliders = if precise {
+                asset::sprite::make_colliders_precise(&images, 0, false)
+            } else {
+                let shape = Some(asset::sprite::ColliderShape::Rectangle);
+                asset::sprite::make_colliders_shaped(&images, 0, false, None, shape)
+            };
             // collect atlas refs
             let renderer = &mut self.renderer;
             let frames = images
@@ -8709,6 +8962,7 @@ impl Game {
             if let Some(frame) = sprite.get_frame(image_index) {
                 // get RGBA
                 if let Err(e) = file::save_image(
+                    &mut self.vfs,
                     file::to_path(&fname).as_ref(),
                     RgbaImage::from_vec(frame.width, frame.height, self.renderer.dump_sprite(frame.atlas_ref).into())
                         .unwrap(),
@@ -8841,17 +9095,17 @@ impl Game {
             let mut dst = self.renderer.dump_sprite(*atlas_ref);
             self.renderer.delete_sprite(*atlas_ref);
             match self.gm_version {
-                Version::GameMaker8_0 => {
+                Version::GameMaker8_1 => {
                     for (dst_row, src_row) in dst.chunks_mut(dst_w as usize * 4).zip(alpha_src.chunks(src_w as usize * 4)) {
                         for (dst_col, src_col) in dst_row.chunks_mut(4).zip(src_row.chunks(4)) {
-                            dst_col[3] = ((src_col[..3].iter().map(|&x| u32::from(x)).sum::<u32>() * u32::from(dst_col[3])) / (3*255)) as u8;
+                            dst_col[3] = (src_col[..3].iter().map(|&x| u16::from(x)).sum::<u16>() / 3u16) as u8;
                         }
                     }
                 },
-                Version::GameMaker8_1 => {
+                _ => {
                     for (dst_row, src_row) in dst.chunks_mut(dst_w as usize * 4).zip(alpha_src.chunks(src_w as usize * 4)) {
                         for (dst_col, src_col) in dst_row.chunks_mut(4).zip(src_row.chunks(4)) {
-                            dst_col[3] = (src_col[..3].iter().map(|&x| u16::from(x)).sum::<u16>() / 3u16) as u8;
+                            dst_col[3] = ((src_col[..3].iter().map(|&x| u32::from(x)).sum::<u32>() * u32::from(dst_col[3])) / (3*255)) as u8;
                         }
                     }
                 },
@@ -8944,8 +9198,14 @@ impl Game {
     }
 
     pub fn background_add(&mut self, args: &[Value]) -> gml::Result<Value> {
-        let (fname, removeback, smooth) = expect_args!(args, [string, bool, bool])?;
-        let mut image = match file::load_image(file::to_path(&fname).as_ref()) {
+        // before 8.0, this also took whether to preload the background
+        let (fname, removeback, smooth) = if self.gm_version.is_pre_8_0() {
+            let (fname, removeback, smooth, _preload) = expect_args!(args, [string, bool, bool, bool])?;
+            (fname, removeback, smooth)
+        } else {
+            expect_args!(args, [string, bool, bool])?
+        };
+        let mut image = match file::load_image(&self.vfs, file::to_path(&fname).as_ref()) {
             Ok(im) => im,
             Err(e) => {
                 eprintln!("Warning: background_add on {} failed: {}", fname, e);
@@ -8970,12 +9230,19 @@ impl Game {
     }
 
     pub fn background_replace(&mut self, args: &[Value]) -> gml::Result<Value> {
-        let (background_id, fname, removeback, smooth) = expect_args!(args, [int, string, bool, bool])?;
+        // before 8.0, this also took whether to preload the background
+        let (background_id, fname, removeback, smooth) = if self.gm_version.is_pre_8_0() {
+            let (background_id, fname, removeback, smooth, _preload) =
+                expect_args!(args, [int, string, bool, bool, bool])?;
+            (background_id, fname, removeback, smooth)
+        } else {
+            expect_args!(args, [int, string, bool, bool])?
+        };
         if let Some(background) = self.assets.backgrounds.get_asset_mut(background_id) {
             if let Some(atlas_ref) = background.atlas_ref {
                 self.renderer.delete_sprite(atlas_ref);
             }
-            let mut image = match file::load_image(file::to_path(&fname).as_ref()) {
+            let mut image = match file::load_image(&self.vfs, file::to_path(&fname).as_ref()) {
                 Ok(im) => im,
                 Err(e) => {
                     eprintln!("Warning: background_replace on {} failed: {}", fname, e);
@@ -9091,6 +9358,7 @@ impl Game {
             if let Some(atlas_ref) = background.atlas_ref {
                 // get RGBA
                 if let Err(e) = file::save_image(
+                    &mut self.vfs,
                     file::to_path(&fname).as_ref(),
                     RgbaImage::from_vec(
                         background.width,
@@ -9140,7 +9408,7 @@ impl Game {
     pub fn sound_add(&mut self, args: &[Value]) -> gml::Result<Value> {
         let (fname, kind, preload) = expect_args!(args, [string, int, bool])?;
         let path_buf = std::path::PathBuf::from(fname.as_ref());
-        let data = match std::fs::read(&path_buf) {
+        let data = match self.vfs.read(fname.as_ref()) {
             Ok(b) => b.into_boxed_slice(),
             Err(_) => return Ok((-1).into()),
         };
@@ -9174,7 +9442,7 @@ impl Game {
 
             if matches!(sound.handle, asset::sound::FileType::None) {
                 let path_buf = std::path::PathBuf::from(fname.as_ref());
-                let data = match std::fs::read(&path_buf) {
+                let data = match self.vfs.read(fname.as_ref()) {
                     Ok(b) => b.into_boxed_slice(),
                     Err(_) => return Ok(0.into()),
                 };
@@ -12497,7 +12765,10 @@ impl Game {
             // Deliberately written in a way that will produce an error when Kind::Midi is added
             use asset::sound::FileType;
             match &sound.handle {
-                FileType::Wav(handle) => handle.set_volume(volume.into()),
+                FileType::Wav(handle) => {
+                    self.audio.stop_fade(sound_id);
+                    handle.set_volume(volume.into());
+                },
                 FileType::Mp3(_) => (),
                 FileType::None => (),
             }
@@ -12507,14 +12778,44 @@ impl Game {
         }
     }
 
-    pub fn sound_fade(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function sound_fade")
+    pub fn sound_fade(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, volume, time) = expect_args!(args, [int, real, real])?;
+        if let Some(sound) = self.assets.sounds.get_asset(sound_id) {
+            use asset::sound::FileType;
+            match &sound.handle {
+                FileType::Wav(handle) => {
+                    // time is in milliseconds, and the fade is advanced by the game clock every frame
+                    let duration = (time.into_inner().max(0.0) * 1_000_000.0) as u128;
+                    if duration == 0 {
+                        self.audio.stop_fade(sound_id);
+                        handle.set_volume(volume.into());
+                    } else {
+                        let nanos = self.clock.as_nanos();
+                        self.audio.fade(sound_id, handle.volume(), volume.into(), nanos, duration);
+                    }
+                },
+                FileType::Mp3(_) => (),
+                FileType::None => (),
+            }
+            Ok(Default::default())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Sound, sound_id))
+        }
     }
 
-    pub fn sound_pan(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function sound_pan")
+    pub fn sound_pan(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, pan) = expect_args!(args, [int, real])?;
+        if let Some(sound) = self.assets.sounds.get_asset(sound_id) {
+            use asset::sound::FileType;
+            match &sound.handle {
+                FileType::Wav(handle) => handle.set_pan(pan.into()),
+                FileType::Mp3(_) => (),
+                FileType::None => (),
+            }
+            Ok(Default::default())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Sound, sound_id))
+        }
     }
 
     pub fn sound_background_tempo(&mut self, _args: &[Value]) -> gml::Result<Value> {
@@ -12534,64 +12835,154 @@ impl Game {
         unimplemented!("Called unimplemented kernel function sound_set_search_directory")
     }
 
-    pub fn sound_effect_set(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 2
-        unimplemented!("Called unimplemented kernel function sound_effect_set")
+    pub fn sound_effect_set(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, flags) = expect_args!(args, [int, int])?;
+        self.update_sound_effects(sound_id, |fx| fx.flags = flags as u32)
     }
 
-    pub fn sound_effect_chorus(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 8
-        unimplemented!("Called unimplemented kernel function sound_effect_chorus")
+    pub fn sound_effect_chorus(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, wetdry, depth, feedback, frequency, wave, delay, phase) =
+            expect_args!(args, [int, real, real, real, real, int, real, int])?;
+        self.update_sound_effects(sound_id, |fx| {
+            fx.chorus = effects::Modulation {
+                wetdry: wetdry.into_inner() as f32,
+                depth: depth.into_inner() as f32,
+                feedback: feedback.into_inner() as f32,
+                frequency: frequency.into_inner() as f32,
+                wave,
+                delay: delay.into_inner() as f32,
+                phase,
+            }
+        })
     }
 
-    pub fn sound_effect_compressor(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 7
-        unimplemented!("Called unimplemented kernel function sound_effect_compressor")
+    pub fn sound_effect_compressor(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, gain, attack, release, threshold, ratio, delay) =
+            expect_args!(args, [int, real, real, real, real, real, real])?;
+        self.update_sound_effects(sound_id, |fx| {
+            fx.compressor = effects::Compressor {
+                gain: gain.into_inner() as f32,
+                attack: attack.into_inner() as f32,
+                release: release.into_inner() as f32,
+                threshold: threshold.into_inner() as f32,
+                ratio: ratio.into_inner() as f32,
+                delay: delay.into_inner() as f32,
+            }
+        })
     }
 
-    pub fn sound_effect_echo(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 6
-        unimplemented!("Called unimplemented kernel function sound_effect_echo")
+    pub fn sound_effect_echo(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, wetdry, feedback, left_delay, right_delay, pan_delay) =
+            expect_args!(args, [int, real, real, real, real, bool])?;
+        self.update_sound_effects(sound_id, |fx| {
+            fx.echo = effects::Echo {
+                wetdry: wetdry.into_inner() as f32,
+                feedback: feedback.into_inner() as f32,
+                left_delay: left_delay.into_inner() as f32,
+                right_delay: right_delay.into_inner() as f32,
+                pan_delay,
+            }
+        })
     }
 
-    pub fn sound_effect_flanger(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 8
-        unimplemented!("Called unimplemented kernel function sound_effect_flanger")
+    pub fn sound_effect_flanger(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, wetdry, depth, feedback, frequency, wave, delay, phase) =
+            expect_args!(args, [int, real, real, real, real, int, real, int])?;
+        self.update_sound_effects(sound_id, |fx| {
+            fx.flanger = effects::Modulation {
+                wetdry: wetdry.into_inner() as f32,
+                depth: depth.into_inner() as f32,
+                feedback: feedback.into_inner() as f32,
+                frequency: frequency.into_inner() as f32,
+                wave,
+                delay: delay.into_inner() as f32,
+                phase,
+            }
+        })
     }
 
-    pub fn sound_effect_gargle(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function sound_effect_gargle")
+    pub fn sound_effect_gargle(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, rate, wave) = expect_args!(args, [int, real, int])?;
+        self.update_sound_effects(sound_id, |fx| fx.gargle = effects::Gargle { rate: rate.into_inner() as f32, wave })
     }
 
-    pub fn sound_effect_equalizer(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function sound_effect_equalizer")
+    pub fn sound_effect_equalizer(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, center, bandwidth, gain) = expect_args!(args, [int, real, real, real])?;
+        self.update_sound_effects(sound_id, |fx| {
+            fx.equalizer = effects::Equalizer {
+                center: center.into_inner() as f32,
+                bandwidth: bandwidth.into_inner() as f32,
+                gain: gain.into_inner() as f32,
+            }
+        })
     }
 
-    pub fn sound_effect_reverb(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 5
-        unimplemented!("Called unimplemented kernel function sound_effect_reverb")
+    pub fn sound_effect_reverb(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, gain, mix, time, ratio) = expect_args!(args, [int, real, real, real, real])?;
+        self.update_sound_effects(sound_id, |fx| {
+            fx.reverb = effects::Reverb {
+                gain: gain.into_inner() as f32,
+                mix: mix.into_inner() as f32,
+                time: time.into_inner() as f32,
+                ratio: ratio.into_inner() as f32,
+            }
+        })
     }
 
-    pub fn sound_3d_set_sound_position(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function sound_3d_set_sound_position")
+    // Changes a sound's effect parameters, which apply to any instances of it which are playing
+    fn update_sound_effects(&mut self, sound_id: i32, f: impl FnOnce(&mut EffectParams)) -> gml::Result<Value> {
+        if self.assets.sounds.get_asset(sound_id).is_some() {
+            let mut params = self.audio.effect_params(sound_id);
+            f(&mut params);
+            self.audio.set_effect_params(sound_id, params);
+            Ok(Default::default())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Sound, sound_id))
+        }
     }
 
-    pub fn sound_3d_set_sound_velocity(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 4
-        unimplemented!("Called unimplemented kernel function sound_3d_set_sound_velocity")
+    pub fn sound_3d_set_sound_position(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, x, y, z) = expect_args!(args, [int, real, real, real])?;
+        self.update_sound_3d(sound_id, |s| s.position = [x.into(), y.into(), z.into()])
     }
 
-    pub fn sound_3d_set_sound_distance(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 3
-        unimplemented!("Called unimplemented kernel function sound_3d_set_sound_distance")
+    pub fn sound_3d_set_sound_velocity(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, x, y, z) = expect_args!(args, [int, real, real, real])?;
+        self.update_sound_3d(sound_id, |s| s.velocity = [x.into(), y.into(), z.into()])
     }
 
-    pub fn sound_3d_set_sound_cone(&mut self, _args: &[Value]) -> gml::Result<Value> {
-        // Expected arg count: 7
-        unimplemented!("Called unimplemented kernel function sound_3d_set_sound_cone")
+    pub fn sound_3d_set_sound_distance(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, min_distance, max_distance) = expect_args!(args, [int, real, real])?;
+        self.update_sound_3d(sound_id, |s| {
+            s.min_distance = min_distance.into();
+            s.max_distance = max_distance.into();
+        })
+    }
+
+    pub fn sound_3d_set_sound_cone(&mut self, args: &[Value]) -> gml::Result<Value> {
+        let (sound_id, x, y, z, angle_inside, angle_outside, volume_outside) =
+            expect_args!(args, [int, real, real, real, real, real, real])?;
+        self.update_sound_3d(sound_id, |s| {
+            s.cone_orientation = [x.into(), y.into(), z.into()];
+            s.cone_inside = angle_inside.into();
+            s.cone_outside = angle_outside.into();
+            s.cone_volume = volume_outside.into();
+        })
+    }
+
+    // Changes a sound's 3D settings, and updates how loud it is and where it's heard from
+    fn update_sound_3d(&mut self, sound_id: i32, f: impl FnOnce(&mut Sound3D)) -> gml::Result<Value> {
+        if let Some(sound) = self.assets.sounds.get_asset(sound_id) {
+            let mut sound_3d = self.audio.sound_3d(sound_id);
+            f(&mut sound_3d);
+            if let asset::sound::FileType::Wav(handle) = &sound.handle {
+                handle.set_3d(&sound_3d);
+            }
+            self.audio.set_sound_3d(sound_id, sound_3d);
+            Ok(Default::default())
+        } else {
+            Err(gml::Error::NonexistentAsset(asset::Type::Sound, sound_id))
+        }
     }
 
     pub fn cd_init(&self, _args: &[Value]) -> gml::Result<Value> {
@@ -13443,8 +13834,8 @@ impl Game {
 
     pub fn d3d_model_load(&mut self, args: &[Value]) -> gml::Result<Value> {
         let (model_id, fname) = expect_args!(args, [int, string])?;
-        fn load_model(fname: &str) -> Result<model::Model, Box<dyn std::error::Error>> {
-            let mut file = std::io::BufReader::new(std::fs::File::open(file::to_path(&fname).as_ref())?);
+        fn load_model(vfs: &Vfs, fname: &str) -> Result<model::Model, Box<dyn std::error::Error>> {
+            let mut file = std::io::Cursor::new(vfs.read(file::to_path(&fname).as_ref())?);
             let version = file::read_real(&mut file)?;
             if version != 100.0 {
                 return Err("invalid version".into())
@@ -13551,7 +13942,7 @@ impl Game {
             Ok(model::Model { old_draw_colour: None, commands, cache: None })
         }
         if let Some(model) = self.models.get_mut(model_id) {
-            match load_model(&fname) {
+            match load_model(&self.vfs, &fname) {
                 Ok(new_model) => *model = new_model,
                 Err(e) => println!("WARNING: d3d_model_load failed: {}", e),
             }
@@ -13561,8 +13952,8 @@ impl Game {
 
     pub fn d3d_model_save(&mut self, args: &[Value]) -> gml::Result<Value> {
         let (model_id, fname) = expect_args!(args, [int, string])?;
-        fn save_model(model: &model::Model, fname: &str) -> std::io::Result<()> {
-            let mut file = std::io::BufWriter::new(std::fs::File::create(file::to_path(&fname).as_ref())?);
+        fn save_model(vfs: &mut Vfs, model: &model::Model, fname: &str) -> std::io::Result<()> {
+            let mut file = Vec::new();
             writeln!(&mut file, "100\r\n{}\r", model.commands.len())?;
             for cmd in &model.commands {
                 let (cmd, args) = cmd.to_line();
@@ -13572,11 +13963,10 @@ impl Game {
                 }
                 writeln!(&mut file, "\r")?;
             }
-            file.flush()?;
-            Ok(())
+            vfs.write(file::to_path(&fname).as_ref(), file)
         }
         if let Some(model) = self.models.get(model_id) {
-            if let Err(e) = save_model(model, &fname) {
+            if let Err(e) = save_model(&mut self.vfs, model, &fname) {
                 println!("WARNING: d3d_model_save failed: {}", e);
             }
         }
@@ -13599,13 +13989,13 @@ impl Game {
             self.renderer.mult_model_matrix(translation);
 
             let draw_colour = (u32::from(self.draw_colour) as i32 & 0xfeffff, self.draw_alpha.into_inner());
-            if model.cache.is_none() || self.gm_version == Version::GameMaker8_0 {
+            if model.cache.is_none() || self.gm_version != Version::GameMaker8_1 {
                 // GM8.0 does not use model caching.
                 // GM8.1 draws the model semi-normally once, then caches that and redraws.
                 let mut buffers = Default::default();
                 let mut primitive_draw: Box<dyn FnMut(&mut Renderer)> = match self.gm_version {
-                    Version::GameMaker8_0 => Box::new(|r| r.draw_primitive_3d()),
                     Version::GameMaker8_1 => Box::new(|r| r.extend_buffers(&mut buffers)),
+                    _ => Box::new(|r| r.draw_primitive_3d()),
                 };
                 let mut uses_draw_colour = false;
                 for command in &model.commands {
@@ -13697,8 +14087,8 @@ impl Game {
                             steps,
                         } => {
                             let x1 = match self.gm_version {
-                                Version::GameMaker8_0 => *x1,
                                 Version::GameMaker8_1 => x + *x1, // why is gm8 like this
+                                _ => *x1,
                             };
                             model::draw_cone(
                                 &mut self.renderer,
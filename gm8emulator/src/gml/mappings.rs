@@ -1396,11 +1396,11 @@ pub const FUNCTIONS: phf::OrderedMap<&'static str, Function> = phf_ordered_map!
     "file_write_real" => Function::Engine(Game::file_write_real),
     "file_writeln" => Function::Engine(Game::file_writeln),
     "file_exists" => Function::Volatile(Game::file_exists),
-    "file_delete" => Function::Volatile(Game::file_delete),
-    "file_rename" => Function::Volatile(Game::file_rename),
-    "file_copy" => Function::Volatile(Game::file_copy),
+    "file_delete" => Function::Engine(Game::file_delete),
+    "file_rename" => Function::Engine(Game::file_rename),
+    "file_copy" => Function::Engine(Game::file_copy),
     "directory_exists" => Function::Volatile(Game::directory_exists),
-    "directory_create" => Function::Volatile(Game::directory_create),
+    "directory_create" => Function::Engine(Game::directory_create),
     "file_find_first" => Function::Engine(Game::file_find_first),
     "file_find_next" => Function::Engine(Game::file_find_next),
     "file_find_close" => Function::Engine(Game::file_find_close),
@@ -1419,16 +1419,16 @@ pub const FUNCTIONS: phf::OrderedMap<&'static str, Function> = phf_ordered_map!
     "parameter_count" => Function::Constant(Game::parameter_count),
     "parameter_string" => Function::Constant(Game::parameter_string),
     "environment_get_variable" => Function::Volatile(Game::environment_get_variable),
-    "registry_write_string" => Function::Volatile(Game::registry_write_string),
-    "registry_write_real" => Function::Volatile(Game::registry_write_real),
-    "registry_read_string" => Function::Volatile(Game::registry_read_string),
-    "registry_read_real" => Function::Volatile(Game::registry_read_real),
-    "registry_exists" => Function::Volatile(Game::registry_exists),
-    "registry_write_string_ext" => Function::Volatile(Game::registry_write_string_ext),
-    "registry_write_real_ext" => Function::Volatile(Game::registry_write_real_ext),
-    "registry_read_string_ext" => Function::Volatile(Game::registry_read_string_ext),
-    "registry_read_real_ext" => Function::Volatile(Game::registry_read_real_ext),
-    "registry_exists_ext" => Function::Volatile(Game::registry_exists_ext),
+    "registry_write_string" => Function::Engine(Game::registry_write_string),
+    "registry_write_real" => Function::Engine(Game::registry_write_real),
+    "registry_read_string" => Function::Constant(Game::registry_read_string),
+    "registry_read_real" => Function::Constant(Game::registry_read_real),
+    "registry_exists" => Function::Constant(Game::registry_exists),
+    "registry_write_string_ext" => Function::Engine(Game::registry_write_string_ext),
+    "registry_write_real_ext" => Function::Engine(Game::registry_write_real_ext),
+    "registry_read_string_ext" => Function::Constant(Game::registry_read_string_ext),
+    "registry_read_real_ext" => Function::Constant(Game::registry_read_real_ext),
+    "registry_exists_ext" => Function::Constant(Game::registry_exists_ext),
     "registry_set_root" => Function::Engine(Game::registry_set_root),
     "ini_open" => Function::Engine(Game::ini_open),
     "ini_close" => Function::Engine(Game::ini_close),
@@ -1520,20 +1520,20 @@ pub const FUNCTIONS: phf::OrderedMap<&'static str, Function> = phf_ordered_map!
     "mouse_check_button_released" => Function::Constant(Game::mouse_check_button_released),
     "mouse_wheel_up" => Function::Constant(Game::mouse_wheel_up),
     "mouse_wheel_down" => Function::Constant(Game::mouse_wheel_down),
-    "joystick_exists" => Function::Engine(Game::joystick_exists),
-    "joystick_direction" => Function::Engine(Game::joystick_direction),
-    "joystick_name" => Function::Engine(Game::joystick_name),
-    "joystick_axes" => Function::Engine(Game::joystick_axes),
-    "joystick_buttons" => Function::Engine(Game::joystick_buttons),
-    "joystick_has_pov" => Function::Engine(Game::joystick_has_pov),
-    "joystick_check_button" => Function::Engine(Game::joystick_check_button),
-    "joystick_xpos" => Function::Engine(Game::joystick_xpos),
-    "joystick_ypos" => Function::Engine(Game::joystick_ypos),
-    "joystick_zpos" => Function::Engine(Game::joystick_zpos),
-    "joystick_rpos" => Function::Engine(Game::joystick_rpos),
-    "joystick_upos" => Function::Engine(Game::joystick_upos),
-    "joystick_vpos" => Function::Engine(Game::joystick_vpos),
-    "joystick_pov" => Function::Engine(Game::joystick_pov),
+    "joystick_exists" => Function::Constant(Game::joystick_exists),
+    "joystick_direction" => Function::Constant(Game::joystick_direction),
+    "joystick_name" => Function::Constant(Game::joystick_name),
+    "joystick_axes" => Function::Constant(Game::joystick_axes),
+    "joystick_buttons" => Function::Constant(Game::joystick_buttons),
+    "joystick_has_pov" => Function::Constant(Game::joystick_has_pov),
+    "joystick_check_button" => Function::Constant(Game::joystick_check_button),
+    "joystick_xpos" => Function::Constant(Game::joystick_xpos),
+    "joystick_ypos" => Function::Constant(Game::joystick_ypos),
+    "joystick_zpos" => Function::Constant(Game::joystick_zpos),
+    "joystick_rpos" => Function::Constant(Game::joystick_rpos),
+    "joystick_upos" => Function::Constant(Game::joystick_upos),
+    "joystick_vpos" => Function::Constant(Game::joystick_vpos),
+    "joystick_pov" => Function::Constant(Game::joystick_pov),
     "keyboard_clear" => Function::Engine(Game::keyboard_clear),
     "mouse_clear" => Function::Engine(Game::mouse_clear),
     "io_clear" => Function::Engine(Game::io_clear),
@@ -1658,9 +1658,9 @@ pub const FUNCTIONS: phf::OrderedMap<&'static str, Function> = phf_ordered_map!
     "date_compare_time" => Function::Pure(Game::date_compare_time),
     "date_date_of" => Function::Pure(Game::date_date_of),
     "date_time_of" => Function::Pure(Game::date_time_of),
-    "date_datetime_string" => Function::Constant(Game::date_datetime_string),
-    "date_date_string" => Function::Constant(Game::date_date_string),
-    "date_time_string" => Function::Constant(Game::date_time_string),
+    "date_datetime_string" => Function::Pure(Game::date_datetime_string),
+    "date_date_string" => Function::Pure(Game::date_date_string),
+    "date_time_string" => Function::Pure(Game::date_time_string),
     "date_days_in_month" => Function::Pure(Game::date_days_in_month),
     "date_days_in_year" => Function::Pure(Game::date_days_in_year),
     "date_leap_year" => Function::Pure(Game::date_leap_year),
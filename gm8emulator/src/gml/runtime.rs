@@ -1227,6 +1227,9 @@ impl Game {
             InstanceVariable::GamemakerStandard => Ok(gml::TRUE.into()), // yeah!
             InstanceVariable::GamemakerVersion => Ok(match self.gm_version {
                 // the docs claim these range from 800-809, 810-819. they don't.
+                Version::GameMaker6_0 => 600f64.into(),
+                Version::GameMaker6_1 => 610f64.into(),
+                Version::GameMaker7_0 => 700f64.into(),
                 Version::GameMaker8_0 => 800f64.into(),
                 Version::GameMaker8_1 => 810f64.into(),
             }),
@@ -56,6 +56,11 @@ impl<T> HandleList<T> {
     pub fn put(&mut self, handle: T) -> i32 {
         self.add(handle).unwrap()
     }
+
+    /// Iterates over the handles which are in use, along with their indices.
+    pub fn iter(&self) -> impl Iterator<Item = (i32, &T)> {
+        self.0.iter().enumerate().filter_map(|(i, x)| Some((i as i32, x.as_ref()?)))
+    }
 }
 
 impl<T, const LEN: usize> HandleArray<T, LEN> {
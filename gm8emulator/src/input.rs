@@ -1,4 +1,4 @@
-use crate::types::ArraySerde;
+use crate::{math::Real, types::ArraySerde};
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::{
@@ -1100,6 +1100,72 @@ const fn gen_default_keymap() -> [u8; KEY_MAX] {
 }
 const DEFAULT_KEYMAP: [u8; KEY_MAX] = gen_default_keymap();
 
+/// How many joysticks GM8 supports.
+pub const JOYSTICK_COUNT: usize = 2;
+
+/// How many buttons each virtual joystick has.
+pub const JOYSTICK_BUTTONS: usize = 16;
+
+/// The axes of a joystick, in the order they're stored.
+pub const JOYSTICK_AXES: [&str; 6] = ["X", "Y", "Z", "R", "U", "V"];
+
+/// The state of an emulated joystick. There's no real device behind it: it's set by the replay every frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Joystick {
+    pub connected: bool,
+    pub axes: [Real; JOYSTICK_AXES.len()], // -1 to 1, with positive x and y being right and down
+    pub buttons: u16,                      // bit n is button n + 1
+    pub pov: i32,                          // degrees clockwise from forwards, or -1 if centred
+}
+
+impl Joystick {
+    pub const fn new() -> Self {
+        Self { connected: false, axes: [Real::ZERO; JOYSTICK_AXES.len()], buttons: 0, pov: -1 }
+    }
+
+    /// Whether a button is held, numbered from 1 like in GML.
+    pub fn button(&self, number: i32) -> bool {
+        (1..=JOYSTICK_BUTTONS as i32).contains(&number) && self.buttons & (1 << (number - 1)) != 0
+    }
+
+    pub fn set_button(&mut self, number: i32, held: bool) {
+        if (1..=JOYSTICK_BUTTONS as i32).contains(&number) {
+            if held {
+                self.buttons |= 1 << (number - 1);
+            } else {
+                self.buttons &= !(1 << (number - 1));
+            }
+        }
+    }
+
+    /// Which way the stick is pushed along x and y, each -1, 0 or 1. It has to be pushed at least halfway.
+    pub fn pushed(&self) -> (i32, i32) {
+        let threshold = Real::from(0.5);
+        let sign = |axis: Real| {
+            if axis < -threshold {
+                -1
+            } else if axis > threshold {
+                1
+            } else {
+                0
+            }
+        };
+        (sign(self.axes[0]), sign(self.axes[1]))
+    }
+
+    /// Which way the stick is pushed, as the virtual key code of the matching numpad key (101 for the middle).
+    pub fn direction(&self) -> u8 {
+        let (dx, dy) = self.pushed();
+        (i32::from(Button::Keypad5 as u8) + dx - dy * 3) as u8
+    }
+}
+
+impl Default for Joystick {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Input {
     // basic state
@@ -1109,6 +1175,7 @@ pub struct Input {
     button_state_release: ArraySerde<bool, KEY_MAX>,
     mouse_position: (i32, i32),
     mouse_wheel: (bool, bool),
+    joysticks: [Joystick; JOYSTICK_COUNT],
 
     // gamemaker weirdness
     key_current: u8,
@@ -1119,6 +1186,43 @@ pub struct Input {
     numlock_state: bool, // spoofed!
 }
 
+/// The layout of Input in version 1 savestates, which didn't have joysticks.
+#[derive(Serialize, Deserialize)]
+pub struct InputV1 {
+    button_remap: ArraySerde<u8, KEY_MAX>,
+    button_state: ArraySerde<bool, KEY_MAX>,
+    button_state_press: ArraySerde<bool, KEY_MAX>,
+    button_state_release: ArraySerde<bool, KEY_MAX>,
+    mouse_position: (i32, i32),
+    mouse_wheel: (bool, bool),
+    key_current: u8,
+    key_previous: u8,
+    mouse_current: i8,
+    mouse_previous: i8,
+    mouse_position_previous: (i32, i32),
+    numlock_state: bool,
+}
+
+impl From<InputV1> for Input {
+    fn from(input: InputV1) -> Self {
+        Self {
+            button_remap: input.button_remap,
+            button_state: input.button_state,
+            button_state_press: input.button_state_press,
+            button_state_release: input.button_state_release,
+            mouse_position: input.mouse_position,
+            mouse_wheel: input.mouse_wheel,
+            joysticks: [Joystick::new(); JOYSTICK_COUNT],
+            key_current: input.key_current,
+            key_previous: input.key_previous,
+            mouse_current: input.mouse_current,
+            mouse_previous: input.mouse_previous,
+            mouse_position_previous: input.mouse_position_previous,
+            numlock_state: input.numlock_state,
+        }
+    }
+}
+
 impl Input {
     pub const fn new() -> Self {
         Input {
@@ -1128,6 +1232,7 @@ impl Input {
             button_state_release: ArraySerde([false; KEY_MAX]),
             mouse_position: (0, 0),
             mouse_wheel: (false, false),
+            joysticks: [Joystick::new(); JOYSTICK_COUNT],
             key_current: 0,
             key_previous: 0,
             mouse_current: 0,
@@ -1192,6 +1297,21 @@ impl Input {
         self.mouse_wheel.1 = true;
     }
 
+    #[inline]
+    pub fn set_joysticks(&mut self, joysticks: [Joystick; JOYSTICK_COUNT]) {
+        self.joysticks = joysticks;
+    }
+
+    #[inline]
+    pub fn joysticks(&self) -> [Joystick; JOYSTICK_COUNT] {
+        self.joysticks
+    }
+
+    /// Gets a connected joystick by its GML id, which starts at 1.
+    pub fn joystick(&self, id: i32) -> Option<&Joystick> {
+        usize::try_from(id - 1).ok().and_then(|i| self.joysticks.get(i)).filter(|j| j.connected)
+    }
+
     // == GameMaker Mappings ==
 
     fn keyboard_check_any_internal_indirect(&self, state: &[bool; KEY_MAX]) -> bool {
@@ -15,8 +15,10 @@ mod types;
 mod util;
 
 use game::{
+    framedump::FrameDump,
+    replay::libtas::KeyMap,
     savestate::{self, SaveState},
-    Game, GameClock, PlayType, Replay,
+    statediff, statehash, Game, GameClock, PlayType, Replay,
 };
 use std::{
     env, fs,
@@ -40,6 +42,209 @@ fn help(argv0: &str, opts: getopts::Options) {
     );
 }
 
+fn load_replay(filepath: &PathBuf) -> Result<Replay, String> {
+    match filepath.extension().and_then(|x| x.to_str()) {
+        Some("bin") => match SaveState::from_file(filepath, &mut savestate::Buffer::new()) {
+            Ok(state) => Ok(state.into_replay()),
+            Err(e) => Err(format!("couldn't load {:?}: {:?}", filepath, e)),
+        },
+
+        Some("gmtas") => match Replay::from_file(filepath) {
+            Ok(replay) => Ok(replay),
+            Err(e) => Err(format!("couldn't load {:?}: {:?}", filepath, e)),
+        },
+
+        Some("gmtxt") => match Replay::from_text_file(filepath) {
+            Ok(replay) => Ok(replay),
+            Err(e) => Err(format!("couldn't load {:?}: {:?}", filepath, e)),
+        },
+
+        _ => Err(format!("unknown filetype for {:?}, expected '.bin', '.gmtas' or '.gmtxt'", filepath)),
+    }
+}
+
+fn convert_replay(paths: &[String]) -> i32 {
+    if paths.len() != 2 {
+        eprintln!("--convert-replay needs an input replay and an output path");
+        return EXIT_FAILURE;
+    }
+    let replay = match load_replay(&PathBuf::from(&paths[0])) {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_FAILURE;
+        },
+    };
+    save_replay(&replay, &PathBuf::from(&paths[1]))
+}
+
+fn save_replay(replay: &Replay, output: &PathBuf) -> i32 {
+    let result = match output.extension().and_then(|x| x.to_str()) {
+        Some("gmtas") => replay.to_file(output),
+        Some("gmtxt") => replay.to_text_file(output),
+        _ => {
+            eprintln!("unknown output filetype, expected '.gmtas' or '.gmtxt'");
+            return EXIT_FAILURE;
+        },
+    };
+    match result {
+        Ok(()) => EXIT_SUCCESS,
+        Err(e) => {
+            eprintln!("couldn't write {:?}: {:?}", output, e);
+            EXIT_FAILURE
+        },
+    }
+}
+
+fn import_libtas(paths: &[String], keymap_path: Option<String>) -> i32 {
+    if paths.len() != 2 {
+        eprintln!("--import-libtas needs a libTAS inputs file and an output path");
+        return EXIT_FAILURE;
+    }
+    let mut keymap = KeyMap::default();
+    if let Some(path) = keymap_path {
+        match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| keymap.load_overrides(&text).map_err(|e| e.to_string()))
+        {
+            Ok(()) => (),
+            Err(e) => {
+                eprintln!("couldn't load keymap '{}': {}", path, e);
+                return EXIT_FAILURE;
+            },
+        }
+    }
+    let inputs = match fs::read_to_string(&paths[0]) {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            eprintln!("couldn't read '{}': {}", paths[0], e);
+            return EXIT_FAILURE;
+        },
+    };
+    // start the same way a fresh recording would
+    let start_time = gml::datetime::now_as_nanos();
+    let start_seed = gml::rand::Random::new().seed();
+    let import = match Replay::from_libtas(&inputs, &keymap, start_time, start_seed) {
+        Ok(import) => import,
+        Err(e) => {
+            eprintln!("couldn't parse '{}': {}", paths[0], e);
+            return EXIT_FAILURE;
+        },
+    };
+    for keysym in &import.unmapped_keys {
+        eprintln!("warning: keysym {:x} has no mapping and was skipped", keysym);
+    }
+    println!("imported {} frames", import.replay.frame_count());
+    save_replay(&import.replay, &PathBuf::from(&paths[1]))
+}
+
+fn diff_savestates(paths: &[String]) -> i32 {
+    if paths.len() != 2 {
+        eprintln!("--diff-savestates needs exactly two savestates as input");
+        return EXIT_FAILURE;
+    }
+    let mut buffer = savestate::Buffer::new();
+    let mut states = Vec::with_capacity(2);
+    for path in paths {
+        match SaveState::from_file(&PathBuf::from(path), &mut buffer) {
+            Ok(state) => states.push(state),
+            Err(e) => {
+                eprintln!("couldn't load savestate '{}': {:?}", path, e);
+                return EXIT_FAILURE;
+            },
+        }
+    }
+    let diff = statediff::StateDiff::new(&states[0], &states[1]);
+    print!("{}", diff);
+    if diff.is_empty() {
+        println!();
+        EXIT_SUCCESS
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+fn upgrade_savestates(paths: &[String]) -> i32 {
+    if paths.len() != 1 {
+        eprintln!("--upgrade-savestates needs exactly one directory as input");
+        return EXIT_FAILURE;
+    }
+    let mut buffer = savestate::Buffer::new();
+    let (mut upgraded, mut failed) = (0, 0);
+    // go through subdirectories too, since projects keep branches in their own folder
+    let mut dirs = vec![PathBuf::from(&paths[0])];
+    while let Some(dir) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("couldn't read directory {:?}: {}", dir, e);
+                failed += 1;
+                continue
+            },
+        };
+        for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+            if path.is_dir() {
+                dirs.push(path);
+                continue
+            }
+            if path.extension().and_then(|x| x.to_str()) != Some("bin") {
+                continue
+            }
+            let (state, version) = match SaveState::from_file_versioned(&path, &mut buffer) {
+                Ok((_, savestate::FILE_VERSION)) => continue,
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    eprintln!("couldn't load {:?}: {:?}", path, e);
+                    failed += 1;
+                    continue
+                },
+            };
+            // write next to the original first, so it isn't lost if saving fails halfway through
+            let temp_path = path.with_extension("bin.tmp");
+            let result = match state.save_to_file(&temp_path, &mut buffer) {
+                Ok(()) => fs::rename(&temp_path, &path).map_err(|e| e.to_string()),
+                Err(e) => Err(format!("{:?}", e)),
+            };
+            match result {
+                Ok(()) => {
+                    println!("upgraded {:?} from version {}", path, version);
+                    upgraded += 1;
+                },
+                Err(e) => {
+                    eprintln!("couldn't save {:?}: {}", path, e);
+                    let _ = fs::remove_file(&temp_path);
+                    failed += 1;
+                },
+            }
+        }
+    }
+    println!("upgraded {} savestates to version {}, {} failed", upgraded, savestate::FILE_VERSION, failed);
+    if failed == 0 { EXIT_SUCCESS } else { EXIT_FAILURE }
+}
+
+fn compare_hash_logs(paths: &[String]) -> i32 {
+    if paths.len() != 2 {
+        eprintln!("--compare-hash-logs needs exactly two hash log files as input");
+        return EXIT_FAILURE;
+    }
+    let mut logs = Vec::with_capacity(2);
+    for path in paths {
+        match statehash::read_log(Path::new(path)) {
+            Ok(log) => logs.push(log),
+            Err(e) => {
+                eprintln!("couldn't read hash log '{}': {}", path, e);
+                return EXIT_FAILURE;
+            },
+        }
+    }
+    let comparison = statehash::compare(&logs[0], &logs[1]);
+    println!("{}", comparison);
+    match comparison {
+        statehash::Comparison::Identical(_) => EXIT_SUCCESS,
+        _ => EXIT_FAILURE,
+    }
+}
+
 fn main() {
     process::exit(xmain());
 }
@@ -55,10 +260,23 @@ fn xmain() -> i32 {
     opts.optflag("v", "verbose", "enables verbose logging");
     opts.optflag("r", "realtime", "disables clock spoofing");
     opts.optflag("c", "capture", "captures a recording");
+    opts.optflag("", "headless", "plays back a replay with no window or graphics, then prints a summary");
+    opts.optflag("", "software", "with --headless, still draws every frame using the software renderer");
     opts.optflagopt("l", "no-framelimit-until", "disables the frame-limiter until specified frame", "FRAME");
     opts.optopt("n", "project-name", "name of TAS project to create or load", "NAME");
-    opts.optopt("f", "replay-file", "path to savestate file to replay", "FILE");
+    opts.optopt("f", "replay-file", "path to savestate, .gmtas or .gmtxt file to replay", "FILE");
     opts.optopt("o", "output-file", "output savestate name in replay mode", "FILE.bin");
+    opts.optopt("", "audio-out", "with -f, renders the replay's audio to a WAV file instead of playing it", "FILE.wav");
+    opts.optopt("", "dump-frames", "with -f, writes every frame to a .y4m file or a directory of PNGs", "PATH");
+    opts.optopt("", "dump-scale", "with --dump-frames, scales frames up by a whole number", "N");
+    opts.optopt("", "hash-log", "writes a hash of the game state after every frame, for finding desyncs", "FILE");
+    opts.optflag("", "compare-hash-logs", "compares the two hash logs given as input and reports the first difference");
+    opts.optflag("", "diff-savestates", "compares the two .bin savestates given as input and lists every difference");
+    opts.optflag("", "convert-replay", "converts the replay given as input to the output path, by file extension");
+    opts.optflag("", "import-libtas", "converts the libTAS inputs file given as input to the output path");
+    opts.optflag("", "upgrade-savestates", "upgrades the .bin savestates in the directory given as input, in place");
+    opts.optopt("", "keymap", "with --import-libtas, overrides the default keysym to keycode mapping", "FILE");
+    opts.optflag("", "seed-files", "with -n, lets the sandboxed game read the real files in its directories");
     opts.optmulti("a", "game-arg", "argument to pass to the game", "ARG");
     opts.optflagopt("p", "start-save", "Either loads the savestate specified after this parameter or starts at the first frame. If a .gmtas is specified by -f this will start the replay from this savestate instead", "savestate");
 
@@ -82,10 +300,29 @@ fn xmain() -> i32 {
         return EXIT_SUCCESS;
     }
 
+    if matches.opt_present("compare-hash-logs") {
+        return compare_hash_logs(&matches.free);
+    }
+    if matches.opt_present("diff-savestates") {
+        return diff_savestates(&matches.free);
+    }
+    if matches.opt_present("convert-replay") {
+        return convert_replay(&matches.free);
+    }
+    if matches.opt_present("import-libtas") {
+        return import_libtas(&matches.free, matches.opt_str("keymap"));
+    }
+    if matches.opt_present("upgrade-savestates") {
+        return upgrade_savestates(&matches.free);
+    }
+
     let strict = matches.opt_present("s");
     let multithread = !matches.opt_present("t");
     let spoof_time = !matches.opt_present("r");
     let capture_recording = matches.opt_present("c");
+    let headless = matches.opt_present("headless");
+    let seed_files = matches.opt_present("seed-files");
+    let software_render = matches.opt_present("software");
     let frame_limit_at = matches
         .opt_str("l")
         .map(|frame| match frame.parse::<usize>() {
@@ -95,9 +332,22 @@ fn xmain() -> i32 {
             },
         })
         .unwrap_or(0);
-    let frame_limiter = !matches.opt_present("l");
+    let dump_frames_path = matches.opt_str("dump-frames").map(PathBuf::from);
+    let dump_scale = match matches.opt_str("dump-scale").map(|scale| scale.parse::<u32>()) {
+        Some(Ok(scale)) if scale > 0 => scale,
+        Some(_) => {
+            eprintln!("invalid scale for --dump-scale: must be a whole number above 0");
+            return EXIT_FAILURE;
+        },
+        None => 1,
+    };
+    let audio_out_path = matches.opt_str("audio-out").map(PathBuf::from);
+    // dumped frames and audio are timed by the game clock, so there's no need to wait for real time to catch up
+    let frame_limiter =
+        !matches.opt_present("l") && !headless && dump_frames_path.is_none() && audio_out_path.is_none();
     let verbose = matches.opt_present("v");
     let output_bin = matches.opt_str("o").map(PathBuf::from);
+    let hash_log_path = matches.opt_str("hash-log").map(PathBuf::from);
     let pause = matches.opt_present("p");
     let start_save_path = matches.opt_str("p").map(PathBuf::from);
     let project_path = matches.opt_str("n").map(|name| {
@@ -114,6 +364,44 @@ fn xmain() -> i32 {
         }
     }
 
+    if hash_log_path.is_some() && !matches.opt_present("f") && project_path.is_none() {
+        eprintln!("--hash-log requires a replay file (-f) or a project (-n)");
+        return EXIT_FAILURE;
+    }
+
+    if audio_out_path.is_some() && (!matches.opt_present("f") || project_path.is_some() || capture_recording) {
+        eprintln!("--audio-out requires a replay file (-f), and can't be used with -n or -c");
+        return EXIT_FAILURE;
+    }
+
+    if dump_frames_path.is_some() {
+        if !matches.opt_present("f") || project_path.is_some() || capture_recording {
+            eprintln!("--dump-frames requires a replay file (-f), and can't be used with -n or -c");
+            return EXIT_FAILURE;
+        }
+        if headless && !software_render {
+            eprintln!("--dump-frames with --headless requires --software, or every frame would be blank");
+            return EXIT_FAILURE;
+        }
+    } else if matches.opt_present("dump-scale") {
+        eprintln!("--dump-scale requires --dump-frames");
+        return EXIT_FAILURE;
+    }
+
+    if headless {
+        if !matches.opt_present("f") {
+            eprintln!("--headless requires a replay file (-f)");
+            return EXIT_FAILURE;
+        }
+        if project_path.is_some() || capture_recording {
+            eprintln!("--headless can't be used with -n or -c");
+            return EXIT_FAILURE;
+        }
+    } else if software_render {
+        eprintln!("--software requires --headless");
+        return EXIT_FAILURE;
+    }
+
     let temp_dir = project_path.as_ref().map(|proj_path| {
         // attempt to find temp dir in project path
         std::fs::read_dir(proj_path)
@@ -141,32 +429,25 @@ fn xmain() -> i32 {
             })
     });
     let can_clear_temp_dir = temp_dir.is_none();
-    let replay = match matches
-        .opt_str("f")
-        .map(|filename| {
-            let filepath = PathBuf::from(&filename);
-            match filepath.extension().and_then(|x| x.to_str()) {
-                Some("bin") => match SaveState::from_file(&filepath, &mut savestate::Buffer::new()) {
-                    Ok(state) => Ok(state.into_replay()),
-                    Err(e) => Err(format!("couldn't load {:?}: {:?}", filepath, e)),
-                },
-
-                Some("gmtas") => match Replay::from_file(&filepath) {
-                    Ok(replay) => Ok(replay),
-                    Err(e) => Err(format!("couldn't load {:?}: {:?}", filepath, e)),
-                },
-
-                _ => Err("unknown filetype for -f, expected '.bin' or '.gmtas'".into()),
-            }
-        })
-        .transpose()
-    {
+    let replay = match matches.opt_str("f").map(|filename| load_replay(&PathBuf::from(&filename))).transpose() {
         Ok(r) => r,
         Err(e) => {
             eprintln!("{}", e);
             return EXIT_FAILURE;
         },
     };
+    // a replay has to see the same files it was recorded with
+    let seed_files = match &replay {
+        Some(replay) if replay.seeded_files != seed_files => {
+            if replay.seeded_files {
+                println!("Note: this replay was recorded with --seed-files, so it's being played with it too");
+            } else {
+                println!("Note: this replay was recorded without --seed-files, so it's being played without it");
+            }
+            replay.seeded_files
+        },
+        _ => seed_files,
+    };
 
     let input = {
         if matches.free.len() == 1 {
@@ -186,29 +467,30 @@ fn xmain() -> i32 {
 
     let file_path = Path::new(&input);
 
-    let mut file = match fs::read(file_path) {
-        Ok(data) => data,
-        Err(err) => {
-            eprintln!("failed to open '{}': {}", input, err);
-            return EXIT_FAILURE;
-        },
-    };
-
     if verbose {
         println!("loading '{}'...", input);
     }
 
-    #[rustfmt::skip]
-    let assets = gm8exe::reader::from_exe(
-        &mut file,                              // mut exe: AsRef<[u8]>
-        if verbose {                            // logger: Option<Fn(&str)>
-            Some(|s: &str| println!("{}", s))
+    let logger = if verbose { Some(|s: &str| println!("{}", s)) } else { None };
+    // source trees written by the decompiler can be run directly too, like projects
+    let assets = if file_path.is_dir() {
+        gm8exe::tree::from_tree(file_path, logger, multithread)
+    } else {
+        let mut file = match fs::read(file_path) {
+            Ok(data) => data,
+            Err(err) => {
+                eprintln!("failed to open '{}': {}", input, err);
+                return EXIT_FAILURE;
+            },
+        };
+
+        // .gmk and .gm81 projects can be run directly, without building them in the IDE first
+        if file.starts_with(&gm8exe::gmk::MAGIC.to_le_bytes()) {
+            gm8exe::gmk::from_gmk(&file, logger, strict, multithread)
         } else {
-            None
-        },
-        strict,                                 // strict: bool
-        multithread,                            // multithread: bool
-    );
+            gm8exe::reader::from_exe(&mut file, logger, strict, multithread)
+        }
+    };
     let assets = match assets {
         Ok(assets) => assets,
         Err(err) => {
@@ -245,6 +527,9 @@ fn xmain() -> i32 {
         frame_limit_at,
         capture_recording,
         play_type,
+        seed_files,
+        headless,
+        software_render,
     ) {
         Ok(g) => g,
         Err(e) => {
@@ -253,6 +538,33 @@ fn xmain() -> i32 {
         },
     };
 
+    if let Some(path) = &hash_log_path {
+        match statehash::HashLog::create(path) {
+            Ok(log) => components.state_hash_log = Some(log),
+            Err(e) => {
+                eprintln!("couldn't create hash log {:?}: {}", path, e);
+                return EXIT_FAILURE;
+            },
+        }
+    }
+
+    if let Some(path) = &audio_out_path {
+        if let Err(e) = components.audio.render_to_wav(path) {
+            eprintln!("couldn't create audio file {:?}: {}", path, e);
+            return EXIT_FAILURE;
+        }
+    }
+
+    if let Some(path) = &dump_frames_path {
+        match FrameDump::new(path, dump_scale) {
+            Ok(dump) => components.frame_dump = Some(dump),
+            Err(e) => {
+                eprintln!("couldn't create frame dump {:?}: {}", path, e);
+                return EXIT_FAILURE;
+            },
+        }
+    }
+
     let time_now = GameClock::SpoofedNanos(gml::datetime::now_as_nanos());
 
     if let Err(err) = if let Some(path) = project_path {
@@ -273,7 +585,11 @@ fn xmain() -> i32 {
             .map(|i| PathBuf::from(components.decode_str(i.name.as_ref()).into_owned()))
             .collect::<Vec<_>>();
         let result = if let Some(replay) = replay {
-            components.replay(replay, output_bin, start_save_path.as_ref())
+            components.replay(replay, output_bin, start_save_path.as_ref()).map(|summary| {
+                if headless {
+                    println!("{}", summary);
+                }
+            })
         } else {
             components.clock = if spoof_time { time_now } else { GameClock::StartupEpoch(std::time::Instant::now()) };
             components.run()
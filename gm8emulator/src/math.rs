@@ -254,6 +254,7 @@ impl std::iter::Sum<Self> for Real {
 impl Real {
     /// The lenience between values when compared.
     pub const CMP_EPSILON: Self = Self(1e-13);
+    pub const ZERO: Self = Self(0.0);
 
     #[inline(always)]
     pub fn into_inner(self) -> f64 {
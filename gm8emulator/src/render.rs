@@ -1,7 +1,9 @@
 //! Game rendering functionality
 
 pub mod atlas;
+pub mod headless;
 pub mod opengl;
+pub mod software;
 
 use crate::types::Colour;
 use atlas::{AtlasRect, AtlasRef};
@@ -551,11 +553,25 @@ impl Default for RendererOptions {
     }
 }
 
+/// Which RendererTrait implementation a Renderer should be created with.
+pub enum Backend<'a> {
+    /// Hardware rendering to a window through OpenGL.
+    OpenGL { connection: &'a Connection, window: &'a Window },
+    /// Draws nothing and needs no window. Used for verifying replays on machines without a display.
+    Headless,
+    /// Rasterizes on the CPU and needs no window, but unlike Headless, pixels can still be read back.
+    Software,
+}
+
 impl Renderer {
-    pub fn new(backend: (), connection: &Connection, options: &RendererOptions, window: &Window, clear_colour: Colour) -> Result<Self, String> {
-        Ok(Self(Box::new(match backend {
-            () => opengl::RendererImpl::new(options, connection, window, clear_colour)?,
-        })))
+    pub fn new(backend: Backend, options: &RendererOptions, clear_colour: Colour) -> Result<Self, String> {
+        Ok(Self(match backend {
+            Backend::OpenGL { connection, window } => {
+                Box::new(opengl::RendererImpl::new(options, connection, window, clear_colour)?)
+            },
+            Backend::Headless => Box::new(headless::RendererImpl::new(options)),
+            Backend::Software => Box::new(software::RendererImpl::new(options, clear_colour)),
+        }))
     }
 
     pub fn max_texture_size(&self) -> u32 {
@@ -1228,6 +1244,101 @@ pub struct RendererState {
     pub zbuf_trashed: bool,
 }
 
+/// A builder to be used for building basic shapes.
+struct ShapeBuilder {
+    primitive: PrimitiveBuilder,
+    outline: bool,
+    depth: f32,
+    alpha: f64,
+}
+
+impl ShapeBuilder {
+    fn new(outline: bool, atlas_ref: AtlasRect, alpha: f64, depth: f32) -> Self {
+        Self {
+            primitive: PrimitiveBuilder::new(
+                atlas_ref,
+                if outline { PrimitiveType::LineStrip } else { PrimitiveType::TriFan },
+                false,
+            ),
+            outline,
+            depth,
+            alpha,
+        }
+    }
+
+    /// Shortcut for basic shapes.
+    fn push_point(&mut self, x: f64, y: f64, colour: i32) -> &mut Self {
+        self.primitive.push_vertex([x as f32, y as f32, self.depth], [0.0, 0.0], split_colour(colour, self.alpha), [
+            0.0, 0.0, 0.0,
+        ]);
+        self
+    }
+
+    /// Should only be called once. This is only used for basic shapes, so it's fine for it to be *possible* to
+    /// call it multiple times, as that makes things easier elsewhere.
+    fn build(&mut self) -> &PrimitiveBuilder {
+        if self.outline {
+            let vertices = self.primitive.get_vertices();
+            if vertices.len() > 2 {
+                let vertex = vertices[0];
+                self.primitive.push_vertex_raw(vertex);
+            }
+        }
+        &self.primitive
+    }
+}
+
+/// How many atlases are kept for the game's own assets when `packed` were built from them. Any atlases after these
+/// are surfaces and such made at runtime. Every backend numbers its atlases the same way, so that texture IDs in
+/// savestates mean the same thing whichever backend made them.
+fn stock_atlas_count(packed: usize) -> u32 {
+    packed as u32 + 2 // TODO remove +2 when -o works
+}
+
+/// Builds a view matrix centered on the given source rectangle, rotated by `angle` degrees
+fn make_view_matrix(x: f64, y: f64, z: f64, w: f64, h: f64, angle: f64) -> [f32; 16] {
+    // Note: sin is negated because it's the same as negating the angle, which is how GM8 does view angles
+    let angle = angle.to_radians();
+    let sin_angle = -angle.sin() as f32;
+    let cos_angle = angle.cos() as f32;
+
+    #[rustfmt::skip]
+    let view_matrix: [f32; 16] = {
+        // source rectangle's center coordinates aka -(x + w/2) and -(y + h/2)
+        let scx = -((x as f32) + (w as f32 / 2.0));
+        let scy = -((y as f32) + (h as f32 / 2.0));
+        let scz = -z as f32;
+        mat4mult(
+            // Place camera at (scx, scy, scz)
+            [
+                1.0, 0.0, 0.0, 0.0,
+                0.0, 1.0, 0.0, 0.0,
+                0.0, 0.0, 1.0, 0.0,
+                scx, scy, scz, 1.0,
+            ],
+            // Rotate to view_angle
+            [
+                cos_angle,  sin_angle, 0.0, 0.0,
+                -sin_angle, cos_angle, 0.0, 0.0,
+                0.0,        0.0,       1.0, 0.0,
+                0.0,        0.0,       0.0, 1.0,
+            ]
+        )
+    };
+
+    view_matrix
+}
+
+/// Splits a BGR colour into normalized RGBA components
+fn split_colour(rgb: i32, alpha: f64) -> [f32; 4] {
+    [
+        ((rgb & 0xFF) as f32) / 255.0,
+        (((rgb >> 8) & 0xFF) as f32) / 255.0,
+        (((rgb >> 16) & 0xFF) as f32) / 255.0,
+        alpha.max(0.0).min(1.0) as f32,
+    ]
+}
+
 /// Multiply two mat4's together
 fn mat4mult(m1: [f32; 16], m2: [f32; 16]) -> [f32; 16] {
     [
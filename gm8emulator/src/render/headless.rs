@@ -0,0 +1,710 @@
+use crate::{
+    render::{
+        atlas::{AtlasBuilder, AtlasRect, AtlasRef},
+        mat4mult, split_colour, stock_atlas_count, BlendType, Fog, Light, PrimitiveBuilder, PrimitiveShape,
+        PrimitiveType, RendererOptions, RendererTrait, SavedTexture, Scaling, VertexBuffer,
+    },
+    types::Colour,
+};
+use std::{any::Any, cell::Cell};
+
+/// Largest texture the headless renderer will pretend to support.
+const MAX_TEXTURE_SIZE: u32 = 8192;
+
+/// A renderer which draws nothing and needs neither a window nor a graphics context.
+///
+/// Textures, surfaces and framebuffers are only tracked by their dimensions, so everything that can affect
+/// game logic (texture IDs, sprite sizes, renderer state) behaves exactly like the OpenGL renderer,
+/// while reading pixels back will only ever give transparent black.
+pub struct RendererImpl {
+    atlas_sizes: Vec<Option<(i32, i32, bool)>>, // width, height, has zbuffer
+    texture_rects: Vec<Option<AtlasRect>>,
+    stock_texture_count: usize,
+    stock_atlas_count: u32,
+    framebuffer_size: (u32, u32),
+    stored_framebuffer_size: Option<(u32, u32)>,
+    zbuf_trashed: bool,
+    white_pixel: AtlasRect,
+
+    model_matrix: [f32; 16],
+    alpha_blending: bool,
+    colour_blending: bool,
+    blend_mode: (BlendType, BlendType),
+    interpolate_pixels: bool,
+    texture_repeat: bool,
+    vsync: Cell<bool>,
+    depth_test: bool,
+    write_depth: bool,
+    culling: bool,
+    fog: Option<Fog>,
+    gouraud: bool,
+    lighting_enabled: bool,
+    ambient_colour: i32,
+    lights: [(bool, Light); 8],
+
+    circle_precision: i32,
+    using_3d: bool,
+    perspective: bool,
+    depth: f32,
+    primitive_2d: PrimitiveBuilder,
+    primitive_3d: PrimitiveBuilder,
+}
+
+impl RendererImpl {
+    pub fn new(options: &RendererOptions) -> Self {
+        #[rustfmt::skip]
+        let identity_matrix: [f32; 16] = [
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ];
+        Self {
+            atlas_sizes: vec![],
+            texture_rects: vec![],
+            stock_texture_count: 0,
+            stock_atlas_count: 0,
+            framebuffer_size: options.size,
+            stored_framebuffer_size: None,
+            zbuf_trashed: false,
+            white_pixel: Default::default(),
+
+            model_matrix: identity_matrix,
+            alpha_blending: true,
+            colour_blending: true,
+            blend_mode: (BlendType::SrcAlpha, BlendType::InvSrcAlpha),
+            interpolate_pixels: options.interpolate_pixels,
+            texture_repeat: false,
+            vsync: Cell::new(options.vsync),
+            depth_test: false,
+            write_depth: false,
+            culling: false,
+            fog: None,
+            gouraud: true,
+            lighting_enabled: false,
+            ambient_colour: 0,
+            lights: [(false, Light::Directional { direction: [0.0; 3], colour: 0 }); 8],
+
+            circle_precision: 24,
+            using_3d: false,
+            perspective: false,
+            depth: 0.0,
+            primitive_2d: PrimitiveBuilder::new(Default::default(), PrimitiveType::PointList, false),
+            primitive_3d: PrimitiveBuilder::new(Default::default(), PrimitiveType::PointList, false),
+        }
+    }
+
+    fn get_rect_mut(&mut self, id: AtlasRef) -> Option<&mut AtlasRect> {
+        id.0.try_into()
+            .ok()
+            .and_then(move |id: usize| self.texture_rects.get_mut(id))
+            .and_then(|o: &mut Option<AtlasRect>| o.as_mut())
+    }
+}
+
+impl RendererTrait for RendererImpl {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn max_texture_size(&self) -> u32 {
+        MAX_TEXTURE_SIZE
+    }
+
+    fn push_atlases(&mut self, mut atl: AtlasBuilder) -> Result<(), String> {
+        assert!(self.atlas_sizes.is_empty(), "atlases should be initialized only once");
+
+        let white_pixel_ref =
+            atl.texture(1, 1, 0, 0, Box::new([0xFF, 0xFF, 0xFF, 0xFF])).ok_or("Couldn't pack white_pixel")?;
+        let (packers, mut sprites) = atl.into_inner();
+        self.white_pixel = sprites[white_pixel_ref.0 as usize].0;
+
+        // update primitive buffers with white pixel
+        self.reset_primitive_2d(PrimitiveType::PointList, None);
+        self.reset_primitive_3d(PrimitiveType::PointList, None);
+
+        // keep the same atlas numbering as the OpenGL renderer so texture IDs line up
+        self.atlas_sizes = packers.iter().map(|p| Some((p.size().0, p.size().1, false))).collect();
+        self.stock_atlas_count = stock_atlas_count(packers.len());
+        self.texture_rects = sprites.drain(..).map(|(ar, _)| Some(ar)).collect();
+        self.stock_texture_count = self.texture_rects.len();
+
+        Ok(())
+    }
+
+    fn upload_sprite(
+        &mut self,
+        _data: Box<[u8]>,
+        width: i32,
+        height: i32,
+        origin_x: i32,
+        origin_y: i32,
+    ) -> Result<AtlasRef, String> {
+        let atlas_ref = self.create_surface(width, height, false)?;
+        if let Some(rect) = self.get_rect_mut(atlas_ref) {
+            rect.origin_x = origin_x as f32 / width as f32;
+            rect.origin_y = origin_y as f32 / height as f32;
+        }
+        Ok(atlas_ref)
+    }
+
+    fn duplicate_sprite(&mut self, atlas_ref: AtlasRef) -> Result<AtlasRef, String> {
+        if let Some(rect) = self.get_rect(atlas_ref).cloned() {
+            let sprite = self.create_surface(rect.w, rect.h, false)?;
+            let new_rect = self.get_rect_mut(sprite).unwrap();
+            new_rect.origin_x = rect.origin_x;
+            new_rect.origin_y = rect.origin_y;
+            Ok(sprite)
+        } else {
+            Ok(AtlasRef(-1))
+        }
+    }
+
+    fn delete_sprite(&mut self, atlas_ref: AtlasRef) {
+        // this only deletes sprites created with upload_sprite
+        if let Some(rect) = atlas_ref
+            .0
+            .try_into()
+            .ok()
+            .and_then(|id: usize| self.texture_rects.get_mut(id))
+            .and_then(|o: &mut Option<AtlasRect>| o.take())
+        {
+            if rect.atlas_id >= self.stock_atlas_count {
+                self.atlas_sizes[rect.atlas_id as usize] = None;
+            }
+        }
+    }
+
+    fn set_vsync(&self, vsync: bool) {
+        self.vsync.set(vsync);
+    }
+
+    fn get_vsync(&self) -> bool {
+        self.vsync.get()
+    }
+
+    fn wait_vsync(&self) {}
+
+    fn create_sprite_colour(&mut self, width: i32, height: i32, _col: Colour) -> Result<AtlasRef, String> {
+        self.create_surface(width, height, false)
+    }
+
+    fn create_surface(&mut self, width: i32, height: i32, has_zbuffer: bool) -> Result<AtlasRef, String> {
+        let atlas_id = if let Some(id) = self.atlas_sizes.iter().position(|x| x.is_none()) {
+            id as u32
+        } else {
+            self.atlas_sizes.push(None);
+            self.atlas_sizes.len() as u32 - 1
+        };
+        self.atlas_sizes[atlas_id as usize] = Some((width, height, has_zbuffer));
+        let id = self.texture_rects.len() as i32;
+        self.texture_rects.push(Some(AtlasRect {
+            atlas_id,
+            x: 0,
+            y: 0,
+            w: width,
+            h: height,
+            origin_x: 0.0,
+            origin_y: 0.0,
+        }));
+        Ok(AtlasRef(id))
+    }
+
+    fn set_target(&mut self, atlas_ref: AtlasRef) {
+        if let Some(rect) = self.get_rect(atlas_ref) {
+            let AtlasRect { x, y, w, h, .. } = *rect;
+            self.set_view(x, y, w, h, 0.0, x, y, w, h);
+        }
+    }
+
+    fn reset_target(&mut self) {
+        let (width, height) = self.framebuffer_size;
+        self.set_view(0, 0, width as _, height as _, 0.0, 0, 0, width as _, height as _);
+    }
+
+    fn copy_surface(
+        &mut self,
+        _dest: AtlasRef,
+        _dest_x: i32,
+        _dest_y: i32,
+        _src: AtlasRef,
+        _src_x: i32,
+        _src_y: i32,
+        _width: i32,
+        _height: i32,
+    ) {
+    }
+
+    fn set_zbuf_trashed(&mut self, trashed: bool) {
+        self.zbuf_trashed = trashed;
+    }
+
+    fn get_zbuf_trashed(&self) -> bool {
+        self.zbuf_trashed
+    }
+
+    fn resize_framebuffer(&mut self, width: u32, height: u32, store: bool) {
+        if store {
+            self.stored_framebuffer_size = Some(self.framebuffer_size);
+        }
+        self.framebuffer_size = (width, height);
+    }
+
+    fn get_texture_id(&mut self, atl_ref: AtlasRef) -> i32 {
+        atl_ref.0
+    }
+
+    fn get_texture_from_id(&self, id: i32) -> Option<AtlasRef> {
+        Some(AtlasRef(id))
+    }
+
+    fn get_texture_rects(&self) -> Vec<Option<AtlasRect>> {
+        self.texture_rects[self.stock_texture_count..].to_vec()
+    }
+
+    fn set_texture_rects(&mut self, rects: &[Option<AtlasRect>]) {
+        self.texture_rects.truncate(self.stock_texture_count);
+        self.texture_rects.extend_from_slice(rects);
+    }
+
+    fn dump_sprite_part(&self, atlas_ref: AtlasRef, _part_x: i32, _part_y: i32, part_w: i32, part_h: i32) -> Box<[u8]> {
+        match self.get_rect(atlas_ref) {
+            Some(_) => vec![0; (part_w * part_h * 4) as usize].into_boxed_slice(),
+            None => Box::new([]),
+        }
+    }
+
+    fn get_pixels(&self, _x: i32, _y: i32, w: i32, h: i32) -> Box<[u8]> {
+        vec![0; (w * h * 4) as usize].into_boxed_slice()
+    }
+
+    fn stored_pixels(&self) -> Box<[u8]> {
+        let (width, height) = self.stored_size();
+        vec![0; (width * height * 4) as usize].into_boxed_slice()
+    }
+
+    fn stored_zbuffer(&self) -> Box<[f32]> {
+        let (width, height) = self.stored_size();
+        vec![0.0; (width * height) as usize].into_boxed_slice()
+    }
+
+    fn set_stored(&mut self, _rgba: Box<[u8]>, _zbuf: Box<[f32]>, fb_w: u32, fb_h: u32) {
+        self.stored_framebuffer_size = Some((fb_w, fb_h));
+    }
+
+    fn dump_dynamic_textures(&self) -> Vec<Option<SavedTexture>> {
+        self.atlas_sizes
+            .iter()
+            .skip(self.stock_atlas_count as usize)
+            .map(|size| {
+                size.map(|(width, height, has_zbuffer)| {
+                    let len = (width * height) as usize;
+                    SavedTexture {
+                        width,
+                        height,
+                        pixels: vec![0; len * 4].into_boxed_slice(),
+                        zbuf: has_zbuffer.then(|| vec![0.0; len].into_boxed_slice()),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn upload_dynamic_textures(&mut self, textures: &[Option<SavedTexture>]) {
+        self.atlas_sizes.truncate(self.stock_atlas_count as usize);
+        self.atlas_sizes.resize(self.stock_atlas_count as usize, None);
+        self.atlas_sizes.extend(textures.iter().map(|tex| tex.as_ref().map(|t| (t.width, t.height, t.zbuf.is_some()))));
+    }
+
+    fn get_rect(&self, id: AtlasRef) -> Option<&AtlasRect> {
+        id.0.try_into()
+            .ok()
+            .and_then(|id: usize| self.texture_rects.get(id))
+            .and_then(|o: &Option<AtlasRect>| o.as_ref())
+    }
+
+    fn draw_sprite_general(
+        &mut self,
+        _texture: AtlasRef,
+        _part_x: f64,
+        _part_y: f64,
+        _part_w: f64,
+        _part_h: f64,
+        _x: f64,
+        _y: f64,
+        _xscale: f64,
+        _yscale: f64,
+        _angle: f64,
+        _col1: i32,
+        _col2: i32,
+        _col3: i32,
+        _col4: i32,
+        _alpha: f64,
+        _use_origin: bool,
+    ) {
+        self.set_texture_repeat(false);
+    }
+
+    fn draw_sprite_pos(
+        &mut self,
+        _texture: AtlasRef,
+        _x1: f64,
+        _y1: f64,
+        _x2: f64,
+        _y2: f64,
+        _x3: f64,
+        _y3: f64,
+        _x4: f64,
+        _y4: f64,
+        _alpha: f64,
+    ) {
+        self.set_texture_repeat(false);
+    }
+
+    fn draw_rectangle(&mut self, _x1: f64, _y1: f64, _x2: f64, _y2: f64, _colour: i32, _alpha: f64) {}
+
+    fn draw_rectangle_outline(&mut self, _x1: f64, _y1: f64, _x2: f64, _y2: f64, _colour: i32, _alpha: f64) {}
+
+    fn draw_rectangle_gradient(
+        &mut self,
+        _x1: f64,
+        _y1: f64,
+        _x2: f64,
+        _y2: f64,
+        _c1: i32,
+        _c2: i32,
+        _c3: i32,
+        _c4: i32,
+        _alpha: f64,
+        _outline: bool,
+    ) {
+    }
+
+    fn draw_point(&mut self, _x: f64, _y: f64, _colour: i32, _alpha: f64) {}
+
+    fn draw_line(
+        &mut self,
+        _x1: f64,
+        _y1: f64,
+        _x2: f64,
+        _y2: f64,
+        _width: Option<f64>,
+        _c1: i32,
+        _c2: i32,
+        _alpha: f64,
+    ) {
+    }
+
+    fn draw_triangle(
+        &mut self,
+        _x1: f64,
+        _y1: f64,
+        _x2: f64,
+        _y2: f64,
+        _x3: f64,
+        _y3: f64,
+        _c1: i32,
+        _c2: i32,
+        _c3: i32,
+        _alpha: f64,
+        _outline: bool,
+    ) {
+    }
+
+    fn draw_ellipse(
+        &mut self,
+        _x: f64,
+        _y: f64,
+        _rad_x: f64,
+        _rad_y: f64,
+        _c1: i32,
+        _c2: i32,
+        _alpha: f64,
+        _outline: bool,
+    ) {
+    }
+
+    fn draw_roundrect(
+        &mut self,
+        _x1: f64,
+        _y1: f64,
+        _x2: f64,
+        _y2: f64,
+        _c1: i32,
+        _c2: i32,
+        _alpha: f64,
+        _outline: bool,
+    ) {
+    }
+
+    fn set_circle_precision(&mut self, prec: i32) {
+        self.circle_precision = (prec.max(4).min(64) >> 2) << 2;
+    }
+
+    fn get_circle_precision(&self) -> i32 {
+        self.circle_precision
+    }
+
+    fn reset_primitive_2d(&mut self, ptype: PrimitiveType, atlas_ref: Option<AtlasRef>) {
+        let ar = atlas_ref.and_then(|ar| self.get_rect(ar).copied());
+        self.primitive_2d = PrimitiveBuilder::new(ar.unwrap_or(self.white_pixel), ptype, ar.is_some());
+    }
+
+    fn vertex_2d(&mut self, x: f64, y: f64, xtex: f64, ytex: f64, col: i32, alpha: f64) {
+        // primitives are part of the renderer state, so they still need building
+        self.primitive_2d.push_vertex(
+            [x as f32, y as f32, self.depth],
+            [xtex as f32, ytex as f32],
+            split_colour(col, alpha),
+            [0.0, 0.0, 0.0],
+        );
+    }
+
+    fn draw_primitive_2d(&mut self) {}
+
+    fn get_primitive_2d(&self) -> PrimitiveBuilder {
+        self.primitive_2d.clone()
+    }
+
+    fn set_primitive_2d(&mut self, prim: PrimitiveBuilder) {
+        self.primitive_2d = prim;
+    }
+
+    fn reset_primitive_3d(&mut self, ptype: PrimitiveType, atlas_ref: Option<AtlasRef>) {
+        let ar = atlas_ref.and_then(|ar| self.get_rect(ar).copied());
+        self.primitive_3d = PrimitiveBuilder::new(ar.unwrap_or(self.white_pixel), ptype, ar.is_some());
+    }
+
+    fn vertex_3d(
+        &mut self,
+        x: f64,
+        y: f64,
+        z: f64,
+        nx: f64,
+        ny: f64,
+        nz: f64,
+        xtex: f64,
+        ytex: f64,
+        col: i32,
+        alpha: f64,
+    ) {
+        self.primitive_3d.push_vertex(
+            [x as f32, y as f32, z as f32],
+            [xtex as f32, ytex as f32],
+            split_colour(col, alpha),
+            [nx as f32, ny as f32, nz as f32],
+        );
+    }
+
+    fn draw_primitive_3d(&mut self) {}
+
+    fn get_primitive_3d(&self) -> PrimitiveBuilder {
+        self.primitive_3d.clone()
+    }
+
+    fn set_primitive_3d(&mut self, prim: PrimitiveBuilder) {
+        self.primitive_3d = prim;
+    }
+
+    fn extend_buffers(&self, buf: &mut VertexBuffer) {
+        // models keep their vertex buffers in the game state, so these still need filling
+        let verts = self.primitive_3d.get_vertices();
+        match self.primitive_3d.get_shape() {
+            PrimitiveShape::Point => buf.points.extend_from_slice(verts),
+            PrimitiveShape::Line => buf.lines.extend_from_slice(&verts[..verts.len() / 2 * 2]),
+            PrimitiveShape::Triangle => buf.tris.extend_from_slice(&verts[..verts.len() / 3 * 3]),
+        }
+    }
+
+    fn draw_buffers(&mut self, _atlas_ref: Option<AtlasRef>, _buf: &VertexBuffer) {}
+
+    fn get_alpha_blending(&self) -> bool {
+        self.alpha_blending
+    }
+
+    fn set_alpha_blending(&mut self, alphablend: bool) {
+        self.alpha_blending = alphablend;
+    }
+
+    fn get_colour_blending(&self) -> bool {
+        self.colour_blending
+    }
+
+    fn set_colour_blending(&mut self, modulate: bool) {
+        self.colour_blending = modulate;
+    }
+
+    fn get_blend_mode(&self) -> (BlendType, BlendType) {
+        self.blend_mode
+    }
+
+    fn set_blend_mode(&mut self, src: BlendType, dst: BlendType) {
+        self.blend_mode = (src, dst);
+    }
+
+    fn get_pixel_interpolation(&self) -> bool {
+        self.interpolate_pixels
+    }
+
+    fn set_pixel_interpolation(&mut self, lerping: bool) {
+        self.interpolate_pixels = lerping;
+    }
+
+    fn get_texture_repeat(&self) -> bool {
+        self.texture_repeat
+    }
+
+    fn set_texture_repeat(&mut self, repeat: bool) {
+        self.texture_repeat = repeat;
+    }
+
+    fn flush_queue(&mut self) {}
+
+    fn set_view_matrix(&mut self, _view: [f32; 16]) {}
+
+    fn set_viewproj_matrix(&mut self, _view: [f32; 16], _proj: [f32; 16]) {}
+
+    fn get_model_matrix(&self) -> [f32; 16] {
+        self.model_matrix
+    }
+
+    fn set_model_matrix(&mut self, model: [f32; 16]) {
+        self.model_matrix = model;
+    }
+
+    fn mult_model_matrix(&mut self, model: [f32; 16]) {
+        self.model_matrix = mat4mult(self.model_matrix, model);
+    }
+
+    fn set_projection_ortho(&mut self, _x: f64, _y: f64, _w: f64, _h: f64, _angle: f64) {}
+
+    fn set_projection_perspective(&mut self, _x: f64, _y: f64, _w: f64, _h: f64, _angle: f64) {}
+
+    fn set_view(
+        &mut self,
+        _src_x: i32,
+        _src_y: i32,
+        _src_w: i32,
+        _src_h: i32,
+        _src_angle: f64,
+        _port_x: i32,
+        _port_y: i32,
+        _port_w: i32,
+        _port_h: i32,
+    ) {
+    }
+
+    fn clear_view(&mut self, _colour: Colour, _alpha: f64) {}
+
+    fn clear_view_no_zbuf(&mut self, _colour: Colour, _alpha: f64) {}
+
+    fn clear_zbuf(&mut self) {}
+
+    fn get_3d(&self) -> bool {
+        self.using_3d
+    }
+
+    fn set_3d(&mut self, use_3d: bool) {
+        self.using_3d = use_3d;
+        self.set_depth_test(use_3d);
+        self.set_perspective(use_3d);
+    }
+
+    fn get_depth(&self) -> f32 {
+        self.depth
+    }
+
+    fn set_depth(&mut self, depth: f32) {
+        self.depth = if self.using_3d { depth.max(-16000.0).min(16000.0) } else { 0.0 };
+    }
+
+    fn get_depth_test(&self) -> bool {
+        self.depth_test
+    }
+
+    fn set_depth_test(&mut self, depth_test: bool) {
+        self.depth_test = depth_test && self.using_3d;
+    }
+
+    fn get_write_depth(&self) -> bool {
+        self.write_depth
+    }
+
+    fn set_write_depth(&mut self, write_depth: bool) {
+        self.write_depth = write_depth;
+    }
+
+    fn get_culling(&self) -> bool {
+        self.culling
+    }
+
+    fn set_culling(&mut self, culling: bool) {
+        self.culling = culling;
+    }
+
+    fn get_perspective(&self) -> bool {
+        self.perspective
+    }
+
+    fn set_perspective(&mut self, perspective: bool) {
+        self.perspective = perspective;
+    }
+
+    fn get_fog(&self) -> Option<Fog> {
+        self.fog.clone()
+    }
+
+    fn set_fog(&mut self, fog: Option<Fog>) {
+        self.fog = fog;
+    }
+
+    fn get_gouraud(&self) -> bool {
+        self.gouraud
+    }
+
+    fn set_gouraud(&mut self, gouraud: bool) {
+        self.gouraud = gouraud;
+    }
+
+    fn get_lighting_enabled(&self) -> bool {
+        self.lighting_enabled
+    }
+
+    fn set_lighting_enabled(&mut self, enabled: bool) {
+        self.lighting_enabled = enabled;
+    }
+
+    fn get_ambient_colour(&self) -> i32 {
+        self.ambient_colour
+    }
+
+    fn set_ambient_colour(&mut self, colour: i32) {
+        self.ambient_colour = colour;
+    }
+
+    fn get_lights(&self) -> [(bool, Light); 8] {
+        self.lights
+    }
+
+    fn set_lights(&mut self, lights: [(bool, Light); 8]) {
+        self.lights = lights;
+    }
+
+    fn set_light_enabled(&mut self, id: usize, enabled: bool) {
+        self.lights[id].0 = enabled;
+    }
+
+    fn set_light(&mut self, id: usize, light: Light) {
+        self.lights[id].1 = light;
+    }
+
+    fn present(&mut self, _window_width: u32, _window_height: u32, _scaling: Scaling) {}
+
+    fn draw_stored(&mut self, _x: i32, _y: i32, _w: u32, _h: u32) {}
+
+    fn stored_size(&self) -> (u32, u32) {
+        self.stored_framebuffer_size.unwrap_or(self.framebuffer_size)
+    }
+
+    fn finish(&mut self, _window_width: u32, _window_height: u32, _clear_colour: Colour) {}
+}
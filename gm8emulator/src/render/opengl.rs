@@ -1,8 +1,9 @@
 use crate::{
     render::{
         atlas::{AtlasBuilder, AtlasRect, AtlasRef},
-        mat4mult, BlendType, Fog, Light, PrimitiveBuilder, PrimitiveShape, PrimitiveType, RendererOptions,
-        RendererTrait, SavedTexture, Scaling, Vertex, VertexBuffer,
+        make_view_matrix, mat4mult, split_colour, stock_atlas_count, BlendType, Fog, Light, PrimitiveBuilder,
+        PrimitiveShape, PrimitiveType, RendererOptions, RendererTrait, SavedTexture, Scaling, ShapeBuilder, Vertex,
+        VertexBuffer,
     },
     types::Colour,
 };
@@ -225,48 +226,6 @@ unsafe fn shader_info_log(gl: &gl::Gl, name: &str, id: GLuint) -> String {
     )
 }
 
-fn make_view_matrix(x: f64, y: f64, z: f64, w: f64, h: f64, angle: f64) -> [f32; 16] {
-    // Note: sin is negated because it's the same as negating the angle, which is how GM8 does view angles
-    let angle = angle.to_radians();
-    let sin_angle = -angle.sin() as f32;
-    let cos_angle = angle.cos() as f32;
-
-    #[rustfmt::skip]
-    let view_matrix: [f32; 16] = {
-        // source rectangle's center coordinates aka -(x + w/2) and -(y + h/2)
-        let scx = -((x as f32) + (w as f32 / 2.0));
-        let scy = -((y as f32) + (h as f32 / 2.0));
-        let scz = -z as f32;
-        mat4mult(
-            // Place camera at (scx, scy, scz)
-            [
-                1.0, 0.0, 0.0, 0.0,
-                0.0, 1.0, 0.0, 0.0,
-                0.0, 0.0, 1.0, 0.0,
-                scx, scy, scz, 1.0,
-            ],
-            // Rotate to view_angle
-            [
-                cos_angle,  sin_angle, 0.0, 0.0,
-                -sin_angle, cos_angle, 0.0, 0.0,
-                0.0,        0.0,       1.0, 0.0,
-                0.0,        0.0,       0.0, 1.0,
-            ]
-        )
-    };
-
-    view_matrix
-}
-
-fn split_colour(rgb: i32, alpha: f64) -> [f32; 4] {
-    [
-        ((rgb & 0xFF) as f32) / 255.0,
-        (((rgb >> 8) & 0xFF) as f32) / 255.0,
-        (((rgb >> 16) & 0xFF) as f32) / 255.0,
-        alpha.max(0.0).min(1.0) as f32,
-    ]
-}
-
 // TODO: probably put this in render.rs instead
 impl VertexBuffer {
     pub fn swap_colour(&mut self, old: (i32, f64), new: (i32, f64)) {
@@ -342,50 +301,6 @@ impl From<PrimitiveShape> for GLenum {
     }
 }
 
-/// A builder to be used for building basic shapes.
-struct ShapeBuilder {
-    primitive: PrimitiveBuilder,
-    outline: bool,
-    depth: f32,
-    alpha: f64,
-}
-
-impl ShapeBuilder {
-    fn new(outline: bool, atlas_ref: AtlasRect, alpha: f64, depth: f32) -> Self {
-        Self {
-            primitive: PrimitiveBuilder::new(
-                atlas_ref,
-                if outline { PrimitiveType::LineStrip } else { PrimitiveType::TriFan },
-                false,
-            ),
-            outline,
-            depth,
-            alpha,
-        }
-    }
-
-    /// Shortcut for basic shapes.
-    fn push_point(&mut self, x: f64, y: f64, colour: i32) -> &mut Self {
-        self.primitive.push_vertex([x as f32, y as f32, self.depth], [0.0, 0.0], split_colour(colour, self.alpha), [
-            0.0, 0.0, 0.0,
-        ]);
-        self
-    }
-
-    /// Should only be called once. This is only used for basic shapes, so it's fine for it to be *possible* to
-    /// call it multiple times, as that makes things easier elsewhere.
-    fn build(&mut self) -> &PrimitiveBuilder {
-        if self.outline {
-            let vertices = self.primitive.get_vertices();
-            if vertices.len() > 2 {
-                let vertex = vertices[0];
-                self.primitive.push_vertex_raw(vertex);
-            }
-        }
-        &self.primitive
-    }
-}
-
 // TODO: Implement Drop trait for RendererImpl to delete OpenGL objects we create? This doesn't make
 // much sense in Release builds - because then we're doing the OS's work for it and just increasing
 // the process termination time - but can be quite useful for Debug ones.
@@ -917,7 +832,7 @@ impl RendererTrait for RendererImpl {
             self.texture_ids = textures.iter().map(|t| Some(*t)).collect();
             self.zbuf_ids.resize(self.texture_ids.len(), None);
             self.fbo_ids = fbo_ids;
-            self.stock_atlas_count = textures.len() as u32 + 2; // TODO remove +2 when -o works
+            self.stock_atlas_count = stock_atlas_count(textures.len());
         }
 
         // store packers, discard pixeldata
@@ -0,0 +1,1625 @@
+use crate::{
+    render::{
+        atlas::{AtlasBuilder, AtlasRect, AtlasRef},
+        make_view_matrix, mat4mult, split_colour, stock_atlas_count, BlendType, Fog, Light, PrimitiveBuilder,
+        PrimitiveShape, PrimitiveType, RendererOptions, RendererTrait, SavedTexture, Scaling, ShapeBuilder, Vertex,
+        VertexBuffer,
+    },
+    types::Colour,
+};
+use std::{any::Any, cell::Cell, f64::consts::PI};
+
+/// Largest texture the software renderer will allocate.
+/// Every atlas lives uncompressed in system memory, so this is lower than most GPUs would report.
+const MAX_TEXTURE_SIZE: u32 = 4096;
+
+/// Number of values interpolated across a primitive: texture coordinate (2), blend colour (4) and fog depth (1).
+const VARYINGS: usize = 7;
+
+/// An RGBA texture, optionally with a depth buffer attached. Row 0 is the top of the image.
+#[derive(Clone, Default)]
+struct Texture {
+    width: i32,
+    height: i32,
+    pixels: Vec<u8>,
+    zbuf: Option<Vec<f32>>,
+}
+
+impl Texture {
+    fn new(width: i32, height: i32, has_zbuffer: bool) -> Self {
+        let len = (width.max(0) * height.max(0)) as usize;
+        Self { width, height, pixels: vec![0; len * 4], zbuf: has_zbuffer.then(|| vec![1.0; len]) }
+    }
+
+    fn from_saved(tex: &SavedTexture) -> Self {
+        Self {
+            width: tex.width,
+            height: tex.height,
+            pixels: tex.pixels.to_vec(),
+            zbuf: tex.zbuf.as_ref().map(|zbuf| zbuf.to_vec()),
+        }
+    }
+
+    fn to_saved(&self) -> SavedTexture {
+        SavedTexture {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.clone().into_boxed_slice(),
+            zbuf: self.zbuf.clone().map(Vec::into_boxed_slice),
+        }
+    }
+
+    /// Reads a rectangle of RGBA pixels. Anything outside the texture reads as transparent black.
+    fn read(&self, x: i32, y: i32, w: i32, h: i32) -> Box<[u8]> {
+        let (w, h) = (w.max(0), h.max(0));
+        let mut data = vec![0; (w * h * 4) as usize];
+        let (x0, x1) = (x.max(0), (x + w).min(self.width));
+        if x0 < x1 {
+            for row in (0..h).filter(|row| (0..self.height).contains(&(y + row))) {
+                let src = (((y + row) * self.width + x0) * 4) as usize;
+                let dst = ((row * w + x0 - x) * 4) as usize;
+                let len = ((x1 - x0) * 4) as usize;
+                data[dst..dst + len].copy_from_slice(&self.pixels[src..src + len]);
+            }
+        }
+        data.into_boxed_slice()
+    }
+
+    /// Writes a rectangle of RGBA pixels. The rectangle must fit inside the texture.
+    fn write(&mut self, x: i32, y: i32, w: i32, h: i32, data: &[u8]) {
+        for (row, line) in data.chunks_exact((w * 4) as usize).take(h as usize).enumerate() {
+            let dst = (((y + row as i32) * self.width + x) * 4) as usize;
+            self.pixels[dst..dst + line.len()].copy_from_slice(line);
+        }
+    }
+
+    /// Fills the given bounds with a colour and/or a depth value.
+    fn fill(&mut self, [x0, y0, x1, y1]: [i32; 4], colour: Option<[u8; 4]>, depth: Option<f32>) {
+        for y in y0..y1 {
+            let (start, end) = ((y * self.width + x0) as usize, (y * self.width + x1) as usize);
+            if let Some(colour) = colour {
+                self.pixels[start * 4..end * 4].chunks_exact_mut(4).for_each(|p| p.copy_from_slice(&colour));
+            }
+            if let (Some(depth), Some(zbuf)) = (depth, self.zbuf.as_mut()) {
+                zbuf[start..end].fill(depth);
+            }
+        }
+    }
+
+    /// Copies a rectangle of colour data from another texture, scaling it with nearest-neighbour filtering.
+    /// Pixels which would land outside this texture or read from outside the source are skipped.
+    fn blit(&mut self, src: &Texture, (src_x, src_y, src_w, src_h): (i32, i32, i32, i32), dest: (i32, i32, i32, i32)) {
+        let (dest_x, dest_y, dest_w, dest_h) = dest;
+        for row in 0..dest_h {
+            let y = dest_y + row;
+            let sy = src_y + ((f64::from(row) + 0.5) * f64::from(src_h) / f64::from(dest_h)) as i32;
+            if !(0..self.height).contains(&y) || !(0..src.height).contains(&sy) {
+                continue
+            }
+            for col in 0..dest_w {
+                let x = dest_x + col;
+                let sx = src_x + ((f64::from(col) + 0.5) * f64::from(src_w) / f64::from(dest_w)) as i32;
+                if !(0..self.width).contains(&x) || !(0..src.width).contains(&sx) {
+                    continue
+                }
+                let (dst, src_i) = (((y * self.width + x) * 4) as usize, ((sy * src.width + sx) * 4) as usize);
+                self.pixels[dst..dst + 4].copy_from_slice(&src.pixels[src_i..src_i + 4]);
+            }
+        }
+    }
+
+    /// Fetches a single texel, wrapping around the edges like GL_REPEAT.
+    fn fetch(&self, x: i32, y: i32) -> [f32; 4] {
+        if self.width <= 0 || self.height <= 0 {
+            return [0.0; 4]
+        }
+        let i = ((y.rem_euclid(self.height) * self.width + x.rem_euclid(self.width)) * 4) as usize;
+        let p = &self.pixels[i..i + 4];
+        [p[0], p[1], p[2], p[3]].map(|c| f32::from(c) / 255.0)
+    }
+
+    /// Samples at a coordinate in texel space, using either nearest-neighbour or bilinear filtering.
+    fn sample(&self, x: f32, y: f32, lerp: bool) -> [f32; 4] {
+        if lerp {
+            let (x, y) = (x - 0.5, y - 0.5);
+            let (x0, y0) = (x.floor(), y.floor());
+            let (fx, fy) = (x - x0, y - y0);
+            let (x0, y0) = (x0 as i32, y0 as i32);
+            let top = mix(self.fetch(x0, y0), self.fetch(x0 + 1, y0), fx);
+            let bottom = mix(self.fetch(x0, y0 + 1), self.fetch(x0 + 1, y0 + 1), fx);
+            mix(top, bottom, fy)
+        } else {
+            self.fetch(x.floor() as i32, y.floor() as i32)
+        }
+    }
+}
+
+/// A vertex after the equivalent of the vertex shader has been run on it.
+#[derive(Clone, Copy)]
+struct ShadedVertex {
+    clip: [f32; 4],
+    varying: [f32; VARYINGS],
+    flat: [f32; 4],
+    atlas_xywh: [f32; 4],
+}
+
+impl ShadedVertex {
+    /// Interpolates towards another vertex. Flat attributes are kept from `self`.
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mut out = *self;
+        out.clip.iter_mut().zip(&other.clip).for_each(|(a, b)| *a += (b - *a) * t);
+        out.varying.iter_mut().zip(&other.varying).for_each(|(a, b)| *a += (b - *a) * t);
+        out
+    }
+
+    /// Signed distance from the near plane. Negative means the vertex is clipped.
+    fn near_distance(&self) -> f32 {
+        self.clip[2] + self.clip[3]
+    }
+
+    fn to_screen(&self, (vx, vy, vw, vh): (i32, i32, i32, i32)) -> ScreenVertex {
+        let inv_w = 1.0 / self.clip[3];
+        // the extra half pixel matches the offset the OpenGL renderer applies to emulate DX's screen space
+        ScreenVertex {
+            x: vx as f32 + (self.clip[0] * inv_w + 1.0) * 0.5 * vw as f32 + 0.5,
+            y: vy as f32 + (1.0 - self.clip[1] * inv_w) * 0.5 * vh as f32 + 0.5,
+            z: (self.clip[2] * inv_w + 1.0) * 0.5,
+            inv_w,
+            varying: self.varying.map(|v| v * inv_w),
+        }
+    }
+}
+
+/// A vertex in window space. Varyings are pre-divided by w for perspective-correct interpolation.
+#[derive(Clone, Copy)]
+struct ScreenVertex {
+    x: f32,
+    y: f32,
+    z: f32,
+    inv_w: f32,
+    varying: [f32; VARYINGS],
+}
+
+impl ScreenVertex {
+    fn lerp(&self, other: &Self, t: f32) -> (f32, f32, [f32; VARYINGS]) {
+        let inv_w = self.inv_w + (other.inv_w - self.inv_w) * t;
+        let mut varying = self.varying;
+        varying.iter_mut().zip(&other.varying).for_each(|(a, b)| *a = (*a + (b - *a) * t) / inv_w);
+        (self.z + (other.z - self.z) * t, inv_w, varying)
+    }
+}
+
+/// The render target of a single draw call.
+struct DrawTarget<'a> {
+    texture: &'a mut Texture,
+    bounds: [i32; 4], // x0, y0, x1, y1 - the viewport clipped to the texture
+    use_zbuf: bool,
+}
+
+/// Per-primitive inputs to the fragment stage, taken from the provoking vertex.
+struct Primitive<'a> {
+    source: &'a Texture,
+    flat: [f32; 4],
+    atlas_xywh: [f32; 4],
+}
+
+impl<'a> Primitive<'a> {
+    fn new(source: &'a Texture, provoking: &ShadedVertex) -> Self {
+        Self { source, flat: provoking.flat, atlas_xywh: provoking.atlas_xywh }
+    }
+}
+
+/// A renderer which rasterizes everything on the CPU, so it needs neither a window nor a graphics context.
+///
+/// It follows the OpenGL renderer and its shaders as closely as it can, including texture and surface
+/// numbering, so its output can be compared against it. There's nothing to present to: frames are only
+/// ever seen by reading pixels back, for example with `get_pixels()`.
+pub struct RendererImpl {
+    textures: Vec<Option<Texture>>,
+    texture_rects: Vec<Option<AtlasRect>>,
+    stock_texture_count: usize,
+    stock_atlas_count: u32,
+    framebuffer: Texture,
+    stored_framebuffer: Option<Texture>,
+    target: Option<u32>, // atlas being drawn to, or None for the framebuffer
+    viewport: (i32, i32, i32, i32),
+    zbuf_24: bool,
+    normalize_normals: bool,
+    zbuf_trashed: bool,
+    white_pixel: AtlasRect,
+
+    model_matrix: [f32; 16],
+    view_matrix: [f32; 16],
+    proj_matrix: [f32; 16],
+    alpha_blending: bool,
+    colour_blending: bool,
+    blend_mode: (BlendType, BlendType),
+    interpolate_pixels: bool,
+    texture_repeat: bool,
+    vsync: Cell<bool>,
+    depth_test: bool,
+    write_depth: bool,
+    culling: bool,
+    fog: Option<Fog>,
+    gouraud: bool,
+    lighting_enabled: bool,
+    ambient_colour: i32,
+    lights: [(bool, Light); 8],
+
+    circle_precision: i32,
+    using_3d: bool,
+    perspective: bool,
+    depth: f32,
+    primitive_2d: PrimitiveBuilder,
+    primitive_3d: PrimitiveBuilder,
+}
+
+impl RendererImpl {
+    pub fn new(options: &RendererOptions, clear_colour: Colour) -> Self {
+        #[rustfmt::skip]
+        let identity_matrix: [f32; 16] = [
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ];
+        let (width, height) = (options.size.0 as i32, options.size.1 as i32);
+        let mut renderer = Self {
+            textures: vec![],
+            texture_rects: vec![],
+            stock_texture_count: 0,
+            stock_atlas_count: 0,
+            framebuffer: Texture::new(width, height, true),
+            stored_framebuffer: None,
+            target: None,
+            viewport: (0, 0, width, height),
+            zbuf_24: options.zbuf_24,
+            normalize_normals: options.normalize_normals,
+            zbuf_trashed: false,
+            white_pixel: Default::default(),
+
+            model_matrix: identity_matrix,
+            view_matrix: identity_matrix,
+            proj_matrix: identity_matrix,
+            alpha_blending: true,
+            colour_blending: true,
+            blend_mode: (BlendType::SrcAlpha, BlendType::InvSrcAlpha),
+            interpolate_pixels: options.interpolate_pixels,
+            texture_repeat: false,
+            vsync: Cell::new(options.vsync),
+            depth_test: false,
+            write_depth: false,
+            culling: false,
+            fog: None,
+            gouraud: true,
+            lighting_enabled: false,
+            ambient_colour: 0,
+            lights: [(false, Light::Directional { direction: [0.0; 3], colour: 0 }); 8],
+
+            circle_precision: 24,
+            using_3d: false,
+            perspective: false,
+            depth: 0.0,
+            primitive_2d: PrimitiveBuilder::new(Default::default(), PrimitiveType::PointList, false),
+            primitive_3d: PrimitiveBuilder::new(Default::default(), PrimitiveType::PointList, false),
+        };
+
+        // Start first frame
+        renderer.setup_frame(clear_colour);
+        renderer
+    }
+
+    fn setup_frame(&mut self, clear_colour: Colour) {
+        let (width, height) = (self.framebuffer.width, self.framebuffer.height);
+        self.set_view(0, 0, width, height, 0.0, 0, 0, width, height);
+        self.clear_view(clear_colour, 1.0);
+    }
+
+    fn get_rect_mut(&mut self, id: AtlasRef) -> Option<&mut AtlasRect> {
+        id.0.try_into()
+            .ok()
+            .and_then(move |id: usize| self.texture_rects.get_mut(id))
+            .and_then(|o: &mut Option<AtlasRect>| o.as_mut())
+    }
+
+    fn target_texture(&self) -> Option<&Texture> {
+        match self.target {
+            Some(id) => self.textures.get(id as usize).and_then(Option::as_ref),
+            None => Some(&self.framebuffer),
+        }
+    }
+
+    /// The area that can currently be drawn to, i.e. the viewport clipped to the target's size.
+    fn bounds(&self, texture: &Texture) -> [i32; 4] {
+        let (x, y, w, h) = self.viewport;
+        [x.max(0), y.max(0), (x + w).min(texture.width).max(0), (y + h).min(texture.height).max(0)]
+    }
+
+    /// Emulates glClear() on the current target, which is limited to the scissor box (here, the viewport).
+    fn clear_target(&mut self, colour: Option<[u8; 4]>, depth: Option<f32>) {
+        let depth = depth.filter(|_| self.target.is_some() || !self.zbuf_trashed);
+        let bounds = match self.target_texture() {
+            Some(texture) => self.bounds(texture),
+            None => return,
+        };
+        let texture = match self.target {
+            Some(id) => self.textures[id as usize].as_mut().unwrap(),
+            None => &mut self.framebuffer,
+        };
+        texture.fill(bounds, colour, depth);
+    }
+
+    fn push_primitive(&mut self, builder: &PrimitiveBuilder) {
+        self.draw_vertices(builder.get_atlas_id(), builder.get_shape(), builder.get_vertices());
+    }
+
+    /// Draws a list of vertices to the current target, textured with the given atlas.
+    fn draw_vertices(&mut self, atlas_id: u32, shape: PrimitiveShape, vertices: &[Vertex]) {
+        if vertices.is_empty() {
+            return
+        }
+
+        // move the target out of self so it can be drawn to while the rest of self is borrowed
+        let mut texture = match self.target {
+            Some(id) => match self.textures.get_mut(id as usize).and_then(Option::take) {
+                Some(texture) => texture,
+                None => return,
+            },
+            None => std::mem::take(&mut self.framebuffer),
+        };
+        let target_copy;
+        let source = if self.target == Some(atlas_id) {
+            // sampling the texture being drawn to is undefined in GL, so just read what was there beforehand
+            target_copy = texture.clone();
+            Some(&target_copy)
+        } else {
+            self.textures.get(atlas_id as usize).and_then(Option::as_ref)
+        };
+        if let Some(source) = source {
+            let mut target = DrawTarget {
+                bounds: self.bounds(&texture),
+                use_zbuf: self.target.is_some() || !self.zbuf_trashed,
+                texture: &mut texture,
+            };
+            self.rasterize(&mut target, source, shape, vertices);
+        }
+        match self.target {
+            Some(id) => self.textures[id as usize] = Some(texture),
+            None => self.framebuffer = texture,
+        }
+    }
+
+    fn rasterize(&self, target: &mut DrawTarget, source: &Texture, shape: PrimitiveShape, vertices: &[Vertex]) {
+        let viewproj = mat4mult(self.view_matrix, self.proj_matrix);
+        let shaded = vertices.iter().map(|v| self.shade_vertex(v, &viewproj)).collect::<Vec<_>>();
+        match shape {
+            PrimitiveShape::Point => {
+                for v in &shaded {
+                    self.raster_point(target, &Primitive::new(source, v), v);
+                }
+            },
+            PrimitiveShape::Line => {
+                for line in shaded.chunks_exact(2) {
+                    self.raster_line(target, &Primitive::new(source, &line[0]), line[0], line[1]);
+                }
+            },
+            PrimitiveShape::Triangle => {
+                for tri in shaded.chunks_exact(3) {
+                    self.raster_triangle(target, &Primitive::new(source, &tri[0]), [tri[0], tri[1], tri[2]]);
+                }
+            },
+        }
+    }
+
+    /// Does the same job as vertex.glsl.
+    fn shade_vertex(&self, vertex: &Vertex, viewproj: &[f32; 16]) -> ShadedVertex {
+        let [x, y, z] = vertex.pos;
+        let world_pos = transform([x, y, z, 1.0], &self.model_matrix);
+        let clip = transform(world_pos, viewproj);
+        let textured = !vertex.tex_coord.iter().any(|c| c.is_nan());
+        let tex_coord = if textured { vertex.tex_coord } else { [0.0; 2] };
+        let mut blend = if textured && !self.colour_blending { [1.0; 4] } else { vertex.blend };
+        let mut flat = [1.0; 4];
+        if self.lighting_enabled {
+            let light_col = self.light_colour(world_pos, vertex.normal);
+            let ambient = split_colour(self.ambient_colour, 1.0);
+            let lit = if self.gouraud { &mut blend } else { &mut flat };
+            for ((c, light), ambient) in lit.iter_mut().zip(light_col).zip(ambient) {
+                *c = *c * light + ambient;
+            }
+        }
+        ShadedVertex {
+            clip,
+            varying: [tex_coord[0], tex_coord[1], blend[0], blend[1], blend[2], blend[3], clip[2]],
+            flat,
+            atlas_xywh: vertex.atlas_xywh,
+        }
+    }
+
+    fn light_colour(&self, world_pos: [f32; 4], normal: [f32; 3]) -> [f32; 3] {
+        let [nx, ny, nz] = normal;
+        let normal = transform([nx, ny, nz, 0.0], &self.model_matrix);
+        let mut normal = [-normal[0], -normal[1], -normal[2]];
+        if self.normalize_normals {
+            normal = normalize(normal);
+        }
+        let mut light_col = [0.0; 3];
+        for (_, light) in self.lights.iter().filter(|(enabled, _)| *enabled) {
+            let (ray, colour) = match *light {
+                Light::Directional { direction, colour } => (direction, split_colour(colour, 1.0)),
+                Light::Point { position, range, colour } => {
+                    let ray = [world_pos[0] - position[0], world_pos[1] - position[1], world_pos[2] - position[2]];
+                    let dist = dot(ray, ray).sqrt();
+                    let falloff = if dist < range { 1.0 / (1.0 + (4.0 / range) * dist) } else { 0.0 };
+                    (ray, split_colour(colour, 1.0).map(|c| c * falloff))
+                },
+            };
+            let intensity = dot(normalize(ray), normal).max(0.0).min(1.0);
+            light_col.iter_mut().zip(colour).for_each(|(c, light)| *c += light * intensity);
+        }
+        light_col
+    }
+
+    fn raster_point(&self, target: &mut DrawTarget, prim: &Primitive, v: &ShadedVertex) {
+        if v.near_distance() < 0.0 {
+            return
+        }
+        let s = v.to_screen(self.viewport);
+        let (x, y) = (s.x.floor() as i32, s.y.floor() as i32);
+        let [x0, y0, x1, y1] = target.bounds;
+        if (x0..x1).contains(&x) && (y0..y1).contains(&y) {
+            self.shade_fragment(target, prim, x, y, s.z, v.varying);
+        }
+    }
+
+    /// Draws the pixels whose centres the line passes by along its major axis, leaving out the last one like
+    /// GL's diamond-exit rule does.
+    fn raster_line(&self, target: &mut DrawTarget, prim: &Primitive, mut a: ShadedVertex, mut b: ShadedVertex) {
+        let (da, db) = (a.near_distance(), b.near_distance());
+        if da < 0.0 && db < 0.0 {
+            return
+        } else if da < 0.0 {
+            a = a.lerp(&b, da / (da - db));
+        } else if db < 0.0 {
+            b = a.lerp(&b, da / (da - db));
+        }
+        let (a, b) = (a.to_screen(self.viewport), b.to_screen(self.viewport));
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let x_major = dx.abs() >= dy.abs();
+        let (start, delta) = if x_major { (a.x, dx) } else { (a.y, dy) };
+        if delta == 0.0 || !delta.is_finite() {
+            return
+        }
+        let [x0, y0, x1, y1] = target.bounds;
+        let (min, max) = if x_major { (x0, x1) } else { (y0, y1) };
+        let (lo, hi) = if delta > 0.0 { (start, start + delta) } else { (start + delta, start) };
+        for i in ((lo - 0.5).floor() as i32).max(min)..((hi + 0.5).ceil() as i32).min(max) {
+            let t = (i as f32 + 0.5 - start) / delta;
+            if !(0.0..1.0).contains(&t) {
+                continue
+            }
+            let (x, y) = if x_major { (i, (a.y + dy * t).floor() as i32) } else { ((a.x + dx * t).floor() as i32, i) };
+            if (x0..x1).contains(&x) && (y0..y1).contains(&y) {
+                let (z, _, varying) = a.lerp(&b, t);
+                self.shade_fragment(target, prim, x, y, z, varying);
+            }
+        }
+    }
+
+    fn raster_triangle(&self, target: &mut DrawTarget, prim: &Primitive, vertices: [ShadedVertex; 3]) {
+        // clip against the near plane, then fan out whatever polygon is left
+        let mut polygon = Vec::with_capacity(4);
+        for i in 0..3 {
+            let (a, b) = (&vertices[i], &vertices[(i + 1) % 3]);
+            let (da, db) = (a.near_distance(), b.near_distance());
+            if da >= 0.0 {
+                polygon.push(a.to_screen(self.viewport));
+            }
+            if (da >= 0.0) != (db >= 0.0) {
+                polygon.push(a.lerp(b, da / (da - db)).to_screen(self.viewport));
+            }
+        }
+        for i in 2..polygon.len() {
+            self.raster_screen_triangle(target, prim, polygon[0], polygon[i - 1], polygon[i]);
+        }
+    }
+
+    fn raster_screen_triangle(
+        &self,
+        target: &mut DrawTarget,
+        prim: &Primitive,
+        a: ScreenVertex,
+        mut b: ScreenVertex,
+        mut c: ScreenVertex,
+    ) {
+        // window space has the same orientation as GL's, so counter-clockwise (positive area) is the front
+        let mut area = edge(&a, &b, c.x, c.y);
+        if area == 0.0 || !area.is_finite() {
+            return
+        }
+        if area < 0.0 {
+            if self.culling {
+                return
+            }
+            std::mem::swap(&mut b, &mut c);
+            area = -area;
+        }
+
+        let [bx0, by0, bx1, by1] = target.bounds;
+        let x0 = (a.x.min(b.x).min(c.x).floor() as i32).max(bx0);
+        let y0 = (a.y.min(b.y).min(c.y).floor() as i32).max(by0);
+        let x1 = (a.x.max(b.x).max(c.x).ceil() as i32).min(bx1);
+        let y1 = (a.y.max(b.y).max(c.y).ceil() as i32).min(by1);
+        for y in y0..y1 {
+            let cy = y as f32 + 0.5;
+            for x in x0..x1 {
+                let cx = x as f32 + 0.5;
+                let (w0, w1, w2) = (edge(&b, &c, cx, cy), edge(&c, &a, cx, cy), edge(&a, &b, cx, cy));
+                if !(covers(w0, &b, &c) && covers(w1, &c, &a) && covers(w2, &a, &b)) {
+                    continue
+                }
+                let (l0, l1, l2) = (w0 / area, w1 / area, w2 / area);
+                let z = l0 * a.z + l1 * b.z + l2 * c.z;
+                let inv_w = l0 * a.inv_w + l1 * b.inv_w + l2 * c.inv_w;
+                let mut varying = [0.0; VARYINGS];
+                for (i, v) in varying.iter_mut().enumerate() {
+                    *v = (l0 * a.varying[i] + l1 * b.varying[i] + l2 * c.varying[i]) / inv_w;
+                }
+                self.shade_fragment(target, prim, x, y, z, varying);
+            }
+        }
+    }
+
+    /// Does the same job as fragment.glsl, followed by the depth test and blending.
+    fn shade_fragment(
+        &self,
+        target: &mut DrawTarget,
+        prim: &Primitive,
+        x: i32,
+        y: i32,
+        z: f32,
+        varying: [f32; VARYINGS],
+    ) {
+        if !(0.0..=1.0).contains(&z) {
+            return
+        }
+        let tex_col = self.sample(prim, varying[0], varying[1]);
+        let mut colour = [0, 1, 2, 3].map(|i| tex_col[i] * varying[2 + i] * prim.flat[i]);
+        if let Some(fog) = &self.fog {
+            let f = ((fog.end - varying[6]) / (fog.end - fog.begin)).max(0.0).min(1.0);
+            let fog_colour = split_colour(fog.colour, 1.0);
+            colour.iter_mut().zip(fog_colour).take(3).for_each(|(c, fog)| *c = fog + (*c - fog) * f);
+        }
+        // alpha test, which the OpenGL renderer ties to the depth test
+        if self.depth_test && colour[3] <= 0.0 {
+            return
+        }
+
+        let index = (y * target.texture.width + x) as usize;
+        if self.depth_test && target.use_zbuf {
+            if let Some(zbuf) = target.texture.zbuf.as_mut() {
+                let z = self.quantize_depth(z);
+                if z > zbuf[index] {
+                    return
+                }
+                if self.write_depth {
+                    zbuf[index] = z;
+                }
+            }
+        }
+
+        let pixel = &mut target.texture.pixels[index * 4..index * 4 + 4];
+        let src = colour.map(|c| c.max(0.0).min(1.0));
+        let out = if self.alpha_blending {
+            let dst = [pixel[0], pixel[1], pixel[2], pixel[3]].map(|c| f32::from(c) / 255.0);
+            let src_factor = blend_factor(self.blend_mode.0, src, dst);
+            let dst_factor = blend_factor(self.blend_mode.1, src, dst);
+            [0, 1, 2, 3].map(|i| src[i] * src_factor[i] + dst[i] * dst_factor[i])
+        } else {
+            src
+        };
+        pixel.copy_from_slice(&out.map(to_unorm));
+    }
+
+    /// Gets the texture colour for a fragment, handling texture_repeat and interpolation like fragment.glsl.
+    fn sample(&self, prim: &Primitive, u: f32, v: f32) -> [f32; 4] {
+        let [ax, ay, aw, ah] = prim.atlas_xywh;
+        let tex = prim.source;
+        if self.texture_repeat {
+            let (sx, sy) = ((u - u.floor()) * aw, (v - v.floor()) * ah);
+            if self.interpolate_pixels {
+                // mix the four nearest pixels, wrapping around the sprite rather than the whole atlas
+                let (fx, fy) = ((sx - 0.5).floor(), (sy - 0.5).floor());
+                let (w, h) = ((aw as i32).max(1), (ah as i32).max(1));
+                let (left, right) = (ax as i32 + (fx as i32).rem_euclid(w), ax as i32 + (fx as i32 + 1).rem_euclid(w));
+                let (top, bottom) = (ay as i32 + (fy as i32).rem_euclid(h), ay as i32 + (fy as i32 + 1).rem_euclid(h));
+                let (mx, my) = (sx - 0.5 - fx, sy - 0.5 - fy);
+                let mix_top = mix(tex.fetch(left, top), tex.fetch(right, top), mx);
+                let mix_bottom = mix(tex.fetch(left, bottom), tex.fetch(right, bottom), mx);
+                mix(mix_top, mix_bottom, my)
+            } else {
+                let (sx, sy) = (sx.max(0.5).min(aw - 0.5), sy.max(0.5).min(ah - 0.5));
+                tex.sample(ax + sx, ay + sy, false)
+            }
+        } else {
+            // clamp to center of edge pixels
+            let (sx, sy) = ((u * aw).max(0.5).min(aw - 0.5), (v * ah).max(0.5).min(ah - 0.5));
+            tex.sample(ax + sx, ay + sy, self.interpolate_pixels)
+        }
+    }
+
+    /// Rounds a depth value to what a 16- or 24-bit depth buffer would store.
+    fn quantize_depth(&self, z: f32) -> f32 {
+        let max = if self.zbuf_24 { 16777215.0 } else { 65535.0 };
+        (f64::from(z) * max).round() as f32 / max as f32
+    }
+}
+
+/// Multiplies a row vector by a mat4, the same way the shaders do
+fn transform(v: [f32; 4], m: &[f32; 16]) -> [f32; 4] {
+    [0, 1, 2, 3].map(|j| v[0] * m[j] + v[1] * m[4 + j] + v[2] * m[8 + j] + v[3] * m[12 + j])
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = dot(v, v).sqrt();
+    if len > 0.0 { v.map(|c| c / len) } else { v }
+}
+
+fn mix(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [0, 1, 2, 3].map(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+fn to_unorm(c: f32) -> u8 {
+    (c.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+/// Edge function: positive if (x, y) is to the left of a -> b in window space.
+fn edge(a: &ScreenVertex, b: &ScreenVertex, x: f32, y: f32) -> f32 {
+    (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+/// Top-left fill rule, so pixels on an edge shared by two triangles only get drawn once.
+fn covers(w: f32, a: &ScreenVertex, b: &ScreenVertex) -> bool {
+    w > 0.0 || (w == 0.0 && ((a.y == b.y && b.x > a.x) || b.y < a.y))
+}
+
+fn blend_factor(bt: BlendType, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+    match bt {
+        BlendType::Zero => [0.0; 4],
+        BlendType::One => [1.0; 4],
+        BlendType::SrcColour => src,
+        BlendType::InvSrcColour => src.map(|c| 1.0 - c),
+        BlendType::SrcAlpha => [src[3]; 4],
+        BlendType::InvSrcAlpha => [1.0 - src[3]; 4],
+        BlendType::DestAlpha => [dst[3]; 4],
+        BlendType::InvDestAlpha => [1.0 - dst[3]; 4],
+        BlendType::DestColour => dst,
+        BlendType::InvDestColour => dst.map(|c| 1.0 - c),
+        BlendType::SrcAlphaSaturate => {
+            let f = src[3].min(1.0 - dst[3]);
+            [f, f, f, 1.0]
+        },
+    }
+}
+
+impl RendererTrait for RendererImpl {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn max_texture_size(&self) -> u32 {
+        MAX_TEXTURE_SIZE
+    }
+
+    fn push_atlases(&mut self, mut atl: AtlasBuilder) -> Result<(), String> {
+        assert!(self.textures.is_empty(), "atlases should be initialized only once");
+
+        let white_pixel_ref =
+            atl.texture(1, 1, 0, 0, Box::new([0xFF, 0xFF, 0xFF, 0xFF])).ok_or("Couldn't pack white_pixel")?;
+        let (packers, mut sprites) = atl.into_inner();
+        self.white_pixel = sprites[white_pixel_ref.0 as usize].0;
+
+        // update primitive buffers with white pixel
+        self.reset_primitive_2d(PrimitiveType::PointList, None);
+        self.reset_primitive_3d(PrimitiveType::PointList, None);
+
+        self.textures = packers
+            .iter()
+            .map(|packer| {
+                let (width, height) = packer.size();
+                Some(Texture::new(width, height, false))
+            })
+            .collect();
+        for (atl_ref, pixels) in &mut sprites {
+            // atlas data is BGRA
+            pixels.chunks_exact_mut(4).for_each(|p| p.swap(0, 2));
+            if let Some(Some(texture)) = self.textures.get_mut(atl_ref.atlas_id as usize) {
+                texture.write(atl_ref.x, atl_ref.y, atl_ref.w, atl_ref.h, pixels);
+            }
+        }
+        self.stock_atlas_count = stock_atlas_count(packers.len());
+
+        // discard pixeldata
+        self.texture_rects = sprites.drain(..).map(|(ar, _)| Some(ar)).collect();
+        self.stock_texture_count = self.texture_rects.len();
+
+        Ok(())
+    }
+
+    fn upload_sprite(
+        &mut self,
+        data: Box<[u8]>,
+        width: i32,
+        height: i32,
+        origin_x: i32,
+        origin_y: i32,
+    ) -> Result<AtlasRef, String> {
+        let atlas_ref = self.create_surface(width, height, false)?;
+        if let Some(rect) = self.get_rect_mut(atlas_ref) {
+            rect.origin_x = origin_x as f32 / width as f32;
+            rect.origin_y = origin_y as f32 / height as f32;
+            let rect = *rect;
+            if let Some(Some(texture)) = self.textures.get_mut(rect.atlas_id as usize) {
+                texture.write(rect.x, rect.y, rect.w, rect.h, &data);
+            }
+        }
+        Ok(atlas_ref)
+    }
+
+    fn duplicate_sprite(&mut self, atlas_ref: AtlasRef) -> Result<AtlasRef, String> {
+        if let Some(rect) = self.get_rect(atlas_ref).cloned() {
+            let sprite = self.create_surface(rect.w, rect.h, false)?;
+            let new_rect = self.get_rect_mut(sprite).unwrap();
+            new_rect.origin_x = rect.origin_x;
+            new_rect.origin_y = rect.origin_y;
+            let new_rect = *new_rect;
+            let pixels = match self.textures.get(rect.atlas_id as usize) {
+                Some(Some(texture)) => texture.read(rect.x, rect.y, rect.w, rect.h),
+                _ => return Err("Failed to duplicate texture! (source texture doesn't exist)".into()),
+            };
+            if let Some(Some(texture)) = self.textures.get_mut(new_rect.atlas_id as usize) {
+                texture.write(new_rect.x, new_rect.y, new_rect.w, new_rect.h, &pixels);
+            }
+            Ok(sprite)
+        } else {
+            Ok(AtlasRef(-1))
+        }
+    }
+
+    fn delete_sprite(&mut self, atlas_ref: AtlasRef) {
+        // this only deletes sprites created with upload_sprite
+        if let Some(rect) = atlas_ref
+            .0
+            .try_into()
+            .ok()
+            .and_then(|id: usize| self.texture_rects.get_mut(id))
+            .and_then(|o: &mut Option<AtlasRect>| o.take())
+        {
+            if rect.atlas_id >= self.stock_atlas_count {
+                self.textures[rect.atlas_id as usize] = None;
+            }
+        }
+    }
+
+    fn set_vsync(&self, vsync: bool) {
+        self.vsync.set(vsync);
+    }
+
+    fn get_vsync(&self) -> bool {
+        self.vsync.get()
+    }
+
+    fn wait_vsync(&self) {}
+
+    fn create_sprite_colour(&mut self, width: i32, height: i32, col: Colour) -> Result<AtlasRef, String> {
+        let atlas_ref = self.create_surface(width, height, false)?;
+        if let Some(rect) = self.get_rect(atlas_ref).copied() {
+            let colour = [col.r, col.g, col.b, 1.0].map(|c| to_unorm(c as f32));
+            if let Some(Some(texture)) = self.textures.get_mut(rect.atlas_id as usize) {
+                texture.fill([0, 0, texture.width, texture.height], Some(colour), None);
+            }
+        }
+        Ok(atlas_ref)
+    }
+
+    fn create_surface(&mut self, width: i32, height: i32, has_zbuffer: bool) -> Result<AtlasRef, String> {
+        if width < 0 || height < 0 || width as u32 > MAX_TEXTURE_SIZE || height as u32 > MAX_TEXTURE_SIZE {
+            return Err(format!("Failed to allocate {}x{} texture", width, height))
+        }
+        let atlas_id = if let Some(id) = self.textures.iter().position(|x| x.is_none()) {
+            id as u32
+        } else {
+            self.textures.push(None);
+            self.textures.len() as u32 - 1
+        };
+        self.textures[atlas_id as usize] = Some(Texture::new(width, height, has_zbuffer));
+        let id = self.texture_rects.len() as i32;
+        self.texture_rects.push(Some(AtlasRect {
+            atlas_id,
+            x: 0,
+            y: 0,
+            w: width,
+            h: height,
+            origin_x: 0.0,
+            origin_y: 0.0,
+        }));
+        Ok(AtlasRef(id))
+    }
+
+    fn set_target(&mut self, atlas_ref: AtlasRef) {
+        if let Some(rect) = self.get_rect(atlas_ref).copied() {
+            if matches!(self.textures.get(rect.atlas_id as usize), Some(Some(_))) {
+                let AtlasRect { x, y, w, h, .. } = rect;
+                self.target = Some(rect.atlas_id);
+                // set viewport here since set_view doesn't
+                self.viewport = (x, y, w, h);
+                self.set_view(x, y, w, h, 0.0, x, y, w, h);
+            }
+        }
+    }
+
+    fn reset_target(&mut self) {
+        self.target = None;
+        let (width, height) = (self.framebuffer.width, self.framebuffer.height);
+        self.set_view(0, 0, width, height, 0.0, 0, 0, width, height);
+    }
+
+    fn copy_surface(
+        &mut self,
+        dest: AtlasRef,
+        mut dest_x: i32,
+        mut dest_y: i32,
+        src: AtlasRef,
+        mut src_x: i32,
+        mut src_y: i32,
+        mut width: i32,
+        mut height: i32,
+    ) {
+        let (src_rect, dest_rect) = match (self.get_rect(src), self.get_rect(dest)) {
+            (Some(src), Some(dest)) => (*src, *dest),
+            _ => return,
+        };
+        // correct coordinates the same way the OpenGL renderer does
+        if src_x < 0 {
+            dest_x -= src_x;
+            width += src_x;
+            src_x = 0;
+        }
+        if src_y < 0 {
+            dest_y -= src_y;
+            height += src_y;
+            src_y = 0;
+        }
+        if src_x + width > src_rect.w {
+            width = src_rect.w - src_x;
+        }
+        if src_y + height > src_rect.h {
+            height = dest_rect.h - src_y;
+        }
+        if dest_x < 0 {
+            src_x -= dest_x;
+            width += dest_x;
+            dest_x = 0;
+        }
+        if dest_y < 0 {
+            src_y -= dest_y;
+            height += dest_y;
+            dest_y = 0;
+        }
+        if dest_x + width > dest_rect.w {
+            width = dest_rect.w - dest_x;
+        }
+        if dest_y + height > dest_rect.h {
+            height = dest_rect.h - dest_y;
+        }
+        if width > 0 && height > 0 {
+            let source = match self.textures.get(src_rect.atlas_id as usize) {
+                Some(Some(texture)) => texture.clone(),
+                _ => return,
+            };
+            if let Some(Some(texture)) = self.textures.get_mut(dest_rect.atlas_id as usize) {
+                texture.blit(&source, (src_x, src_y, width, height), (dest_x, dest_y, width, height));
+            }
+        }
+    }
+
+    fn set_zbuf_trashed(&mut self, trashed: bool) {
+        self.zbuf_trashed = trashed;
+    }
+
+    fn get_zbuf_trashed(&self) -> bool {
+        self.zbuf_trashed
+    }
+
+    fn resize_framebuffer(&mut self, width: u32, height: u32, store: bool) {
+        let mut framebuffer = Texture::new(width as i32, height as i32, true);
+        let old = std::mem::take(&mut self.framebuffer);
+        // copy old fb onto new
+        let copy_width = framebuffer.width.min(old.width);
+        let copy_height = framebuffer.height.min(old.height);
+        framebuffer.blit(&old, (0, 0, copy_width, copy_height), (0, 0, copy_width, copy_height));
+        if let (Some(new_zbuf), Some(old_zbuf)) = (framebuffer.zbuf.as_mut(), old.zbuf.as_ref()) {
+            for y in 0..copy_height {
+                let (new_row, old_row) = ((y * framebuffer.width) as usize, (y * old.width) as usize);
+                new_zbuf[new_row..new_row + copy_width as usize]
+                    .copy_from_slice(&old_zbuf[old_row..old_row + copy_width as usize]);
+            }
+        }
+        self.framebuffer = framebuffer;
+        self.target = None;
+        if store {
+            self.stored_framebuffer = Some(old);
+        }
+    }
+
+    fn get_texture_id(&mut self, atl_ref: AtlasRef) -> i32 {
+        atl_ref.0
+    }
+
+    fn get_texture_from_id(&self, id: i32) -> Option<AtlasRef> {
+        Some(AtlasRef(id))
+    }
+
+    fn get_texture_rects(&self) -> Vec<Option<AtlasRect>> {
+        self.texture_rects[self.stock_texture_count..].to_vec()
+    }
+
+    fn set_texture_rects(&mut self, rects: &[Option<AtlasRect>]) {
+        self.texture_rects.truncate(self.stock_texture_count);
+        self.texture_rects.extend_from_slice(rects);
+    }
+
+    fn dump_sprite_part(&self, atlas_ref: AtlasRef, part_x: i32, part_y: i32, part_w: i32, part_h: i32) -> Box<[u8]> {
+        let rect = match self.get_rect(atlas_ref) {
+            Some(rect) => rect,
+            None => return Box::new([]),
+        };
+        self.textures[rect.atlas_id as usize].as_ref().expect("Trying to dump nonexistent sprite").read(
+            rect.x + part_x,
+            rect.y + part_y,
+            part_w,
+            part_h,
+        )
+    }
+
+    fn get_pixels(&self, x: i32, y: i32, w: i32, h: i32) -> Box<[u8]> {
+        self.framebuffer.read(x, y, w, h)
+    }
+
+    fn stored_pixels(&self) -> Box<[u8]> {
+        self.stored_framebuffer.as_ref().unwrap_or(&self.framebuffer).pixels.clone().into_boxed_slice()
+    }
+
+    fn stored_zbuffer(&self) -> Box<[f32]> {
+        let fb = self.stored_framebuffer.as_ref().unwrap_or(&self.framebuffer);
+        match &fb.zbuf {
+            Some(zbuf) => zbuf.clone().into_boxed_slice(),
+            None => vec![1.0; (fb.width * fb.height) as usize].into_boxed_slice(),
+        }
+    }
+
+    fn set_stored(&mut self, rgba: Box<[u8]>, zbuf: Box<[f32]>, fb_w: u32, fb_h: u32) {
+        self.stored_framebuffer = Some(Texture {
+            width: fb_w as i32,
+            height: fb_h as i32,
+            pixels: rgba.into_vec(),
+            zbuf: Some(zbuf.into_vec()),
+        });
+    }
+
+    fn dump_dynamic_textures(&self) -> Vec<Option<SavedTexture>> {
+        self.textures
+            .iter()
+            .skip(self.stock_atlas_count as usize)
+            .map(|texture| texture.as_ref().map(Texture::to_saved))
+            .collect()
+    }
+
+    fn upload_dynamic_textures(&mut self, textures: &[Option<SavedTexture>]) {
+        self.textures.truncate(self.stock_atlas_count as usize);
+        self.textures.resize(self.stock_atlas_count as usize, None);
+        self.textures.extend(textures.iter().map(|tex| tex.as_ref().map(Texture::from_saved)));
+    }
+
+    fn get_rect(&self, id: AtlasRef) -> Option<&AtlasRect> {
+        id.0.try_into()
+            .ok()
+            .and_then(|id: usize| self.texture_rects.get(id))
+            .and_then(|o: &Option<AtlasRect>| o.as_ref())
+    }
+
+    fn draw_sprite_general(
+        &mut self,
+        texture: AtlasRef,
+        part_x: f64,
+        part_y: f64,
+        part_w: f64,
+        part_h: f64,
+        x: f64,
+        y: f64,
+        xscale: f64,
+        yscale: f64,
+        angle: f64,
+        col1: i32,
+        col2: i32,
+        col3: i32,
+        col4: i32,
+        alpha: f64,
+        use_origin: bool,
+    ) {
+        let atlas_ref = match self.get_rect(texture) {
+            Some(rect) => *rect,
+            None => return,
+        };
+
+        self.set_texture_repeat(false);
+
+        // get angle
+        let angle = -angle.to_radians();
+        let angle_sin = angle.sin();
+        let angle_cos = angle.cos();
+
+        // get real width of drawn sprite
+        let width: f64 = xscale * part_w;
+        let height: f64 = yscale * part_h;
+        // calculate pre-rotation corner offsets from sprite origin
+        // incl. subtraction 0.5 from left and top (GM does this in an attempt to combat the DX half-pixel offset)
+        let (left, top): (f64, f64) = if use_origin {
+            (-width * f64::from(atlas_ref.origin_x) - 0.5, -height * f64::from(atlas_ref.origin_y) - 0.5)
+        } else {
+            (-0.5, -0.5)
+        };
+        let right: f64 = left + width;
+        let bottom: f64 = top + height;
+
+        // get texture corners
+        let tex_left = part_x / f64::from(atlas_ref.w);
+        let tex_top = part_y / f64::from(atlas_ref.h);
+        let tex_right = tex_left + part_w / f64::from(atlas_ref.w);
+        let tex_bottom = tex_top + part_h / f64::from(atlas_ref.h);
+
+        let (tex_left, tex_top, tex_right, tex_bottom) =
+            (tex_left as f32, tex_top as f32, tex_right as f32, tex_bottom as f32);
+
+        let normal = [0.0, 0.0, 0.0];
+        let depth = self.depth;
+
+        // rotate around draw origin
+        let rotate = |xoff, yoff| {
+            [(x + xoff * angle_cos - yoff * angle_sin) as f32, (y + yoff * angle_cos + xoff * angle_sin) as f32, depth]
+        };
+
+        // push the vertices
+        self.push_primitive(
+            PrimitiveBuilder::new(atlas_ref, PrimitiveType::TriFan, true)
+                .push_vertex(rotate(left, top), [tex_left, tex_top], split_colour(col1, alpha), normal)
+                .push_vertex(rotate(right, top), [tex_right, tex_top], split_colour(col2, alpha), normal)
+                .push_vertex(rotate(right, bottom), [tex_right, tex_bottom], split_colour(col3, alpha), normal)
+                .push_vertex(rotate(left, bottom), [tex_left, tex_bottom], split_colour(col4, alpha), normal),
+        );
+    }
+
+    fn draw_sprite_pos(
+        &mut self,
+        texture: AtlasRef,
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        x3: f64,
+        y3: f64,
+        x4: f64,
+        y4: f64,
+        alpha: f64,
+    ) {
+        let atlas_ref = match self.get_rect(texture) {
+            Some(rect) => *rect,
+            None => return,
+        };
+
+        self.set_texture_repeat(false);
+
+        let normal = [0.0, 0.0, 0.0];
+        let depth = self.depth;
+        let colour = split_colour(0xffffff, alpha);
+
+        // correct for gm offset
+        let correct = |xoff: f64, yoff: f64| [(xoff - 0.5) as f32, (yoff - 0.5) as f32, depth];
+
+        // push the vertices
+        self.push_primitive(
+            PrimitiveBuilder::new(atlas_ref, PrimitiveType::TriFan, true)
+                .push_vertex(correct(x1, y1), [0.0, 0.0], colour, normal)
+                .push_vertex(correct(x2, y2), [1.0, 0.0], colour, normal)
+                .push_vertex(correct(x3, y3), [1.0, 1.0], colour, normal)
+                .push_vertex(correct(x4, y4), [0.0, 1.0], colour, normal),
+        );
+    }
+
+    fn draw_rectangle(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, colour: i32, alpha: f64) {
+        self.draw_rectangle_gradient(x1, y1, x2, y2, colour, colour, colour, colour, alpha, false);
+    }
+
+    fn draw_rectangle_outline(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, colour: i32, alpha: f64) {
+        self.draw_rectangle_gradient(x1, y1, x2, y2, colour, colour, colour, colour, alpha, true);
+    }
+
+    fn draw_rectangle_gradient(
+        &mut self,
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        c1: i32,
+        c2: i32,
+        c3: i32,
+        c4: i32,
+        alpha: f64,
+        outline: bool,
+    ) {
+        let (x1, x2) = if x2 < x1 { (x2, x1) } else { (x1, x2) };
+        let (y1, y2) = if y2 < y1 { (y2, y1) } else { (y1, y2) };
+        let x2 = if x2 == x2.floor() { x2 + 0.01 } else { x2 };
+        let y2 = if y2 == y2.floor() { y2 + 0.01 } else { y2 };
+        self.push_primitive(
+            ShapeBuilder::new(outline, self.white_pixel, alpha, self.depth)
+                .push_point(x1, y1, c1)
+                .push_point(x2, y1, c2)
+                .push_point(x2, y2, c3)
+                .push_point(x1, y2, c4)
+                .build(),
+        );
+    }
+
+    fn draw_point(&mut self, x: f64, y: f64, colour: i32, alpha: f64) {
+        self.draw_vertices(self.white_pixel.atlas_id, PrimitiveShape::Point, &[Vertex {
+            pos: [x as f32, y as f32, self.depth],
+            tex_coord: [f32::NAN; 2],
+            blend: split_colour(colour, alpha),
+            atlas_xywh: self.white_pixel.into(),
+            normal: [0.0, 0.0, 0.0],
+        }]);
+    }
+
+    fn draw_line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, width: Option<f64>, c1: i32, c2: i32, alpha: f64) {
+        if let Some(width) = width {
+            let length = (x2 - x1).hypot(y2 - y1);
+            // on the off chance that they're in different points but the length is still somehow 0, check length
+            if length != 0.0 {
+                // calculate corners
+                let width_x = (y2 - y1) * (width / 2.0) / length;
+                let width_y = (x2 - x1) * (width / 2.0) / length;
+                // actually push the rectangle
+                self.push_primitive(
+                    ShapeBuilder::new(false, self.white_pixel, alpha, self.depth)
+                        .push_point(x1 - width_x, y1 + width_y, c1)
+                        .push_point(x1 + width_x, y1 - width_y, c1)
+                        .push_point(x2 + width_x, y2 - width_y, c2)
+                        .push_point(x2 - width_x, y2 + width_y, c2)
+                        .build(),
+                );
+            }
+        } else {
+            self.push_primitive(
+                ShapeBuilder::new(true, self.white_pixel, alpha, self.depth)
+                    .push_point(x1, y1, c1)
+                    .push_point(x2, y2, c2)
+                    .build(),
+            );
+        }
+    }
+
+    fn draw_triangle(
+        &mut self,
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        x3: f64,
+        y3: f64,
+        c1: i32,
+        c2: i32,
+        c3: i32,
+        alpha: f64,
+        outline: bool,
+    ) {
+        self.push_primitive(
+            ShapeBuilder::new(outline, self.white_pixel, alpha, self.depth)
+                .push_point(x1, y1, c1)
+                .push_point(x2, y2, c2)
+                .push_point(x3, y3, c3)
+                .build(),
+        );
+    }
+
+    fn draw_ellipse(&mut self, x: f64, y: f64, rad_x: f64, rad_y: f64, c1: i32, c2: i32, alpha: f64, outline: bool) {
+        let mut builder = ShapeBuilder::new(outline, self.white_pixel, alpha, self.depth);
+        if !outline {
+            builder.push_point(x, y, c1);
+        }
+        for i in 0..=self.circle_precision {
+            let angle = f64::from(i) * 2.0 * PI / f64::from(self.circle_precision);
+            builder.push_point(x + rad_x * angle.cos(), y + rad_y * angle.sin(), c2);
+        }
+        self.push_primitive(builder.build());
+    }
+
+    fn draw_roundrect(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, c1: i32, c2: i32, alpha: f64, outline: bool) {
+        let x2 = if x2 == x2.floor() { x2 + 0.01 } else { x2 };
+        let y2 = if y2 == y2.floor() { y2 + 0.01 } else { y2 };
+        let xcenter = (x1 + x2) / 2.0;
+        let ycenter = (y1 + y2) / 2.0;
+        let width = (x2 - x1).abs();
+        let height = (y2 - y1).abs();
+        let rad_x = width.min(10.0) / 2.0;
+        let rad_y = height.min(10.0) / 2.0;
+        let rect_half_w = (width / 2.0 - rad_x).max(0.0);
+        let rect_half_h = (height / 2.0 - rad_y).max(0.0);
+        let mut builder = ShapeBuilder::new(outline, self.white_pixel, alpha, self.depth);
+        if !outline {
+            builder.push_point(xcenter, ycenter, c1);
+        }
+        let quarter_circle = self.circle_precision / 4;
+        for quad in 0..4 {
+            let circle_x = xcenter + if quad == 0 || quad == 3 { rect_half_w } else { -rect_half_w };
+            let circle_y = ycenter + if quad < 2 { rect_half_h } else { -rect_half_h };
+            for i in quarter_circle * quad..=quarter_circle * (quad + 1) {
+                let angle = f64::from(i) * 2.0 * PI / f64::from(self.circle_precision);
+                builder.push_point(circle_x + rad_x * angle.cos(), circle_y + rad_y * angle.sin(), c2);
+            }
+        }
+        self.push_primitive(builder.push_point(xcenter + rect_half_w + rad_x, ycenter + rect_half_h, c2).build());
+    }
+
+    fn set_circle_precision(&mut self, prec: i32) {
+        self.circle_precision = (prec.max(4).min(64) >> 2) << 2;
+    }
+
+    fn get_circle_precision(&self) -> i32 {
+        self.circle_precision
+    }
+
+    fn reset_primitive_2d(&mut self, ptype: PrimitiveType, atlas_ref: Option<AtlasRef>) {
+        let ar = atlas_ref.and_then(|ar| self.get_rect(ar).copied());
+        self.primitive_2d = PrimitiveBuilder::new(ar.unwrap_or(self.white_pixel), ptype, ar.is_some());
+    }
+
+    fn vertex_2d(&mut self, x: f64, y: f64, xtex: f64, ytex: f64, col: i32, alpha: f64) {
+        self.primitive_2d.push_vertex(
+            [x as f32, y as f32, self.depth],
+            [xtex as f32, ytex as f32],
+            split_colour(col, alpha),
+            [0.0, 0.0, 0.0],
+        );
+    }
+
+    fn draw_primitive_2d(&mut self) {
+        // cloned to satisfy the borrow checker
+        let primitive = self.primitive_2d.clone();
+        self.push_primitive(&primitive);
+    }
+
+    fn get_primitive_2d(&self) -> PrimitiveBuilder {
+        self.primitive_2d.clone()
+    }
+
+    fn set_primitive_2d(&mut self, prim: PrimitiveBuilder) {
+        self.primitive_2d = prim;
+    }
+
+    fn reset_primitive_3d(&mut self, ptype: PrimitiveType, atlas_ref: Option<AtlasRef>) {
+        let ar = atlas_ref.and_then(|ar| self.get_rect(ar).copied());
+        self.primitive_3d = PrimitiveBuilder::new(ar.unwrap_or(self.white_pixel), ptype, ar.is_some());
+    }
+
+    fn vertex_3d(
+        &mut self,
+        x: f64,
+        y: f64,
+        z: f64,
+        nx: f64,
+        ny: f64,
+        nz: f64,
+        xtex: f64,
+        ytex: f64,
+        col: i32,
+        alpha: f64,
+    ) {
+        self.primitive_3d.push_vertex(
+            [x as f32, y as f32, z as f32],
+            [xtex as f32, ytex as f32],
+            split_colour(col, alpha),
+            [nx as f32, ny as f32, nz as f32],
+        );
+    }
+
+    fn draw_primitive_3d(&mut self) {
+        // See draw_primitive_2d.
+        let primitive = self.primitive_3d.clone();
+        self.push_primitive(&primitive);
+    }
+
+    fn get_primitive_3d(&self) -> PrimitiveBuilder {
+        self.primitive_3d.clone()
+    }
+
+    fn set_primitive_3d(&mut self, prim: PrimitiveBuilder) {
+        self.primitive_3d = prim;
+    }
+
+    fn extend_buffers(&self, buf: &mut VertexBuffer) {
+        let verts = self.primitive_3d.get_vertices();
+        match self.primitive_3d.get_shape() {
+            PrimitiveShape::Point => buf.points.extend_from_slice(verts),
+            PrimitiveShape::Line => buf.lines.extend_from_slice(&verts[..verts.len() / 2 * 2]),
+            PrimitiveShape::Triangle => buf.tris.extend_from_slice(&verts[..verts.len() / 3 * 3]),
+        }
+    }
+
+    fn draw_buffers(&mut self, atlas_ref: Option<AtlasRef>, buf: &VertexBuffer) {
+        let atlas_id = atlas_ref.and_then(|ar| self.get_rect(ar).copied()).unwrap_or(self.white_pixel).atlas_id;
+        self.draw_vertices(atlas_id, PrimitiveShape::Point, &buf.points);
+        self.draw_vertices(atlas_id, PrimitiveShape::Line, &buf.lines);
+        self.draw_vertices(atlas_id, PrimitiveShape::Triangle, &buf.tris);
+    }
+
+    fn get_alpha_blending(&self) -> bool {
+        self.alpha_blending
+    }
+
+    fn set_alpha_blending(&mut self, alphablend: bool) {
+        self.alpha_blending = alphablend;
+    }
+
+    fn get_colour_blending(&self) -> bool {
+        self.colour_blending
+    }
+
+    fn set_colour_blending(&mut self, modulate: bool) {
+        self.colour_blending = modulate;
+    }
+
+    fn get_blend_mode(&self) -> (BlendType, BlendType) {
+        self.blend_mode
+    }
+
+    fn set_blend_mode(&mut self, src: BlendType, dst: BlendType) {
+        self.blend_mode = (src, dst);
+    }
+
+    fn get_pixel_interpolation(&self) -> bool {
+        self.interpolate_pixels
+    }
+
+    fn set_pixel_interpolation(&mut self, lerping: bool) {
+        self.interpolate_pixels = lerping;
+    }
+
+    fn get_texture_repeat(&self) -> bool {
+        self.texture_repeat
+    }
+
+    fn set_texture_repeat(&mut self, repeat: bool) {
+        self.texture_repeat = repeat;
+    }
+
+    /// Everything is drawn as soon as it's submitted, so there's never anything queued.
+    fn flush_queue(&mut self) {}
+
+    fn set_view_matrix(&mut self, view: [f32; 16]) {
+        self.view_matrix = view;
+    }
+
+    fn set_viewproj_matrix(&mut self, view: [f32; 16], proj: [f32; 16]) {
+        self.view_matrix = view;
+        self.proj_matrix = proj;
+    }
+
+    fn get_model_matrix(&self) -> [f32; 16] {
+        self.model_matrix
+    }
+
+    fn set_model_matrix(&mut self, model: [f32; 16]) {
+        self.model_matrix = model;
+    }
+
+    fn mult_model_matrix(&mut self, model: [f32; 16]) {
+        self.model_matrix = mat4mult(self.model_matrix, model);
+    }
+
+    fn set_projection_ortho(&mut self, x: f64, y: f64, w: f64, h: f64, angle: f64) {
+        #[rustfmt::skip]
+        let proj_matrix: [f32; 16] = {
+            // Squish to screen, flip vertically, and constrain z to range 1 - 32000
+            [
+                2.0 / w as f32, 0.0,             0.0,            0.0,
+                0.0,            -2.0 / h as f32, 0.0,            0.0,
+                0.0,            0.0,             1.0 / 31999.0,  0.0,
+                0.0,            0.0,             -1.0 / 31999.0, 1.0,
+            ]
+        };
+
+        self.set_viewproj_matrix(make_view_matrix(x, y, -16000.0, w, h, angle), proj_matrix);
+    }
+
+    fn set_projection_perspective(&mut self, x: f64, y: f64, w: f64, h: f64, angle: f64) {
+        #[rustfmt::skip]
+        let proj_matrix: [f32; 16] = {
+            // Squish to screen, flip vertically, and constrain z to range 1 - 32000
+            [
+                2.0, 0.0,                  0.0,                0.0,
+                0.0, 2.0 * (w / h) as f32, 0.0,                0.0,
+                0.0, 0.0,                  32000.0 / 31999.0,  1.0,
+                0.0, 0.0,                  -32000.0 / 31999.0, 0.0,
+            ]
+        };
+
+        self.set_viewproj_matrix(make_view_matrix(x, y, -w, w, h, angle), proj_matrix);
+    }
+
+    fn set_view(
+        &mut self,
+        src_x: i32,
+        src_y: i32,
+        src_w: i32,
+        src_h: i32,
+        src_angle: f64,
+        port_x: i32,
+        port_y: i32,
+        port_w: i32,
+        port_h: i32,
+    ) {
+        // DX8's viewport function doesn't do anything if a surface is set as the draw target, so emulate that
+        if self.target.is_none() && port_x >= 0 && port_y >= 0 && port_w >= 0 && port_h >= 0 {
+            self.viewport = (port_x, port_y, port_w, port_h);
+        }
+        if self.using_3d && self.perspective {
+            self.set_projection_perspective(src_x.into(), src_y.into(), src_w.into(), src_h.into(), src_angle);
+        } else {
+            self.set_projection_ortho(src_x.into(), src_y.into(), src_w.into(), src_h.into(), src_angle);
+        }
+    }
+
+    fn clear_view(&mut self, colour: Colour, alpha: f64) {
+        let colour = [colour.r, colour.g, colour.b, alpha].map(|c| to_unorm(c as f32));
+        self.clear_target(Some(colour), Some(1.0));
+    }
+
+    fn clear_view_no_zbuf(&mut self, colour: Colour, alpha: f64) {
+        let colour = [colour.r, colour.g, colour.b, alpha].map(|c| to_unorm(c as f32));
+        self.clear_target(Some(colour), None);
+    }
+
+    fn clear_zbuf(&mut self) {
+        if self.using_3d {
+            self.clear_target(None, Some(1.0));
+        }
+    }
+
+    fn get_3d(&self) -> bool {
+        self.using_3d
+    }
+
+    fn set_3d(&mut self, use_3d: bool) {
+        self.using_3d = use_3d;
+        self.set_depth_test(use_3d);
+        self.set_perspective(use_3d);
+    }
+
+    fn get_depth(&self) -> f32 {
+        self.depth
+    }
+
+    fn set_depth(&mut self, depth: f32) {
+        self.depth = if self.using_3d { depth.max(-16000.0).min(16000.0) } else { 0.0 };
+    }
+
+    fn get_depth_test(&self) -> bool {
+        self.depth_test
+    }
+
+    fn set_depth_test(&mut self, depth_test: bool) {
+        self.depth_test = depth_test && self.using_3d;
+    }
+
+    fn get_write_depth(&self) -> bool {
+        self.write_depth
+    }
+
+    fn set_write_depth(&mut self, write_depth: bool) {
+        self.write_depth = write_depth;
+    }
+
+    fn get_culling(&self) -> bool {
+        self.culling
+    }
+
+    fn set_culling(&mut self, culling: bool) {
+        self.culling = culling;
+    }
+
+    fn get_perspective(&self) -> bool {
+        self.perspective
+    }
+
+    fn set_perspective(&mut self, perspective: bool) {
+        self.perspective = perspective;
+    }
+
+    fn get_fog(&self) -> Option<Fog> {
+        self.fog.clone()
+    }
+
+    fn set_fog(&mut self, fog: Option<Fog>) {
+        self.fog = fog;
+    }
+
+    fn get_gouraud(&self) -> bool {
+        self.gouraud
+    }
+
+    fn set_gouraud(&mut self, gouraud: bool) {
+        self.gouraud = gouraud;
+    }
+
+    fn get_lighting_enabled(&self) -> bool {
+        self.lighting_enabled
+    }
+
+    fn set_lighting_enabled(&mut self, enabled: bool) {
+        self.lighting_enabled = enabled;
+    }
+
+    fn get_ambient_colour(&self) -> i32 {
+        self.ambient_colour
+    }
+
+    fn set_ambient_colour(&mut self, colour: i32) {
+        self.ambient_colour = colour;
+    }
+
+    fn get_lights(&self) -> [(bool, Light); 8] {
+        self.lights
+    }
+
+    fn set_lights(&mut self, lights: [(bool, Light); 8]) {
+        self.lights = lights;
+    }
+
+    fn set_light_enabled(&mut self, id: usize, enabled: bool) {
+        self.lights[id].0 = enabled;
+    }
+
+    fn set_light(&mut self, id: usize, light: Light) {
+        self.lights[id].1 = light;
+    }
+
+    /// There's no window to present to, so frames only exist in the framebuffer.
+    fn present(&mut self, _window_width: u32, _window_height: u32, _scaling: Scaling) {}
+
+    fn draw_stored(&mut self, x: i32, y: i32, w: u32, h: u32) {
+        if w == 0 || h == 0 {
+            return
+        }
+        if let Some(stored) = &self.stored_framebuffer {
+            self.framebuffer.blit(stored, (0, 0, stored.width, stored.height), (x, y, w as i32, h as i32));
+        }
+    }
+
+    fn stored_size(&self) -> (u32, u32) {
+        let framebuffer = self.stored_framebuffer.as_ref().unwrap_or(&self.framebuffer);
+        (framebuffer.width as u32, framebuffer.height as u32)
+    }
+
+    fn finish(&mut self, window_width: u32, window_height: u32, clear_colour: Colour) {
+        // Present screen
+        self.present(window_width, window_height, Scaling::Fixed(1.0));
+
+        // Start next frame
+        self.setup_frame(clear_colour)
+    }
+}
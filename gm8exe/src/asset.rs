@@ -81,12 +81,12 @@ impl From<(u32, u32)> for Error {
 }
 
 #[inline(always)]
-fn assert_ver(got: u32, expected: u32) -> Result<(), Error> {
+pub(crate) fn assert_ver(got: u32, expected: u32) -> Result<(), Error> {
     if got != expected { Err(Error::VersionError { expected, got }) } else { Ok(()) }
 }
 
 #[inline(always)]
-fn assert_ver_multiple(got: u32, expected: &[u32]) -> Result<(), Error> {
+pub(crate) fn assert_ver_multiple(got: u32, expected: &[u32]) -> Result<(), Error> {
     if expected.contains(&got) { Ok(()) } else { Err(Error::VersionError { expected: expected[0], got }) }
 }
 
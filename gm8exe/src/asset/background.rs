@@ -1,5 +1,8 @@
 use crate::{
-    asset::{assert_ver, Asset, Error, PascalString, ReadChunk, ReadPascalString, WritePascalString},
+    asset::{
+        assert_ver, sprite::{apply_transparency, remove_transparency}, Asset, Error, PascalString, ReadChunk, ReadPascalString,
+        WritePascalString,
+    },
     GameVersion,
 };
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
@@ -7,6 +10,7 @@ use std::io::{self, Read};
 
 pub const VERSION1: u32 = 710;
 pub const VERSION2: u32 = 800;
+pub const VERSION_PRE_8_0: u32 = 543;
 
 pub struct Background {
     /// The asset name present in GML and the editor.
@@ -25,18 +29,33 @@ pub struct Background {
 }
 
 impl Asset for Background {
-    fn deserialize_exe(mut reader: impl Read, _version: GameVersion, strict: bool) -> Result<Self, Error> {
+    fn deserialize_exe(mut reader: impl Read, version: GameVersion, strict: bool) -> Result<Self, Error> {
         let name = reader.read_pas_string()?;
 
-        let version1 = reader.read_u32::<LE>()?;
-        let version2 = reader.read_u32::<LE>()?;
-        if strict {
-            assert_ver(version1, VERSION1)?;
-            assert_ver(version2, VERSION2)?;
+        if version.is_pre_8_0() {
+            // there's only one version before 8.0
+            reader.read_u32::<LE>()?;
+        } else {
+            let version1 = reader.read_u32::<LE>()?;
+            let version2 = reader.read_u32::<LE>()?;
+            if strict {
+                assert_ver(version1, VERSION1)?;
+                assert_ver(version2, VERSION2)?;
+            }
         }
 
         let width = reader.read_u32::<LE>()?;
         let height = reader.read_u32::<LE>()?;
+
+        // before 8.0, images have no alpha channel, and their transparency settings are stored instead
+        let transparency = if version.is_pre_8_0() {
+            let transparent = reader.read_u32::<LE>()? != 0;
+            let smooth_edges = reader.read_u32::<LE>()? != 0;
+            let _preload = reader.read_u32::<LE>()?;
+            Some((transparent, smooth_edges))
+        } else {
+            None
+        };
         if width > 0 && height > 0 {
             let len = reader.read_u32::<LE>()? as usize;
 
@@ -45,20 +64,43 @@ impl Asset for Background {
                 return Err(Error::MalformedData)
             }
 
-            let data = Some(reader.read_chunk(len)?.into_boxed_slice());
-            Ok(Background { name, width, height, data })
+            let mut data = reader.read_chunk(len)?;
+            if let Some((transparent, smooth_edges)) = transparency {
+                apply_transparency(&mut data, width, height, transparent, smooth_edges);
+            }
+            Ok(Background { name, width, height, data: Some(data.into_boxed_slice()) })
         } else {
             Ok(Background { name, width: 0, height: 0, data: None })
         }
     }
 
-    fn serialize_exe(&self, mut writer: impl io::Write, _version: GameVersion) -> io::Result<()> {
+    fn serialize_exe(&self, mut writer: impl io::Write, version: GameVersion) -> io::Result<()> {
         writer.write_pas_string(&self.name)?;
-        writer.write_u32::<LE>(VERSION1)?;
-        writer.write_u32::<LE>(VERSION2)?;
+        if version.is_pre_8_0() {
+            writer.write_u32::<LE>(VERSION_PRE_8_0)?;
+        } else {
+            writer.write_u32::<LE>(VERSION1)?;
+            writer.write_u32::<LE>(VERSION2)?;
+        }
         writer.write_u32::<LE>(self.width)?;
         writer.write_u32::<LE>(self.height)?;
-        if let Some(pixeldata) = &self.data {
+        if version.is_pre_8_0() {
+            // before 8.0 there's no alpha channel, and the settings it's made from are stored instead
+            let (data, transparent, smooth_edges) = match &self.data {
+                Some(data) => {
+                    let (data, transparent, smooth_edges) = remove_transparency(data, self.width, self.height);
+                    (Some(data), transparent, smooth_edges)
+                },
+                None => (None, false, false),
+            };
+            writer.write_u32::<LE>(transparent.into())?;
+            writer.write_u32::<LE>(smooth_edges.into())?;
+            writer.write_u32::<LE>(1)?; // preload
+            if let Some(pixeldata) = data {
+                writer.write_u32::<LE>(pixeldata.len() as u32)?;
+                writer.write_all(&pixeldata)?;
+            }
+        } else if let Some(pixeldata) = &self.data {
             writer.write_u32::<LE>(pixeldata.len() as u32)?; // TODO: safety. also grep for casts
             writer.write_all(&pixeldata)?;
         }
@@ -6,6 +6,8 @@ use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use std::io::{self, Read};
 
 pub const VERSION: u32 = 800;
+/// Fonts from before GameMaker 8.0 are laid out the same way, and only the version number differs.
+pub const VERSION_PRE_8_0: u32 = 540;
 
 pub struct Font {
     /// The asset name present in GML and the editor.
@@ -62,7 +64,7 @@ impl Asset for Font {
 
         let ver = reader.read_u32::<LE>()?;
         if strict {
-            assert_ver(ver, VERSION)?;
+            assert_ver(ver, if version.is_pre_8_0() { VERSION_PRE_8_0 } else { VERSION })?;
         }
 
         let sys_name = reader.read_pas_string()?;
@@ -73,13 +75,13 @@ impl Asset for Font {
         let range_end = reader.read_u32::<LE>()?;
 
         let (aa_level, charset) = match version {
-            GameVersion::GameMaker8_0 => (0, 0),
             GameVersion::GameMaker8_1 => {
                 let aa_level = (range_start & 0xFF000000) >> 24;
                 let charset = (range_start & 0x00FF0000) >> 16;
                 range_start &= 0x0000FFFF;
                 (aa_level, charset)
             },
+            _ => (0, 0),
         };
 
         let mut dmap = [0u32; 0x600];
@@ -110,17 +112,20 @@ impl Asset for Font {
 
     fn serialize_exe(&self, mut writer: impl io::Write, version: GameVersion) -> io::Result<()> {
         writer.write_pas_string(&self.name)?;
-        writer.write_u32::<LE>(VERSION)?;
+        writer.write_u32::<LE>(if version.is_pre_8_0() { VERSION_PRE_8_0 } else { VERSION })?;
         writer.write_pas_string(&self.sys_name)?;
         writer.write_u32::<LE>(self.size)?;
         writer.write_u32::<LE>(self.bold.into())?;
         writer.write_u32::<LE>(self.italic.into())?;
         match version {
-            GameVersion::GameMaker8_0 => writer.write_u32::<LE>(self.range_start)?,
             GameVersion::GameMaker8_1 => writer
                 .write_u32::<LE>(self.range_start | ((self.aa_level % 0x100) << 24) | ((self.charset % 0x100) << 16))?,
+            _ => writer.write_u32::<LE>(self.range_start)?,
         }
         writer.write_u32::<LE>(self.range_end)?;
+        for val in self.dmap.iter() {
+            writer.write_u32::<LE>(*val)?;
+        }
         writer.write_u32::<LE>(self.map_width)?;
         writer.write_u32::<LE>(self.map_height)?;
         writer.write_u32::<LE>(self.pixel_map.len() as u32)?; // TODO: len as u32
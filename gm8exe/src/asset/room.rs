@@ -139,8 +139,8 @@ impl Asset for Room {
         let persistent = reader.read_u32::<LE>()? != 0;
         let bg_colour = reader.read_u32::<LE>()?.into();
         let (clear_screen, clear_region) = match (version, reader.read_u32::<LE>()?) {
-            (GameVersion::GameMaker8_0, x) => (x != 0, true),
             (GameVersion::GameMaker8_1, x) => ((x & 0b01) != 0, (x & 0b10) == 0),
+            (_, x) => (x != 0, true),
         };
         let creation_code = reader.read_pas_string()?;
 
@@ -247,7 +247,11 @@ impl Asset for Room {
 
     fn serialize_exe(&self, mut writer: impl io::Write, version: GameVersion) -> io::Result<()> {
         writer.write_pas_string(&self.name)?;
-        writer.write_u32::<LE>(VERSION)?;
+        writer.write_u32::<LE>(match (self.uses_810_features, self.uses_811_features) {
+            (_, true) => 811,
+            (true, false) => 810,
+            (false, false) => VERSION,
+        })?;
         writer.write_pas_string(&self.caption)?;
         writer.write_u32::<LE>(self.width)?;
         writer.write_u32::<LE>(self.height)?;
@@ -255,10 +259,10 @@ impl Asset for Room {
         writer.write_u32::<LE>(self.persistent.into())?;
         writer.write_u32::<LE>(self.bg_colour.into())?;
         match version {
-            GameVersion::GameMaker8_0 => writer.write_u32::<LE>(self.clear_screen.into())?,
             GameVersion::GameMaker8_1 => {
                 writer.write_u32::<LE>((u32::from(!self.clear_region) << 1) | u32::from(self.clear_screen))?
             },
+            _ => writer.write_u32::<LE>(self.clear_screen.into())?,
         };
         writer.write_pas_string(&self.creation_code)?;
         writer.write_u32::<LE>(self.backgrounds.len() as u32)?;
@@ -299,6 +303,14 @@ impl Asset for Room {
             writer.write_i32::<LE>(instance.object)?;
             writer.write_i32::<LE>(instance.id)?;
             writer.write_pas_string(&instance.creation_code)?;
+            if self.uses_810_features || self.uses_811_features {
+                writer.write_f64::<LE>(instance.xscale)?;
+                writer.write_f64::<LE>(instance.yscale)?;
+                writer.write_u32::<LE>(instance.blend)?;
+            }
+            if self.uses_811_features {
+                writer.write_f64::<LE>(instance.angle)?;
+            }
         }
         writer.write_u32::<LE>(self.tiles.len() as u32)?;
         for tile in &self.tiles {
@@ -311,6 +323,11 @@ impl Asset for Room {
             writer.write_u32::<LE>(tile.height)?;
             writer.write_i32::<LE>(tile.depth)?;
             writer.write_i32::<LE>(tile.id)?;
+            if self.uses_810_features || self.uses_811_features {
+                writer.write_f64::<LE>(tile.xscale)?;
+                writer.write_f64::<LE>(tile.yscale)?;
+                writer.write_u32::<LE>(tile.blend)?;
+            }
         }
         Ok(())
     }
@@ -81,11 +81,11 @@ impl From<u32> for SoundKind {
 }
 
 impl Asset for Sound {
-    fn deserialize_exe(mut reader: impl Read, _version: GameVersion, strict: bool) -> Result<Self, Error> {
+    fn deserialize_exe(mut reader: impl Read, game_version: GameVersion, strict: bool) -> Result<Self, Error> {
         let name = reader.read_pas_string()?;
 
         let version = reader.read_u32::<LE>()?;
-        if strict {
+        if strict && !game_version.is_pre_8_0() {
             assert_ver(version, VERSION)?;
         }
 
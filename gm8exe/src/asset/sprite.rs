@@ -10,6 +10,7 @@ use std::io::{self, Read};
 pub const VERSION: u32 = 800;
 pub const VERSION_COLLISION: u32 = 800;
 pub const VERSION_FRAME: u32 = 800;
+pub const VERSION_PRE_8_0: u32 = 542;
 
 pub struct Sprite {
     /// The asset name present in GML and the editor.
@@ -61,11 +62,257 @@ pub struct CollisionMap {
     pub data: Box<[bool]>,
 }
 
+/// The shapes the IDE can generate a collision mask with.
+#[derive(Copy, Clone, PartialEq)]
+pub enum ColliderShape {
+    Precise = 0,
+    Rectangle = 1,
+    Disk = 2,
+    Diamond = 3,
+}
+
+impl From<u32> for ColliderShape {
+    fn from(n: u32) -> ColliderShape {
+        match n {
+            1 => ColliderShape::Rectangle,
+            2 => ColliderShape::Disk,
+            3 => ColliderShape::Diamond,
+            _ => ColliderShape::Precise,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct BoundingBox {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+impl CollisionMap {
+    /// Generates collision maps from a sprite's frames, the way the IDE does when it builds a game.
+    /// Pixels collide if their alpha is above the tolerance, or for the other shapes, if they're inside that shape.
+    /// If no bounding box is given it fits around the colliding pixels, and nothing outside it collides either way.
+    pub fn generate(
+        frames: &[Frame],
+        shape: ColliderShape,
+        alpha_tolerance: u8,
+        per_frame: bool,
+        bbox: Option<BoundingBox>,
+    ) -> Vec<CollisionMap> {
+        let (width, height) = match frames.first() {
+            Some(frame) => (frame.width, frame.height),
+            None => return Vec::new(),
+        };
+        if width == 0 || height == 0 {
+            return Vec::new()
+        }
+        let alpha_map = |frames: &[Frame]| {
+            let mut map = vec![false; width as usize * height as usize];
+            for frame in frames {
+                for y in 0..height.min(frame.height) {
+                    for x in 0..width.min(frame.width) {
+                        let alpha = frame.data.get(((y * frame.width + x) * 4 + 3) as usize).copied().unwrap_or(0);
+                        map[(y * width + x) as usize] |= alpha > alpha_tolerance;
+                    }
+                }
+            }
+            map
+        };
+        let maps =
+            if per_frame { frames.chunks(1).map(alpha_map).collect::<Vec<_>>() } else { vec![alpha_map(frames)] };
+
+        maps.into_iter()
+            .map(|pixels| {
+                let bbox = match bbox {
+                    Some(bbox) => BoundingBox {
+                        left: bbox.left.min(width - 1),
+                        right: bbox.right.min(width - 1),
+                        top: bbox.top.min(height - 1),
+                        bottom: bbox.bottom.min(height - 1),
+                    },
+                    None => {
+                        let colliding = |x: u32, y: u32| pixels[(y * width + x) as usize];
+                        let xs = || (0..width).filter(|&x| (0..height).any(|y| colliding(x, y)));
+                        let ys = || (0..height).filter(|&y| (0..width).any(|x| colliding(x, y)));
+                        match (xs().next(), xs().next_back(), ys().next(), ys().next_back()) {
+                            (Some(left), Some(right), Some(top), Some(bottom)) => {
+                                BoundingBox { left, right, top, bottom }
+                            },
+                            _ => BoundingBox { left: 0, right: 0, top: 0, bottom: 0 },
+                        }
+                    },
+                };
+
+                let x_centre = f64::from(bbox.left + bbox.right) / 2.0;
+                let y_centre = f64::from(bbox.top + bbox.bottom) / 2.0;
+                let x_radius = f64::from(bbox.right.saturating_sub(bbox.left)) / 2.0 + 0.5;
+                let y_radius = f64::from(bbox.bottom.saturating_sub(bbox.top)) / 2.0 + 0.5;
+                let mut data = vec![false; pixels.len()].into_boxed_slice();
+                for y in bbox.top..=bbox.bottom {
+                    for x in bbox.left..=bbox.right {
+                        let i = (y * width + x) as usize;
+                        let dx = (f64::from(x) - x_centre) / x_radius;
+                        let dy = (f64::from(y) - y_centre) / y_radius;
+                        data[i] = match shape {
+                            ColliderShape::Precise => pixels[i],
+                            ColliderShape::Rectangle => true,
+                            ColliderShape::Disk => dx * dx + dy * dy < 1.0,
+                            ColliderShape::Diamond => dx.abs() + dy.abs() < 1.0,
+                        };
+                    }
+                }
+
+                CollisionMap {
+                    width,
+                    height,
+                    bbox_left: bbox.left,
+                    bbox_right: bbox.right,
+                    bbox_top: bbox.top,
+                    bbox_bottom: bbox.bottom,
+                    data,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Gives pixel data from before GameMaker 8.0, which had no alpha channel, its transparency.
+/// If `transparent` is set, pixels the colour of the bottom-left one are transparent, and `smooth` fades out the
+/// pixels around them the same way sprite_add does.
+pub(crate) fn apply_transparency(data: &mut [u8], width: u32, height: u32, transparent: bool, smooth: bool) {
+    let (width, height) = (width as usize, height as usize);
+    let background = match data.get((height.max(1) - 1) * width * 4..) {
+        Some([r, g, b, _, ..]) if transparent => Some([*r, *g, *b]),
+        _ => None,
+    };
+    for pixel in data.chunks_exact_mut(4) {
+        pixel[3] = if Some([pixel[0], pixel[1], pixel[2]]) == background { 0 } else { 255 };
+    }
+    if background.is_some() && smooth {
+        for y in 0..height {
+            for x in 0..width {
+                if data[(y * width + x) * 4 + 3] == 0 {
+                    for y in y.saturating_sub(1)..(y + 2).min(height) {
+                        for x in x.saturating_sub(1)..(x + 2).min(width) {
+                            let alpha = &mut data[(y * width + x) * 4 + 3];
+                            if *alpha >= 32 {
+                                *alpha -= 32;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The inverse of `apply_transparency`: works out the settings it would give `data` its alpha channel back with,
+/// and makes a copy of the pixels with the alpha channel left opaque.
+pub(crate) fn remove_transparency(data: &[u8], width: u32, height: u32) -> (Vec<u8>, bool, bool) {
+    let bottom_left = (height.max(1) as usize - 1) * width as usize * 4 + 3;
+    let transparent = data.get(bottom_left) == Some(&0);
+    let smooth = transparent && data.chunks_exact(4).any(|pixel| pixel[3] != 0 && pixel[3] != 255);
+    let mut opaque = data.to_vec();
+    for pixel in opaque.chunks_exact_mut(4) {
+        pixel[3] = 255;
+    }
+    (opaque, transparent, smooth)
+}
+
+impl Sprite {
+    /// Reads the rest of a sprite from GameMaker 6.x or 7.0. These games store the settings for generating the
+    /// collision masks instead of the masks, and a single mask is shared by all the frames.
+    fn deserialize_pre_8_0(mut reader: impl Read, name: PascalString) -> Result<Self, Error> {
+        let width = reader.read_u32::<LE>()?;
+        let height = reader.read_u32::<LE>()?;
+        let bbox_left = reader.read_u32::<LE>()?;
+        let bbox_right = reader.read_u32::<LE>()?;
+        let bbox_bottom = reader.read_u32::<LE>()?;
+        let bbox_top = reader.read_u32::<LE>()?;
+        let transparent = reader.read_u32::<LE>()? != 0;
+        let smooth_edges = reader.read_u32::<LE>()? != 0;
+        let _preload = reader.read_u32::<LE>()?;
+        let bbox_mode = reader.read_u32::<LE>()?;
+        let precise = reader.read_u32::<LE>()? != 0;
+        let origin_x = reader.read_i32::<LE>()?;
+        let origin_y = reader.read_i32::<LE>()?;
+
+        let frame_count = reader.read_u32::<LE>()?;
+        let frames = (0..frame_count)
+            .map(|_| {
+                let len = reader.read_u32::<LE>()? as usize;
+
+                // sanity check
+                if len != (width as usize * height as usize * 4) {
+                    return Err(Error::MalformedData)
+                }
+
+                let mut data = reader.read_chunk(len)?;
+                apply_transparency(&mut data, width, height, transparent, smooth_edges);
+                Ok(Frame { width, height, data: data.into_boxed_slice() })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // without precise collision checking, the whole bounding box collides
+        let shape = if precise { ColliderShape::Precise } else { ColliderShape::Rectangle };
+        let bbox = match bbox_mode {
+            0 => None, // automatic
+            1 => {
+                Some(BoundingBox { left: 0, right: width.saturating_sub(1), top: 0, bottom: height.saturating_sub(1) })
+            },
+            _ => Some(BoundingBox { left: bbox_left, right: bbox_right, top: bbox_top, bottom: bbox_bottom }),
+        };
+        let colliders = CollisionMap::generate(&frames, shape, 0, false, bbox);
+
+        Ok(Sprite { name, origin_x, origin_y, frames, colliders, per_frame_colliders: false })
+    }
+
+    /// Writes the rest of a sprite in the layout from before GameMaker 8.0. Only the first collision mask is kept,
+    /// and it's stored as a manual bounding box, which is precise unless the whole box collides.
+    fn serialize_pre_8_0(&self, mut writer: impl io::Write) -> io::Result<()> {
+        let (width, height) = self.frames.first().map_or((0, 0), |frame| (frame.width, frame.height));
+        let frames =
+            self.frames.iter().map(|frame| remove_transparency(&frame.data, width, height)).collect::<Vec<_>>();
+        let (transparent, smooth_edges) = frames.first().map_or((false, false), |(_, t, s)| (*t, *s));
+        let collider = self.colliders.first();
+        let precise = collider.is_some_and(|c| {
+            (c.bbox_top..=c.bbox_bottom).any(|y| {
+                (c.bbox_left..=c.bbox_right).any(|x| !c.data.get((y * c.width + x) as usize).unwrap_or(&false))
+            })
+        });
+        let bbox_mode = if collider.is_some() { 2 } else { 0 }; // manual, or automatic when there are no frames
+        writer.write_u32::<LE>(width)?;
+        writer.write_u32::<LE>(height)?;
+        writer.write_u32::<LE>(collider.map_or(0, |c| c.bbox_left))?;
+        writer.write_u32::<LE>(collider.map_or(0, |c| c.bbox_right))?;
+        writer.write_u32::<LE>(collider.map_or(0, |c| c.bbox_bottom))?;
+        writer.write_u32::<LE>(collider.map_or(0, |c| c.bbox_top))?;
+        writer.write_u32::<LE>(transparent.into())?;
+        writer.write_u32::<LE>(smooth_edges.into())?;
+        writer.write_u32::<LE>(1)?; // preload
+        writer.write_u32::<LE>(bbox_mode)?;
+        writer.write_u32::<LE>(precise.into())?;
+        writer.write_i32::<LE>(self.origin_x)?;
+        writer.write_i32::<LE>(self.origin_y)?;
+        writer.write_u32::<LE>(self.frames.len() as u32)?;
+        for (data, ..) in &frames {
+            writer.write_u32::<LE>(data.len() as u32)?;
+            writer.write_all(data)?;
+        }
+        Ok(())
+    }
+}
+
 impl Asset for Sprite {
-    fn deserialize_exe(mut reader: impl Read, _version: GameVersion, strict: bool) -> Result<Self, Error> {
+    fn deserialize_exe(mut reader: impl Read, game_version: GameVersion, strict: bool) -> Result<Self, Error> {
         let name = reader.read_pas_string()?;
 
         let version = reader.read_u32::<LE>()?;
+        if game_version.is_pre_8_0() {
+            return Self::deserialize_pre_8_0(reader, name)
+        }
         if strict {
             assert_ver_multiple(version, &[VERSION, 810])?;
         }
@@ -130,8 +377,12 @@ impl Asset for Sprite {
         Ok(Sprite { name, origin_x, origin_y, frames, colliders, per_frame_colliders })
     }
 
-    fn serialize_exe(&self, mut writer: impl io::Write, _version: GameVersion) -> io::Result<()> {
+    fn serialize_exe(&self, mut writer: impl io::Write, version: GameVersion) -> io::Result<()> {
         writer.write_pas_string(&self.name)?;
+        if version.is_pre_8_0() {
+            writer.write_u32::<LE>(VERSION_PRE_8_0)?;
+            return self.serialize_pre_8_0(writer)
+        }
         writer.write_u32::<LE>(VERSION)?;
         writer.write_i32::<LE>(self.origin_x)?;
         writer.write_i32::<LE>(self.origin_y)?;
@@ -164,3 +415,32 @@ impl Asset for Sprite {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transparency() {
+        // a white 3x3 image with a black bottom-left pixel
+        let mut image = [255u8; 36];
+        image[24..27].copy_from_slice(&[0, 0, 0]);
+        let alpha = |data: &[u8]| data.chunks_exact(4).map(|pixel| pixel[3]).collect::<Vec<_>>();
+
+        for (transparent, smooth, expected) in [
+            (false, false, [255, 255, 255, 255, 255, 255, 255, 255, 255]),
+            (true, false, [255, 255, 255, 255, 255, 255, 0, 255, 255]),
+            (true, true, [255, 255, 255, 223, 223, 255, 0, 223, 255]),
+        ] {
+            let mut data = image;
+            apply_transparency(&mut data, 3, 3, transparent, smooth);
+            assert_eq!(alpha(&data), expected);
+
+            let (mut opaque, removed_transparent, removed_smooth) = remove_transparency(&data, 3, 3);
+            assert_eq!((removed_transparent, removed_smooth), (transparent, smooth));
+            assert_eq!(opaque, image);
+            apply_transparency(&mut opaque, 3, 3, removed_transparent, removed_smooth);
+            assert_eq!(opaque, data);
+        }
+    }
+}
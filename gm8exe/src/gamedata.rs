@@ -1,10 +1,34 @@
 pub mod antidec;
+pub mod gm60;
+pub mod gm70;
 pub mod gm80;
 pub mod gm81;
 
 use crate::{reader::ReaderError, upx, GameVersion};
+use byteorder::{ReadBytesExt, LE};
 use std::io::{self, Seek, SeekFrom};
 
+/// The magic number at the start of the gamedata header of games made before GM8.1, followed by their version.
+pub const HEADER_MAGIC: u32 = 1234321;
+
+/// The runners before GM8.0 don't store where their gamedata starts. They read a header every 10000 bytes until
+/// they find one, so this does the same, from the start of the file.
+/// If a header with one of the given versions is found, returns the version and leaves the cursor just past it.
+pub fn seek_header(exe: &mut io::Cursor<&mut [u8]>, versions: &[u32]) -> io::Result<Option<u32>> {
+    let mut pos = 0;
+    while pos + 8 <= exe.get_ref().len() as u64 {
+        exe.set_position(pos);
+        if exe.read_u32::<LE>()? == HEADER_MAGIC {
+            let version = exe.read_u32::<LE>()?;
+            if versions.contains(&version) {
+                return Ok(Some(version))
+            }
+        }
+        pos += 10000;
+    }
+    Ok(None)
+}
+
 /// Identifies the game version and start of gamedata header, given a data cursor.
 /// Also removes any version-specific encryptions.
 pub fn find<F>(
@@ -113,7 +137,13 @@ where
                 // Standard formats
                 if gm80::check(exe, logger)? {
                     Ok(GameVersion::GameMaker8_0)
-                } else if gm81::check(exe, logger)? || gm81::check_lazy(exe, logger)? {
+                } else if gm81::check(exe, logger)? {
+                    Ok(GameVersion::GameMaker8_1)
+                } else if gm70::check(exe, logger)? {
+                    Ok(GameVersion::GameMaker7_0)
+                } else if let Some(version) = gm60::check(exe, logger)? {
+                    Ok(version)
+                } else if gm81::check_lazy(exe, logger)? {
                     Ok(GameVersion::GameMaker8_1)
                 } else {
                     Err(ReaderError::UnknownFormat)
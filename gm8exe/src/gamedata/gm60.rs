@@ -0,0 +1,101 @@
+use crate::{gamedata::seek_header, GameVersion};
+use byteorder::{ReadBytesExt, LE};
+use std::io::{self, Seek, SeekFrom};
+
+/// Check if this is a GameMaker 6.0 or 6.1 game by looking for its gamedata header.
+/// If so, returns which one it is and sets the cursor to the start of the gamedata.
+pub fn check<F>(exe: &mut io::Cursor<&mut [u8]>, logger: Option<F>) -> io::Result<Option<GameVersion>>
+where
+    F: Copy + Fn(&str),
+{
+    log!(logger, "Checking for GM6.x format...");
+    let version = match seek_header(exe, &[600, 610])? {
+        Some(600) => GameVersion::GameMaker6_0,
+        Some(_) => GameVersion::GameMaker6_1,
+        None => return Ok(None),
+    };
+    log!(logger, "Found {:?} header at 0x{:X}", version, exe.position() - 8);
+
+    // debug flag and settings version, the same as 8.0
+    exe.seek(SeekFrom::Current(8))?;
+    Ok(Some(version))
+}
+
+/// Generates the table GameMaker 6.x substitutes each byte of the gamedata with, from the seed stored before it.
+pub fn swap_table(seed: u32) -> [u8; 256] {
+    let mut table = [0u8; 256];
+    for (i, b) in table.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    let a = 6 + (seed % 250);
+    let b = seed / 250;
+    for i in 1..=10000u32 {
+        let j = (1 + (i.wrapping_mul(a).wrapping_add(b) % 254)) as usize;
+        table.swap(j, j + 1);
+    }
+    table
+}
+
+/// Removes GameMaker 6.x protection in-place.
+pub fn decrypt<F>(data: &mut io::Cursor<&mut [u8]>, logger: Option<F>) -> io::Result<()>
+where
+    F: Copy + Fn(&str),
+{
+    let seed = data.read_u32::<LE>()?;
+    let len = data.read_u32::<LE>()? as usize;
+    let pos = data.position() as usize;
+    log!(logger, "Decrypting asset data... (size: {}, seed: {})", len, seed);
+
+    let swap_table = swap_table(seed);
+    let mut reverse_table = [0u8; 256];
+    for i in 0..256 {
+        reverse_table[swap_table[i] as usize] = i as u8;
+    }
+
+    let data = data.get_mut().get_mut(pos..pos + len).ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    for b in data.iter_mut() {
+        *b = reverse_table[*b as usize];
+    }
+    Ok(())
+}
+
+/// Applies GameMaker 6.x protection in-place. `data` is the protected data on its own, after its length.
+pub fn encrypt(data: &mut [u8], seed: u32) {
+    let swap_table = swap_table(seed);
+    for b in data.iter_mut() {
+        *b = swap_table[*b as usize];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamedata::{gm70, HEADER_MAGIC};
+
+    const NO_LOGGER: Option<fn(&str)> = None;
+
+    #[test]
+    fn find_header() {
+        let mut exe = vec![0u8; 40000];
+        // the runner only looks every 10000 bytes, so the first of these isn't a header
+        exe[5000..5008].copy_from_slice(&[HEADER_MAGIC.to_le_bytes(), 600u32.to_le_bytes()].concat());
+        exe[20000..20008].copy_from_slice(&[HEADER_MAGIC.to_le_bytes(), 610u32.to_le_bytes()].concat());
+        let mut cursor = io::Cursor::new(exe.as_mut_slice());
+        assert!(!gm70::check(&mut cursor, NO_LOGGER).unwrap());
+        assert!(matches!(check(&mut cursor, NO_LOGGER).unwrap(), Some(GameVersion::GameMaker6_1)));
+        assert_eq!(cursor.position(), 20016);
+    }
+
+    #[test]
+    fn encryption_round_trip() {
+        let plain = (0..1000u32).map(|i| (i * 7 % 251) as u8).collect::<Vec<_>>();
+        let mut data = [&1234u32.to_le_bytes()[..], &(plain.len() as u32).to_le_bytes(), &plain].concat();
+        encrypt(&mut data[8..], 1234);
+        assert_ne!(data[8..], plain[..]);
+
+        let mut cursor = io::Cursor::new(data.as_mut_slice());
+        decrypt(&mut cursor, NO_LOGGER).unwrap();
+        assert_eq!(cursor.position(), 8);
+        assert_eq!(data[8..], plain[..]);
+    }
+}
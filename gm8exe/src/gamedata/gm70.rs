@@ -0,0 +1,23 @@
+use crate::gamedata::seek_header;
+use std::io::{self, Seek, SeekFrom};
+
+/// Check if this is a GameMaker 7.0 game by looking for its gamedata header.
+/// If so, sets the cursor to the start of the gamedata.
+///
+/// Past the header, 7.0's gamedata is encrypted the same way as 8.0's, so `gm80::decrypt` removes it.
+pub fn check<F>(exe: &mut io::Cursor<&mut [u8]>, logger: Option<F>) -> io::Result<bool>
+where
+    F: Copy + Fn(&str),
+{
+    log!(logger, "Checking for GM7.0 format...");
+    match seek_header(exe, &[700, 701, 702])? {
+        Some(version) => {
+            log!(logger, "Found GM7.0 header (version {}) at 0x{:X}", version, exe.position() - 8);
+
+            // debug flag and settings version, the same as 8.0
+            exe.seek(SeekFrom::Current(8))?;
+            Ok(true)
+        },
+        None => Ok(false),
+    }
+}
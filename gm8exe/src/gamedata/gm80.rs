@@ -184,3 +184,19 @@ where
 
     Ok(())
 }
+
+/// Applies GameMaker 8.0 protection in-place, undoing each pass of `decrypt` in reverse order.
+/// `data` is the protected data on its own, after its length, and `swap_table` must hold every byte value once.
+pub fn encrypt(data: &mut [u8], swap_table: &[u8; 256]) {
+    // encryption: undo the second pass, swapping in the opposite order
+    for i in 0..data.len() {
+        let b = i.saturating_sub(swap_table[i & 0xFF] as usize);
+        data.swap(i, b);
+    }
+
+    // encryption: undo the first pass
+    //   forwards, data[i] = swap[data[i] + (data[i-1] + i)] where data[i-1] has already been encrypted
+    for i in 1..data.len() {
+        data[i] = swap_table[data[i].wrapping_add(data[i - 1].wrapping_add(i as u8)) as usize];
+    }
+}
@@ -4,6 +4,7 @@ use std::{
     iter::once,
 };
 
+#[derive(Clone, Copy)]
 pub enum XorMethod {
     Normal,
     Sudalv,
@@ -53,17 +54,7 @@ where
             },
         };
 
-        // Check if SUDALV's re-encryption is in use
-        exe.set_position(0x0010BB83);
-        let mut buf = [0u8; 8];
-        exe.read_exact(&mut buf)?;
-        let xor_method = match buf {
-            [0x8B, 0x02, 0xC1, 0xE0, 0x10, 0x8B, 0x11, 0x81] => {
-                log!(logger, "Found SUDALV re-encryption");
-                XorMethod::Sudalv
-            },
-            _ => XorMethod::Normal,
-        };
+        let xor_method = xor_method(exe, logger)?;
 
         // Search for header
         exe.set_position(header_start as u64);
@@ -89,6 +80,23 @@ where
     }
 }
 
+/// Check if SUDALV's re-encryption is in use, by looking for its changes to the mask generator.
+pub fn xor_method<F>(exe: &mut io::Cursor<&mut [u8]>, logger: Option<F>) -> io::Result<XorMethod>
+where
+    F: Copy + Fn(&str),
+{
+    exe.set_position(0x0010BB83);
+    let mut buf = [0u8; 8];
+    exe.read_exact(&mut buf)?;
+    Ok(match buf {
+        [0x8B, 0x02, 0xC1, 0xE0, 0x10, 0x8B, 0x11, 0x81] => {
+            log!(logger, "Found SUDALV re-encryption");
+            XorMethod::Sudalv
+        },
+        _ => XorMethod::Normal,
+    })
+}
+
 /// Check if this is a standard gm8.1 game by looking for the default header (last-resort method)
 /// If so, removes gm81 encryption and sets the cursor to the start of the gamedata.
 pub fn check_lazy<F>(exe: &mut io::Cursor<&mut [u8]>, logger: Option<F>) -> io::Result<bool>
@@ -210,6 +218,15 @@ where
     Ok(())
 }
 
+/// Applies GM8.1 encryption in-place. It's a plain xor, so this is the same as decrypting.
+/// The cursor must be just past the magic value, with the seeds to use following it.
+pub fn encrypt<F>(data: &mut io::Cursor<&mut [u8]>, logger: Option<F>, xor_method: XorMethod) -> io::Result<()>
+where
+    F: Copy + Fn(&str),
+{
+    decrypt(data, logger, xor_method)
+}
+
 // it's all just xor mask generator code below here
 
 struct NormalMaskGenerator {
@@ -0,0 +1,729 @@
+//! Reads `.gmk` and `.gm81` project files into the same structure as game executables.
+//!
+//! Projects don't contain everything an executable does: fonts aren't pre-rendered so they have no glyphs, and
+//! extensions are only stored by name so they have no files. Collision masks aren't stored either, so they get
+//! generated from each sprite's mask settings the same way the IDE does when it builds a game.
+
+use crate::{
+    asset::{
+        assert_ver, assert_ver_multiple, background,
+        path::{self, ConnectionKind, Point},
+        room::{self, ViewFollowData},
+        sprite::{self, BoundingBox, ColliderShape, CollisionMap, Frame},
+        Asset, Background, Constant, Error, Extension, Font, IncludedFile, Object, PascalString, Path, ReadChunk,
+        ReadPascalString, Room, Script, Sound, Sprite, Timeline, Trigger, WritePascalString,
+    },
+    reader::{inflate, ReaderError},
+    settings::{GameHelpDialog, Settings},
+    AssetList, GameAssets, GameVersion,
+};
+use byteorder::{ReadBytesExt, LE};
+use flate2::bufread::ZlibDecoder;
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use std::io::{self, Read};
+
+/// The first four bytes of every project file.
+pub const MAGIC: u32 = 1234321;
+
+// Start and end of the block the decompiler puts GM8.1 instance and tile properties in, see `read_instance_compat`
+const COMPAT_START: &[u8] = b"/* gm8.2 compat */\r\n";
+const COMPAT_END: &[u8] = b"/****************/\r\n\r\n";
+
+/// Reads a length-prefixed zlib block from the file without inflating it.
+fn read_block<'a>(src: &mut io::Cursor<&'a [u8]>) -> Result<&'a [u8], ReaderError> {
+    let len = src.read_u32::<LE>()? as usize;
+    let pos = src.position() as usize;
+    let data = src.get_ref().get(pos..pos + len).ok_or(Error::MalformedData)?;
+    src.set_position((pos + len) as u64);
+    Ok(data)
+}
+
+/// Reads a length-prefixed zlib block from inside another one and inflates it.
+fn read_inner_block(reader: &mut impl Read) -> io::Result<Box<[u8]>> {
+    let len = reader.read_u32::<LE>()? as usize;
+    let compressed = reader.read_chunk(len)?;
+    let mut data = Vec::new();
+    inflate(&compressed).read_to_end(&mut data)?;
+    Ok(data.into_boxed_slice())
+}
+
+/// Most assets are stored the same way as in executables apart from a timestamp after their name.
+/// This takes the timestamp out so they can be read with `Asset::deserialize_exe`.
+fn without_timestamp(mut reader: impl Read) -> Result<impl Read, Error> {
+    let name = reader.read_pas_string()?;
+    reader.read_f64::<LE>()?;
+    let mut prefix = Vec::with_capacity(name.0.len() + 4);
+    prefix.write_pas_string(&name)?;
+    Ok(io::Cursor::new(prefix).chain(reader))
+}
+
+fn read_sprite(mut reader: impl Read, strict: bool) -> Result<Sprite, Error> {
+    let name = reader.read_pas_string()?;
+    reader.read_f64::<LE>()?; // timestamp
+    let version = reader.read_u32::<LE>()?;
+    if strict {
+        assert_ver_multiple(version, &[sprite::VERSION, 810])?;
+    }
+
+    let origin_x = reader.read_i32::<LE>()?;
+    let origin_y = reader.read_i32::<LE>()?;
+    let frame_count = reader.read_u32::<LE>()?;
+    let frames = (0..frame_count)
+        .map(|_| {
+            let version = reader.read_u32::<LE>()?;
+            if strict {
+                assert_ver(version, sprite::VERSION_FRAME)?;
+            }
+            let width = reader.read_u32::<LE>()?;
+            let height = reader.read_u32::<LE>()?;
+            let data = if width != 0 && height != 0 {
+                let len = reader.read_u32::<LE>()? as usize;
+                if len != width as usize * height as usize * 4 {
+                    return Err(Error::MalformedData)
+                }
+                reader.read_chunk(len)?.into_boxed_slice()
+            } else {
+                Box::default()
+            };
+            Ok(Frame { width, height, data })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let shape = ColliderShape::from(reader.read_u32::<LE>()?);
+    let alpha_tolerance = reader.read_u32::<LE>()?.min(255) as u8;
+    let per_frame_colliders = reader.read_u32::<LE>()? != 0;
+    let bbox_kind = reader.read_u32::<LE>()?;
+    let left = reader.read_u32::<LE>()?;
+    let right = reader.read_u32::<LE>()?;
+    let bottom = reader.read_u32::<LE>()?;
+    let top = reader.read_u32::<LE>()?;
+    let bbox = match bbox_kind {
+        0 => None, // automatic
+        1 => frames.first().map(|frame| BoundingBox {
+            // full image
+            left: 0,
+            right: frame.width.saturating_sub(1),
+            top: 0,
+            bottom: frame.height.saturating_sub(1),
+        }),
+        _ => Some(BoundingBox { left, right, top, bottom }), // manual
+    };
+    let colliders = CollisionMap::generate(&frames, shape, alpha_tolerance, per_frame_colliders, bbox);
+
+    Ok(Sprite { name, origin_x, origin_y, frames, colliders, per_frame_colliders })
+}
+
+fn read_background(mut reader: impl Read, strict: bool) -> Result<Background, Error> {
+    let name = reader.read_pas_string()?;
+    reader.read_f64::<LE>()?; // timestamp
+    let version = reader.read_u32::<LE>()?;
+    if strict {
+        assert_ver(version, background::VERSION1)?;
+    }
+
+    // Tileset settings, which only the room editor uses
+    for _ in 0..7 {
+        reader.read_u32::<LE>()?;
+    }
+
+    let version = reader.read_u32::<LE>()?;
+    if strict {
+        assert_ver(version, background::VERSION2)?;
+    }
+    let width = reader.read_u32::<LE>()?;
+    let height = reader.read_u32::<LE>()?;
+    if width > 0 && height > 0 {
+        let len = reader.read_u32::<LE>()? as usize;
+        if len == 0 {
+            return Ok(Background { name, width, height, data: None })
+        } else if len != width as usize * height as usize * 4 {
+            return Err(Error::MalformedData)
+        }
+        let data = Some(reader.read_chunk(len)?.into_boxed_slice());
+        Ok(Background { name, width, height, data })
+    } else {
+        Ok(Background { name, width: 0, height: 0, data: None })
+    }
+}
+
+fn read_path(mut reader: impl Read, strict: bool) -> Result<Path, Error> {
+    let name = reader.read_pas_string()?;
+    reader.read_f64::<LE>()?; // timestamp
+    let version = reader.read_u32::<LE>()?;
+    if strict {
+        assert_ver(version, path::VERSION)?;
+    }
+
+    let connection = ConnectionKind::from(reader.read_u32::<LE>()?);
+    let closed = reader.read_u32::<LE>()? != 0;
+    let precision = reader.read_u32::<LE>()?;
+    reader.read_i32::<LE>()?; // room shown in the path editor
+    reader.read_u32::<LE>()?; // snap x
+    reader.read_u32::<LE>()?; // snap y
+    let point_count = reader.read_u32::<LE>()? as usize;
+    let points = (0..point_count)
+        .map(|_| {
+            Ok(Point { x: reader.read_f64::<LE>()?, y: reader.read_f64::<LE>()?, speed: reader.read_f64::<LE>()? })
+        })
+        .collect::<io::Result<_>>()?;
+
+    Ok(Path { name, connection, precision, closed, points })
+}
+
+fn read_font(mut reader: impl Read, version: GameVersion, strict: bool) -> Result<Font, Error> {
+    let name = reader.read_pas_string()?;
+    reader.read_f64::<LE>()?; // timestamp
+    let ver = reader.read_u32::<LE>()?;
+    if strict {
+        assert_ver(ver, crate::asset::font::VERSION)?;
+    }
+
+    let sys_name = reader.read_pas_string()?;
+    let size = reader.read_u32::<LE>()?;
+    let bold = reader.read_u32::<LE>()? != 0;
+    let italic = reader.read_u32::<LE>()? != 0;
+    let mut range_start = reader.read_u32::<LE>()?;
+    let range_end = reader.read_u32::<LE>()?;
+    let (aa_level, charset) = match version {
+        GameVersion::GameMaker8_1 => {
+            let aa_level = (range_start & 0xFF000000) >> 24;
+            let charset = (range_start & 0x00FF0000) >> 16;
+            range_start &= 0x0000FFFF;
+            (aa_level, charset)
+        },
+        _ => (0, 0),
+    };
+
+    // The glyphs only get rendered when the game is built
+    Ok(Font {
+        name,
+        sys_name,
+        size,
+        bold,
+        italic,
+        range_start,
+        range_end,
+        charset,
+        aa_level,
+        dmap: Box::new([0; 0x600]),
+        map_width: 0,
+        map_height: 0,
+        pixel_map: Box::default(),
+    })
+}
+
+/// Splits the decompiler's compatibility block off the front of some creation code, if it has one.
+/// Returns the lines in the block and the rest of the code.
+fn split_compat(code: &PascalString) -> Option<(Vec<&str>, PascalString)> {
+    let rest = code.0.strip_prefix(COMPAT_START)?;
+    let end = rest.windows(COMPAT_END.len()).position(|w| w == COMPAT_END)?;
+    let lines = std::str::from_utf8(&rest[..end]).ok()?.split_terminator("\r\n").collect();
+    Some((lines, PascalString(rest[end + COMPAT_END.len()..].into())))
+}
+
+/// GM8.0 rooms can't store an instance's scale, blend or angle, so the decompiler writes them into the instance's
+/// creation code instead. This reads them back out, returning whether there was one and whether it had an angle.
+fn read_instance_compat(instance: &mut room::Instance) -> Option<bool> {
+    let (lines, code) = split_compat(&instance.creation_code)?;
+    let (mut xscale, mut yscale, mut blend, mut angle) = (1.0, 1.0, u32::MAX, None);
+    for line in lines {
+        let (variable, value) = line.strip_suffix(';')?.split_once('=')?;
+        match variable {
+            "image_xscale" => xscale = value.parse().ok()?,
+            "image_yscale" => yscale = value.parse().ok()?,
+            "image_blend" => blend = value.parse().ok()?,
+            "image_angle" => angle = Some(value.parse().ok()?),
+            _ => return None,
+        }
+    }
+    instance.xscale = xscale;
+    instance.yscale = yscale;
+    instance.blend = blend;
+    instance.angle = angle.unwrap_or(0.0);
+    instance.creation_code = code;
+    Some(angle.is_some())
+}
+
+/// The same as `read_instance_compat`, but for tiles, which are set from the room's creation code.
+/// Returns the creation code without the block if there was one.
+fn read_tile_compat(creation_code: &PascalString, tiles: &mut [room::Tile]) -> Option<PascalString> {
+    let (lines, code) = split_compat(creation_code)?;
+    let mut properties = tiles.iter().map(|t| (t.xscale, t.yscale, t.blend)).collect::<Vec<_>>();
+    for line in lines {
+        let (function, args) = line.strip_suffix(");")?.split_once('(')?;
+        let args = args.split(',').collect::<Vec<_>>();
+        let id = args.first()?.parse::<i32>().ok()?;
+        let tile = &mut properties[tiles.iter().position(|t| t.id == id)?];
+        match (function, args.as_slice()) {
+            ("tile_set_scale", [_, xscale, yscale]) => {
+                tile.0 = xscale.parse().ok()?;
+                tile.1 = yscale.parse().ok()?;
+            },
+            ("tile_set_blend", [_, blend]) => tile.2 = blend.parse().ok()?,
+            _ => return None,
+        }
+    }
+    for (tile, (xscale, yscale, blend)) in tiles.iter_mut().zip(properties) {
+        tile.xscale = xscale;
+        tile.yscale = yscale;
+        tile.blend = blend;
+    }
+    Some(code)
+}
+
+fn read_room(mut reader: impl Read, version: GameVersion, strict: bool) -> Result<Room, Error> {
+    let name = reader.read_pas_string()?;
+    reader.read_f64::<LE>()?; // timestamp
+    let ver = reader.read_u32::<LE>()?;
+    if strict {
+        assert_ver(ver, room::VERSION)?;
+    }
+
+    let caption = reader.read_pas_string()?;
+    let width = reader.read_u32::<LE>()?;
+    let height = reader.read_u32::<LE>()?;
+    reader.read_u32::<LE>()?; // snap x
+    reader.read_u32::<LE>()?; // snap y
+    reader.read_u32::<LE>()?; // isometric grid
+    let speed = reader.read_u32::<LE>()?;
+    let persistent = reader.read_u32::<LE>()? != 0;
+    let bg_colour = reader.read_u32::<LE>()?.into();
+    let (clear_screen, clear_region) = match (version, reader.read_u32::<LE>()?) {
+        (GameVersion::GameMaker8_1, x) => ((x & 0b01) != 0, (x & 0b10) == 0),
+        (_, x) => (x != 0, true),
+    };
+    let mut creation_code = reader.read_pas_string()?;
+
+    let background_count = reader.read_u32::<LE>()? as usize;
+    let backgrounds = (0..background_count)
+        .map(|_| {
+            Ok(room::Background {
+                visible_on_start: reader.read_u32::<LE>()? != 0,
+                is_foreground: reader.read_u32::<LE>()? != 0,
+                source_bg: reader.read_i32::<LE>()?,
+                xoffset: reader.read_i32::<LE>()?,
+                yoffset: reader.read_i32::<LE>()?,
+                tile_horz: reader.read_u32::<LE>()? != 0,
+                tile_vert: reader.read_u32::<LE>()? != 0,
+                hspeed: reader.read_i32::<LE>()?,
+                vspeed: reader.read_i32::<LE>()?,
+                stretch: reader.read_u32::<LE>()? != 0,
+            })
+        })
+        .collect::<io::Result<_>>()?;
+
+    let views_enabled = reader.read_u32::<LE>()? != 0;
+    let view_count = reader.read_u32::<LE>()? as usize;
+    let views = (0..view_count)
+        .map(|_| {
+            Ok(room::View {
+                visible: reader.read_u32::<LE>()? != 0,
+                source_x: reader.read_i32::<LE>()?,
+                source_y: reader.read_i32::<LE>()?,
+                source_w: reader.read_u32::<LE>()?,
+                source_h: reader.read_u32::<LE>()?,
+                port_x: reader.read_i32::<LE>()?,
+                port_y: reader.read_i32::<LE>()?,
+                port_w: reader.read_u32::<LE>()?,
+                port_h: reader.read_u32::<LE>()?,
+                following: ViewFollowData {
+                    hborder: reader.read_i32::<LE>()?,
+                    vborder: reader.read_i32::<LE>()?,
+                    hspeed: reader.read_i32::<LE>()?,
+                    vspeed: reader.read_i32::<LE>()?,
+                    target: reader.read_i32::<LE>()?,
+                },
+            })
+        })
+        .collect::<io::Result<_>>()?;
+
+    let instance_count = reader.read_u32::<LE>()? as usize;
+    let mut instances = (0..instance_count)
+        .map(|_| {
+            let instance = room::Instance {
+                x: reader.read_i32::<LE>()?,
+                y: reader.read_i32::<LE>()?,
+                object: reader.read_i32::<LE>()?,
+                id: reader.read_i32::<LE>()?,
+                creation_code: reader.read_pas_string()?,
+                xscale: 1.0,
+                yscale: 1.0,
+                blend: u32::MAX,
+                angle: 0.0,
+            };
+            reader.read_u32::<LE>()?; // locked in editor
+            Ok(instance)
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let tile_count = reader.read_u32::<LE>()? as usize;
+    let mut tiles = (0..tile_count)
+        .map(|_| {
+            let tile = room::Tile {
+                x: reader.read_i32::<LE>()?,
+                y: reader.read_i32::<LE>()?,
+                source_bg: reader.read_i32::<LE>()?,
+                tile_x: reader.read_u32::<LE>()?,
+                tile_y: reader.read_u32::<LE>()?,
+                width: reader.read_u32::<LE>()?,
+                height: reader.read_u32::<LE>()?,
+                depth: reader.read_i32::<LE>()?,
+                id: reader.read_i32::<LE>()?,
+                xscale: 1.0,
+                yscale: 1.0,
+                blend: u32::MAX,
+            };
+            reader.read_u32::<LE>()?; // locked in editor
+            Ok(tile)
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    // The rest is room editor settings
+
+    let mut uses_810_features = false;
+    let mut uses_811_features = false;
+    if let Some(code) = read_tile_compat(&creation_code, &mut tiles) {
+        creation_code = code;
+        uses_810_features = true;
+    }
+    for instance in &mut instances {
+        if let Some(has_angle) = read_instance_compat(instance) {
+            uses_810_features = true;
+            uses_811_features |= has_angle;
+        }
+    }
+
+    Ok(Room {
+        name,
+        caption,
+        width,
+        height,
+        speed,
+        persistent,
+        bg_colour,
+        clear_screen,
+        clear_region,
+        creation_code,
+        backgrounds,
+        views_enabled,
+        views,
+        instances,
+        tiles,
+        uses_810_features,
+        uses_811_features,
+    })
+}
+
+fn read_settings(mut cfg: impl Read, version: GameVersion) -> Result<(Settings, Option<Vec<u8>>), ReaderError> {
+    fn read_data_maybe(cfg: &mut impl Read) -> io::Result<Option<Box<[u8]>>> {
+        if cfg.read_u32::<LE>()? != 0 { Ok(Some(read_inner_block(cfg)?)) } else { Ok(None) }
+    }
+
+    let fullscreen = cfg.read_u32::<LE>()? != 0;
+    let interpolate_pixels = cfg.read_u32::<LE>()? != 0;
+    let dont_draw_border = cfg.read_u32::<LE>()? != 0;
+    let display_cursor = cfg.read_u32::<LE>()? != 0;
+    let scaling = cfg.read_i32::<LE>()?;
+    let allow_resize = cfg.read_u32::<LE>()? != 0;
+    let window_on_top = cfg.read_u32::<LE>()? != 0;
+    let clear_colour = cfg.read_u32::<LE>()?;
+    let set_resolution = cfg.read_u32::<LE>()? != 0;
+    let colour_depth = cfg.read_u32::<LE>()?;
+    let resolution = cfg.read_u32::<LE>()?;
+    let frequency = cfg.read_u32::<LE>()?;
+    let dont_show_buttons = cfg.read_u32::<LE>()? != 0;
+    let (vsync, force_cpu_render) = match (version, cfg.read_u32::<LE>()?) {
+        (GameVersion::GameMaker8_1, x) => ((x & 1) != 0, (x & (1 << 7)) != 0),
+        (_, x) => (x != 0, true), // see 8.1.141 changelog
+    };
+    let disable_screensaver = cfg.read_u32::<LE>()? != 0;
+    let f4_fullscreen_toggle = cfg.read_u32::<LE>()? != 0;
+    let f1_help_menu = cfg.read_u32::<LE>()? != 0;
+    let esc_close_game = cfg.read_u32::<LE>()? != 0;
+    let f5_save_f6_load = cfg.read_u32::<LE>()? != 0;
+    let f9_screenshot = cfg.read_u32::<LE>()? != 0;
+    let treat_close_as_esc = cfg.read_u32::<LE>()? != 0;
+    let priority = cfg.read_u32::<LE>()?;
+    let freeze_on_lose_focus = cfg.read_u32::<LE>()? != 0;
+    let loading_bar = cfg.read_u32::<LE>()?;
+    let (backdata, frontdata) =
+        if loading_bar == 2 { (read_data_maybe(&mut cfg)?, read_data_maybe(&mut cfg)?) } else { (None, None) };
+    // Unlike executables, there's a flag for whether there's a custom image and another for whether it has any data
+    let custom_load_image = if cfg.read_u32::<LE>()? != 0 { read_data_maybe(&mut cfg)? } else { None };
+    let transparent = cfg.read_u32::<LE>()? != 0;
+    let translucency = cfg.read_u32::<LE>()?;
+    let scale_progress_bar = cfg.read_u32::<LE>()? != 0;
+    let ico_len = cfg.read_u32::<LE>()? as usize;
+    let ico_file = if ico_len != 0 { Some(cfg.read_chunk(ico_len)?) } else { None };
+    let show_error_messages = cfg.read_u32::<LE>()? != 0;
+    let log_errors = cfg.read_u32::<LE>()? != 0;
+    let always_abort = cfg.read_u32::<LE>()? != 0;
+    let (zero_uninitialized_vars, error_on_uninitialized_args) = match (version, cfg.read_u32::<LE>()?) {
+        (GameVersion::GameMaker8_1, x) => ((x & 1) != 0, (x & 2) != 0),
+        (_, x) => (x != 0, false),
+    };
+    // The rest is author, version and company info, which only goes into the executable's resources
+
+    let settings = Settings {
+        fullscreen,
+        scaling,
+        interpolate_pixels,
+        clear_colour,
+        allow_resize,
+        window_on_top,
+        dont_draw_border,
+        dont_show_buttons,
+        display_cursor,
+        freeze_on_lose_focus,
+        disable_screensaver,
+        force_cpu_render,
+        set_resolution,
+        colour_depth,
+        resolution,
+        frequency,
+        vsync,
+        esc_close_game,
+        treat_close_as_esc,
+        f1_help_menu,
+        f4_fullscreen_toggle,
+        f5_save_f6_load,
+        f9_screenshot,
+        priority,
+        custom_load_image,
+        transparent,
+        translucency,
+        loading_bar,
+        backdata,
+        frontdata,
+        scale_progress_bar,
+        show_error_messages,
+        log_errors,
+        always_abort,
+        zero_uninitialized_vars,
+        error_on_uninitialized_args,
+        swap_creation_events: false,
+    };
+    Ok((settings, ico_file))
+}
+
+pub fn from_gmk<I, F>(gmk: I, logger: Option<F>, strict: bool, multithread: bool) -> Result<GameAssets, ReaderError>
+where
+    F: Copy + Fn(&str),
+    I: AsRef<[u8]>,
+{
+    let mut gmk = io::Cursor::new(gmk.as_ref());
+
+    // little helper thing
+    macro_rules! assert_ver {
+        ($expect: expr, $ver: expr) => {{
+            let got = $ver;
+            if strict { assert_ver_multiple(got, $expect) } else { Ok(()) }
+        }};
+    }
+
+    fn get_assets<T, F>(
+        src: &mut io::Cursor<&[u8]>,
+        deserializer: F,
+        strict: bool,
+        multithread: bool,
+    ) -> Result<AssetList<T>, ReaderError>
+    where
+        T: Send,
+        F: Fn(ZlibDecoder<&[u8]>) -> Result<T, Error> + Sync,
+    {
+        let version = src.read_u32::<LE>()?;
+        if strict {
+            assert_ver(version, 800)?;
+        }
+        let count = src.read_u32::<LE>()? as usize;
+        let blocks = (0..count).map(|_| read_block(src)).collect::<Result<Vec<_>, _>>()?;
+
+        // If the first u32 is 0 then it's a deleted asset, and is None.
+        let to_asset = |data: &&[u8]| -> Result<Option<Box<T>>, ReaderError> {
+            let mut data = inflate(*data);
+            match data.read_u32::<LE>()? {
+                0 => Ok(None),
+                _ => Ok(Some(Box::new(deserializer(data)?))),
+            }
+        };
+
+        if multithread { blocks.par_iter().map(to_asset).collect() } else { blocks.iter().map(to_asset).collect() }
+    }
+
+    if gmk.read_u32::<LE>()? != MAGIC {
+        return Err(ReaderError::InvalidGmkHeader)
+    }
+    let game_ver = match gmk.read_u32::<LE>()? {
+        800 => GameVersion::GameMaker8_0,
+        810 => GameVersion::GameMaker8_1,
+        _ => return Err(ReaderError::UnknownFormat),
+    };
+    let game_id = gmk.read_u32::<LE>()?;
+    let guid = [gmk.read_u32::<LE>()?, gmk.read_u32::<LE>()?, gmk.read_u32::<LE>()?, gmk.read_u32::<LE>()?];
+    log!(logger, "Game ID: {}", game_id);
+
+    log!(logger, "Reading settings chunk...");
+    assert_ver!(&[800, 810], gmk.read_u32::<LE>()?)?;
+    let (settings, ico_file_raw) = read_settings(inflate(read_block(&mut gmk)?), game_ver)?;
+
+    let triggers: AssetList<Trigger> =
+        get_assets(&mut gmk, |data| Trigger::deserialize_exe(data, game_ver, strict), strict, multithread)?;
+    log!(logger, " + Read {} triggers", triggers.len());
+    gmk.read_f64::<LE>()?; // timestamp
+
+    assert_ver!(&[800], gmk.read_u32::<LE>()?)?;
+    let constant_count = gmk.read_u32::<LE>()? as usize;
+    let mut constants = Vec::with_capacity(constant_count);
+    for _ in 0..constant_count {
+        let name = gmk.read_pas_string()?;
+        let expression = gmk.read_pas_string()?;
+        constants.push(Constant { name, expression });
+    }
+    gmk.read_f64::<LE>()?; // timestamp
+    log!(logger, " + Read {} constants", constants.len());
+
+    let sounds: AssetList<Sound> = get_assets(
+        &mut gmk,
+        |data| Sound::deserialize_exe(without_timestamp(data)?, game_ver, strict),
+        strict,
+        multithread,
+    )?;
+    log!(logger, " + Read {} sounds", sounds.len());
+    let sprites = get_assets(&mut gmk, |data| read_sprite(data, strict), strict, multithread)?;
+    log!(logger, " + Read {} sprites", sprites.len());
+    let backgrounds = get_assets(&mut gmk, |data| read_background(data, strict), strict, multithread)?;
+    log!(logger, " + Read {} backgrounds", backgrounds.len());
+    let paths = get_assets(&mut gmk, |data| read_path(data, strict), strict, multithread)?;
+    log!(logger, " + Read {} paths", paths.len());
+    let scripts: AssetList<Script> = get_assets(
+        &mut gmk,
+        |data| Script::deserialize_exe(without_timestamp(data)?, game_ver, strict),
+        strict,
+        multithread,
+    )?;
+    log!(logger, " + Read {} scripts", scripts.len());
+    let fonts = get_assets(&mut gmk, |data| read_font(data, game_ver, strict), strict, multithread)?;
+    log!(logger, " + Read {} fonts", fonts.len());
+    let timelines: AssetList<Timeline> = get_assets(
+        &mut gmk,
+        |data| Timeline::deserialize_exe(without_timestamp(data)?, game_ver, strict),
+        strict,
+        multithread,
+    )?;
+    log!(logger, " + Read {} timelines", timelines.len());
+    let objects: AssetList<Object> = get_assets(
+        &mut gmk,
+        |data| Object::deserialize_exe(without_timestamp(data)?, game_ver, strict),
+        strict,
+        multithread,
+    )?;
+    log!(logger, " + Read {} objects", objects.len());
+    let rooms = get_assets(&mut gmk, |data| read_room(data, game_ver, strict), strict, multithread)?;
+    log!(logger, " + Read {} rooms", rooms.len());
+
+    let last_instance_id = gmk.read_i32::<LE>()?;
+    let last_tile_id = gmk.read_i32::<LE>()?;
+
+    // Included Files
+    assert_ver!(&[800], gmk.read_u32::<LE>()?)?;
+    let included_file_count = gmk.read_u32::<LE>()? as usize;
+    let included_files = (0..included_file_count)
+        .map(|_| {
+            let mut data = inflate(read_block(&mut gmk)?);
+            data.read_f64::<LE>()?; // timestamp
+            Ok(IncludedFile::deserialize_exe(data, game_ver, strict)?)
+        })
+        .collect::<Result<Vec<_>, ReaderError>>()?;
+    log!(logger, " + Read {} included files", included_files.len());
+
+    // Only the names of extension packages are stored, the IDE loads the rest from its own copy of them
+    assert_ver!(&[700], gmk.read_u32::<LE>()?)?;
+    let extension_count = gmk.read_u32::<LE>()? as usize;
+    let extensions = (0..extension_count)
+        .map(|_| {
+            Ok(Extension { name: gmk.read_pas_string()?, folder_name: PascalString::default(), files: Vec::new() })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    log!(logger, " + Read {} extension names", extensions.len());
+
+    // Help Dialog
+    assert_ver!(&[800], gmk.read_u32::<LE>()?)?;
+    let help_dialog = {
+        let mut data = inflate(read_block(&mut gmk)?);
+        let bg_colour = data.read_u32::<LE>()?.into();
+        let new_window = data.read_u32::<LE>()? != 0;
+        let caption = data.read_pas_string()?;
+        let left = data.read_i32::<LE>()?;
+        let top = data.read_i32::<LE>()?;
+        let width = data.read_u32::<LE>()?;
+        let height = data.read_u32::<LE>()?;
+        let border = data.read_u32::<LE>()? != 0;
+        let resizable = data.read_u32::<LE>()? != 0;
+        let window_on_top = data.read_u32::<LE>()? != 0;
+        let freeze_game = data.read_u32::<LE>()? != 0;
+        data.read_f64::<LE>()?; // timestamp
+        let info = data.read_pas_string()?;
+        GameHelpDialog {
+            bg_colour,
+            new_window,
+            caption,
+            left,
+            top,
+            width,
+            height,
+            border,
+            resizable,
+            window_on_top,
+            freeze_game,
+            info,
+        }
+    };
+
+    // Action library initialization code
+    assert_ver!(&[500], gmk.read_u32::<LE>()?)?;
+    let str_count = gmk.read_u32::<LE>()? as usize;
+    let mut library_init_strings = Vec::with_capacity(str_count);
+    for _ in 0..str_count {
+        library_init_strings.push(gmk.read_pas_string()?);
+    }
+
+    // Room Order
+    assert_ver!(&[700], gmk.read_u32::<LE>()?)?;
+    let ro_count = gmk.read_u32::<LE>()? as usize;
+    let mut room_order = Vec::with_capacity(ro_count);
+    for _ in 0..ro_count {
+        room_order.push(gmk.read_i32::<LE>()?);
+    }
+    log!(logger, " + Added Room Order LUT: {:?}", room_order);
+
+    // The resource tree comes last, but it's only used by the IDE
+
+    Ok(GameAssets {
+        extensions,
+        sprites,
+        sounds,
+        backgrounds,
+        paths,
+        scripts,
+        fonts,
+        timelines,
+        objects,
+        triggers,
+        constants,
+        rooms,
+        included_files,
+
+        dx_dll: Vec::new(),
+        ico_file_raw,
+        version: game_ver,
+        help_dialog,
+        last_instance_id,
+        last_tile_id,
+        library_init_strings,
+        room_order,
+
+        settings,
+        game_id,
+        guid,
+    })
+}
@@ -21,10 +21,15 @@ macro_rules! log {
 pub mod asset;
 pub mod def;
 pub mod gamedata;
+pub mod gmk;
 pub mod reader;
 pub mod rsrc;
 pub mod settings;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+pub mod tree;
 pub mod upx;
+pub mod writer;
 
 mod colour;
 
@@ -64,8 +69,19 @@ pub struct GameAssets {
 
 #[derive(Copy, Clone, Debug)]
 pub enum GameVersion {
+    GameMaker6_0,
+    GameMaker6_1,
+    GameMaker7_0,
     GameMaker8_0,
     GameMaker8_1,
 }
 
+impl GameVersion {
+    /// Whether this is GameMaker 6.x or 7.0, whose gamedata is laid out differently in places and whose
+    /// sprites and backgrounds use a transparent colour instead of an alpha channel.
+    pub fn is_pre_8_0(self) -> bool {
+        matches!(self, GameVersion::GameMaker6_0 | GameVersion::GameMaker6_1 | GameVersion::GameMaker7_0)
+    }
+}
+
 pub use colour::Colour;
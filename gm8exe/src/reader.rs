@@ -1,6 +1,6 @@
 use crate::{
     asset::*,
-    gamedata::{self, gm80},
+    gamedata::{self, gm60, gm80},
     rsrc,
     settings::{GameHelpDialog, Settings},
     AssetList, GameAssets, GameVersion,
@@ -17,6 +17,8 @@ use std::{
 pub enum ReaderError {
     AssetError(Error),
     InvalidExeHeader,
+    InvalidGmkHeader,
+    InvalidTree(String),
     IO(io::Error),
     PartialUPXPacking,
     UnknownFormat,
@@ -27,6 +29,8 @@ impl Display for ReaderError {
         write!(f, "{}", match self {
             ReaderError::AssetError(err) => format!("asset data error: {}", err),
             ReaderError::InvalidExeHeader => "invalid exe header".into(),
+            ReaderError::InvalidGmkHeader => "invalid gmk header".into(),
+            ReaderError::InvalidTree(err) => format!("invalid source tree: {}", err),
             ReaderError::IO(err) => format!("io error: {}", err),
             ReaderError::PartialUPXPacking => {
                 "looks upx protected, can't locate headers".into()
@@ -159,11 +163,12 @@ where
     let game_ver = gamedata::find(&mut exe, logger, upx_data)?;
 
     // little helper thing
+    // the section versions in games from before GM8.0 changed between releases, so those aren't checked
     macro_rules! assert_ver {
         ($name: literal, $expect: expr, $ver: expr) => {{
             let expected = $expect;
             let got = $ver;
-            if strict {
+            if strict && !game_ver.is_pre_8_0() {
                 if got == expected {
                     Ok(())
                 } else {
@@ -183,6 +188,9 @@ where
 
     log!(logger, "Reading settings chunk...");
 
+    // GM8.0 moved these out of the settings into their own section
+    let mut constants = Vec::new();
+
     let settings = {
         fn read_data_maybe(cfg: &mut impl Read) -> Result<Option<Box<[u8]>>, ReaderError> {
             if cfg.read_u32::<LE>()? != 0 {
@@ -212,16 +220,22 @@ where
         let frequency = cfg.read_u32::<LE>()?;
         let dont_show_buttons = cfg.read_u32::<LE>()? != 0;
         let (vsync, force_cpu_render) = match (game_ver, cfg.read_u32::<LE>()?) {
-            (GameVersion::GameMaker8_0, x) => (x != 0, true), // see 8.1.141 changelog
             (GameVersion::GameMaker8_1, x) => ((x & 1) != 0, (x & (1 << 7)) != 0),
+            (_, x) => (x != 0, true), // see 8.1.141 changelog
+        };
+        let disable_screensaver = match game_ver {
+            GameVersion::GameMaker6_0 | GameVersion::GameMaker6_1 | GameVersion::GameMaker7_0 => false,
+            GameVersion::GameMaker8_0 | GameVersion::GameMaker8_1 => cfg.read_u32::<LE>()? != 0,
         };
-        let disable_screensaver = cfg.read_u32::<LE>()? != 0;
         let f4_fullscreen_toggle = cfg.read_u32::<LE>()? != 0;
         let f1_help_menu = cfg.read_u32::<LE>()? != 0;
         let esc_close_game = cfg.read_u32::<LE>()? != 0;
         let f5_save_f6_load = cfg.read_u32::<LE>()? != 0;
-        let f9_screenshot = cfg.read_u32::<LE>()? != 0;
-        let treat_close_as_esc = cfg.read_u32::<LE>()? != 0;
+        // 6.x always closes the game from the close button
+        let (f9_screenshot, treat_close_as_esc) = match game_ver {
+            GameVersion::GameMaker6_0 | GameVersion::GameMaker6_1 => (false, true),
+            _ => (cfg.read_u32::<LE>()? != 0, cfg.read_u32::<LE>()? != 0),
+        };
         let priority = cfg.read_u32::<LE>()?;
         let freeze_on_lose_focus = cfg.read_u32::<LE>()? != 0;
         let loading_bar = cfg.read_u32::<LE>()?;
@@ -235,9 +249,15 @@ where
         let log_errors = cfg.read_u32::<LE>()? != 0;
         let always_abort = cfg.read_u32::<LE>()? != 0;
         let (zero_uninitialized_vars, error_on_uninitialized_args) = match (game_ver, cfg.read_u32::<LE>()?) {
-            (GameVersion::GameMaker8_0, x) => (x != 0, false),
             (GameVersion::GameMaker8_1, x) => ((x & 1) != 0, (x & 2) != 0),
+            (_, x) => (x != 0, false),
         };
+        if game_ver.is_pre_8_0() {
+            let constant_count = cfg.read_u32::<LE>()? as usize;
+            for _ in 0..constant_count {
+                constants.push(Constant { name: cfg.read_pas_string()?, expression: cfg.read_pas_string()? });
+            }
+        }
         let swap_creation_events = match cfg.read_u32::<LE>() {
             Ok(_webgl) => cfg.read_u32::<LE>()? != 0,
             Err(_) => false,
@@ -400,7 +420,10 @@ where
     exe.read_exact(&mut dx_dll)?;
 
     // yeah
-    gm80::decrypt(&mut exe, logger)?;
+    match game_ver {
+        GameVersion::GameMaker6_0 | GameVersion::GameMaker6_1 => gm60::decrypt(&mut exe, logger)?,
+        _ => gm80::decrypt(&mut exe, logger)?,
+    }
 
     // Garbage field - random bytes
     let garbage_dwords = exe.read_u32::<LE>()?;
@@ -474,13 +497,17 @@ where
         get_assets(src, |data| <T as Asset>::deserialize_exe(data, version, strict), multithread)
     }
 
-    assert_ver!("extensions header", 700, exe.read_u32::<LE>()?)?;
-    let extension_count = exe.read_u32::<LE>()? as usize;
-    let mut extensions = Vec::with_capacity(extension_count);
-    for _ in 0..extension_count {
-        let ext = Extension::read(&mut exe, strict)?;
-        log!(logger, "+ Added extension '{}' (files: {})", ext.name, ext.files.len());
-        extensions.push(ext);
+    // Extensions, which 7.0 added
+    let mut extensions = Vec::new();
+    if !matches!(game_ver, GameVersion::GameMaker6_0 | GameVersion::GameMaker6_1) {
+        assert_ver!("extensions header", 700, exe.read_u32::<LE>()?)?;
+        let extension_count = exe.read_u32::<LE>()? as usize;
+        extensions.reserve(extension_count);
+        for _ in 0..extension_count {
+            let ext = Extension::read(&mut exe, strict)?;
+            log!(logger, "+ Added extension '{}' (files: {})", ext.name, ext.files.len());
+            extensions.push(ext);
+        }
     }
 
     // Rewrap data immutable.
@@ -488,9 +515,13 @@ where
     let mut exe = io::Cursor::new(exe.into_inner() as &[u8]);
     exe.set_position(prev_pos);
 
-    // Triggers
-    assert_ver!("triggers header", 800, exe.read_u32::<LE>()?)?;
-    let triggers: AssetList<Trigger> = get_assets_ex(&mut exe, game_ver, strict, multithread)?;
+    // Triggers and constants, which 8.0 added and moved out of the settings respectively
+    let triggers: AssetList<Trigger> = if game_ver.is_pre_8_0() {
+        Vec::new()
+    } else {
+        assert_ver!("triggers header", 800, exe.read_u32::<LE>()?)?;
+        get_assets_ex(&mut exe, game_ver, strict, multithread)?
+    };
     if logger.is_some() {
         triggers.iter().flatten().for_each(|trigger| {
             log!(
@@ -503,15 +534,18 @@ where
         });
     }
 
-    // Constants
-    assert_ver!("constants header", 800, exe.read_u32::<LE>()?)?;
-    let constant_count = exe.read_u32::<LE>()? as usize;
-    let mut constants = Vec::with_capacity(constant_count);
-    for _ in 0..constant_count {
-        let name = exe.read_pas_string()?;
-        let expression = exe.read_pas_string()?;
-        log!(logger, " + Added constant '{}' (expression: {})", name, expression);
-        constants.push(Constant { name, expression });
+    if !game_ver.is_pre_8_0() {
+        assert_ver!("constants header", 800, exe.read_u32::<LE>()?)?;
+        let constant_count = exe.read_u32::<LE>()? as usize;
+        constants.reserve(constant_count);
+        for _ in 0..constant_count {
+            let name = exe.read_pas_string()?;
+            let expression = exe.read_pas_string()?;
+            constants.push(Constant { name, expression });
+        }
+    }
+    for constant in &constants {
+        log!(logger, " + Added constant '{}' (expression: {})", constant.name, constant.expression);
     }
 
     // Sounds
@@ -0,0 +1,349 @@
+//! Test data shared by this crate's tests and the decompiler's. It's only built for tests, or with the
+//! `test-support` feature.
+
+use crate::{
+    asset::{
+        self,
+        extension::{self, CallingConvention, FileKind, FunctionValueKind},
+        included_file::ExportSetting,
+        path::{ConnectionKind, Point},
+        room::{self, ViewFollowData},
+        sound::SoundFX,
+        sprite::{ColliderShape, CollisionMap, Frame},
+        CodeAction, PascalString, SoundKind, TriggerKind,
+    },
+    settings::{GameHelpDialog, Settings},
+    GameAssets, GameVersion,
+};
+
+/// A code action running the given GML.
+pub fn action(code: &str) -> CodeAction {
+    let mut param_strings: [PascalString; 8] = Default::default();
+    param_strings[0] = code.into();
+    CodeAction {
+        id: 603,
+        applies_to: -1,
+        is_condition: false,
+        invert_condition: false,
+        is_relative: false,
+        lib_id: 1,
+        action_kind: 7,
+        execution_type: 2,
+        can_be_relative: 0,
+        applies_to_something: true,
+        fn_name: "".into(),
+        fn_code: "".into(),
+        param_count: 1,
+        param_types: [1, 0, 0, 0, 0, 0, 0, 0],
+        param_strings,
+    }
+}
+
+/// Makes a game with one of everything, the way it would be read from an executable.
+/// Before 8.0, images are opaque except where they're the colour of their bottom-left pixel, and sprites have one
+/// collision mask for all their frames.
+pub fn game(version: GameVersion) -> GameAssets {
+    let (opaque, translucent) = if version.is_pre_8_0() { (255, 255) } else { (200, 100) };
+    let is_6_x = matches!(version, GameVersion::GameMaker6_0 | GameVersion::GameMaker6_1);
+
+    // a 4x4 diamond, and the same thing with one more pixel
+    let mut pixels = [0u8; 64];
+    for i in [1, 4, 5, 6, 9] {
+        pixels[i * 4..i * 4 + 4].copy_from_slice(&[255, 0, 0, opaque]);
+    }
+    let mut pixels2 = pixels;
+    pixels2[15 * 4..16 * 4].copy_from_slice(&[0, 0, 255, translucent]);
+    let frame = Frame { width: 4, height: 4, data: Box::new(pixels) };
+    let frame2 = Frame { width: 4, height: 4, data: Box::new(pixels2) };
+    let frames = vec![frame, frame2];
+    let per_frame_colliders = !version.is_pre_8_0();
+    let colliders = CollisionMap::generate(&frames, ColliderShape::Precise, 0, per_frame_colliders, None);
+
+    // a red pixel above a transparent one
+    let bg_pixels: Box<[u8]> =
+        if version.is_pre_8_0() { Box::new([255, 0, 0, 255, 0, 0, 0, 0]) } else { Box::new([9; 8]) };
+
+    let mut dmap = Box::new([0; 0x600]);
+    dmap[0x41 * 6] = 7;
+
+    let mut events: Vec<Vec<(u32, Vec<CodeAction>)>> = (0..12).map(|_| Vec::new()).collect();
+    events[0].push((0, vec![action("x = 1;")]));
+    events[3].push((1, vec![action("y += 1;"), action("x -= 1;")]));
+
+    let mut game = GameAssets {
+        triggers: vec![
+            None,
+            Some(Box::new(asset::Trigger {
+                name: "trigger".into(),
+                condition: "return true;".into(),
+                moment: TriggerKind::BeginStep,
+                constant_name: "ev_trigger".into(),
+            })),
+        ],
+        constants: vec![asset::Constant { name: "five".into(), expression: "2 + 3".into() }],
+        extensions: vec![asset::Extension {
+            name: "ext".into(),
+            folder_name: "folder".into(),
+            files: vec![
+                extension::File {
+                    name: "ext.gml".into(),
+                    kind: FileKind::GmlScript,
+                    initializer: "ext_init".into(),
+                    finalizer: "".into(),
+                    functions: vec![extension::FileFunction {
+                        name: "ext_init".into(),
+                        external_name: "ext_init".into(),
+                        convention: CallingConvention::Gml,
+                        id: 1,
+                        arg_count: 1,
+                        arg_types: [FunctionValueKind::GMString; 17],
+                        return_type: FunctionValueKind::GMReal,
+                    }],
+                    consts: vec![extension::FileConst { name: "EXT".into(), value: "2".into() }],
+                    contents: (0..=255).collect(),
+                },
+                extension::File {
+                    name: "lib.lib".into(),
+                    kind: FileKind::ActionLibrary,
+                    initializer: "".into(),
+                    finalizer: "".into(),
+                    functions: Vec::new(),
+                    consts: Vec::new(),
+                    contents: Box::new([]),
+                },
+            ],
+        }],
+        sprites: vec![Some(Box::new(asset::Sprite {
+            name: "spr".into(),
+            origin_x: 2,
+            origin_y: -1,
+            frames,
+            colliders,
+            per_frame_colliders,
+        }))],
+        sounds: vec![Some(Box::new(asset::Sound {
+            name: "snd".into(),
+            source: "C:\\snd.wav".into(),
+            extension: ".wav".into(),
+            data: Some(Box::new([1, 2, 3, 4])),
+            kind: SoundKind::BackgroundMusic,
+            volume: 0.5,
+            pan: -0.25,
+            preload: true,
+            fx: SoundFX { chorus: true, echo: false, flanger: false, gargle: true, reverb: false },
+        }))],
+        backgrounds: vec![
+            Some(Box::new(asset::Background { name: "bg".into(), width: 1, height: 2, data: Some(bg_pixels) })),
+            Some(Box::new(asset::Background { name: "empty".into(), width: 0, height: 0, data: None })),
+        ],
+        paths: vec![Some(Box::new(asset::Path {
+            name: "pth".into(),
+            connection: ConnectionKind::SmoothCurve,
+            precision: 4,
+            closed: true,
+            points: vec![Point { x: 1.0, y: 2.0, speed: 100.0 }, Point { x: -3.5, y: 0.0, speed: 50.0 }],
+        }))],
+        scripts: vec![Some(Box::new(asset::Script { name: "scr".into(), source: "return argument0;".into() })), None],
+        fonts: vec![Some(Box::new(asset::Font {
+            name: "fnt".into(),
+            sys_name: "Arial".into(),
+            size: 12,
+            bold: true,
+            italic: false,
+            range_start: 32,
+            range_end: 127,
+            charset: match version {
+                GameVersion::GameMaker8_1 => 1,
+                _ => 0,
+            },
+            aa_level: match version {
+                GameVersion::GameMaker8_1 => 3,
+                _ => 0,
+            },
+            dmap,
+            map_width: 1,
+            map_height: 1,
+            pixel_map: Box::new([255]),
+        }))],
+        timelines: vec![Some(Box::new(asset::Timeline {
+            name: "tl".into(),
+            moments: vec![(0, vec![action("a = 0;")]), (30, vec![action("a = 30;")])],
+        }))],
+        objects: vec![Some(Box::new(asset::Object {
+            name: "obj".into(),
+            sprite_index: 0,
+            solid: true,
+            visible: true,
+            depth: -10,
+            persistent: false,
+            parent_index: -1,
+            mask_index: -1,
+            events,
+        }))],
+        rooms: vec![Some(Box::new(asset::Room {
+            name: "rm".into(),
+            caption: "Room".into(),
+            width: 640,
+            height: 480,
+            speed: 50,
+            persistent: false,
+            bg_colour: 0xFF8000.into(),
+            clear_screen: true,
+            clear_region: true,
+            creation_code: "global.a = 1;".into(),
+            backgrounds: (0..8)
+                .map(|i| room::Background {
+                    visible_on_start: i == 0,
+                    is_foreground: false,
+                    source_bg: if i == 0 { 0 } else { -1 },
+                    xoffset: 0,
+                    yoffset: 0,
+                    tile_horz: true,
+                    tile_vert: true,
+                    hspeed: 0,
+                    vspeed: 0,
+                    stretch: false,
+                })
+                .collect(),
+            views_enabled: false,
+            views: (0..8)
+                .map(|_| room::View {
+                    visible: false,
+                    source_x: 0,
+                    source_y: 0,
+                    source_w: 640,
+                    source_h: 480,
+                    port_x: 0,
+                    port_y: 0,
+                    port_w: 640,
+                    port_h: 480,
+                    following: ViewFollowData { hborder: 32, vborder: 32, hspeed: -1, vspeed: -1, target: -1 },
+                })
+                .collect(),
+            instances: vec![
+                room::Instance {
+                    x: 16,
+                    y: 32,
+                    object: 0,
+                    id: 100001,
+                    creation_code: "".into(),
+                    xscale: 1.0,
+                    yscale: 1.0,
+                    blend: u32::MAX,
+                    angle: 0.0,
+                },
+                room::Instance {
+                    x: 48,
+                    y: 32,
+                    object: 0,
+                    id: 100002,
+                    creation_code: "speed = 2;".into(),
+                    xscale: -1.0,
+                    yscale: 2.5,
+                    blend: 255,
+                    angle: 90.0,
+                },
+            ],
+            tiles: vec![room::Tile {
+                x: 0,
+                y: 0,
+                source_bg: 0,
+                tile_x: 0,
+                tile_y: 0,
+                width: 1,
+                height: 2,
+                depth: 1000000,
+                id: 10000001,
+                xscale: 2.0,
+                yscale: 0.5,
+                blend: 65280,
+            }],
+            uses_810_features: true,
+            uses_811_features: true,
+        }))],
+        included_files: vec![asset::IncludedFile {
+            file_name: "data.txt".into(),
+            source_path: "C:\\data.txt".into(),
+            data_exists: true,
+            source_length: 3,
+            stored_in_gmk: true,
+            embedded_data: Some(Box::new(*b"abc")),
+            export_settings: ExportSetting::CustomFolder("out".into()),
+            overwrite_file: true,
+            free_memory: true,
+            remove_at_end: false,
+        }],
+        version,
+
+        dx_dll: Vec::new(),
+        ico_file_raw: Some(vec![0, 0, 1, 0]),
+        help_dialog: GameHelpDialog {
+            bg_colour: 0xFFFFE1.into(),
+            new_window: true,
+            caption: "Game Information".into(),
+            left: -1,
+            top: -1,
+            width: 600,
+            height: 400,
+            border: true,
+            resizable: true,
+            window_on_top: false,
+            freeze_game: true,
+            info: "{\\rtf1 help}".into(),
+        },
+        last_instance_id: 100002,
+        last_tile_id: 10000001,
+        library_init_strings: vec!["__init_lib();".into()],
+        room_order: vec![0],
+
+        settings: Settings {
+            fullscreen: false,
+            scaling: -1,
+            interpolate_pixels: true,
+            clear_colour: 0,
+            allow_resize: false,
+            window_on_top: false,
+            dont_draw_border: false,
+            dont_show_buttons: false,
+            display_cursor: true,
+            freeze_on_lose_focus: false,
+            disable_screensaver: !version.is_pre_8_0(),
+            force_cpu_render: true,
+            set_resolution: false,
+            colour_depth: 0,
+            resolution: 0,
+            frequency: 0,
+            vsync: true,
+            esc_close_game: true,
+            treat_close_as_esc: true,
+            f1_help_menu: true,
+            f4_fullscreen_toggle: true,
+            f5_save_f6_load: false,
+            f9_screenshot: !is_6_x,
+            priority: 1,
+            custom_load_image: Some(Box::new([7; 16])),
+            transparent: false,
+            translucency: 255,
+            loading_bar: 2,
+            backdata: Some(Box::new([1; 8])),
+            frontdata: Some(Box::new([1, 2, 3])),
+            scale_progress_bar: true,
+            show_error_messages: true,
+            log_errors: false,
+            always_abort: false,
+            zero_uninitialized_vars: true,
+            error_on_uninitialized_args: true,
+            swap_creation_events: false,
+        },
+        game_id: 123456,
+        guid: [1, 2, 3, 4],
+    };
+    // 7.0 added extensions and 8.0 added triggers
+    if is_6_x {
+        game.extensions.clear();
+    }
+    if version.is_pre_8_0() {
+        game.triggers.clear();
+    }
+    game
+}
@@ -0,0 +1,1121 @@
+//! Reads a game from a tree of source files, the kind the decompiler writes with `--tree`.
+//!
+//! There's one folder per group in the resource tree, plus folders for the things which aren't in it. Each folder has
+//! an `index.txt` listing its assets' file names in ID order, with an empty line for each deleted asset, so IDs don't
+//! change. If a name had to be changed to be a valid file name, the real name follows it on the same line, after a
+//! tab. A folder that doesn't exist is the same as an empty one.
+//!
+//! GML goes in `.gml` files as-is. Everything else goes in INI files, where assets refer to each other by file name,
+//! or by ID if the asset doesn't exist. INI files are UTF-8, so names and strings in them that aren't get mangled.
+//! Images are PNGs, and any other data is stored raw.
+//!
+//! Object events and timeline moments hold a list of actions. Each action starts with a line like
+//! `#action id=603 applies_to=-2`, giving any fields which differ from an "Execute Code" action applied to `self`,
+//! and the rest is the code, or nothing for actions which don't run code. The line is left out for the first action
+//! if there's nothing to put in it, so events with a single code action are plain GML.
+//!
+//! Collision masks aren't stored, so they're generated from each sprite's mask settings when it's read,
+//! the same as with projects.
+
+use crate::{
+    asset::{
+        code_action,
+        extension::{self, CallingConvention, FileConst, FileFunction, FileKind, FunctionValueKind},
+        included_file::ExportSetting,
+        path::{ConnectionKind, Point},
+        room::{self, ViewFollowData},
+        sound::{SoundFX, SoundKind},
+        sprite::{BoundingBox, ColliderShape, CollisionMap, Frame},
+        trigger::TriggerKind,
+        Background, CodeAction, Constant, Extension, Font, IncludedFile, Object, PascalString, Path, Room, Script,
+        Sound, Sprite, Timeline, Trigger,
+    },
+    reader::ReaderError,
+    settings::{GameHelpDialog, Settings},
+    AssetList, GameAssets, GameVersion,
+};
+use ini::{Ini, ParseOption};
+use rayon::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    fs, io,
+    path::{self as fs_path, PathBuf},
+    str::FromStr,
+};
+
+/// The file in each group's folder which lists its assets.
+pub const INDEX_FILE: &str = "index.txt";
+
+const ACTION_HEADER: &[u8] = b"#action";
+
+/// Names of the event types, in the same order as their IDs, matching the `ev_` constants in GML.
+pub const EVENT_NAMES: [&str; 12] = [
+    "create",
+    "destroy",
+    "alarm",
+    "step",
+    "collision",
+    "keyboard",
+    "mouse",
+    "other",
+    "draw",
+    "keypress",
+    "keyrelease",
+    "trigger",
+];
+
+/// Names that Windows won't let you give to a file, with or without an extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2",
+    "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Replaces any characters which can't be in a file name.
+pub fn sanitize(name: &[u8]) -> String {
+    let mut file_name = String::from_utf8_lossy(name)
+        .chars()
+        .map(|c| if c.is_control() || "<>:\"/\\|?*\t".contains(c) { '_' } else { c })
+        .collect::<String>();
+    if file_name.ends_with(['.', ' ']) {
+        file_name.push('_');
+    }
+    file_name
+}
+
+/// Picks a file name for each asset, keeping its name where possible so that they're easy to find.
+/// Characters which aren't allowed in file names are replaced, and clashes get the asset's ID added.
+pub fn file_names<'a, I>(names: I) -> Vec<Option<String>>
+where
+    I: IntoIterator<Item = Option<&'a PascalString>>,
+{
+    let mut taken = HashSet::new();
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(id, name)| {
+            let mut file_name = sanitize(&name?.0);
+            let upper = file_name.to_uppercase();
+            if file_name.is_empty() || RESERVED_NAMES.contains(&upper.as_str()) || taken.contains(&upper) {
+                file_name = format!("{}_{}", file_name, id);
+            }
+            taken.insert(file_name.to_uppercase());
+            Some(file_name)
+        })
+        .collect()
+}
+
+fn default_action() -> CodeAction {
+    let mut param_types = [0; code_action::PARAM_COUNT];
+    param_types[0] = 1;
+    CodeAction {
+        id: 603,
+        applies_to: -1,
+        is_condition: false,
+        invert_condition: false,
+        is_relative: false,
+        lib_id: 1,
+        action_kind: 7,
+        execution_type: 2,
+        can_be_relative: 0,
+        applies_to_something: true,
+        fn_name: PascalString::default(),
+        fn_code: PascalString::default(),
+        param_count: 1,
+        param_types,
+        param_strings: Default::default(),
+    }
+}
+
+fn list<T: Display>(values: impl IntoIterator<Item = T>) -> String {
+    values.into_iter().map(|x| x.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn escape(value: &[u8], output: &mut Vec<u8>) {
+    for &byte in value {
+        match byte {
+            b'\\' => output.extend_from_slice(b"\\\\"),
+            b' ' => output.extend_from_slice(b"\\s"),
+            b'\r' => output.extend_from_slice(b"\\r"),
+            b'\n' => output.extend_from_slice(b"\\n"),
+            b'\t' => output.extend_from_slice(b"\\t"),
+            _ => output.push(byte),
+        }
+    }
+}
+
+fn unescape(value: &[u8]) -> Result<Vec<u8>, String> {
+    let mut output = Vec::with_capacity(value.len());
+    let mut bytes = value.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte != b'\\' {
+            output.push(byte);
+            continue
+        }
+        output.push(match bytes.next() {
+            Some(b'\\') => b'\\',
+            Some(b's') => b' ',
+            Some(b'r') => b'\r',
+            Some(b'n') => b'\n',
+            Some(b't') => b'\t',
+            _ => return Err(format!("bad escape sequence in {}", String::from_utf8_lossy(value))),
+        });
+    }
+    Ok(output)
+}
+
+/// Writes a list of actions in the format described at the top of this file.
+pub fn write_actions(actions: &[CodeAction]) -> Vec<u8> {
+    let default = default_action();
+    let mut output = Vec::new();
+    for (i, action) in actions.iter().enumerate() {
+        let mut header = ACTION_HEADER.to_vec();
+        let mut field = |name: &str, value: &[u8]| {
+            header.push(b' ');
+            header.extend_from_slice(name.as_bytes());
+            header.push(b'=');
+            escape(value, &mut header);
+        };
+        let mut number = |name: &str, value: String, default: String| {
+            if value != default {
+                field(name, value.as_bytes());
+            }
+        };
+        number("lib_id", action.lib_id.to_string(), default.lib_id.to_string());
+        number("id", action.id.to_string(), default.id.to_string());
+        number("kind", action.action_kind.to_string(), default.action_kind.to_string());
+        number("execution_type", action.execution_type.to_string(), default.execution_type.to_string());
+        number("can_be_relative", action.can_be_relative.to_string(), default.can_be_relative.to_string());
+        number(
+            "applies_to_something",
+            action.applies_to_something.to_string(),
+            default.applies_to_something.to_string(),
+        );
+        number("is_condition", action.is_condition.to_string(), default.is_condition.to_string());
+        number("applies_to", action.applies_to.to_string(), default.applies_to.to_string());
+        number("relative", action.is_relative.to_string(), default.is_relative.to_string());
+        number("invert", action.invert_condition.to_string(), default.invert_condition.to_string());
+        number("param_count", action.param_count.to_string(), default.param_count.to_string());
+        number("param_types", list(action.param_types), list(default.param_types));
+        if !action.fn_name.0.is_empty() {
+            field("fn_name", &action.fn_name.0);
+        }
+        if !action.fn_code.0.is_empty() {
+            field("fn_code", &action.fn_code.0);
+        }
+        // code actions have their code in the body, other actions have all their arguments in the header
+        let body_param = if action.action_kind == 7 { 1 } else { 0 };
+        for (j, param) in action.param_strings.iter().enumerate().skip(body_param) {
+            if !param.0.is_empty() {
+                field(&format!("arg{}", j), &param.0);
+            }
+        }
+
+        if i != 0 {
+            output.extend_from_slice(b"\r\n");
+        }
+        // an empty file is an event with no actions, so an empty code action needs its header
+        let body = if body_param == 1 { action.param_strings[0].0.as_ref() } else { &[] };
+        if i != 0 || header.len() != ACTION_HEADER.len() || body.is_empty() {
+            output.extend_from_slice(&header);
+            output.extend_from_slice(b"\r\n");
+        }
+        output.extend_from_slice(body);
+    }
+    output
+}
+
+/// Whether an action header starts at the beginning of `data`.
+fn starts_with_header(data: &[u8]) -> bool {
+    data.starts_with(ACTION_HEADER) && matches!(data.get(ACTION_HEADER.len()), None | Some(b' ' | b'\r' | b'\n'))
+}
+
+/// Reads a list of actions in the format described at the top of this file.
+pub fn read_actions(data: &[u8]) -> Result<Vec<CodeAction>, String> {
+    if data.is_empty() {
+        return Ok(Vec::new())
+    }
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for i in 0..data.len() {
+        if data[i..].starts_with(b"\r\n") && starts_with_header(&data[i + 2..]) {
+            segments.push(&data[start..i]);
+            start = i + 2;
+        }
+    }
+    segments.push(&data[start..]);
+
+    segments
+        .into_iter()
+        .map(|segment| {
+            let mut action = default_action();
+            let (header, body) = if starts_with_header(segment) {
+                match segment.iter().position(|&b| b == b'\n') {
+                    Some(end) => (&segment[..end], &segment[end + 1..]),
+                    None => (segment, &[][..]),
+                }
+            } else {
+                (&[][..], segment)
+            };
+            let header = header.strip_suffix(b"\r").unwrap_or(header);
+            for field in header.get(ACTION_HEADER.len()..).unwrap_or_default().split(|&b| b == b' ') {
+                if field.is_empty() {
+                    continue
+                }
+                let equals = field.iter().position(|&b| b == b'=').ok_or("action field has no value")?;
+                let (key, value) = (String::from_utf8_lossy(&field[..equals]), unescape(&field[equals + 1..])?);
+                let text = String::from_utf8_lossy(&value);
+                let bad_value = || format!("bad value for action field {}: {}", key, text);
+                fn parse<T: FromStr>(text: &str, bad_value: impl Fn() -> String) -> Result<T, String> {
+                    text.parse().map_err(|_| bad_value())
+                }
+                match key.as_ref() {
+                    "lib_id" => action.lib_id = parse(&text, bad_value)?,
+                    "id" => action.id = parse(&text, bad_value)?,
+                    "kind" => action.action_kind = parse(&text, bad_value)?,
+                    "execution_type" => action.execution_type = parse(&text, bad_value)?,
+                    "can_be_relative" => action.can_be_relative = parse(&text, bad_value)?,
+                    "applies_to_something" => action.applies_to_something = parse(&text, bad_value)?,
+                    "is_condition" => action.is_condition = parse(&text, bad_value)?,
+                    "applies_to" => action.applies_to = parse(&text, bad_value)?,
+                    "relative" => action.is_relative = parse(&text, bad_value)?,
+                    "invert" => action.invert_condition = parse(&text, bad_value)?,
+                    "param_count" => action.param_count = parse(&text, bad_value)?,
+                    "param_types" => {
+                        let types = text.split(',').map(|x| parse(x, bad_value)).collect::<Result<Vec<u32>, _>>()?;
+                        action.param_types = types.try_into().map_err(|_| bad_value())?;
+                    },
+                    "fn_name" => action.fn_name = PascalString(value.into()),
+                    "fn_code" => action.fn_code = PascalString(value.into()),
+                    arg => match arg.strip_prefix("arg").and_then(|i| i.parse::<usize>().ok()) {
+                        Some(i) if i < code_action::PARAM_COUNT => action.param_strings[i] = PascalString(value.into()),
+                        _ => return Err(format!("unknown action field {}", key)),
+                    },
+                }
+            }
+            if action.action_kind == 7 {
+                action.param_strings[0] = PascalString(body.into());
+            } else if !body.is_empty() {
+                return Err(format!("action {} isn't a code action, but has code after it", action.id))
+            }
+            Ok(action)
+        })
+        .collect()
+}
+
+fn invalid(path: &fs_path::Path, reason: impl Display) -> ReaderError {
+    ReaderError::InvalidTree(format!("{}: {}", path.display(), reason))
+}
+
+fn read_file(path: &fs_path::Path) -> Result<Vec<u8>, ReaderError> {
+    fs::read(path).map_err(|e| invalid(path, e))
+}
+
+/// Reads a file which doesn't have to exist.
+fn read_file_maybe(path: &fs_path::Path) -> Result<Option<Vec<u8>>, ReaderError> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(invalid(path, e)),
+    }
+}
+
+fn read_gml(path: &fs_path::Path) -> Result<PascalString, ReaderError> {
+    Ok(PascalString(read_file_maybe(path)?.unwrap_or_default().into()))
+}
+
+/// Reads a PNG, converting it to RGBA.
+fn read_png(path: &fs_path::Path) -> Result<(u32, u32, Vec<u8>), ReaderError> {
+    let file = fs::File::open(path).map_err(|e| invalid(path, e))?;
+    let mut decoder = png::Decoder::new(io::BufReader::new(file));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| invalid(path, e))?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).map_err(|e| invalid(path, e))?;
+    data.truncate(info.buffer_size());
+    let data = match info.color_type {
+        png::ColorType::Rgba => data,
+        png::ColorType::Rgb => data.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => data.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => data.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+        png::ColorType::Indexed => return Err(invalid(path, "couldn't expand indexed colours")),
+    };
+    Ok((info.width, info.height, data))
+}
+
+/// An INI file from the tree, which knows where it came from for error messages.
+struct IniFile {
+    path: PathBuf,
+    ini: Ini,
+}
+
+impl IniFile {
+    fn load(path: PathBuf) -> Result<Self, ReaderError> {
+        // GML strings are quoted, so quotes have to be kept
+        let options = ParseOption { enabled_quote: false, ..Default::default() };
+        let ini = Ini::load_from_file_opt(&path, options).map_err(|e| invalid(&path, e))?;
+        Ok(Self { path, ini })
+    }
+
+    fn has_section(&self, section: &str) -> bool {
+        self.ini.section(Some(section)).is_some()
+    }
+
+    fn str(&self, section: &str, key: &str) -> Result<&str, ReaderError> {
+        self.ini.get_from(Some(section), key).ok_or_else(|| invalid(&self.path, format!("no {} in [{}]", key, section)))
+    }
+
+    fn get<T: FromStr>(&self, section: &str, key: &str) -> Result<T, ReaderError> {
+        let value = self.str(section, key)?;
+        value.parse().map_err(|_| invalid(&self.path, format!("bad value for {}: {}", key, value)))
+    }
+
+    fn list<T: FromStr>(&self, section: &str, key: &str) -> Result<Vec<T>, ReaderError> {
+        let value = self.str(section, key)?;
+        value
+            .split(',')
+            .map(|x| x.trim().parse().map_err(|_| invalid(&self.path, format!("bad value for {}: {}", key, value))))
+            .collect()
+    }
+
+    fn string(&self, section: &str, key: &str) -> Result<PascalString, ReaderError> {
+        Ok(self.str(section, key)?.into())
+    }
+
+    fn reference(&self, section: &str, key: &str, index: &Index) -> Result<i32, ReaderError> {
+        let value = self.str(section, key)?;
+        index.reference(value).ok_or_else(|| invalid(&self.path, format!("no asset called {}", value)))
+    }
+}
+
+/// The contents of a group's index file: each asset's file name and real name, by ID.
+struct Index {
+    dir: PathBuf,
+    entries: Vec<Option<(String, Box<[u8]>)>>,
+    ids: HashMap<String, i32>,
+}
+
+impl Index {
+    fn load(dir: PathBuf) -> Result<Self, ReaderError> {
+        let path = dir.join(INDEX_FILE);
+        let data = read_file_maybe(&path)?.unwrap_or_default();
+        let mut lines = data.split(|&b| b == b'\n').collect::<Vec<_>>();
+        if lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        let entries = lines
+            .into_iter()
+            .map(|line| {
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                if line.is_empty() {
+                    return Ok(None)
+                }
+                let (file_name, name) = match line.iter().position(|&b| b == b'\t') {
+                    Some(tab) => (&line[..tab], &line[tab + 1..]),
+                    None => (line, line),
+                };
+                let file_name = std::str::from_utf8(file_name).map_err(|e| invalid(&path, e))?;
+                Ok(Some((file_name.to_string(), name.into())))
+            })
+            .collect::<Result<Vec<_>, ReaderError>>()?;
+        let ids = entries.iter().enumerate().filter_map(|(i, e)| Some((e.as_ref()?.0.clone(), i as i32))).collect();
+        Ok(Self { dir, entries, ids })
+    }
+
+    /// Looks up an asset the way they're referred to in the tree: by file name or ID, or -1 if empty.
+    fn reference(&self, value: &str) -> Option<i32> {
+        match value {
+            "" => Some(-1),
+            _ => self.ids.get(value).copied().or_else(|| value.parse().ok()),
+        }
+    }
+
+    /// Reads each asset in the group, in parallel if `multithread` is set.
+    fn read<T, F>(&self, multithread: bool, read: F) -> Result<AssetList<T>, ReaderError>
+    where
+        T: Send,
+        F: Fn(&fs_path::Path, &str, PascalString) -> Result<T, ReaderError> + Sync,
+    {
+        let to_asset = |entry: &Option<(String, Box<[u8]>)>| match entry {
+            Some((file_name, name)) => Ok(Some(Box::new(read(&self.dir, file_name, PascalString(name.clone()))?))),
+            None => Ok(None),
+        };
+        if multithread {
+            self.entries.par_iter().map(to_asset).collect()
+        } else {
+            self.entries.iter().map(to_asset).collect()
+        }
+    }
+}
+
+fn read_sprite(dir: &fs_path::Path, file_name: &str, name: PascalString) -> Result<Sprite, ReaderError> {
+    let dir = dir.join(file_name);
+    let ini = IniFile::load(dir.join("sprite.ini"))?;
+    let s = "sprite";
+    let frame_count = ini.get::<usize>(s, "frames")?;
+    let frames = (0..frame_count)
+        .map(|i| {
+            let path = dir.join(format!("{}.png", i));
+            if !path.exists() {
+                return Ok(Frame { width: 0, height: 0, data: Box::default() })
+            }
+            let (width, height, data) = read_png(&path)?;
+            Ok(Frame { width, height, data: data.into() })
+        })
+        .collect::<Result<Vec<_>, ReaderError>>()?;
+    let per_frame_colliders = ini.get(s, "per_frame_colliders")?;
+
+    // The decompiler writes out the mask settings it worked out from the game's collision maps, if it could
+    let colliders = if ini.str(s, "shape").is_ok() {
+        let shape = ColliderShape::from(ini.get::<u32>(s, "shape")?);
+        let alpha_tolerance = ini.get::<u32>(s, "alpha_tolerance")?.min(255) as u8;
+        let bbox = BoundingBox {
+            left: ini.get(s, "bbox_left")?,
+            right: ini.get(s, "bbox_right")?,
+            top: ini.get(s, "bbox_top")?,
+            bottom: ini.get(s, "bbox_bottom")?,
+        };
+        CollisionMap::generate(&frames, shape, alpha_tolerance, per_frame_colliders, Some(bbox))
+    } else {
+        CollisionMap::generate(&frames, ColliderShape::Precise, 0, per_frame_colliders, None)
+    };
+
+    Ok(Sprite {
+        name,
+        origin_x: ini.get(s, "origin_x")?,
+        origin_y: ini.get(s, "origin_y")?,
+        frames,
+        colliders,
+        per_frame_colliders,
+    })
+}
+
+fn read_sound(dir: &fs_path::Path, file_name: &str, name: PascalString) -> Result<Sound, ReaderError> {
+    let ini = IniFile::load(dir.join(format!("{}.ini", file_name)))?;
+    let s = "sound";
+    let extension = ini.string(s, "extension")?;
+    let data = read_file_maybe(&dir.join(format!("{}{}", file_name, sanitize(&extension.0))))?;
+    Ok(Sound {
+        name,
+        source: ini.string(s, "source")?,
+        extension,
+        data: data.map(Vec::into_boxed_slice),
+        kind: SoundKind::from(ini.get::<u32>(s, "kind")?),
+        volume: ini.get(s, "volume")?,
+        pan: ini.get(s, "pan")?,
+        preload: ini.get(s, "preload")?,
+        fx: SoundFX {
+            chorus: ini.get(s, "chorus")?,
+            echo: ini.get(s, "echo")?,
+            flanger: ini.get(s, "flanger")?,
+            gargle: ini.get(s, "gargle")?,
+            reverb: ini.get(s, "reverb")?,
+        },
+    })
+}
+
+fn read_background(dir: &fs_path::Path, file_name: &str, name: PascalString) -> Result<Background, ReaderError> {
+    let path = dir.join(format!("{}.png", file_name));
+    if !path.exists() {
+        let ini = IniFile::load(dir.join(format!("{}.ini", file_name)))?;
+        return Ok(Background {
+            name,
+            width: ini.get("background", "width")?,
+            height: ini.get("background", "height")?,
+            data: None,
+        })
+    }
+    let (width, height, data) = read_png(&path)?;
+    Ok(Background { name, width, height, data: Some(data.into()) })
+}
+
+fn read_path(dir: &fs_path::Path, file_name: &str, name: PascalString) -> Result<Path, ReaderError> {
+    let ini = IniFile::load(dir.join(format!("{}.ini", file_name)))?;
+    let s = "path";
+    let points = (0..)
+        .map_while(|i| ini.ini.get_from(Some("points"), &i.to_string()).map(|_| i))
+        .map(|i| match ini.list::<f64>("points", &i.to_string())?.as_slice() {
+            &[x, y, speed] => Ok(Point { x, y, speed }),
+            _ => Err(invalid(&ini.path, format!("point {} should be x,y,speed", i))),
+        })
+        .collect::<Result<_, ReaderError>>()?;
+    Ok(Path {
+        name,
+        connection: ConnectionKind::from(u32::from(ini.get::<bool>(s, "smooth")?)),
+        precision: ini.get(s, "precision")?,
+        closed: ini.get(s, "closed")?,
+        points,
+    })
+}
+
+fn read_font(dir: &fs_path::Path, file_name: &str, name: PascalString) -> Result<Font, ReaderError> {
+    let ini = IniFile::load(dir.join(format!("{}.ini", file_name)))?;
+    let s = "font";
+    let mut dmap = Box::new([0; 0x600]);
+    if let Some(glyphs) = ini.ini.section(Some("glyphs")) {
+        for (key, _) in glyphs.iter() {
+            let bad_glyph = || invalid(&ini.path, format!("bad glyph {}", key));
+            let glyph = key.parse::<usize>().ok().and_then(|i| dmap.get_mut(i * 6..i * 6 + 6)).ok_or_else(bad_glyph)?;
+            let values = ini.list::<u32>("glyphs", key)?;
+            if values.len() != 6 {
+                return Err(bad_glyph())
+            }
+            glyph.copy_from_slice(&values);
+        }
+    }
+    let (map_width, map_height, pixel_map) = match read_file_maybe(&dir.join(format!("{}.png", file_name)))? {
+        Some(_) => {
+            // the pixel map is just alpha, which gets written as a greyscale image
+            let (width, height, data) = read_png(&dir.join(format!("{}.png", file_name)))?;
+            (width, height, data.chunks_exact(4).map(|p| p[0]).collect())
+        },
+        None => (0, 0, Box::default()),
+    };
+    Ok(Font {
+        name,
+        sys_name: ini.string(s, "sys_name")?,
+        size: ini.get(s, "size")?,
+        bold: ini.get(s, "bold")?,
+        italic: ini.get(s, "italic")?,
+        range_start: ini.get(s, "range_start")?,
+        range_end: ini.get(s, "range_end")?,
+        charset: ini.get(s, "charset")?,
+        aa_level: ini.get(s, "aa_level")?,
+        dmap,
+        map_width,
+        map_height,
+        pixel_map,
+    })
+}
+
+/// Reads every `.gml` file in a folder, along with its name without the extension.
+fn read_gml_dir(dir: &fs_path::Path) -> Result<Vec<(String, Vec<CodeAction>)>, ReaderError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(invalid(dir, e)),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| invalid(dir, e))?.path();
+        if path.extension().is_some_and(|x| x == "gml") {
+            if let Some(stem) = path.file_stem().and_then(|x| x.to_str()) {
+                let actions = read_actions(&read_file(&path)?).map_err(|e| invalid(&path, e))?;
+                files.push((stem.to_string(), actions));
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn read_timeline(dir: &fs_path::Path, file_name: &str, name: PascalString) -> Result<Timeline, ReaderError> {
+    let dir = dir.join(file_name);
+    let mut moments = read_gml_dir(&dir)?
+        .into_iter()
+        .map(|(moment, actions)| match moment.parse() {
+            Ok(moment) => Ok((moment, actions)),
+            Err(_) => Err(invalid(&dir, format!("{}.gml isn't a moment", moment))),
+        })
+        .collect::<Result<Vec<_>, ReaderError>>()?;
+    moments.sort_by_key(|(moment, _)| *moment);
+    Ok(Timeline { name, moments })
+}
+
+fn read_object(
+    dir: &fs_path::Path,
+    file_name: &str,
+    name: PascalString,
+    sprites: &Index,
+    objects: &Index,
+) -> Result<Object, ReaderError> {
+    let dir = dir.join(file_name);
+    let ini = IniFile::load(dir.join("object.ini"))?;
+    let s = "object";
+    let mut events = EVENT_NAMES.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+    for (event, actions) in read_gml_dir(&dir)? {
+        let bad_event = || invalid(&dir, format!("{}.gml isn't an event", event));
+        let (event_name, sub) = event.split_once('_').ok_or_else(bad_event)?;
+        let event_type = EVENT_NAMES.iter().position(|&x| x == event_name).ok_or_else(bad_event)?;
+        // collision events are named after the other object
+        let sub = match event_name {
+            "collision" => objects.reference(sub).and_then(|x| u32::try_from(x).ok()),
+            _ => sub.parse().ok(),
+        };
+        events[event_type].push((sub.ok_or_else(bad_event)?, actions));
+    }
+    for sub_events in &mut events {
+        sub_events.sort_by_key(|(sub, _)| *sub);
+    }
+    Ok(Object {
+        name,
+        sprite_index: ini.reference(s, "sprite", sprites)?,
+        solid: ini.get(s, "solid")?,
+        visible: ini.get(s, "visible")?,
+        depth: ini.get(s, "depth")?,
+        persistent: ini.get(s, "persistent")?,
+        parent_index: ini.reference(s, "parent", objects)?,
+        mask_index: ini.reference(s, "mask", sprites)?,
+        events,
+    })
+}
+
+/// Splits a line of `instances.txt` or `tiles.txt` into its numbers and the asset name at the end.
+fn read_row<'a>(path: &fs_path::Path, line: &'a str, count: usize) -> Result<(Vec<&'a str>, &'a str), ReaderError> {
+    let mut fields = line.splitn(count + 1, ' ').collect::<Vec<_>>();
+    if fields.len() != count + 1 {
+        return Err(invalid(path, format!("expected {} values: {}", count + 1, line)))
+    }
+    let name = fields.pop().unwrap_or_default();
+    Ok((fields, name))
+}
+
+fn read_rows(path: &fs_path::Path) -> Result<Vec<String>, ReaderError> {
+    let data = read_file_maybe(path)?.unwrap_or_default();
+    let text = String::from_utf8(data).map_err(|e| invalid(path, e))?;
+    Ok(text
+        .lines()
+        .map(|line| line.trim_end_matches('\r').to_string())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect())
+}
+
+fn parse<T: FromStr>(path: &fs_path::Path, value: &str) -> Result<T, ReaderError> {
+    value.parse().map_err(|_| invalid(path, format!("bad value: {}", value)))
+}
+
+fn read_room(
+    dir: &fs_path::Path,
+    file_name: &str,
+    name: PascalString,
+    backgrounds: &Index,
+    objects: &Index,
+) -> Result<Room, ReaderError> {
+    let dir = dir.join(file_name);
+    let ini = IniFile::load(dir.join("room.ini"))?;
+    let s = "room";
+
+    let room_backgrounds = (0..)
+        .map(|i| format!("background{}", i))
+        .take_while(|section| ini.has_section(section))
+        .map(|section| {
+            let s = section.as_str();
+            Ok(room::Background {
+                visible_on_start: ini.get(s, "visible")?,
+                is_foreground: ini.get(s, "foreground")?,
+                source_bg: ini.reference(s, "background", backgrounds)?,
+                xoffset: ini.get(s, "x")?,
+                yoffset: ini.get(s, "y")?,
+                tile_horz: ini.get(s, "tile_horz")?,
+                tile_vert: ini.get(s, "tile_vert")?,
+                hspeed: ini.get(s, "hspeed")?,
+                vspeed: ini.get(s, "vspeed")?,
+                stretch: ini.get(s, "stretch")?,
+            })
+        })
+        .collect::<Result<_, ReaderError>>()?;
+    let views = (0..)
+        .map(|i| format!("view{}", i))
+        .take_while(|section| ini.has_section(section))
+        .map(|section| {
+            let s = section.as_str();
+            Ok(room::View {
+                visible: ini.get(s, "visible")?,
+                source_x: ini.get(s, "source_x")?,
+                source_y: ini.get(s, "source_y")?,
+                source_w: ini.get(s, "source_w")?,
+                source_h: ini.get(s, "source_h")?,
+                port_x: ini.get(s, "port_x")?,
+                port_y: ini.get(s, "port_y")?,
+                port_w: ini.get(s, "port_w")?,
+                port_h: ini.get(s, "port_h")?,
+                following: ViewFollowData {
+                    hborder: ini.get(s, "hborder")?,
+                    vborder: ini.get(s, "vborder")?,
+                    hspeed: ini.get(s, "hspeed")?,
+                    vspeed: ini.get(s, "vspeed")?,
+                    target: ini.reference(s, "target", objects)?,
+                },
+            })
+        })
+        .collect::<Result<_, ReaderError>>()?;
+
+    let path = dir.join("instances.txt");
+    let instances = read_rows(&path)?
+        .iter()
+        .map(|line| {
+            let (fields, object) = read_row(&path, line, 7)?;
+            let id = parse(&path, fields[0])?;
+            Ok(room::Instance {
+                x: parse(&path, fields[1])?,
+                y: parse(&path, fields[2])?,
+                object: objects.reference(object).ok_or_else(|| invalid(&path, format!("no object {}", object)))?,
+                id,
+                creation_code: read_gml(&dir.join(format!("instance_{}.gml", id)))?,
+                xscale: parse(&path, fields[3])?,
+                yscale: parse(&path, fields[4])?,
+                blend: parse(&path, fields[5])?,
+                angle: parse(&path, fields[6])?,
+            })
+        })
+        .collect::<Result<_, ReaderError>>()?;
+
+    let path = dir.join("tiles.txt");
+    let tiles = read_rows(&path)?
+        .iter()
+        .map(|line| {
+            let (fields, bg) = read_row(&path, line, 11)?;
+            Ok(room::Tile {
+                id: parse(&path, fields[0])?,
+                x: parse(&path, fields[1])?,
+                y: parse(&path, fields[2])?,
+                tile_x: parse(&path, fields[3])?,
+                tile_y: parse(&path, fields[4])?,
+                width: parse(&path, fields[5])?,
+                height: parse(&path, fields[6])?,
+                depth: parse(&path, fields[7])?,
+                xscale: parse(&path, fields[8])?,
+                yscale: parse(&path, fields[9])?,
+                blend: parse(&path, fields[10])?,
+                source_bg: backgrounds.reference(bg).ok_or_else(|| invalid(&path, format!("no background {}", bg)))?,
+            })
+        })
+        .collect::<Result<_, ReaderError>>()?;
+
+    Ok(Room {
+        name,
+        caption: ini.string(s, "caption")?,
+        width: ini.get(s, "width")?,
+        height: ini.get(s, "height")?,
+        speed: ini.get(s, "speed")?,
+        persistent: ini.get(s, "persistent")?,
+        bg_colour: ini.get::<u32>(s, "bg_colour")?.into(),
+        clear_screen: ini.get(s, "clear_screen")?,
+        clear_region: ini.get(s, "clear_region")?,
+        creation_code: read_gml(&dir.join("creation.gml"))?,
+        backgrounds: room_backgrounds,
+        views_enabled: ini.get(s, "views_enabled")?,
+        views,
+        instances,
+        tiles,
+        uses_810_features: ini.get(s, "uses_810_features")?,
+        uses_811_features: ini.get(s, "uses_811_features")?,
+    })
+}
+
+fn read_trigger(dir: &fs_path::Path, file_name: &str, name: PascalString) -> Result<Trigger, ReaderError> {
+    let ini = IniFile::load(dir.join(format!("{}.ini", file_name)))?;
+    Ok(Trigger {
+        name,
+        condition: read_gml(&dir.join(format!("{}.gml", file_name)))?,
+        moment: TriggerKind::from(ini.get::<u32>("trigger", "moment")?),
+        constant_name: ini.string("trigger", "constant_name")?,
+    })
+}
+
+fn read_included_file(dir: &fs_path::Path, file_name: &str, name: PascalString) -> Result<IncludedFile, ReaderError> {
+    let ini = IniFile::load(dir.join(format!("{}.ini", file_name)))?;
+    let s = "file";
+    let export_settings = match ini.get::<u32>(s, "export")? {
+        0 => ExportSetting::NoExport,
+        1 => ExportSetting::TempFolder,
+        2 => ExportSetting::GameFolder,
+        _ => ExportSetting::CustomFolder(ini.string(s, "export_folder")?),
+    };
+    Ok(IncludedFile {
+        file_name: name,
+        source_path: ini.string(s, "source_path")?,
+        data_exists: ini.get(s, "data_exists")?,
+        source_length: ini.get(s, "source_length")?,
+        stored_in_gmk: ini.get(s, "stored_in_gmk")?,
+        embedded_data: read_file_maybe(&dir.join(format!("{}.dat", file_name)))?.map(Vec::into_boxed_slice),
+        export_settings,
+        overwrite_file: ini.get(s, "overwrite_file")?,
+        free_memory: ini.get(s, "free_memory")?,
+        remove_at_end: ini.get(s, "remove_at_end")?,
+    })
+}
+
+fn read_extension(dir: &fs_path::Path, file_name: &str, name: PascalString) -> Result<Extension, ReaderError> {
+    let dir = dir.join(file_name);
+    let ini = IniFile::load(dir.join("extension.ini"))?;
+    let files = (0..)
+        .map(|i| (i, format!("file{}", i)))
+        .take_while(|(_, section)| ini.has_section(section))
+        .map(|(i, section)| {
+            let s = section.as_str();
+            let kind = FileKind::from(ini.get::<u32>(s, "kind")?);
+            let functions = (0..)
+                .map(|j| format!("file{}.function{}", i, j))
+                .take_while(|section| ini.has_section(section))
+                .map(|section| {
+                    let s = section.as_str();
+                    let mut arg_types = [FunctionValueKind::GMReal; extension::ARG_MAX];
+                    for (arg, kind) in arg_types.iter_mut().zip(ini.list::<u32>(s, "arg_types")?) {
+                        *arg = FunctionValueKind::from(kind);
+                    }
+                    Ok(FileFunction {
+                        name: ini.string(s, "name")?,
+                        external_name: ini.string(s, "external_name")?,
+                        convention: CallingConvention::from(ini.get::<u32>(s, "convention")?),
+                        id: ini.get(s, "id")?,
+                        arg_count: ini.get(s, "arg_count")?,
+                        arg_types,
+                        return_type: FunctionValueKind::from(ini.get::<u32>(s, "return_type")?),
+                    })
+                })
+                .collect::<Result<_, ReaderError>>()?;
+            let consts = (0..)
+                .map(|j| format!("file{}.const{}", i, j))
+                .take_while(|section| ini.has_section(section))
+                .map(|section| {
+                    Ok(FileConst { name: ini.string(&section, "name")?, value: ini.string(&section, "value")? })
+                })
+                .collect::<Result<_, ReaderError>>()?;
+            let contents = match kind {
+                FileKind::ActionLibrary => Box::default(),
+                _ => read_file(&dir.join(ini.str(s, "contents")?))?.into(),
+            };
+            Ok(extension::File {
+                name: ini.string(s, "name")?,
+                kind,
+                initializer: ini.string(s, "initializer")?,
+                finalizer: ini.string(s, "finalizer")?,
+                functions,
+                consts,
+                contents,
+            })
+        })
+        .collect::<Result<_, ReaderError>>()?;
+    Ok(Extension { name, folder_name: ini.string("extension", "folder_name")?, files })
+}
+
+fn read_settings(ini: &IniFile) -> Result<Settings, ReaderError> {
+    let dir = ini.path.parent().unwrap_or(fs_path::Path::new(""));
+    let s = "settings";
+    Ok(Settings {
+        fullscreen: ini.get(s, "fullscreen")?,
+        scaling: ini.get(s, "scaling")?,
+        interpolate_pixels: ini.get(s, "interpolate_pixels")?,
+        clear_colour: ini.get(s, "clear_colour")?,
+        allow_resize: ini.get(s, "allow_resize")?,
+        window_on_top: ini.get(s, "window_on_top")?,
+        dont_draw_border: ini.get(s, "dont_draw_border")?,
+        dont_show_buttons: ini.get(s, "dont_show_buttons")?,
+        display_cursor: ini.get(s, "display_cursor")?,
+        freeze_on_lose_focus: ini.get(s, "freeze_on_lose_focus")?,
+        disable_screensaver: ini.get(s, "disable_screensaver")?,
+        force_cpu_render: ini.get(s, "force_cpu_render")?,
+        set_resolution: ini.get(s, "set_resolution")?,
+        colour_depth: ini.get(s, "colour_depth")?,
+        resolution: ini.get(s, "resolution")?,
+        frequency: ini.get(s, "frequency")?,
+        vsync: ini.get(s, "vsync")?,
+        esc_close_game: ini.get(s, "esc_close_game")?,
+        treat_close_as_esc: ini.get(s, "treat_close_as_esc")?,
+        f1_help_menu: ini.get(s, "f1_help_menu")?,
+        f4_fullscreen_toggle: ini.get(s, "f4_fullscreen_toggle")?,
+        f5_save_f6_load: ini.get(s, "f5_save_f6_load")?,
+        f9_screenshot: ini.get(s, "f9_screenshot")?,
+        priority: ini.get(s, "priority")?,
+        custom_load_image: read_file_maybe(&dir.join("loading_image.dat"))?.map(Vec::into_boxed_slice),
+        transparent: ini.get(s, "transparent")?,
+        translucency: ini.get(s, "translucency")?,
+        loading_bar: ini.get(s, "loading_bar")?,
+        backdata: read_file_maybe(&dir.join("loading_bar_back.dat"))?.map(Vec::into_boxed_slice),
+        frontdata: read_file_maybe(&dir.join("loading_bar_front.dat"))?.map(Vec::into_boxed_slice),
+        scale_progress_bar: ini.get(s, "scale_progress_bar")?,
+        show_error_messages: ini.get(s, "show_error_messages")?,
+        log_errors: ini.get(s, "log_errors")?,
+        always_abort: ini.get(s, "always_abort")?,
+        zero_uninitialized_vars: ini.get(s, "zero_uninitialized_vars")?,
+        error_on_uninitialized_args: ini.get(s, "error_on_uninitialized_args")?,
+        swap_creation_events: ini.get(s, "swap_creation_events")?,
+    })
+}
+
+fn read_help_dialog(ini: &IniFile) -> Result<GameHelpDialog, ReaderError> {
+    let dir = ini.path.parent().unwrap_or(fs_path::Path::new(""));
+    let s = "game_information";
+    Ok(GameHelpDialog {
+        bg_colour: ini.get::<u32>(s, "bg_colour")?.into(),
+        new_window: ini.get(s, "new_window")?,
+        caption: ini.string(s, "caption")?,
+        left: ini.get(s, "left")?,
+        top: ini.get(s, "top")?,
+        width: ini.get(s, "width")?,
+        height: ini.get(s, "height")?,
+        border: ini.get(s, "border")?,
+        resizable: ini.get(s, "resizable")?,
+        window_on_top: ini.get(s, "window_on_top")?,
+        freeze_game: ini.get(s, "freeze_game")?,
+        info: read_gml(&dir.join("Game Information.rtf"))?,
+    })
+}
+
+pub fn from_tree<F>(path: &fs_path::Path, logger: Option<F>, multithread: bool) -> Result<GameAssets, ReaderError>
+where
+    F: Copy + Fn(&str),
+{
+    let ini = IniFile::load(path.join("game.ini"))?;
+    let version = match ini.get::<u32>("game", "version")? {
+        600 => GameVersion::GameMaker6_0,
+        610 => GameVersion::GameMaker6_1,
+        700 => GameVersion::GameMaker7_0,
+        800 => GameVersion::GameMaker8_0,
+        810 => GameVersion::GameMaker8_1,
+        _ => return Err(ReaderError::UnknownFormat),
+    };
+    let game_id = ini.get("game", "game_id")?;
+    let guid =
+        ini.list::<u32>("game", "guid")?.try_into().map_err(|_| invalid(&ini.path, "guid should be 4 numbers"))?;
+    log!(logger, "Game ID: {}", game_id);
+
+    let settings = read_settings(&ini)?;
+    let help_dialog = read_help_dialog(&ini)?;
+    let constants = ini
+        .ini
+        .section(Some("constants"))
+        .map(|section| {
+            section
+                .iter()
+                .map(|(name, expression)| Constant { name: name.into(), expression: expression.into() })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Assets refer to each other by file name, so these have to be loaded first
+    let sprite_index = Index::load(path.join("Sprites"))?;
+    let background_index = Index::load(path.join("Backgrounds"))?;
+    let object_index = Index::load(path.join("Objects"))?;
+    let room_index = Index::load(path.join("Rooms"))?;
+
+    let triggers = Index::load(path.join("Triggers"))?.read(multithread, read_trigger)?;
+    log!(logger, " + Read {} triggers", triggers.len());
+    let sounds = Index::load(path.join("Sounds"))?.read(multithread, read_sound)?;
+    log!(logger, " + Read {} sounds", sounds.len());
+    let sprites = sprite_index.read(multithread, read_sprite)?;
+    log!(logger, " + Read {} sprites", sprites.len());
+    let backgrounds = background_index.read(multithread, read_background)?;
+    log!(logger, " + Read {} backgrounds", backgrounds.len());
+    let paths = Index::load(path.join("Paths"))?.read(multithread, read_path)?;
+    log!(logger, " + Read {} paths", paths.len());
+    let scripts = Index::load(path.join("Scripts"))?.read(multithread, |dir, file_name, name| {
+        Ok(Script { name, source: read_gml(&dir.join(format!("{}.gml", file_name)))? })
+    })?;
+    log!(logger, " + Read {} scripts", scripts.len());
+    let fonts = Index::load(path.join("Fonts"))?.read(multithread, read_font)?;
+    log!(logger, " + Read {} fonts", fonts.len());
+    let timelines = Index::load(path.join("Time Lines"))?.read(multithread, read_timeline)?;
+    log!(logger, " + Read {} timelines", timelines.len());
+    let objects = object_index
+        .read(multithread, |dir, file_name, name| read_object(dir, file_name, name, &sprite_index, &object_index))?;
+    log!(logger, " + Read {} objects", objects.len());
+    let rooms = room_index
+        .read(multithread, |dir, file_name, name| read_room(dir, file_name, name, &background_index, &object_index))?;
+    log!(logger, " + Read {} rooms", rooms.len());
+
+    let included_files = Index::load(path.join("Included Files"))?
+        .read(false, read_included_file)?
+        .into_iter()
+        .map(|file| file.map(|file| *file).ok_or_else(|| invalid(path, "included files can't be deleted")))
+        .collect::<Result<Vec<_>, _>>()?;
+    log!(logger, " + Read {} included files", included_files.len());
+    let extensions = Index::load(path.join("Extensions"))?
+        .read(false, read_extension)?
+        .into_iter()
+        .map(|extension| extension.map(|x| *x).ok_or_else(|| invalid(path, "extensions can't be deleted")))
+        .collect::<Result<Vec<_>, _>>()?;
+    log!(logger, " + Read {} extensions", extensions.len());
+
+    let library_init_strings = (0..)
+        .map(|i| path.join("Library Init").join(format!("{}.gml", i)))
+        .map_while(|path| read_file_maybe(&path).transpose())
+        .map(|code| Ok(PascalString(code?.into())))
+        .collect::<Result<_, ReaderError>>()?;
+
+    // Without a room order, rooms go in ID order
+    let order_path = path.join("Rooms").join("order.txt");
+    let room_order = match read_file_maybe(&order_path)? {
+        Some(_) => read_rows(&order_path)?
+            .iter()
+            .map(|room| room_index.reference(room).ok_or_else(|| invalid(&order_path, format!("no room {}", room))))
+            .collect::<Result<_, _>>()?,
+        None => rooms.iter().enumerate().filter(|(_, room)| room.is_some()).map(|(i, _)| i as i32).collect(),
+    };
+    log!(logger, " + Added Room Order LUT: {:?}", room_order);
+
+    Ok(GameAssets {
+        extensions,
+        sprites,
+        sounds,
+        backgrounds,
+        paths,
+        scripts,
+        fonts,
+        timelines,
+        objects,
+        triggers,
+        constants,
+        rooms,
+        included_files,
+
+        dx_dll: Vec::new(),
+        ico_file_raw: read_file_maybe(&path.join("icon.ico"))?,
+        version,
+        help_dialog,
+        last_instance_id: ini.get("game", "last_instance_id")?,
+        last_tile_id: ini.get("game", "last_tile_id")?,
+        library_init_strings,
+        room_order,
+
+        settings,
+        game_id,
+        guid,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_are_unique_and_valid() {
+        let names: Vec<PascalString> =
+            ["spr_player", "SPR_PLAYER", "", "a/b", "con", "dots...", "spr_player_1"].map(PascalString::from).into();
+        let file_names = file_names(names.iter().map(Some).chain([None]));
+        let expected = ["spr_player", "SPR_PLAYER_1", "_2", "a_b", "con_4", "dots..._", "spr_player_1_6"];
+        assert_eq!(file_names, expected.iter().map(|s| Some(s.to_string())).chain([None]).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn actions() {
+        let action = |code: &str| {
+            let mut action = default_action();
+            action.param_strings[0] = code.into();
+            action
+        };
+        let code = action("x = 1;\r\ny = 2;\r\n");
+        let other = CodeAction { applies_to: -2, ..action("z = 3;") };
+        let mut dnd = CodeAction { id: 101, action_kind: 0, param_count: 2, ..action("speed = 4") };
+        dnd.param_strings[1] = "a\\b".into();
+        let empty = action("");
+
+        let output = write_actions(&[code, other, dnd, empty]);
+        let expected = concat!(
+            "x = 1;\r\ny = 2;\r\n\r\n",
+            "#action applies_to=-2\r\nz = 3;\r\n",
+            "#action id=101 kind=0 param_count=2 arg0=speed\\s=\\s4 arg1=a\\\\b\r\n\r\n",
+            "#action\r\n",
+        );
+        assert_eq!(String::from_utf8_lossy(&output), expected);
+
+        let actions = read_actions(&output).unwrap();
+        assert_eq!(write_actions(&actions), output);
+        assert_eq!(actions[0].param_strings[0].0.as_ref(), b"x = 1;\r\ny = 2;\r\n");
+        assert_eq!(actions[2].param_strings[0].0.as_ref(), b"speed = 4");
+        assert_eq!(actions[2].param_strings[1].0.as_ref(), b"a\\b");
+        assert_eq!(actions[3].param_strings[0].0.as_ref(), b"");
+        assert!(read_actions(b"").unwrap().is_empty());
+        assert!(read_actions(b"#action id=101 kind=0\r\ncode").is_err());
+    }
+}
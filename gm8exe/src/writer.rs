@@ -0,0 +1,527 @@
+//! Builds game executables from `GameAssets`, the inverse of `reader::from_exe`.
+//!
+//! GameMaker games are a runner with the gamedata appended to it, so rather than shipping a runner of our own, the
+//! runner and the gamedata header are taken from an existing game and the rest of the gamedata is replaced.
+//! The stub's icon is kept, as it lives in the runner's resources.
+//!
+//! Games made with GameMaker 6.x and 7.0 can be written too. Whatever their versions can't store, such as triggers,
+//! and extensions before 7.0, is left out.
+
+use crate::{
+    asset::{Asset, WritePascalString},
+    gamedata::{gm60, gm70, gm80, gm81},
+    settings::GameHelpDialog,
+    AssetList, GameAssets, GameVersion,
+};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use flate2::{write::ZlibEncoder, Compression};
+use std::{
+    fmt::{self, Display},
+    io::{self, Read, Seek, SeekFrom, Write},
+};
+
+#[derive(Debug)]
+pub enum WriterError {
+    IO(io::Error),
+    UnknownStubFormat,
+    VersionMismatch { stub: GameVersion, assets: GameVersion },
+}
+impl std::error::Error for WriterError {}
+impl Display for WriterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            WriterError::IO(err) => format!("io error: {}", err),
+            WriterError::UnknownStubFormat => {
+                "unknown stub format, only unprotected games can be used".into()
+            },
+            WriterError::VersionMismatch { stub, assets } => {
+                format!("stub is a {:?} game but the assets are for {:?}", stub, assets)
+            },
+        })
+    }
+}
+
+impl From<io::Error> for WriterError {
+    fn from(err: io::Error) -> Self {
+        WriterError::IO(err)
+    }
+}
+
+/// The parts of an existing game which are reused.
+struct Stub {
+    version: GameVersion,
+    /// Everything before the gamedata header.
+    runner: Vec<u8>,
+    /// The decrypted gamedata header, up to the settings chunk.
+    header: Vec<u8>,
+    xor_method: Option<gm81::XorMethod>,
+    dx_dll_name: Vec<u8>,
+    dx_dll: Vec<u8>,
+}
+
+/// Locates the gamedata in a standard game. Protected and packed games aren't supported,
+/// as their runners are modified to read the gamedata differently.
+fn read_stub<F>(mut stub: Vec<u8>, logger: Option<F>) -> Result<Stub, WriterError>
+where
+    F: Copy + Fn(&str),
+{
+    let mut exe = io::Cursor::new(stub.as_mut_slice());
+    // These leave the cursor at the settings chunk, which is a known distance from the start of the header.
+    let (version, header_start, xor_method) = if gm80::check(&mut exe, logger)? {
+        (GameVersion::GameMaker8_0, exe.position() - 16, None)
+    } else if gm81::check(&mut exe, logger)? {
+        let pos = exe.position();
+        let xor_method = gm81::xor_method(&mut exe, logger)?;
+        exe.set_position(pos);
+        (GameVersion::GameMaker8_1, pos - 36, Some(xor_method))
+    } else if gm70::check(&mut exe, logger)? {
+        (GameVersion::GameMaker7_0, exe.position() - 16, None)
+    } else if let Some(version) = gm60::check(&mut exe, logger)? {
+        (version, exe.position() - 16, None)
+    } else if gm81::check_lazy(&mut exe, logger)? {
+        (GameVersion::GameMaker8_1, exe.position() - 36, Some(gm81::XorMethod::Normal))
+    } else {
+        return Err(WriterError::UnknownStubFormat)
+    };
+    let header_end = exe.position();
+    log!(logger, "Found {:?} gamedata header at 0x{:X}", version, header_start);
+
+    // skip the settings to get to the DirectX DLL, which is needed if the assets didn't come from an exe
+    let settings_len = exe.read_u32::<LE>()?;
+    exe.seek(SeekFrom::Current(settings_len.into()))?;
+    let mut dx_dll_name = vec![0; exe.read_u32::<LE>()? as usize];
+    exe.read_exact(&mut dx_dll_name)?;
+    let mut dx_dll = vec![0; exe.read_u32::<LE>()? as usize];
+    exe.read_exact(&mut dx_dll)?;
+
+    Ok(Stub {
+        version,
+        runner: stub[..header_start as usize].to_vec(),
+        header: stub[header_start as usize..header_end as usize].to_vec(),
+        xor_method,
+        dx_dll_name,
+        dx_dll,
+    })
+}
+
+fn zlib(f: impl FnOnce(&mut ZlibEncoder<Vec<u8>>) -> io::Result<()>) -> io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    f(&mut encoder)?;
+    encoder.finish()
+}
+
+fn write_block(writer: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    writer.write_u32::<LE>(data.len() as u32)?;
+    writer.write_all(data)
+}
+
+fn write_settings(writer: &mut impl Write, assets: &GameAssets) -> io::Result<()> {
+    fn write_data_maybe(cfg: &mut impl Write, data: &Option<Box<[u8]>>) -> io::Result<()> {
+        match data {
+            Some(data) => {
+                cfg.write_u32::<LE>(1)?;
+                write_block(cfg, data)
+            },
+            None => cfg.write_u32::<LE>(0),
+        }
+    }
+
+    let (settings, version) = (&assets.settings, assets.version);
+    let data = zlib(|cfg| {
+        cfg.write_u32::<LE>(settings.fullscreen.into())?;
+        cfg.write_u32::<LE>(settings.interpolate_pixels.into())?;
+        cfg.write_u32::<LE>(settings.dont_draw_border.into())?;
+        cfg.write_u32::<LE>(settings.display_cursor.into())?;
+        cfg.write_i32::<LE>(settings.scaling)?;
+        cfg.write_u32::<LE>(settings.allow_resize.into())?;
+        cfg.write_u32::<LE>(settings.window_on_top.into())?;
+        cfg.write_u32::<LE>(settings.clear_colour)?;
+        cfg.write_u32::<LE>(settings.set_resolution.into())?;
+        cfg.write_u32::<LE>(settings.colour_depth)?;
+        cfg.write_u32::<LE>(settings.resolution)?;
+        cfg.write_u32::<LE>(settings.frequency)?;
+        cfg.write_u32::<LE>(settings.dont_show_buttons.into())?;
+        cfg.write_u32::<LE>(match version {
+            GameVersion::GameMaker8_1 => u32::from(settings.vsync) | (u32::from(settings.force_cpu_render) << 7),
+            _ => settings.vsync.into(),
+        })?;
+        if !version.is_pre_8_0() {
+            cfg.write_u32::<LE>(settings.disable_screensaver.into())?;
+        }
+        cfg.write_u32::<LE>(settings.f4_fullscreen_toggle.into())?;
+        cfg.write_u32::<LE>(settings.f1_help_menu.into())?;
+        cfg.write_u32::<LE>(settings.esc_close_game.into())?;
+        cfg.write_u32::<LE>(settings.f5_save_f6_load.into())?;
+        if !matches!(version, GameVersion::GameMaker6_0 | GameVersion::GameMaker6_1) {
+            cfg.write_u32::<LE>(settings.f9_screenshot.into())?;
+            cfg.write_u32::<LE>(settings.treat_close_as_esc.into())?;
+        }
+        cfg.write_u32::<LE>(settings.priority)?;
+        cfg.write_u32::<LE>(settings.freeze_on_lose_focus.into())?;
+        cfg.write_u32::<LE>(settings.loading_bar)?;
+        if settings.loading_bar != 0 {
+            write_data_maybe(cfg, &settings.backdata)?;
+            write_data_maybe(cfg, &settings.frontdata)?;
+        }
+        write_data_maybe(cfg, &settings.custom_load_image)?;
+        cfg.write_u32::<LE>(settings.transparent.into())?;
+        cfg.write_u32::<LE>(settings.translucency)?;
+        cfg.write_u32::<LE>(settings.scale_progress_bar.into())?;
+        cfg.write_u32::<LE>(settings.show_error_messages.into())?;
+        cfg.write_u32::<LE>(settings.log_errors.into())?;
+        cfg.write_u32::<LE>(settings.always_abort.into())?;
+        cfg.write_u32::<LE>(match version {
+            GameVersion::GameMaker8_1 => {
+                u32::from(settings.zero_uninitialized_vars) | (u32::from(settings.error_on_uninitialized_args) << 1)
+            },
+            _ => settings.zero_uninitialized_vars.into(),
+        })?;
+        if version.is_pre_8_0() {
+            write_constants(cfg, assets)?;
+        }
+        if let GameVersion::GameMaker8_1 = version {
+            cfg.write_u32::<LE>(0)?; // webgl
+            cfg.write_u32::<LE>(settings.swap_creation_events.into())?;
+        }
+        Ok(())
+    })?;
+    write_block(writer, &data)
+}
+
+fn write_constants(writer: &mut impl Write, assets: &GameAssets) -> io::Result<()> {
+    writer.write_u32::<LE>(assets.constants.len() as u32)?;
+    for constant in &assets.constants {
+        writer.write_pas_string(&constant.name)?;
+        writer.write_pas_string(&constant.expression)?;
+    }
+    Ok(())
+}
+
+fn write_assets<T: Asset>(writer: &mut impl Write, assets: &AssetList<T>, version: GameVersion) -> io::Result<()> {
+    writer.write_u32::<LE>(800)?;
+    writer.write_u32::<LE>(assets.len() as u32)?;
+    for asset in assets {
+        let data = zlib(|data| match asset {
+            Some(asset) => {
+                data.write_u32::<LE>(1)?;
+                asset.serialize_exe(data, version)
+            },
+            None => data.write_u32::<LE>(0),
+        })?;
+        write_block(writer, &data)?;
+    }
+    Ok(())
+}
+
+fn write_help_dialog(writer: &mut impl Write, help_dialog: &GameHelpDialog) -> io::Result<()> {
+    writer.write_u32::<LE>(800)?;
+    let data = zlib(|data| {
+        data.write_u32::<LE>(help_dialog.bg_colour.into())?;
+        data.write_u32::<LE>(help_dialog.new_window.into())?;
+        data.write_pas_string(&help_dialog.caption)?;
+        data.write_i32::<LE>(help_dialog.left)?;
+        data.write_i32::<LE>(help_dialog.top)?;
+        data.write_u32::<LE>(help_dialog.width)?;
+        data.write_u32::<LE>(help_dialog.height)?;
+        data.write_u32::<LE>(help_dialog.border.into())?;
+        data.write_u32::<LE>(help_dialog.resizable.into())?;
+        data.write_u32::<LE>(help_dialog.window_on_top.into())?;
+        data.write_u32::<LE>(help_dialog.freeze_game.into())?;
+        data.write_pas_string(&help_dialog.info)
+    })?;
+    write_block(writer, &data)
+}
+
+/// Everything protected by GM8.0's or GM6.x's encryption, from the pro flag to the room order.
+fn write_protected(writer: &mut impl Write, assets: &GameAssets) -> io::Result<()> {
+    let version = assets.version;
+
+    writer.write_u32::<LE>(0)?; // garbage dwords
+    writer.write_u32::<LE>(1)?; // pro flag
+    writer.write_u32::<LE>(assets.game_id)?;
+    for part in &assets.guid {
+        writer.write_u32::<LE>(*part)?;
+    }
+
+    if !matches!(version, GameVersion::GameMaker6_0 | GameVersion::GameMaker6_1) {
+        writer.write_u32::<LE>(700)?;
+        writer.write_u32::<LE>(assets.extensions.len() as u32)?;
+        for extension in &assets.extensions {
+            // the seed is treated as signed when it's used, and GameMaker's are never negative
+            extension.write(writer, assets.game_id >> 1)?;
+        }
+    }
+
+    // before 8.0 there were no triggers, and the constants were in the settings
+    if !version.is_pre_8_0() {
+        write_assets(writer, &assets.triggers, version)?;
+
+        writer.write_u32::<LE>(800)?;
+        write_constants(writer, assets)?;
+    }
+
+    write_assets(writer, &assets.sounds, version)?;
+    write_assets(writer, &assets.sprites, version)?;
+    write_assets(writer, &assets.backgrounds, version)?;
+    write_assets(writer, &assets.paths, version)?;
+    write_assets(writer, &assets.scripts, version)?;
+    write_assets(writer, &assets.fonts, version)?;
+    write_assets(writer, &assets.timelines, version)?;
+    write_assets(writer, &assets.objects, version)?;
+    write_assets(writer, &assets.rooms, version)?;
+
+    writer.write_i32::<LE>(assets.last_instance_id)?;
+    writer.write_i32::<LE>(assets.last_tile_id)?;
+
+    // included files don't have an "exists" flag like the other asset lists
+    writer.write_u32::<LE>(800)?;
+    writer.write_u32::<LE>(assets.included_files.len() as u32)?;
+    for file in &assets.included_files {
+        write_block(writer, &zlib(|data| file.serialize_exe(data, version))?)?;
+    }
+
+    write_help_dialog(writer, &assets.help_dialog)?;
+
+    writer.write_u32::<LE>(500)?;
+    writer.write_u32::<LE>(assets.library_init_strings.len() as u32)?;
+    for init_string in &assets.library_init_strings {
+        writer.write_pas_string(init_string)?;
+    }
+
+    writer.write_u32::<LE>(700)?;
+    writer.write_u32::<LE>(assets.room_order.len() as u32)?;
+    for room in &assets.room_order {
+        writer.write_i32::<LE>(*room)?;
+    }
+
+    Ok(())
+}
+
+/// Makes the swap table for GM8.0's encryption by shuffling every byte value, seeded with the game ID.
+fn swap_table(seed: u32) -> [u8; 256] {
+    let mut table = [0u8; 256];
+    for (i, val) in table.iter_mut().enumerate() {
+        *val = i as u8;
+    }
+    let mut state = seed;
+    for i in (1..table.len()).rev() {
+        state = state.wrapping_mul(1103515245).wrapping_add(12345);
+        table.swap(i, (state >> 16) as usize % (i + 1));
+    }
+    table
+}
+
+/// Builds a game executable out of `assets`, using the runner from `stub`, which must be a game made with the same
+/// version of GameMaker.
+pub fn to_exe<F>(assets: &GameAssets, stub: Vec<u8>, logger: Option<F>) -> Result<Vec<u8>, WriterError>
+where
+    F: Copy + Fn(&str),
+{
+    let stub = read_stub(stub, logger)?;
+    match (stub.version, assets.version) {
+        (GameVersion::GameMaker8_0, GameVersion::GameMaker8_0) => (),
+        (GameVersion::GameMaker8_1, GameVersion::GameMaker8_1) => (),
+        (GameVersion::GameMaker7_0, GameVersion::GameMaker7_0) => (),
+        (GameVersion::GameMaker6_1, GameVersion::GameMaker6_1) => (),
+        (GameVersion::GameMaker6_0, GameVersion::GameMaker6_0) => (),
+        (stub, assets) => return Err(WriterError::VersionMismatch { stub, assets }),
+    }
+
+    let mut exe = stub.runner;
+    let header_start = exe.len();
+    exe.extend_from_slice(&stub.header);
+
+    log!(logger, "Writing settings...");
+    write_settings(&mut exe, assets)?;
+
+    // games loaded from anywhere but an exe won't have the DLL, so the stub's is used instead
+    write_block(&mut exe, &stub.dx_dll_name)?;
+    if assets.dx_dll.is_empty() {
+        write_block(&mut exe, &stub.dx_dll)?;
+    } else {
+        write_block(&mut exe, &assets.dx_dll)?;
+    }
+
+    log!(logger, "Writing assets...");
+    let mut protected = Vec::new();
+    write_protected(&mut protected, assets)?;
+
+    log!(logger, "Encrypting asset data... (size: {})", protected.len());
+    match assets.version {
+        GameVersion::GameMaker6_0 | GameVersion::GameMaker6_1 => {
+            gm60::encrypt(&mut protected, assets.game_id);
+            exe.write_u32::<LE>(assets.game_id)?; // seed
+            write_block(&mut exe, &protected)?;
+        },
+        _ => {
+            let swap_table = swap_table(assets.game_id);
+            gm80::encrypt(&mut protected, &swap_table);
+            exe.write_u32::<LE>(0)?; // garbage 1
+            exe.write_u32::<LE>(0)?; // garbage 2
+            exe.write_all(&swap_table)?;
+            write_block(&mut exe, &protected)?;
+        },
+    }
+
+    if let Some(xor_method) = stub.xor_method {
+        // GM8.1 encryption starts after the magic value and picks up the seeds kept from the stub's header
+        let mut cursor = io::Cursor::new(exe.as_mut_slice());
+        cursor.set_position(header_start as u64 + 8);
+        gm81::encrypt(&mut cursor, logger, xor_method)?;
+    }
+
+    Ok(exe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{reader::from_exe, test_support::game};
+
+    const NO_LOGGER: Option<fn(&str)> = None;
+
+    fn put(data: &mut [u8], pos: usize, bytes: &[u8]) {
+        data[pos..pos + bytes.len()].copy_from_slice(bytes);
+    }
+
+    /// Makes the smallest executable which the reader will recognise as a standard game, with no assets.
+    fn stub(version: GameVersion) -> Vec<u8> {
+        let header_start = match version {
+            GameVersion::GameMaker6_0 | GameVersion::GameMaker6_1 => 60000,
+            GameVersion::GameMaker7_0 => 70000,
+            GameVersion::GameMaker8_0 => 0x150000,
+            GameVersion::GameMaker8_1 => 0x230000,
+        };
+        let mut exe = vec![0u8; header_start];
+        put(&mut exe, 0, b"MZ");
+        put(&mut exe, 0x3C, &0x80u32.to_le_bytes());
+        put(&mut exe, 0x80, b"PE\0\0\x4C\x01");
+        match version {
+            GameVersion::GameMaker6_0 | GameVersion::GameMaker6_1 | GameVersion::GameMaker7_0 => {
+                let header_version: u32 = match version {
+                    GameVersion::GameMaker6_0 => 600,
+                    GameVersion::GameMaker6_1 => 610,
+                    _ => 700,
+                };
+                // the runner only looks for the header every 10000 bytes, so this one gets skipped
+                put(&mut exe, 25000, &[1234321u32.to_le_bytes(), header_version.to_le_bytes()].concat());
+                for dword in [1234321, header_version, 0, header_version] {
+                    exe.write_u32::<LE>(dword).unwrap();
+                }
+            },
+            GameVersion::GameMaker8_0 => {
+                put(&mut exe, 0xA49BE, &[0x8B, 0x45, 0xF4, 0xE8, 0x2A, 0xBD, 0xFD, 0xFF, 0x3D]);
+                put(&mut exe, 0xA49C7, &1234321u32.to_le_bytes());
+                put(&mut exe, 0xA49CB, &[0x0F, 0x85, 0x18, 0x01, 0x00, 0x00]);
+                put(&mut exe, 0xA49E2, &[0x8B, 0xC6, 0xE8, 0x07, 0xBD, 0xFD, 0xFF, 0x3D]);
+                put(&mut exe, 0xA49EA, &800u32.to_le_bytes());
+                put(&mut exe, 0xA49EE, &[0x0F, 0x85, 0xF5, 0x00, 0x00, 0x00]);
+                put(&mut exe, 0x144AC0, &(header_start as u32).to_le_bytes());
+                for dword in [1234321, 800, 0, 800] {
+                    exe.write_u32::<LE>(dword).unwrap();
+                }
+            },
+            GameVersion::GameMaker8_1 => {
+                put(&mut exe, 0x226CF3, &[0xE8, 0x80, 0xF2, 0xDD, 0xFF, 0xC7, 0x45, 0xF0]);
+                put(&mut exe, 0x226CFB, &(header_start as u32).to_le_bytes());
+                put(&mut exe, 0x226D7C, &[0x81, 0x7D, 0xEC, 0x67, 0x00, 0x14, 0xF7, 0x74]);
+                for dword in [0xF7000000, 0x00140067, 1234, 5678, 810, 0, 0, 0, 800] {
+                    exe.write_u32::<LE>(dword).unwrap();
+                }
+            },
+        }
+        write_block(&mut exe, &zlib(|_| Ok(())).unwrap()).unwrap();
+        write_block(&mut exe, b"D3DX8.dll").unwrap();
+        // long enough that GM8.1's encryption, which starts up to 265 bytes in, has something to encrypt
+        write_block(&mut exe, &[0xDD; 300]).unwrap();
+        if let GameVersion::GameMaker8_1 = version {
+            let mut cursor = io::Cursor::new(exe.as_mut_slice());
+            cursor.set_position(header_start as u64 + 8);
+            gm81::encrypt(&mut cursor, NO_LOGGER, gm81::XorMethod::Normal).unwrap();
+        }
+        exe
+    }
+
+    #[test]
+    fn encryption_round_trip() {
+        let swap_table = swap_table(42);
+        let plain = (0..1000u32).map(|i| (i * 7 % 251) as u8).collect::<Vec<_>>();
+        let mut data = vec![0u8; 8];
+        write_block(&mut data, &plain).unwrap();
+        gm80::encrypt(&mut data[12..], &swap_table);
+        assert_ne!(data[12..], plain[..]);
+
+        let mut exe = [&[0u32.to_le_bytes(), 0u32.to_le_bytes()].concat()[..], &swap_table[..], &data[8..]].concat();
+        let mut cursor = io::Cursor::new(exe.as_mut_slice());
+        gm80::decrypt(&mut cursor, NO_LOGGER).unwrap();
+        assert_eq!(exe[8 + 256 + 4..], plain[..]);
+    }
+
+    const VERSIONS: [GameVersion; 5] = [
+        GameVersion::GameMaker6_0,
+        GameVersion::GameMaker6_1,
+        GameVersion::GameMaker7_0,
+        GameVersion::GameMaker8_0,
+        GameVersion::GameMaker8_1,
+    ];
+
+    #[test]
+    fn round_trip() {
+        for version in VERSIONS {
+            let exe = to_exe(&game(version), stub(version), NO_LOGGER).unwrap();
+            let read = from_exe(exe.clone(), NO_LOGGER, true, false).unwrap();
+            assert_eq!(to_exe(&read, stub(version), NO_LOGGER).unwrap(), exe);
+
+            assert_eq!(read.dx_dll, [0xDD; 300]);
+            assert_eq!(read.settings.frontdata.as_deref(), Some(&[1, 2, 3][..]));
+            assert_eq!(read.constants[0].expression.0.as_ref(), b"2 + 3");
+            if let Some(extension) = read.extensions.first() {
+                assert!(extension.files[0].contents.iter().copied().eq(0..=255));
+            }
+            assert_eq!(read.fonts[0].as_ref().unwrap().dmap[0x41 * 6], 7);
+            let room = read.rooms[0].as_ref().unwrap();
+            assert_eq!(room.instances[1].angle, 90.0);
+            assert_eq!(room.tiles[0].yscale, 0.5);
+            assert!(read.scripts[1].is_none());
+        }
+    }
+
+    #[test]
+    fn pre_8_0_layouts() {
+        for version in &VERSIONS[..3] {
+            let exe = to_exe(&game(*version), stub(*version), NO_LOGGER).unwrap();
+            let read = from_exe(exe, NO_LOGGER, true, false).unwrap();
+
+            // 6.x has fewer settings, and neither has the one 8.0 added
+            let is_6_x = matches!(version, GameVersion::GameMaker6_0 | GameVersion::GameMaker6_1);
+            assert!(!read.settings.disable_screensaver);
+            assert_eq!(read.settings.f9_screenshot, !is_6_x);
+            assert!(read.settings.treat_close_as_esc);
+            assert_eq!(read.extensions.is_empty(), is_6_x);
+            assert!(read.triggers.is_empty());
+
+            // the transparent colour is taken from the bottom-left pixel, and the frames share one collision mask
+            let sprite = read.sprites[0].as_ref().unwrap();
+            assert_eq!(sprite.frames[0].data[..8], [0, 0, 0, 0, 255, 0, 0, 255]);
+            assert_eq!(sprite.frames[1].data[15 * 4..], [0, 0, 255, 255]);
+            assert!(!sprite.per_frame_colliders);
+            let mask = &sprite.colliders[0];
+            assert_eq!((mask.bbox_left, mask.bbox_top, mask.bbox_right, mask.bbox_bottom), (0, 0, 3, 3));
+            assert!(mask.data[1] && mask.data[15] && !mask.data[0]);
+            let background = read.backgrounds[0].as_ref().unwrap();
+            assert_eq!(background.data.as_deref(), Some(&[255, 0, 0, 255, 0, 0, 0, 0][..]));
+
+            // fonts are laid out the same as in 8.0, and draw the same way as 8.0's
+            let font = read.fonts[0].as_ref().unwrap();
+            assert_eq!((font.range_start, font.range_end, font.charset, font.aa_level), (32, 127, 0, 0));
+        }
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let result = to_exe(&game(GameVersion::GameMaker8_1), stub(GameVersion::GameMaker8_0), NO_LOGGER);
+        assert!(matches!(result, Err(WriterError::VersionMismatch { .. })));
+        let result = to_exe(&game(GameVersion::GameMaker8_0), vec![0; 0x400000], NO_LOGGER);
+        assert!(matches!(result, Err(WriterError::UnknownStubFormat)));
+    }
+}